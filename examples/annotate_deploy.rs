@@ -0,0 +1,31 @@
+//! Minimal embedding example: mark a deployment on one dashboard.
+//!
+//! cargo run --example annotate_deploy -- Service-Dashboard 1.9.0
+
+use anyhow::{Context, Result};
+use cwnote::{Annotation, Annotator, Target, WidgetSelector};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let dashboard = args
+        .next()
+        .context("usage: annotate_deploy <dashboard> <version>")?;
+    let version = args
+        .next()
+        .context("usage: annotate_deploy <dashboard> <version>")?;
+
+    let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+    let annotator = Annotator::new(aws_sdk_cloudwatch::Client::new(&config));
+
+    annotator
+        .annotate(
+            &Target::Dashboard(dashboard),
+            &WidgetSelector::default(),
+            &Annotation::new("deploy", version),
+        )
+        .await?;
+
+    println!("undo with: cwnote remove --run-id {}", annotator.run_id());
+    Ok(())
+}
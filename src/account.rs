@@ -0,0 +1,35 @@
+use anyhow::{Context, Result};
+
+use crate::aws_client::{self, AssumeRole};
+
+/// Human-readable name of the account a run acts in, the one of `role` if
+/// given: its IAM alias (e.g. `payments-prod`), or the 12-digit account id
+/// if it has no alias.
+pub async fn resolve_account_alias(
+    region: Option<&str>,
+    profile: Option<&str>,
+    role: Option<&AssumeRole>,
+) -> Result<String> {
+    let iam = aws_client::make_iam_client(region, profile, role).await?;
+    let resp = iam
+        .list_account_aliases()
+        .send()
+        .await
+        .context("failed to list IAM account aliases")?;
+
+    if let Some(alias) = resp.account_aliases().first() {
+        return Ok(alias.clone());
+    }
+
+    let sts = aws_client::make_sts_client(region, profile, role).await?;
+    let identity = sts
+        .get_caller_identity()
+        .send()
+        .await
+        .context("failed to get caller identity")?;
+
+    identity
+        .account()
+        .map(str::to_string)
+        .context("caller identity has no account id")
+}
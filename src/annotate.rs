@@ -1,60 +1,533 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use aws_sdk_cloudwatch::types::DashboardEntry;
 use aws_sdk_cloudwatch::Client;
-use log::{info, warn};
+use log::{error, info, warn};
 use std::fs::{self, File};
 use std::io::prelude::*;
-use std::path::PathBuf;
-
-use chrono::Utc;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use clap::ValueEnum;
+use regex::Regex;
 use serde_json::{Map, Value};
 
-const EXPORT_DIR_ENV: &str = "CWNOTE_EXPORT_DIR";
-const WIDGET_TYPE_METRIC: &str = "metric";
-const JSON_KEY_PROPERTIES: &str = "properties";
-const JSON_KEY_TITLE: &str = "title";
-const JSON_KEY_TYPE: &str = "type";
-const JSON_KEY_ANNOTATIONS: &str = "annotations";
-const JSON_KEY_VERTICAL: &str = "vertical";
-const JSON_KEY_LABEL: &str = "label";
-const JSON_KEY_VALUE: &str = "value";
-const TS_FORMAT: &str = "%Y-%m-%d-%H-%M-%S";
-
-/// Controlls which widget we annotate.
+use crate::exclude::DashboardExclusions;
+use crate::glob::Glob;
+use crate::iac::IacGuard;
+use crate::list::{self, ListedAnnotation};
+use crate::pacing::{Pacer, RetryPolicy};
+use crate::policy::{DashboardPolicy, JSON_KEY_POLICY};
+use crate::progress::Progress;
+use crate::provenance::Provenance;
+use crate::remove::{self, Direction};
+use crate::selector::{selected_widgets, WidgetSelector};
+use crate::template::{self, TemplateVars};
+use crate::time_display::TimeDisplay;
+use crate::timezone::Timezone;
+use crate::{
+    backup, body_diff, body_size, duration, expect_change, interactive, interrupt, maintenance,
+    preview, title_tag, widget_support,
+};
+
+pub const EXPORT_DIR_ENV: &str = "CWNOTE_EXPORT_DIR";
+pub const WIDGET_TYPE_METRIC: &str = "metric";
+pub const WIDGET_TYPE_TEXT: &str = "text";
+pub const JSON_KEY_MARKDOWN: &str = "markdown";
+pub const JSON_KEY_PROPERTIES: &str = "properties";
+pub const JSON_KEY_TITLE: &str = "title";
+pub const JSON_KEY_METRICS: &str = "metrics";
+pub const JSON_KEY_TYPE: &str = "type";
+pub const JSON_KEY_ANNOTATIONS: &str = "annotations";
+pub const JSON_KEY_VERTICAL: &str = "vertical";
+pub const JSON_KEY_HORIZONTAL: &str = "horizontal";
+pub const JSON_KEY_LABEL: &str = "label";
+pub const JSON_KEY_VALUE: &str = "value";
+pub const JSON_KEY_RUN_ID: &str = "cwnoteRunId";
+pub const JSON_KEY_REGION: &str = "region";
+const JSON_KEY_COLOR: &str = "color";
+pub const JSON_KEY_FILL: &str = "fill";
+const JSON_KEY_START: &str = "start";
+const JSON_KEY_END: &str = "end";
+const JSON_KEY_PERIOD_OVERRIDE: &str = "periodOverride";
+/// Timestamp prefix of exported dashboard files.
+pub const TS_FORMAT: &str = "%Y-%m-%d-%H-%M-%S";
+/// Ends the name of a `--dry-run=mutate-local` body, before `.json`, e.g.
+/// `2025-01-20-12-00-00-orders.dry-run.json`.
+pub const DRY_RUN_EXPORT_SUFFIX: &str = ".dry-run";
+// Future annotation times within this many seconds are treated as clock skew.
+const MAX_FUTURE_SKEW_SECS: i64 = 300;
+/// Vertical annotations per widget after which a graph is hard to read.
+pub const DEFAULT_ANNOTATION_BUDGET: usize = 20;
+
+/// Optional look of an annotation on the widgets of one route.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnnotationStyle {
+    /// Hex color, e.g. `#ff7f0e`.
+    pub color: Option<String>,
+    /// Shade the graph `before` or `after` the line.
+    pub fill: Option<String>,
+}
+
+impl AnnotationStyle {
+    /// Copy of `ann` (object or band) with the style set on its head.
+    pub fn apply(&self, ann: &Value) -> Value {
+        let mut styled = ann.clone();
+        let head = match styled {
+            Value::Array(ref mut items) => items.first_mut(),
+            ref mut object => Some(object),
+        };
+        if let Some(head) = head.and_then(|h| h.as_object_mut()) {
+            if let Some(ref color) = self.color {
+                head.insert(JSON_KEY_COLOR.to_string(), Value::String(color.clone()));
+            }
+            if let Some(ref fill) = self.fill {
+                head.insert(JSON_KEY_FILL.to_string(), Value::String(fill.clone()));
+            }
+        }
+        styled
+    }
+}
+
+/// Which side of a vertical line CloudWatch shades.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum VerticalFill {
+    Before,
+    After,
+    /// No shading (CloudWatch's default).
+    None,
+}
+
+impl VerticalFill {
+    /// Value of the annotation's `fill` key, if it gets one.
+    pub fn json_value(self) -> Option<&'static str> {
+        match self {
+            VerticalFill::Before => Some("before"),
+            VerticalFill::After => Some("after"),
+            VerticalFill::None => None,
+        }
+    }
+}
+
+/// Widgets to annotate together with how the annotation looks there.
+#[derive(Debug, Clone, Default)]
+pub struct Route {
+    pub selector: WidgetSelector,
+    pub style: AnnotationStyle,
+}
+
+/// How much of a run `--dry-run` still does; none of them writes a dashboard.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DryRunLevel {
+    /// Don't call GetDashboard either: work on the latest export of each
+    /// dashboard (see `CWNOTE_EXPORT_DIR`).
+    ReadOnly,
+    /// Fetch and compute, then write the annotated body to an export file.
+    MutateLocal,
+    /// Fetch and compute, and log what would change.
+    #[default]
+    Full,
+}
+
+/// When an annotation counts as already on a widget, so a re-run doesn't
+/// add it twice (`--skip-if-exists`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SkipIfExists {
+    /// Only existing annotations within this of the new one's time count;
+    /// `None` matches on label and value alone.
+    pub tolerance: Option<Duration>,
+}
+
+impl SkipIfExists {
+    /// `true` if `vertical` already holds an annotation with the label and
+    /// value of `ann` (within the tolerance).
+    pub fn exists(&self, vertical: &[Value], ann: &Value) -> bool {
+        let Some(new) = annotation_head(ann) else {
+            return false;
+        };
+        let time = |head: &Map<String, Value>| {
+            head.get(JSON_KEY_VALUE)
+                .and_then(|v| v.as_str())
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        };
+        vertical.iter().filter_map(annotation_head).any(|old| {
+            old.get(JSON_KEY_LABEL) == new.get(JSON_KEY_LABEL)
+                && self
+                    .tolerance
+                    .is_none_or(|tolerance| match (time(old), time(new)) {
+                        (Some(old), Some(new)) => (old - new).abs() <= tolerance,
+                        _ => false,
+                    })
+        })
+    }
+}
+
+/// How a dashboard write behaves beyond the annotations themselves.
+#[derive(Debug, Clone)]
+pub struct AnnotateOptions {
+    /// Dry run: don't write anything, just log what would change.
+    pub dry_run: bool,
+    /// What a dry run still reads and writes locally.
+    pub dry_run_level: DryRunLevel,
+    /// New dashboard `start` (visible window) written together with the
+    /// annotations, e.g. `-PT3H`, so a fresh marker is actually in view.
+    pub set_start: Option<String>,
+    /// Warn about widgets carrying at least this many vertical annotations
+    /// (`0` disables the check).
+    pub annotation_budget: usize,
+    /// Warn when the written body is at least this many bytes (`0` disables
+    /// the check).
+    pub body_size_warning: usize,
+    /// Drop the annotations of the oldest cwnote runs when a write would not
+    /// fit the body size limit, as the `pruneOldest` policy does.
+    pub auto_prune: bool,
+    /// Treat a dashboard without widgets as an error instead of skipping it.
+    pub fail_on_empty_dashboard: bool,
+    /// In dry runs, draw the first selected widget's recent data with the
+    /// annotation position marked.
+    pub preview: bool,
+    /// Compare the first selected widget's metric before and after the
+    /// annotation time and report whether it shifted.
+    pub expect_change: bool,
+    /// Tag appended to the titles of annotated widgets, e.g. `[v1.2.3]`.
+    pub tag_title: Option<String>,
+    /// How times are shown in dry-run output.
+    pub time_display: TimeDisplay,
+    /// Per-widget-group variants (from `--manifest`). When set, these replace
+    /// the single selector and all of them are applied in the same write.
+    pub routes: Vec<Route>,
+    /// Skip dashboards managed by infrastructure as code (unless allowed).
+    pub iac_guard: IacGuard,
+    /// Hex color of the annotations, unless a route sets its own.
+    pub color: Option<String>,
+    /// Shading of the annotations, unless a route sets its own.
+    pub fill: Option<VerticalFill>,
+    /// Creator, scope, ttl and link written onto every annotation.
+    pub provenance: Provenance,
+    /// Dashboards skipped although the target (suffix, regex, glob, list) picks them.
+    pub exclusions: DashboardExclusions,
+    /// How throttled GetDashboard/PutDashboard calls are retried.
+    pub retry: RetryPolicy,
+    /// How many dashboards of a multi-dashboard run are updated at once.
+    pub concurrency: usize,
+    /// Log running totals after every dashboard of a multi-dashboard run.
+    pub progress: bool,
+    /// Ask before updating the dashboards a pattern matched (interactive
+    /// runs without `--yes`).
+    pub confirm: bool,
+    /// Leave out annotations a widget already has, e.g. when a pipeline is
+    /// re-run.
+    pub skip_if_exists: Option<SkipIfExists>,
+    /// After annotating, trim each annotated widget's vertical annotations
+    /// with the new annotations' labels down to the newest this many.
+    pub keep_last: Option<usize>,
+    /// Sort the vertical annotations of annotated widgets by time instead of
+    /// leaving the new ones appended at the end.
+    pub sort: bool,
+    /// After the write, read the dashboard back and fail unless every
+    /// annotated widget carries the new annotations.
+    pub verify: bool,
+    /// Directory the original body is saved to before every write.
+    pub backup_dir: Option<PathBuf>,
+    /// S3 location the original body is uploaded to before every write.
+    pub backup_s3: Option<backup::S3Backup>,
+}
+
+impl Default for AnnotateOptions {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            dry_run_level: DryRunLevel::default(),
+            set_start: None,
+            annotation_budget: DEFAULT_ANNOTATION_BUDGET,
+            body_size_warning: body_size::DEFAULT_WARN_BYTES,
+            auto_prune: false,
+            fail_on_empty_dashboard: false,
+            preview: false,
+            expect_change: false,
+            tag_title: None,
+            time_display: TimeDisplay::default(),
+            routes: Vec::new(),
+            iac_guard: IacGuard::default(),
+            color: None,
+            fill: None,
+            provenance: Provenance::default(),
+            exclusions: DashboardExclusions::default(),
+            retry: RetryPolicy::default(),
+            concurrency: 1,
+            progress: false,
+            confirm: false,
+            skip_if_exists: None,
+            keep_last: None,
+            sort: false,
+            verify: false,
+            backup_dir: None,
+            backup_s3: None,
+        }
+    }
+}
+
+/// Check a dashboard `start` value: a negative ISO8601 duration relative to
+/// now (e.g. `-PT3H`, `-P7D`) or an absolute RFC3339 timestamp.
+pub fn validate_start(start: &str) -> Result<()> {
+    let relative = start
+        .strip_prefix("-P")
+        .filter(|rest| !rest.is_empty())
+        .map(|rest| {
+            rest.chars()
+                .all(|c| c.is_ascii_digit() || "YMWDTHS.".contains(c))
+        })
+        .unwrap_or(false);
+
+    if relative || DateTime::parse_from_rfc3339(start).is_ok() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "--set-start '{start}' must be a negative ISO8601 duration (e.g. -PT3H) or an RFC3339 timestamp"
+        ))
+    }
+}
+
+/// Check an explicit annotation time (RFC3339, naive in `zone`, epoch
+/// seconds/milliseconds, or relative like `-15m`) against `now`.
+///
+/// With `clamp_to_now`, times slightly ahead of `now` (clock skew) are clamped
+/// to `now` and anything further in the future is rejected unless
+/// `allow_future` is set, since such markers are usually timezone mistakes and
+/// never show up in the default view. Without it, future times only warn.
+pub fn clamp_time(
+    time: &str,
+    now: DateTime<Utc>,
+    zone: &Timezone,
+    clamp_to_now: bool,
+    allow_future: bool,
+) -> Result<String> {
+    let parsed = duration::parse_time_expr(time, now, zone).context("invalid --time")?;
+    // RFC3339 input is written as given; naive, epoch and relative times are resolved
+    // so the dashboard only ever gets RFC3339.
+    let resolved = if DateTime::parse_from_rfc3339(time).is_ok() {
+        time.to_string()
+    } else {
+        parsed.to_rfc3339()
+    };
+
+    if parsed <= now || allow_future || !clamp_to_now {
+        if parsed > now {
+            warn!("--time {time} is in the future; the marker won't be visible until then");
+        }
+        return Ok(resolved);
+    }
+
+    if parsed - now <= chrono::Duration::seconds(MAX_FUTURE_SKEW_SECS) {
+        return Ok(now.to_rfc3339());
+    }
+
+    Err(anyhow!(
+        "--time {time} is {} minute(s) in the future (check the timezone, or pass --allow-future)",
+        (parsed - now).num_minutes()
+    ))
+}
+
+/// End of a band starting at `start` (RFC3339): `end_time` as given, or
+/// `start + duration`. The end has to come after the start.
+pub fn band_end(
+    start: &str,
+    end_time: Option<&str>,
+    duration: Option<chrono::Duration>,
+) -> Result<Option<String>> {
+    let start_parsed = DateTime::parse_from_rfc3339(start)
+        .with_context(|| format!("band start '{start}' is not RFC3339"))?;
+
+    let end = match (end_time, duration) {
+        (Some(end), None) => DateTime::parse_from_rfc3339(end)
+            .with_context(|| format!("--end-time '{end}' is not RFC3339"))?,
+        (None, Some(duration)) => start_parsed + duration,
+        (None, None) => return Ok(None),
+        (Some(_), Some(_)) => {
+            return Err(anyhow!(
+                "Please specify either --end-time OR --duration, not both"
+            ))
+        }
+    };
+
+    if end <= start_parsed {
+        return Err(anyhow!(
+            "band end {} is not after its start {start}",
+            end.to_rfc3339()
+        ));
+    }
+    Ok(Some(end.to_rfc3339()))
+}
+
+/// Describe the dashboard's visible time window settings, e.g. for dry-run output.
+fn describe_view(body: &Value) -> String {
+    let setting = |key: &str| {
+        body.get(key)
+            .and_then(|v| v.as_str())
+            .unwrap_or("<unset>")
+            .to_string()
+    };
+    format!(
+        "start={}, end={}, periodOverride={}",
+        setting(JSON_KEY_START),
+        setting(JSON_KEY_END),
+        setting(JSON_KEY_PERIOD_OVERRIDE)
+    )
+}
+
+/// Returns `true` if the body has a non-empty `widgets` array. A missing or
+/// `null` array is how CloudWatch stores a freshly created, empty dashboard.
+pub fn has_widgets(body: &Value) -> bool {
+    body.get("widgets")
+        .and_then(|w| w.as_array())
+        .is_some_and(|w| !w.is_empty())
+}
+
+/// Title of a widget, or `<untitled>`.
+pub fn widget_title(widget: &Value) -> String {
+    widget
+        .get(JSON_KEY_PROPERTIES)
+        .and_then(|p| p.get(JSON_KEY_TITLE))
+        .and_then(|t| t.as_str())
+        .unwrap_or("<untitled>")
+        .to_string()
+}
+
+/// Widgets (by index) with at least `budget` vertical annotations, as
+/// `(title, count)`. Untitled widgets are reported as `<untitled>`.
+pub fn crowded_widgets(body: &Value, budget: usize, indices: &[usize]) -> Vec<(String, usize)> {
+    if budget == 0 {
+        return Vec::new();
+    }
+    let widgets = body
+        .get("widgets")
+        .and_then(|w| w.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    indices
+        .iter()
+        .filter_map(|i| {
+            let props = widgets.get(*i)?.get(JSON_KEY_PROPERTIES)?;
+            let count = props
+                .get(JSON_KEY_ANNOTATIONS)
+                .and_then(|a| a.get(JSON_KEY_VERTICAL))
+                .and_then(|v| v.as_array())
+                .map_or(0, Vec::len);
+            let title = props
+                .get(JSON_KEY_TITLE)
+                .and_then(|t| t.as_str())
+                .unwrap_or("<untitled>");
+            (count >= budget).then(|| (title.to_string(), count))
+        })
+        .collect()
+}
+
+/// What to write onto the selected widgets.
 #[derive(Debug, Clone)]
-pub struct WidgetSelector {
-    pub title_contains: Option<String>,
+pub struct AnnotationSpec {
+    pub label: String,
+    pub value: String,
+    /// Annotation time (RFC3339). `None` means "now".
+    pub time: Option<String>,
+    /// End of a band annotation (RFC3339). `None` draws a single line.
+    pub end_time: Option<String>,
+    /// Id of the cwnote run creating the annotation, used by `remove --run-id`.
+    pub run_id: String,
 }
 
-impl WidgetSelector {
-    /// Returns `true` if the given widget matches the selector's criteria.
+impl AnnotationSpec {
+    /// Annotation time (and band end) for humans, e.g. `now` or
+    /// `2025-01-20T01:00:00+00:00 until 2025-01-20T02:30:00+00:00`.
+    pub fn describe_time(&self, display: TimeDisplay) -> String {
+        let start = self
+            .time
+            .as_deref()
+            .map_or_else(|| "now".to_string(), |t| display.format_rfc3339(t));
+        match self.end_time {
+            Some(ref end) => format!("{start} until {}", display.format_rfc3339(end)),
+            None => start,
+        }
+    }
+
+    /// Build the (first) vertical annotation object as CloudWatch expects it.
     ///
-    /// Currently this selector supports filtering by widget title. If
-    /// `title_contains` is set, the widget's `properties.title` field must
-    /// contain the specified substring. If the widget has no title or the
-    /// substring does not match, the method returns `false`.
+    /// CloudWatch ignores unknown keys, so the run id travels along as
+    /// metadata without affecting how the annotation is rendered.
+    pub fn to_json(&self) -> Map<String, Value> {
+        let ts = match self.time {
+            Some(ref s) => s.clone(),
+            None => Utc::now().to_rfc3339(),
+        };
+
+        let mut ann_obj = Map::new();
+        ann_obj.insert(
+            JSON_KEY_LABEL.to_string(),
+            Value::String(format!("{}: {}", self.label, self.value)),
+        );
+        ann_obj.insert(JSON_KEY_VALUE.to_string(), Value::String(ts));
+        ann_obj.insert(
+            JSON_KEY_RUN_ID.to_string(),
+            Value::String(self.run_id.clone()),
+        );
+
+        // Optional: color, visible, etc.
+        // ann_obj.insert("color".into(), Value::String("#ff9900".into()));
+
+        ann_obj
+    }
+
+    /// Build the entry to push onto `annotations.vertical`.
     ///
-    /// If no title filter is configured, all widgets are considered a match.
-    pub fn matches(&self, widget_obj: &Map<String, Value>) -> bool {
-        // If we have a title filter, go check it.
-        if let Some(ref title_filter) = self.title_contains {
-            let title = widget_obj
-                .get(JSON_KEY_PROPERTIES)
-                .and_then(|p| p.get(JSON_KEY_TITLE))
-                .and_then(|t| t.as_str())
-                .unwrap_or("");
-            if !title.contains(title_filter) {
-                return false;
+    /// CloudWatch draws a band when the entry is an array of two annotation
+    /// objects; the second one only carries the end time.
+    pub fn to_annotation(&self) -> Value {
+        let head = Value::Object(self.to_json());
+        match self.end_time {
+            Some(ref end) => {
+                let mut tail = Map::new();
+                tail.insert(JSON_KEY_VALUE.to_string(), Value::String(end.clone()));
+                Value::Array(vec![head, Value::Object(tail)])
             }
+            None => head,
         }
-        true
     }
 }
 
-// Internal helper that saves the modified dashboard to file.
-fn save_to_file(updated_body: &str, dashboard_name: &str) -> Result<()> {
-    // Sanitize dashboard name e.g: strange+dashboard/chars -> strange-dashboard-chars
-    let sanitized_name: String = dashboard_name
+/// Returns the object holding label/value/metadata of an annotation entry,
+/// i.e. the entry itself or the first object of a band.
+pub fn annotation_head(ann: &Value) -> Option<&Map<String, Value>> {
+    match ann {
+        Value::Object(obj) => Some(obj),
+        Value::Array(band) => band.first().and_then(|a| a.as_object()),
+        _ => None,
+    }
+}
+
+/// Mutable counterpart of [`annotation_head`].
+pub fn annotation_head_mut(ann: &mut Value) -> Option<&mut Map<String, Value>> {
+    match ann {
+        Value::Object(obj) => Some(obj),
+        Value::Array(band) => band.first_mut().and_then(|a| a.as_object_mut()),
+        _ => None,
+    }
+}
+
+/// Directory set by `CWNOTE_EXPORT_DIR`; without it exports go to the
+/// current directory.
+pub fn export_dir() -> Option<PathBuf> {
+    std::env::var(EXPORT_DIR_ENV)
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .map(PathBuf::from)
+}
+
+/// Dashboard name as used in export file names, e.g.
+/// `strange+dashboard/chars` -> `strange-dashboard-chars`.
+pub fn export_name(dashboard_name: &str) -> String {
+    dashboard_name
         .chars()
         .map(|c| {
             let c = c.to_ascii_lowercase();
@@ -64,16 +537,28 @@ fn save_to_file(updated_body: &str, dashboard_name: &str) -> Result<()> {
                 '-'
             }
         })
-        .collect();
+        .collect()
+}
 
+/// Saves the modified dashboard to file and returns its path.
+pub fn save_to_file(updated_body: &str, dashboard_name: &str) -> Result<PathBuf> {
+    write_export(updated_body, dashboard_name, "")
+}
+
+/// Saves the body a `--dry-run=mutate-local` computed and returns its path.
+/// The file is marked with [`DRY_RUN_EXPORT_SUFFIX`], so a read-only dry run
+/// never takes it for what the dashboard looks like.
+pub fn save_dry_run_to_file(updated_body: &str, dashboard_name: &str) -> Result<PathBuf> {
+    write_export(updated_body, dashboard_name, DRY_RUN_EXPORT_SUFFIX)
+}
+
+fn write_export(updated_body: &str, dashboard_name: &str, suffix: &str) -> Result<PathBuf> {
+    let sanitized_name = export_name(dashboard_name);
     let ts = Utc::now().format(TS_FORMAT).to_string();
-    let fname = format!("{}-{}.json", ts, sanitized_name);
-    let export_dir = std::env::var(EXPORT_DIR_ENV)
-        .ok()
-        .filter(|v| !v.trim().is_empty());
-    let path = if let Some(dir) = export_dir {
+    let fname = format!("{}-{}{}.json", ts, sanitized_name, suffix);
+    let path = if let Some(dir) = export_dir() {
         fs::create_dir_all(&dir).expect("Could not create export directory!");
-        PathBuf::from(dir).join(fname)
+        dir.join(fname)
     } else {
         PathBuf::from(fname)
     };
@@ -82,362 +567,2382 @@ fn save_to_file(updated_body: &str, dashboard_name: &str) -> Result<()> {
 
     file.write_all(updated_body.as_bytes())
         .expect("Cannot write file!");
-    Ok(())
+    Ok(path)
+}
+
+/// Widgets each route claims, in widget order. Routes are tried in order and
+/// the first one selecting a widget wins, so no widget gets the same
+/// annotation twice. Widgets that can't show vertical annotations (e.g. bar
+/// charts) are skipped.
+pub fn route_widgets<'a>(
+    widgets: &[Value],
+    routes: &'a [Route],
+) -> Result<Vec<(usize, &'a Route)>> {
+    let mut claimed: Vec<Option<&Route>> = vec![None; widgets.len()];
+    for route in routes {
+        for i in selected_widgets(widgets, &route.selector)? {
+            claimed[i].get_or_insert(route);
+        }
+    }
+    for (i, route) in claimed.iter_mut().enumerate() {
+        let Some(widget) = widgets[i].as_object() else {
+            continue;
+        };
+        if route.is_some() && !widget_support::supports(widget, Direction::Vertical) {
+            info!(
+                "'{}': {} widgets show no vertical annotations, skipped",
+                widget_title(&widgets[i]),
+                widget_support::describe(widget)
+            );
+            *route = None;
+        }
+    }
+
+    Ok(claimed
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, route)| route.map(|r| (i, r)))
+        .collect())
 }
 
-/// Internal helper: apply a single annotation object to all matching widgets.
-/// Returns the number of widgets annotated.
-fn apply_annotation_to_body(
+/// Widgets [`apply_annotations`] annotated and those it left out because
+/// they would not fit, by widget index.
+#[derive(Debug, Default, PartialEq)]
+struct Applied {
+    annotated: Vec<usize>,
+    left_out: Vec<usize>,
+}
+
+/// Push every annotation onto each targeted widget, styled by its route,
+/// while the serialized body stays within `max_bytes`. Targets are handled in
+/// order; a widget that would not fit is left out and the next ones are still
+/// tried. With `tag`, each widget is measured with its title tagged, so
+/// tagging afterwards keeps the body within the limit. With `skip`,
+/// annotations a widget already has are left out. `{widget_region}` in the
+/// labels is rendered per widget (see [`widget_annotations`]) before either.
+fn apply_annotations(
     body: &mut Value,
-    ann_obj: &Map<String, Value>,
-    selector: &WidgetSelector,
-) -> usize {
-    let mut widgets_annotated = 0usize;
-
-    if let Some(widgets) = body.get_mut("widgets").and_then(|w| w.as_array_mut()) {
-        for widget in widgets.iter_mut() {
-            if let Some(widget_obj) = widget.as_object_mut() {
-                // Only metric widgets.
-                let is_metric = widget_obj.get(JSON_KEY_TYPE).and_then(|t| t.as_str())
-                    == Some(WIDGET_TYPE_METRIC);
-                if !is_metric {
-                    continue;
-                }
+    anns: &[Value],
+    targets: &[(usize, &Route)],
+    max_bytes: usize,
+    skip: Option<SkipIfExists>,
+    tag: Option<&str>,
+    default_region: &str,
+) -> Applied {
+    let mut applied = Applied::default();
+    let mut size = body_size::json_len(body);
+    let Some(widgets) = body.get_mut("widgets").and_then(|w| w.as_array_mut()) else {
+        return applied;
+    };
 
-                // Apply selector (e.g. title contains substring).
-                if !selector.matches(widget_obj) {
-                    continue;
-                }
+    for (i, route) in targets {
+        let mut widget = widgets[*i].clone();
+        let anns = widget_annotations(anns, &widget, default_region);
+        let Some(widget_obj) = widget.as_object_mut() else {
+            continue;
+        };
+        match annotations_array(widget_obj, JSON_KEY_VERTICAL) {
+            Some(vertical_arr) => {
+                let fresh: Vec<Value> = anns
+                    .iter()
+                    .filter(|ann| !skip.is_some_and(|s| s.exists(vertical_arr, ann)))
+                    .map(|ann| route.style.apply(ann))
+                    .collect();
+                vertical_arr.extend(fresh);
+            }
+            None => {
+                warn!(
+                    "'{}': properties/annotations have an unexpected shape, leaving the widget alone",
+                    widget_title(&widgets[*i])
+                );
+                continue;
+            }
+        }
 
-                let props_val = widget_obj
-                    .entry(JSON_KEY_PROPERTIES)
-                    .or_insert_with(|| Value::Object(Map::new()));
-                let props_obj = props_val
-                    .as_object_mut()
-                    .expect("properties should be object");
-
-                let anns_val = props_obj
-                    .entry(JSON_KEY_ANNOTATIONS)
-                    .or_insert_with(|| Value::Object(Map::new()));
-                let anns_obj = anns_val
-                    .as_object_mut()
-                    .expect("annotations should be object");
-
-                let vertical_val = anns_obj
-                    .entry(JSON_KEY_VERTICAL)
-                    .or_insert_with(|| Value::Array(Vec::new()));
-                let vertical_arr = vertical_val
-                    .as_array_mut()
-                    .expect("vertical should be array");
-
-                vertical_arr.push(Value::Object(ann_obj.clone()));
-                widgets_annotated += 1;
+        // Only this widget's part of the body changes.
+        let widget_len = match tag {
+            Some(tag) => {
+                let mut tagged = widget.clone();
+                if let Some(tagged_obj) = tagged.as_object_mut() {
+                    title_tag::tag_title(tagged_obj, tag);
+                }
+                body_size::json_len(&tagged)
             }
+            None => body_size::json_len(&widget),
+        };
+        let grown = size + widget_len - body_size::json_len(&widgets[*i]);
+        if grown > max_bytes {
+            applied.left_out.push(*i);
+            continue;
         }
+        size = grown;
+        widgets[*i] = widget;
+        applied.annotated.push(*i);
     }
-
-    widgets_annotated
+    applied
 }
 
-/// Annotate a single dashboard by name.
-pub async fn annotate_single_dashboard(
-    client: &Client,
-    dashboard_name: &str,
-    label: &str,
-    value: &str,
-    time_override: Option<&str>,
-    dry_run: bool,
-    selector: &WidgetSelector,
-) -> Result<()> {
-    // 1) Get current dashboard.
-    let resp = client
-        .get_dashboard()
-        .dashboard_name(dashboard_name)
-        .send()
-        .await
-        .with_context(|| format!("failed to get dashboard {dashboard_name}"))?;
+/// `{widget_region}` of a widget: its own region, else `default_region`.
+fn widget_region_vars(props: &Map<String, Value>, default_region: &str) -> TemplateVars {
+    let mut vars = TemplateVars::new();
+    vars.insert(
+        template::VAR_WIDGET_REGION,
+        props
+            .get(JSON_KEY_REGION)
+            .and_then(|r| r.as_str())
+            .unwrap_or(default_region)
+            .to_string(),
+    );
+    vars
+}
 
-    let body_str = resp
-        .dashboard_body()
-        .with_context(|| format!("dashboard {dashboard_name} has no body"))?;
+/// `text` as [`widget_annotations`] renders it on the widgets at `indices`,
+/// once per distinct rendering, for describing the change (e.g. in a dry run).
+/// Text without `{widget_region}` is returned as is.
+fn render_per_widget(
+    body: &Value,
+    indices: &[usize],
+    text: &str,
+    default_region: &str,
+) -> Vec<String> {
+    if !template::uses(text, template::VAR_WIDGET_REGION) {
+        return vec![text.to_string()];
+    }
+    let empty = Map::new();
+    let mut rendered: Vec<String> = Vec::new();
+    for i in indices {
+        let props = body["widgets"][*i]
+            .get(JSON_KEY_PROPERTIES)
+            .and_then(|p| p.as_object())
+            .unwrap_or(&empty);
+        let text = template::render(text, &widget_region_vars(props, default_region));
+        if !rendered.contains(&text) {
+            rendered.push(text);
+        }
+    }
+    rendered
+}
 
-    let mut body: Value =
-        serde_json::from_str(body_str).context("failed to parse dashboard body JSON")?;
+/// `anns` as they land on `widget`: `{widget_region}` in their labels
+/// rendered with the widget's own region.
+fn widget_annotations(anns: &[Value], widget: &Value, default_region: &str) -> Vec<Value> {
+    let empty = Map::new();
+    let props = widget
+        .get(JSON_KEY_PROPERTIES)
+        .and_then(|p| p.as_object())
+        .unwrap_or(&empty);
+    let vars = widget_region_vars(props, default_region);
+    anns.iter()
+        .map(|ann| {
+            let mut ann = ann.clone();
+            if let Some(Value::String(label)) =
+                annotation_head_mut(&mut ann).and_then(|head| head.get_mut(JSON_KEY_LABEL))
+            {
+                if template::uses(label, template::VAR_WIDGET_REGION) {
+                    *label = template::render(label, &vars);
+                }
+            }
+            ann
+        })
+        .collect()
+}
 
-    // 2) Determine annotation timestamp.
-    let ts = match time_override {
-        Some(s) => s.to_string(),
-        None => Utc::now().to_rfc3339(),
+/// Trim the vertical annotations labelled with one of `labels` on the widgets
+/// at `indices` down to the newest `keep` per label (by time; array order
+/// breaks ties). Returns the widget index and the annotation of every entry
+/// dropped.
+fn keep_last(
+    body: &mut Value,
+    indices: &[usize],
+    labels: &[&str],
+    keep: usize,
+) -> Vec<(usize, Value)> {
+    let mut dropped = Vec::new();
+    let Some(widgets) = body.get_mut("widgets").and_then(|w| w.as_array_mut()) else {
+        return dropped;
     };
 
-    // 3) Build annotation object
-    let mut ann_obj = Map::new();
-    ann_obj.insert(
-        JSON_KEY_LABEL.to_string(),
-        Value::String(format!("{label}: {value}")),
-    );
-    ann_obj.insert(JSON_KEY_VALUE.to_string(), Value::String(ts));
-
-    // Optional: color, visible, etc.
-    // ann_obj.insert("color".into(), Value::String("#ff9900".into()));
-
-    // 4) Insert annotation into selected metric widgets.
-    let widgets_annotated = apply_annotation_to_body(&mut body, &ann_obj, selector);
+    for &i in indices {
+        let Some(vertical) = widgets[i]
+            .get_mut(JSON_KEY_PROPERTIES)
+            .and_then(|p| p.get_mut(JSON_KEY_ANNOTATIONS))
+            .and_then(|a| a.get_mut(JSON_KEY_VERTICAL))
+            .and_then(|v| v.as_array_mut())
+        else {
+            continue;
+        };
+        let mut drop = vec![false; vertical.len()];
+        for label in labels {
+            let mut entries: Vec<(Option<DateTime<FixedOffset>>, usize)> = vertical
+                .iter()
+                .enumerate()
+                .filter_map(|(j, ann)| {
+                    let head = annotation_head(ann)?;
+                    let ann_label = head.get(JSON_KEY_LABEL)?.as_str()?;
+                    remove::label_matches(ann_label, label).then(|| {
+                        let time = head
+                            .get(JSON_KEY_VALUE)
+                            .and_then(|v| v.as_str())
+                            .and_then(|t| DateTime::parse_from_rfc3339(t).ok());
+                        (time, j)
+                    })
+                })
+                .collect();
+            // Newest first; entries without a time count as oldest.
+            entries.sort_by(|a, b| b.cmp(a));
+            for &(_, j) in entries.iter().skip(keep) {
+                drop[j] = true;
+            }
+        }
 
-    if widgets_annotated == 0 {
-        info!("{dashboard_name}: No matching metric widgets found (nothing to annotate)");
-        return Ok(());
+        let mut j = 0;
+        vertical.retain(|ann| {
+            j += 1;
+            if drop[j - 1] {
+                dropped.push((i, ann.clone()));
+            }
+            !drop[j - 1]
+        });
     }
+    dropped
+}
 
-    if dry_run {
-        info! {
-            target: "dry-run",
-            "{}: would annotate {} metric widget(s) with value: {}.",
-            dashboard_name, widgets_annotated, value
+/// Sort the vertical annotations of the widgets at `indices` by their (start)
+/// time, oldest first. The sort is stable and entries without a parseable
+/// time go last. Returns how many widgets changed order.
+fn sort_vertical(body: &mut Value, indices: &[usize]) -> usize {
+    let Some(widgets) = body.get_mut("widgets").and_then(|w| w.as_array_mut()) else {
+        return 0;
+    };
+
+    let mut reordered = 0;
+    for &i in indices {
+        let Some(vertical) = widgets[i]
+            .get_mut(JSON_KEY_PROPERTIES)
+            .and_then(|p| p.get_mut(JSON_KEY_ANNOTATIONS))
+            .and_then(|a| a.get_mut(JSON_KEY_VERTICAL))
+            .and_then(|v| v.as_array_mut())
+        else {
+            continue;
         };
-        info! {
-        target: "dry-run",
-        "Annotate object: {:?}.", ann_obj};
-        return Ok(());
+        let time = |ann: &Value| {
+            annotation_head(ann)
+                .and_then(|head| head.get(JSON_KEY_VALUE))
+                .and_then(|v| v.as_str())
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        };
+        let before = vertical.clone();
+        vertical.sort_by_key(|ann| {
+            let time = time(ann);
+            (time.is_none(), time)
+        });
+        if *vertical != before {
+            reordered += 1;
+        }
     }
+    reordered
+}
 
-    // 5) Serialize back and put dashboard.
-    let updated_body =
-        serde_json::to_string(&body).context("failed to serialize updated dashboard body")?;
+/// Titles of the widgets at `indices` whose vertical annotations in `fetched`
+/// lack one of `run_id`'s annotations in `written`.
+fn unverified_widgets(
+    written: &Value,
+    fetched: &Value,
+    indices: &[usize],
+    run_id: &str,
+) -> Vec<String> {
+    let vertical = |body: &Value, i: usize| -> Vec<Value> {
+        body.get("widgets")
+            .and_then(|w| w.get(i))
+            .and_then(|w| w.get(JSON_KEY_PROPERTIES))
+            .and_then(|p| p.get(JSON_KEY_ANNOTATIONS))
+            .and_then(|a| a.get(JSON_KEY_VERTICAL))
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default()
+    };
 
-    let result = client
-        .put_dashboard()
-        .dashboard_name(dashboard_name)
-        .dashboard_body(&updated_body)
-        .send()
-        .await;
+    indices
+        .iter()
+        .filter(|&&i| {
+            let present = vertical(fetched, i);
+            vertical(written, i)
+                .iter()
+                .filter(|ann| {
+                    annotation_head(ann)
+                        .and_then(|head| head.get(JSON_KEY_RUN_ID))
+                        .and_then(|r| r.as_str())
+                        == Some(run_id)
+                })
+                .any(|ann| !present.contains(ann))
+        })
+        .map(|&i| widget_title(&written["widgets"][i]))
+        .collect()
+}
 
-    match result {
-        Ok(_resp) => {
-            info!(
-                "Annotated {} metric widget(s) on dashboard '{}' with value '{}'",
-                widgets_annotated, dashboard_name, value
-            );
-            // 6) Save dashboard JSON to file.
-            if let Err(err) = save_to_file(&updated_body, dashboard_name) {
-                warn!("Export failed for '{dashboard_name}': {err}");
-            }
-        }
-        Err(err) => {
-            return Err(anyhow::anyhow!("Failed to put updated dashboard: {}", err));
-        }
-    }
+/// The widget's `properties.annotations.<key>` array (`vertical` or
+/// `horizontal`), created if missing.
+///
+/// Only missing containers are inserted (at the end, so nothing else moves);
+/// an existing value of the wrong type is never coerced and yields `None`.
+pub fn annotations_array<'a>(
+    widget_obj: &'a mut Map<String, Value>,
+    key: &str,
+) -> Option<&'a mut Vec<Value>> {
+    widget_obj
+        .entry(JSON_KEY_PROPERTIES)
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()?
+        .entry(JSON_KEY_ANNOTATIONS)
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()?
+        .entry(key)
+        .or_insert_with(|| Value::Array(Vec::new()))
+        .as_array_mut()
+}
 
-    Ok(())
+/// What happened on one dashboard, e.g. for reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DashboardOutcome {
+    pub dashboard: String,
+    pub status: OutcomeStatus,
+    /// How many widgets the selector (or manifest routes) picked.
+    pub widgets_matched: usize,
+    /// Titles of the annotated widgets.
+    pub widgets: Vec<String>,
+    /// Changes made (or planned in a dry run), e.g. `+ version: 1.9.0 at now`.
+    pub changes: Vec<String>,
+    /// Why the update failed, for `OutcomeStatus::Failed`.
+    pub error: Option<String>,
 }
 
-/// Annotate all dashboards whose name starts with the given suffix.
-pub async fn annotate_dashboards_by_suffix(
-    client: &Client,
-    suffix: &str,
-    label: &str,
-    value: &str,
-    time_override: Option<&str>,
-    dry_run: bool,
-    selector: &WidgetSelector,
-) -> Result<()> {
-    let dashboards = list_dashboards_with_suffix(client, suffix).await?;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutcomeStatus {
+    Annotated,
+    NoWidgets,
+    NoMatchingWidgets,
+    /// Every matching widget already had the annotations (`--skip-if-exists`).
+    AlreadyAnnotated,
+    /// Left alone because infrastructure as code owns the dashboard.
+    IacManaged,
+    /// The update failed; the run went on with the other dashboards.
+    Failed,
+}
 
-    if dashboards.is_empty() {
-        info!("No dashboards found with suffix '{}'", suffix);
-        return Ok(());
+impl OutcomeStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OutcomeStatus::Annotated => "annotated",
+            OutcomeStatus::NoWidgets => "no-widgets",
+            OutcomeStatus::NoMatchingWidgets => "no-matching-widgets",
+            OutcomeStatus::AlreadyAnnotated => "already-annotated",
+            OutcomeStatus::IacManaged => "iac-managed",
+            OutcomeStatus::Failed => "failed",
+        }
     }
+}
 
-    info!(
-        "{} dashboard(s) match suffix '{}':",
-        dashboards.len(),
-        suffix
-    );
-    for d in &dashboards {
-        info!("  - {}", d);
+impl DashboardOutcome {
+    fn skipped(dashboard: &str, status: OutcomeStatus) -> Self {
+        Self {
+            dashboard: dashboard.to_string(),
+            status,
+            widgets_matched: 0,
+            widgets: Vec::new(),
+            changes: Vec::new(),
+            error: None,
+        }
     }
 
-    for name in dashboards {
-        annotate_single_dashboard(
-            client,
-            &name,
-            label,
-            value,
-            time_override,
-            dry_run,
-            selector,
-        )
-        .await?;
+    fn failed(dashboard: &str, err: &anyhow::Error) -> Self {
+        Self {
+            error: Some(format!("{err:#}")),
+            ..Self::skipped(dashboard, OutcomeStatus::Failed)
+        }
     }
 
-    Ok(())
+    /// The outcome as a JSON object, for `--output json`.
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({
+            "dashboard": self.dashboard,
+            "status": self.status.as_str(),
+            "widgetsMatched": self.widgets_matched,
+            "widgetsAnnotated": self.widgets.len(),
+            "widgets": self.widgets,
+            "changes": self.changes,
+            "error": self.error,
+        })
+    }
+
+    /// One-line summary, e.g. `orders-prod: annotated 3 widget(s)`.
+    pub fn summary(&self) -> String {
+        let result = match self.status {
+            OutcomeStatus::Annotated => format!("annotated {} widget(s)", self.widgets.len()),
+            OutcomeStatus::NoWidgets => "skipped, no widgets".to_string(),
+            OutcomeStatus::NoMatchingWidgets => "skipped, no matching widgets".to_string(),
+            OutcomeStatus::AlreadyAnnotated => "skipped, already annotated".to_string(),
+            OutcomeStatus::IacManaged => "skipped, managed by infrastructure as code".to_string(),
+            OutcomeStatus::Failed => {
+                format!(
+                    "FAILED: {}",
+                    self.error.as_deref().unwrap_or("unknown error")
+                )
+            }
+        };
+        format!("{}: {result}", self.dashboard)
+    }
 }
 
-/// List dashboards whose names start with the given suffix.
-async fn list_dashboards_with_suffix(client: &Client, suffix: &str) -> Result<Vec<String>> {
-    let mut result = Vec::new();
-    let mut next_token: Option<String> = None;
+/// How many times a dashboard edited by someone else between our read and
+/// write is annotated again from its fresh body before giving up.
+pub const MAX_CONCURRENT_EDIT_RETRIES: usize = 3;
+
+/// The dashboard body changed between GetDashboard and PutDashboard, e.g.
+/// someone saved it in the console; writing would drop their change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ConcurrentEdit(String);
+
+impl std::fmt::Display for ConcurrentEdit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: dashboard changed while it was being annotated; not overwriting it",
+            self.0
+        )
+    }
+}
 
+impl std::error::Error for ConcurrentEdit {}
+
+/// Run `attempt` again while it fails with [`ConcurrentEdit`], up to
+/// [`MAX_CONCURRENT_EDIT_RETRIES`] times. Every attempt reads the dashboard
+/// afresh, so the annotations land on the other edit instead of replacing it.
+async fn retry_concurrent_edits<T, F, Fut>(dashboard_name: &str, mut attempt: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    let mut retries = 0;
     loop {
-        let mut req = client.list_dashboards();
-        if let Some(ref token) = next_token {
-            req = req.next_token(token);
-        }
-
-        let resp = req.send().await.context("failed to list dashboards")?;
-
-        let entries: &[DashboardEntry] = resp.dashboard_entries();
-
-        for entry in entries {
-            if let Some(name) = entry.dashboard_name() {
-                if name.ends_with(suffix) {
-                    result.push(name.to_string());
-                }
+        match attempt().await {
+            Err(err) if err.is::<ConcurrentEdit>() && retries < MAX_CONCURRENT_EDIT_RETRIES => {
+                retries += 1;
+                warn!(
+                    "{dashboard_name}: changed by someone else since it was read; \
+                     annotating the current body (retry {retries}/{MAX_CONCURRENT_EDIT_RETRIES})"
+                );
             }
+            result => return result,
         }
+    }
+}
 
-        match resp.next_token() {
-            Some(t) if !t.is_empty() => {
-                next_token = Some(t.to_string());
-            }
-            _ => break,
-        }
+/// Annotate a single dashboard by name, at the pace of `pacer`.
+///
+/// All `specs` are applied in one GetDashboard/PutDashboard round trip. If the
+/// dashboard changes before the write, or CloudWatch throttles, the round
+/// trip starts over; what follows a successful write (export, `--verify`,
+/// `--expect-change`) runs once.
+pub async fn annotate_single_dashboard(
+    client: &Client,
+    pacer: &mut Pacer,
+    dashboard_name: &str,
+    specs: &[AnnotationSpec],
+    options: &AnnotateOptions,
+    selector: &WidgetSelector,
+) -> Result<DashboardOutcome> {
+    let (outcome, written) = pacer
+        .run(|| {
+            retry_concurrent_edits(dashboard_name, || {
+                annotate_dashboard_once(client, dashboard_name, specs, options, selector)
+            })
+        })
+        .await?;
+    if let Some(written) = written {
+        after_write(client, dashboard_name, specs, options, &written).await?;
     }
+    Ok(outcome)
+}
 
-    Ok(result)
+/// A body PutDashboard accepted, for the steps after the write.
+struct Written {
+    body: Value,
+    serialized: String,
+    /// Indices of the annotated widgets.
+    annotated: Vec<usize>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
-    use std::fs;
+/// Export the written body, then `--verify` and `--expect-change` it.
+async fn after_write(
+    client: &Client,
+    dashboard_name: &str,
+    specs: &[AnnotationSpec],
+    options: &AnnotateOptions,
+    written: &Written,
+) -> Result<()> {
+    // 5) Save dashboard JSON to file.
+    if let Err(err) = save_to_file(&written.serialized, dashboard_name) {
+        warn!("Export failed for '{dashboard_name}': {err}");
+    }
+    if options.verify {
+        verify_written(
+            client,
+            dashboard_name,
+            &written.body,
+            specs,
+            &written.annotated,
+        )
+        .await?;
+    }
+    if options.expect_change {
+        report_change(
+            client,
+            dashboard_name,
+            &written.body,
+            specs,
+            &written.annotated,
+        )
+        .await;
+    }
+    Ok(())
+}
+
+async fn annotate_dashboard_once(
+    client: &Client,
+    dashboard_name: &str,
+    specs: &[AnnotationSpec],
+    options: &AnnotateOptions,
+    selector: &WidgetSelector,
+) -> Result<(DashboardOutcome, Option<Written>)> {
+    // 1) Get current dashboard.
+    let read_only = options.dry_run && options.dry_run_level == DryRunLevel::ReadOnly;
+    let (body_str, mut body) = if read_only {
+        let body_str = exported_body(dashboard_name)?;
+        let body =
+            serde_json::from_str(&body_str).context("failed to parse dashboard body JSON")?;
+        (body_str, body)
+    } else {
+        read_dashboard(client, dashboard_name).await?
+    };
+    // Kept for the diff a dry run shows.
+    let original = options.dry_run.then(|| body.clone());
+
+    let default_region = client
+        .config()
+        .region()
+        .map(|r| r.to_string())
+        .unwrap_or_default();
+    let AnnotatedBody {
+        outcome,
+        annotated,
+        ann_objs,
+        pruned,
+        trimmed,
+        view_before,
+    } = match annotate_body(
+        dashboard_name,
+        &mut body,
+        specs,
+        options,
+        selector,
+        &default_region,
+    )? {
+        BodyAnnotation::Annotated(annotated) => annotated,
+        BodyAnnotation::Skipped(outcome) => return Ok((outcome, None)),
+    };
+    let widgets_annotated = annotated.len();
+    let values = specs
+        .iter()
+        .map(|s| s.value.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if options.dry_run {
+        info! {
+            target: "dry-run",
+            "{}: would annotate {} metric widget(s) with value: {}.",
+            dashboard_name, widgets_annotated, values
+        };
+        info! {
+            target: "dry-run",
+            "{}: dashboard view {}.", dashboard_name, view_before
+        };
+        if options.set_start.is_some() {
+            info! {
+                target: "dry-run",
+                "{}: would change dashboard view to {}.",
+                dashboard_name, describe_view(&body)
+            };
+        }
+        for ann in &pruned {
+            info! {
+                target: "dry-run",
+                "{}: would prune '{}' at {} from widget '{}' (run {}).",
+                dashboard_name, ann.label, options.time_display.format_rfc3339(&ann.value),
+                ann.widget, ann.run_id.as_deref().unwrap_or_default()
+            };
+        }
+        for ann in &trimmed {
+            info! {
+                target: "dry-run",
+                "{}: would trim '{}' at {} from widget '{}' (keep last {}).",
+                dashboard_name, ann.label, options.time_display.format_rfc3339(&ann.value),
+                ann.widget, options.keep_last.unwrap_or_default()
+            };
+        }
+        for spec in specs {
+            for label in render_per_widget(&body, &annotated, &spec.label, &default_region) {
+                info! {
+                    target: "dry-run",
+                    "{}: would add '{}: {}' at {}.",
+                    dashboard_name, label, spec.value, spec.describe_time(options.time_display)
+                };
+            }
+        }
+        for ann_obj in &ann_objs {
+            let label = ann_obj
+                .get(JSON_KEY_LABEL)
+                .and_then(|l| l.as_str())
+                .unwrap_or_default();
+            for label in render_per_widget(&body, &annotated, label, &default_region) {
+                let mut ann_obj = ann_obj.clone();
+                ann_obj[JSON_KEY_LABEL] = Value::String(label);
+                info! {
+                target: "dry-run",
+                "Annotate object: {:?}.", ann_obj};
+            }
+        }
+        if let Some(ref original) = original {
+            info! {
+                target: "dry-run",
+                "{}: dashboard body diff:\n{}",
+                dashboard_name, body_diff::diff(original, &body)
+            };
+        }
+        if options.dry_run_level == DryRunLevel::MutateLocal {
+            let updated_body = serde_json::to_string(&body)
+                .context("failed to serialize updated dashboard body")?;
+            let path = save_dry_run_to_file(&updated_body, dashboard_name)?;
+            info! {
+                target: "dry-run",
+                "{}: wrote the annotated body to {}.", dashboard_name, path.display()
+            };
+        }
+        if read_only && (options.preview || options.expect_change) {
+            info!("{dashboard_name}: read-only dry run, skipping metric reads");
+        } else {
+            if options.preview {
+                show_preview(client, dashboard_name, &body, specs, &annotated).await;
+            }
+            if options.expect_change {
+                report_change(client, dashboard_name, &body, specs, &annotated).await;
+            }
+        }
+        return Ok((outcome, None));
+    }
+
+    // 4) Check nobody saved the dashboard in the meantime, then serialize back
+    // and put dashboard. (CloudWatch has no conditional put, so a save in the
+    // last moment can still slip through.)
+    let current = client
+        .get_dashboard()
+        .dashboard_name(dashboard_name)
+        .send()
+        .await
+        .with_context(|| format!("failed to get dashboard {dashboard_name}"))?;
+    if current.dashboard_body() != Some(body_str.as_str()) {
+        return Err(ConcurrentEdit(dashboard_name.to_string()).into());
+    }
+    if let Some(ref dir) = options.backup_dir {
+        let path = backup::save_local(dir, dashboard_name, &body_str)
+            .with_context(|| format!("{dashboard_name}: backup failed, not writing"))?;
+        info!(
+            "{dashboard_name}: saved the original body to {}",
+            path.display()
+        );
+    }
+    if let Some(ref s3) = options.backup_s3 {
+        let run_id = specs.first().map(|s| s.run_id.as_str()).unwrap_or_default();
+        let uri = s3
+            .save(dashboard_name, run_id, &body_str)
+            .await
+            .with_context(|| format!("{dashboard_name}: backup failed, not writing"))?;
+        info!("{dashboard_name}: uploaded the original body to {uri}");
+    }
+
+    let updated_body = write_dashboard(client, dashboard_name, &body).await?;
+    info!(
+        "Annotated {} metric widget(s) on dashboard '{}' with value '{}'",
+        widgets_annotated, dashboard_name, values
+    );
+
+    Ok((
+        outcome,
+        Some(Written {
+            body,
+            serialized: updated_body,
+            annotated,
+        }),
+    ))
+}
+
+/// A dashboard body annotated in memory, before it is written anywhere.
+struct AnnotatedBody {
+    outcome: DashboardOutcome,
+    /// Indices of the annotated widgets.
+    annotated: Vec<usize>,
+    ann_objs: Vec<Value>,
+    pruned: Vec<ListedAnnotation>,
+    trimmed: Vec<ListedAnnotation>,
+    view_before: String,
+}
+
+enum BodyAnnotation {
+    Annotated(AnnotatedBody),
+    /// Nothing to write, e.g. no matching widgets.
+    Skipped(DashboardOutcome),
+}
+
+/// Whether a dashboard may be annotated at all.
+pub enum Admission {
+    /// Go ahead, within the owner's policy (the default one if none is set).
+    Allowed(DashboardPolicy),
+    /// Left alone, e.g. no widgets or managed by infrastructure as code.
+    Skipped(DashboardOutcome),
+}
+
+/// The checks every annotating command makes before it changes `body`: it
+/// has widgets, isn't managed by infrastructure as code, and its owner's
+/// policy allows every one of `labels`.
+pub fn admit<'a>(
+    dashboard_name: &str,
+    body: &Value,
+    iac_guard: &IacGuard,
+    fail_on_empty_dashboard: bool,
+    labels: impl IntoIterator<Item = &'a str>,
+) -> Result<Admission> {
+    if !has_widgets(body) {
+        if fail_on_empty_dashboard {
+            return Err(anyhow!("{dashboard_name}: dashboard has no widgets"));
+        }
+        info!("{dashboard_name}: dashboard has no widgets (nothing to annotate)");
+        return Ok(Admission::Skipped(DashboardOutcome::skipped(
+            dashboard_name,
+            OutcomeStatus::NoWidgets,
+        )));
+    }
+
+    if iac_guard.skips(dashboard_name, body) {
+        return Ok(Admission::Skipped(DashboardOutcome::skipped(
+            dashboard_name,
+            OutcomeStatus::IacManaged,
+        )));
+    }
+
+    // The dashboard owner's policy (if any) is checked before anything changes.
+    let policy = DashboardPolicy::from_body(body)
+        .with_context(|| format!("{dashboard_name}: invalid {JSON_KEY_POLICY} block"))?
+        .unwrap_or_default();
+    for label in labels {
+        policy
+            .check_label(label)
+            .with_context(|| format!("{dashboard_name}: refused by dashboard policy"))?;
+    }
+    Ok(Admission::Allowed(policy))
+}
+
+/// GetDashboard `dashboard_name` and return its body as read and parsed.
+pub async fn read_dashboard(client: &Client, dashboard_name: &str) -> Result<(String, Value)> {
+    let resp = client
+        .get_dashboard()
+        .dashboard_name(dashboard_name)
+        .send()
+        .await
+        .with_context(|| format!("failed to get dashboard {dashboard_name}"))?;
+    let body_str = resp
+        .dashboard_body()
+        .with_context(|| format!("dashboard {dashboard_name} has no body"))?
+        .to_string();
+    let body = serde_json::from_str(&body_str).context("failed to parse dashboard body JSON")?;
+    Ok((body_str, body))
+}
+
+/// PutDashboard `body` as `dashboard_name` and return it as written.
+pub async fn write_dashboard(
+    client: &Client,
+    dashboard_name: &str,
+    body: &Value,
+) -> Result<String> {
+    let updated_body =
+        serde_json::to_string(body).context("failed to serialize updated dashboard body")?;
+    client
+        .put_dashboard()
+        .dashboard_name(dashboard_name)
+        .dashboard_body(&updated_body)
+        .send()
+        .await
+        .with_context(|| format!("failed to put updated dashboard {dashboard_name}"))?;
+    Ok(updated_body)
+}
+
+/// Everything `annotate` does to a dashboard body short of reading and
+/// writing it: policy, routes, the size limit, pruning, tags and the view.
+/// `default_region` fills `{widget_region}` for widgets without a region.
+fn annotate_body(
+    dashboard_name: &str,
+    body: &mut Value,
+    specs: &[AnnotationSpec],
+    options: &AnnotateOptions,
+    selector: &WidgetSelector,
+    default_region: &str,
+) -> Result<BodyAnnotation> {
+    let policy = match admit(
+        dashboard_name,
+        body,
+        &options.iac_guard,
+        options.fail_on_empty_dashboard,
+        specs.iter().map(|s| s.label.as_str()),
+    )? {
+        Admission::Allowed(policy) => policy,
+        Admission::Skipped(outcome) => return Ok(BodyAnnotation::Skipped(outcome)),
+    };
+    let routes: Vec<Route> = if options.routes.is_empty() {
+        vec![Route {
+            selector: selector.clone(),
+            ..Default::default()
+        }]
+    } else {
+        options.routes.clone()
+    };
+    let routes: Vec<Route> = routes
+        .into_iter()
+        .map(|route| Route {
+            selector: policy.restrict(&route.selector),
+            style: AnnotationStyle {
+                color: route.style.color.or_else(|| options.color.clone()),
+                fill: route.style.fill.or_else(|| {
+                    options
+                        .fill
+                        .and_then(VerticalFill::json_value)
+                        .map(str::to_string)
+                }),
+            },
+        })
+        .collect();
+
+    // 2) Build annotation objects.
+    let ann_objs: Vec<Value> = specs
+        .iter()
+        .map(|spec| {
+            let mut ann = spec.to_annotation();
+            if let Some(head) = annotation_head_mut(&mut ann) {
+                options.provenance.write(head);
+            }
+            ann
+        })
+        .collect();
+
+    // 3) Insert annotations into the metric widgets each route selects. Every
+    // annotation lands on the same widgets.
+    let widgets = body
+        .get("widgets")
+        .and_then(|w| w.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let mut targets = match route_widgets(widgets, &routes) {
+        Ok(targets) => targets,
+        Err(err) => {
+            warn!("{err}");
+            Vec::new()
+        }
+    };
+    let widgets_matched = targets.len();
+    // A widget without a region of its own takes the run's; a run on an
+    // --input-file without --region has none to give.
+    if default_region.is_empty()
+        && specs
+            .iter()
+            .any(|s| template::uses(&s.label, template::VAR_WIDGET_REGION))
+    {
+        let regionless = targets.iter().find(|(i, _)| {
+            widgets[*i]
+                .get(JSON_KEY_PROPERTIES)
+                .and_then(|p| p.get(JSON_KEY_REGION))
+                .is_none()
+        });
+        if let Some((i, _)) = regionless {
+            return Err(anyhow!(
+                "{dashboard_name}: '{}' has no region for {{{}}}; pass --region",
+                widget_title(&widgets[*i]),
+                template::VAR_WIDGET_REGION
+            ));
+        }
+    }
+    // Widgets that already have every annotation are not touched at all.
+    let mut already_annotated = Vec::new();
+    if let Some(skip) = options.skip_if_exists {
+        targets.retain(|(i, _)| {
+            let existing = widgets[*i]
+                .get(JSON_KEY_PROPERTIES)
+                .and_then(|p| p.get(JSON_KEY_ANNOTATIONS))
+                .and_then(|a| a.get(JSON_KEY_VERTICAL))
+                .and_then(|v| v.as_array())
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let done = widget_annotations(&ann_objs, &widgets[*i], default_region)
+                .iter()
+                .all(|ann| skip.exists(existing, ann));
+            if done {
+                already_annotated.push(widget_title(&widgets[*i]));
+            }
+            !done
+        });
+        if !already_annotated.is_empty() {
+            info!(
+                "{dashboard_name}: already annotated, skipped: {}",
+                already_annotated.join(", ")
+            );
+        }
+    }
+    // `--set-start` is applied after annotating; leave room for it.
+    let start_growth = match options.set_start {
+        Some(ref start) => {
+            let mut started = body.clone();
+            if let Some(started_obj) = started.as_object_mut() {
+                started_obj.insert(JSON_KEY_START.to_string(), Value::String(start.clone()));
+            }
+            body_size::json_len(&started).saturating_sub(body_size::json_len(body))
+        }
+        None => 0,
+    };
+    let mut pruned = Vec::new();
+    if policy.prune_oldest || options.auto_prune {
+        // Make room up front, so the limit below cuts nothing if pruning helps.
+        let mut full = body.clone();
+        apply_annotations(
+            &mut full,
+            &ann_objs,
+            &targets,
+            usize::MAX,
+            options.skip_if_exists,
+            options.tag_title.as_deref(),
+            default_region,
+        );
+        let excess =
+            (body_size::json_len(&full) + start_growth).saturating_sub(body_size::MAX_BODY_BYTES);
+        if excess > 0 {
+            let run_id = specs.first().map(|s| s.run_id.as_str()).unwrap_or_default();
+            pruned = body_size::prune_oldest_runs(body, run_id, excess)
+                .into_iter()
+                .filter_map(|(i, ann)| {
+                    let title = widget_title(&body["widgets"][i]);
+                    list::listed_annotation(dashboard_name, &title, Direction::Vertical, &ann)
+                })
+                .collect::<Vec<_>>();
+            info!(
+                "{dashboard_name}: pruned {} annotation(s) of older runs to stay under the size limit",
+                pruned.len()
+            );
+        }
+    }
+    let Applied {
+        annotated,
+        left_out,
+    } = apply_annotations(
+        body,
+        &ann_objs,
+        &targets,
+        body_size::MAX_BODY_BYTES.saturating_sub(start_growth),
+        options.skip_if_exists,
+        options.tag_title.as_deref(),
+        default_region,
+    );
+    let left_out: Vec<String> = left_out
+        .iter()
+        .map(|i| widget_title(&body["widgets"][*i]))
+        .collect();
+    if !left_out.is_empty() {
+        warn!(
+            "{dashboard_name}: body would exceed {} bytes; not annotated: {}",
+            body_size::MAX_BODY_BYTES,
+            left_out.join(", ")
+        );
+    }
+    let widgets_annotated = annotated.len();
+    let mut trimmed = Vec::new();
+    if let Some(keep) = options.keep_last {
+        let labels: Vec<&str> = specs.iter().map(|s| s.label.as_str()).collect();
+        trimmed = keep_last(body, &annotated, &labels, keep)
+            .into_iter()
+            .filter_map(|(i, ann)| {
+                let title = widget_title(&body["widgets"][i]);
+                list::listed_annotation(dashboard_name, &title, Direction::Vertical, &ann)
+            })
+            .collect::<Vec<_>>();
+        if !trimmed.is_empty() {
+            info!(
+                "{dashboard_name}: trimmed {} older annotation(s) to keep the last {keep} per label",
+                trimmed.len()
+            );
+        }
+    }
+    let sorted = if options.sort {
+        sort_vertical(body, &annotated)
+    } else {
+        0
+    };
+    if widgets_annotated == 0 && !already_annotated.is_empty() {
+        info!("{dashboard_name}: every matching widget is already annotated (nothing to add)");
+        return Ok(BodyAnnotation::Skipped(DashboardOutcome {
+            widgets_matched,
+            ..DashboardOutcome::skipped(dashboard_name, OutcomeStatus::AlreadyAnnotated)
+        }));
+    }
+    if widgets_annotated == 0 {
+        info!("{dashboard_name}: No matching metric widgets found (nothing to annotate)");
+        return Ok(BodyAnnotation::Skipped(DashboardOutcome {
+            widgets_matched,
+            ..DashboardOutcome::skipped(dashboard_name, OutcomeStatus::NoMatchingWidgets)
+        }));
+    }
+
+    let mut outcome = DashboardOutcome {
+        dashboard: dashboard_name.to_string(),
+        status: OutcomeStatus::Annotated,
+        error: None,
+        widgets_matched,
+        widgets: annotated
+            .iter()
+            .map(|i| widget_title(&body["widgets"][*i]))
+            .collect(),
+        changes: specs
+            .iter()
+            .flat_map(|spec| {
+                render_per_widget(body, &annotated, &spec.label, default_region)
+                    .into_iter()
+                    .map(|label| {
+                        format!(
+                            "+ {}: {} at {}",
+                            label,
+                            spec.value,
+                            spec.describe_time(options.time_display)
+                        )
+                    })
+            })
+            .collect(),
+    };
+    for ann in &pruned {
+        outcome.changes.push(format!(
+            "- {} at {} on '{}' (older run, size limit)",
+            ann.label,
+            options.time_display.format_rfc3339(&ann.value),
+            ann.widget
+        ));
+    }
+    for ann in &trimmed {
+        outcome.changes.push(format!(
+            "- {} at {} on '{}' (keep last {})",
+            ann.label,
+            options.time_display.format_rfc3339(&ann.value),
+            ann.widget,
+            options.keep_last.unwrap_or_default()
+        ));
+    }
+    if sorted > 0 {
+        outcome.changes.push(format!(
+            "sorted the annotations of {sorted} widget(s) by time"
+        ));
+    }
+    if !already_annotated.is_empty() {
+        outcome.changes.push(format!(
+            "already annotated: {}",
+            already_annotated.join(", ")
+        ));
+    }
+    if !left_out.is_empty() {
+        outcome.changes.push(format!(
+            "size limit: not annotated: {}",
+            left_out.join(", ")
+        ));
+    }
+
+    if let Some(ref tag) = options.tag_title {
+        let tagged = title_tag::tag_widgets(body, tag, &annotated);
+        info!("{dashboard_name}: tagged {tagged} widget title(s) with '{tag}'");
+        outcome
+            .changes
+            .push(format!("title tag '{tag}' on {tagged} widget(s)"));
+    }
+
+    if let Some(max) = policy.max_annotations {
+        if let Some((title, count)) = crowded_widgets(body, max.saturating_add(1), &annotated)
+            .into_iter()
+            .next()
+        {
+            return Err(anyhow!(
+                "{dashboard_name}: refused by dashboard policy: widget '{title}' would have \
+                 {count} annotations, {JSON_KEY_POLICY} allows {max}"
+            ));
+        }
+    }
+
+    for (title, count) in crowded_widgets(body, options.annotation_budget, &annotated) {
+        warn!(
+            "{dashboard_name}: widget '{title}' has {count} vertical annotations (budget {}); \
+             consider pruning old runs with `cwnote remove --run-id`",
+            options.annotation_budget
+        );
+    }
+
+    let size = body_size::json_len(body);
+    if options.body_size_warning > 0 && size >= options.body_size_warning {
+        warn!(
+            "{dashboard_name}: body is {size} bytes, close to the {} byte limit; \
+             prune old annotations (`cwnote prune`) or annotate with --auto-prune",
+            body_size::MAX_BODY_BYTES
+        );
+    }
+
+    let view_before = describe_view(body);
+    if let Some(ref start) = options.set_start {
+        if let Some(body_obj) = body.as_object_mut() {
+            body_obj.insert(JSON_KEY_START.to_string(), Value::String(start.clone()));
+        }
+        outcome
+            .changes
+            .push(format!("view: {view_before} -> {}", describe_view(body)));
+    }
+
+    Ok(BodyAnnotation::Annotated(AnnotatedBody {
+        outcome,
+        annotated,
+        ann_objs,
+        pruned,
+        trimmed,
+        view_before,
+    }))
+}
+
+/// Annotate the dashboard body in the local file `input` as
+/// [`annotate_single_dashboard`] would, without any AWS calls, and write the
+/// result to `output` (stdout without one), e.g. for dashboards kept as JSON
+/// in git. Pretty-printed input is written back pretty-printed; a body with
+/// nothing to annotate is written back as read.
+pub fn annotate_file(
+    input: &Path,
+    output: Option<&Path>,
+    specs: &[AnnotationSpec],
+    options: &AnnotateOptions,
+    selector: &WidgetSelector,
+    default_region: &str,
+) -> Result<DashboardOutcome> {
+    let body_str =
+        fs::read_to_string(input).with_context(|| format!("failed to read {}", input.display()))?;
+    let dashboard_name = input
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| input.display().to_string());
+    let mut body: Value = serde_json::from_str(&body_str)
+        .with_context(|| format!("failed to parse {} as a dashboard body", input.display()))?;
+    let original = body.clone();
+
+    let (outcome, updated_body) = match annotate_body(
+        &dashboard_name,
+        &mut body,
+        specs,
+        options,
+        selector,
+        default_region,
+    )? {
+        BodyAnnotation::Skipped(outcome) => (outcome, body_str.clone()),
+        BodyAnnotation::Annotated(annotated) => {
+            (annotated.outcome, to_string_like(&body, &body_str)?)
+        }
+    };
+
+    if options.dry_run {
+        info! {
+            target: "dry-run",
+            "{}: dashboard body diff:\n{}",
+            dashboard_name, body_diff::diff(&original, &body)
+        };
+        return Ok(outcome);
+    }
+    match output {
+        Some(path) => {
+            fs::write(path, &updated_body)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            info!(
+                "{dashboard_name}: wrote the annotated body to {}",
+                path.display()
+            );
+        }
+        None => std::io::stdout()
+            .write_all(updated_body.as_bytes())
+            .context("failed to write the annotated body to stdout")?,
+    }
+    Ok(outcome)
+}
+
+/// `body` as JSON laid out like the file it was read from: on one line if
+/// `original` is, else pretty-printed with `original`'s indentation (two or
+/// four spaces, tabs, ...) and its trailing newline.
+fn to_string_like(body: &Value, original: &str) -> Result<String> {
+    if !original.trim_end().contains('\n') {
+        return Ok(serde_json::to_string(body)?);
+    }
+    let indent = original
+        .lines()
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ");
+    // Strings can't span lines in JSON, so leading spaces are all indentation.
+    let mut pretty = serde_json::to_string_pretty(body)?
+        .lines()
+        .map(|line| {
+            let text = line.trim_start_matches(' ');
+            indent.repeat((line.len() - text.len()) / 2) + text
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if original.ends_with('\n') {
+        pretty.push('\n');
+    }
+    Ok(pretty)
+}
+
+/// Read `dashboard_name` back after a write and fail unless the annotated
+/// widgets carry everything this run wrote.
+async fn verify_written(
+    client: &Client,
+    dashboard_name: &str,
+    written: &Value,
+    specs: &[AnnotationSpec],
+    annotated: &[usize],
+) -> Result<()> {
+    let resp = client
+        .get_dashboard()
+        .dashboard_name(dashboard_name)
+        .send()
+        .await
+        .with_context(|| format!("{dashboard_name}: failed to read the dashboard back"))?;
+    let fetched: Value = serde_json::from_str(resp.dashboard_body().unwrap_or_default())
+        .with_context(|| format!("{dashboard_name}: failed to parse the dashboard read back"))?;
+
+    let run_id = specs.first().map(|s| s.run_id.as_str()).unwrap_or_default();
+    let missing = unverified_widgets(written, &fetched, annotated, run_id);
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "{dashboard_name}: verification failed, annotations missing after the write on: {}",
+            missing.join(", ")
+        ));
+    }
+    info!(
+        "{dashboard_name}: verified the annotations on {} widget(s)",
+        annotated.len()
+    );
+    Ok(())
+}
+
+/// Body of the latest export of `dashboard_name`, for read-only dry runs.
+fn exported_body(dashboard_name: &str) -> Result<String> {
+    let dir = export_dir().unwrap_or_else(|| PathBuf::from("."));
+    let export = maintenance::latest_export(&dir, dashboard_name)?.with_context(|| {
+        format!(
+            "{dashboard_name}: no export in {} to dry-run on (read-only skips GetDashboard)",
+            dir.display()
+        )
+    })?;
+    info!(
+        "{dashboard_name}: read-only dry run on {} (exported {})",
+        export.path.display(),
+        export.taken
+    );
+    export.read()
+}
+
+/// First annotated widget and the first annotation's time, which is what
+/// `--preview` and `--expect-change` look at. Problems are logged and yield `None`.
+fn primary_widget<'a>(
+    dashboard_name: &str,
+    body: &'a Value,
+    specs: &[AnnotationSpec],
+    annotated: &[usize],
+) -> Option<(&'a Map<String, Value>, DateTime<Utc>)> {
+    let widget = body
+        .get("widgets")
+        .and_then(|w| w.get(*annotated.first()?))
+        .and_then(|w| w.as_object())?;
+
+    let at = match specs.first().and_then(|s| s.time.as_deref()) {
+        Some(time) => match DateTime::parse_from_rfc3339(time) {
+            Ok(t) => t.with_timezone(&Utc),
+            Err(err) => {
+                warn!("{dashboard_name}: bad annotation time '{time}': {err}");
+                return None;
+            }
+        },
+        None => Utc::now(),
+    };
+
+    Some((widget, at))
+}
+
+/// Draw the first selected widget with the first annotation's position.
+/// Preview problems are only logged; they never fail a dry run.
+async fn show_preview(
+    client: &Client,
+    dashboard_name: &str,
+    body: &Value,
+    specs: &[AnnotationSpec],
+    annotated: &[usize],
+) {
+    let Some((widget, at)) = primary_widget(dashboard_name, body, specs, annotated) else {
+        return;
+    };
+
+    match preview::render_widget(client, widget, at).await {
+        Ok(lines) => {
+            for line in lines {
+                info!(target: "dry-run", "{dashboard_name}: {line}");
+            }
+        }
+        Err(err) => warn!("{dashboard_name}: no preview: {err:#}"),
+    }
+}
+
+/// Log whether the first selected widget's metric shifted around the
+/// annotation. Like the preview this is informational only.
+async fn report_change(
+    client: &Client,
+    dashboard_name: &str,
+    body: &Value,
+    specs: &[AnnotationSpec],
+    annotated: &[usize],
+) {
+    let Some((widget, at)) = primary_widget(dashboard_name, body, specs, annotated) else {
+        return;
+    };
+
+    match expect_change::check_widget(client, widget, at).await {
+        Ok(report) => info!("{dashboard_name}: {report}"),
+        Err(err) => warn!("{dashboard_name}: no change check: {err:#}"),
+    }
+}
+
+/// Annotate all dashboards whose name starts with the given suffix.
+pub async fn annotate_dashboards_by_suffix(
+    client: &Client,
+    suffix: &str,
+    specs: &[AnnotationSpec],
+    options: &AnnotateOptions,
+    selector: &WidgetSelector,
+) -> Result<Vec<DashboardOutcome>> {
+    let dashboards = list_dashboards_with_suffix(client, suffix).await?;
+    annotate_dashboards(
+        client,
+        &dashboards,
+        &format!("suffix '{suffix}'"),
+        specs,
+        options,
+        selector,
+    )
+    .await
+}
+
+/// Annotate every dashboard whose name matches `regex`.
+pub async fn annotate_dashboards_by_regex(
+    client: &Client,
+    regex: &Regex,
+    specs: &[AnnotationSpec],
+    options: &AnnotateOptions,
+    selector: &WidgetSelector,
+) -> Result<Vec<DashboardOutcome>> {
+    let dashboards = list_dashboards_matching(client, |name| regex.is_match(name)).await?;
+    annotate_dashboards(
+        client,
+        &dashboards,
+        &format!("regex '{regex}'"),
+        specs,
+        options,
+        selector,
+    )
+    .await
+}
+
+/// Annotate every dashboard whose whole name matches `glob`, e.g. `prod-*-api`.
+pub async fn annotate_dashboards_by_glob(
+    client: &Client,
+    glob: &Glob,
+    specs: &[AnnotationSpec],
+    options: &AnnotateOptions,
+    selector: &WidgetSelector,
+) -> Result<Vec<DashboardOutcome>> {
+    let dashboards = list_dashboards_matching(client, |name| glob.is_match(name)).await?;
+    annotate_dashboards(
+        client,
+        &dashboards,
+        &format!("glob '{glob}'"),
+        specs,
+        options,
+        selector,
+    )
+    .await
+}
+
+/// Annotate `dashboards` one after the other, found by `matched_by` (for logs).
+async fn annotate_dashboards(
+    client: &Client,
+    dashboards: &[String],
+    matched_by: &str,
+    specs: &[AnnotationSpec],
+    options: &AnnotateOptions,
+    selector: &WidgetSelector,
+) -> Result<Vec<DashboardOutcome>> {
+    if dashboards.is_empty() {
+        info!("No dashboards found with {matched_by}");
+        return Ok(Vec::new());
+    }
+
+    info!("{} dashboard(s) match {matched_by}:", dashboards.len());
+    let dashboards = options.exclusions.apply(dashboards.to_vec());
+    for d in &dashboards {
+        info!("  - {}", d);
+    }
+    if options.confirm && !dashboards.is_empty() && !interactive::confirm("Proceed?")? {
+        return Err(anyhow!("Aborted: no dashboards were updated"));
+    }
+
+    Ok(annotate_each(client, &dashboards, specs, options, selector).await)
+}
+
+/// Annotate every one of `dashboards` and log the summary table. A failing
+/// dashboard doesn't stop the run: it is recorded as
+/// `OutcomeStatus::Failed`, and the caller fails once all are done.
+async fn annotate_each(
+    client: &Client,
+    dashboards: &[String],
+    specs: &[AnnotationSpec],
+    options: &AnnotateOptions,
+    selector: &WidgetSelector,
+) -> Vec<DashboardOutcome> {
+    let failed = |name: &str, err: anyhow::Error| {
+        error!("{name}: {err:#}");
+        DashboardOutcome::failed(name, &err)
+    };
+
+    let mut outcomes = Vec::with_capacity(dashboards.len());
+    if options.concurrency > 1 {
+        let results = annotate_concurrently(client, dashboards, specs, options, selector).await;
+        for (name, result) in dashboards.iter().zip(results) {
+            outcomes.push(result.unwrap_or_else(|err| failed(name, err)));
+        }
+    } else {
+        let mut pacer = Pacer::new(options.retry);
+        let mut progress = Progress::new(dashboards.len(), options.progress);
+        for (i, name) in dashboards.iter().enumerate() {
+            if interrupt::stop_before(&dashboards[i..]) {
+                break;
+            }
+            let outcome =
+                annotate_single_dashboard(client, &mut pacer, name, specs, options, selector)
+                    .await
+                    .unwrap_or_else(|err| failed(name, err));
+            progress.record(&outcome);
+            outcomes.push(outcome);
+        }
+    }
+
+    log_summary(&outcomes, options.dry_run);
+    outcomes
+}
+
+/// Annotate `dashboards` with up to `options.concurrency` updates in flight.
+/// Results come back in the order of `dashboards`, whatever order the
+/// updates finish in, one per dashboard started before a Ctrl-C. The
+/// updates share one [`Pacer`], so throttling slows all of them down.
+async fn annotate_concurrently(
+    client: &Client,
+    dashboards: &[String],
+    specs: &[AnnotationSpec],
+    options: &AnnotateOptions,
+    selector: &WidgetSelector,
+) -> Vec<Result<DashboardOutcome>> {
+    info!(
+        "Updating up to {} dashboards at a time",
+        options.concurrency
+    );
+    let permits = Arc::new(Semaphore::new(options.concurrency));
+    let shared = Arc::new((specs.to_vec(), options.clone(), selector.clone()));
+    let pacer = Pacer::new(options.retry);
+    let mut tasks = JoinSet::new();
+    let mut started = 0;
+    for (i, name) in dashboards.iter().enumerate() {
+        let permit = Arc::clone(&permits)
+            .acquire_owned()
+            .await
+            .expect("the semaphore is never closed");
+        if interrupt::stop_before(&dashboards[i..]) {
+            break;
+        }
+        let (client, shared, name) = (client.clone(), Arc::clone(&shared), name.clone());
+        let mut pacer = pacer.clone();
+        tasks.spawn(async move {
+            let _permit = permit;
+            let (specs, options, selector) = &*shared;
+            let outcome =
+                annotate_single_dashboard(&client, &mut pacer, &name, specs, options, selector)
+                    .await;
+            (i, outcome)
+        });
+        started += 1;
+    }
+
+    let mut results: Vec<Option<Result<DashboardOutcome>>> = (0..started).map(|_| None).collect();
+    let mut progress = Progress::new(dashboards.len(), options.progress);
+    while let Some(joined) = tasks.join_next().await {
+        // A panicked update leaves its slot empty and is reported below.
+        if let Ok((i, outcome)) = joined {
+            match outcome {
+                Ok(ref outcome) => progress.record(outcome),
+                Err(_) => progress.record_failure(),
+            }
+            results[i] = Some(outcome);
+        }
+    }
+    results
+        .into_iter()
+        .zip(dashboards)
+        .map(|(result, name)| {
+            result.unwrap_or_else(|| Err(anyhow!("{name}: the dashboard update panicked")))
+        })
+        .collect()
+}
+
+/// Annotate the explicitly named `dashboards` (repeated `--dashboard`, or a
+/// list from `--dashboards-file`/`--dashboards-stdin`).
+pub async fn annotate_dashboards_by_name(
+    client: &Client,
+    dashboards: &[String],
+    specs: &[AnnotationSpec],
+    options: &AnnotateOptions,
+    selector: &WidgetSelector,
+) -> Result<Vec<DashboardOutcome>> {
+    let dashboards = options.exclusions.apply(dashboards.to_vec());
+    Ok(annotate_each(client, &dashboards, specs, options, selector).await)
+}
+
+/// Log the summary table of a multi-dashboard run.
+fn log_summary(outcomes: &[DashboardOutcome], dry_run: bool) {
+    if outcomes.is_empty() {
+        return;
+    }
+    info!("Summary:");
+    for line in summary_table(outcomes, dry_run) {
+        info!("{line}");
+    }
+}
+
+/// One aligned row per dashboard: name, widgets matched and annotated, and
+/// `updated`, `dry-run`, `skipped` or `failed` with the reason.
+pub fn summary_table(outcomes: &[DashboardOutcome], dry_run: bool) -> Vec<String> {
+    let header = ["DASHBOARD", "MATCHED", "ANNOTATED", "STATUS", "NOTE"].map(str::to_string);
+    let rows: Vec<[String; 5]> = outcomes
+        .iter()
+        .map(|o| {
+            let (status, note) = match o.status {
+                OutcomeStatus::Annotated if dry_run => ("dry-run", ""),
+                OutcomeStatus::Annotated => ("updated", ""),
+                OutcomeStatus::NoWidgets => ("skipped", "no widgets"),
+                OutcomeStatus::NoMatchingWidgets => ("skipped", "no matching widgets"),
+                OutcomeStatus::AlreadyAnnotated => ("unchanged", "already annotated"),
+                OutcomeStatus::IacManaged => ("skipped", "managed by infrastructure as code"),
+                OutcomeStatus::Failed => ("failed", o.error.as_deref().unwrap_or("unknown error")),
+            };
+            [
+                o.dashboard.clone(),
+                o.widgets_matched.to_string(),
+                o.widgets.len().to_string(),
+                status.to_string(),
+                note.to_string(),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.clone().map(|h| h.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    std::iter::once(&header)
+        .chain(&rows)
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect();
+            cells.join("  ").trim_end().to_string()
+        })
+        .collect()
+}
+
+/// How many of `outcomes` failed.
+pub fn failed_count(outcomes: &[DashboardOutcome]) -> usize {
+    outcomes
+        .iter()
+        .filter(|o| o.status == OutcomeStatus::Failed)
+        .count()
+}
+
+/// Dispatch annotations to a single dashboard or all dashboards matching a suffix.
+pub async fn annotate_target(
+    client: &Client,
+    dashboard: Option<&str>,
+    suffix: Option<&str>,
+    specs: &[AnnotationSpec],
+    options: &AnnotateOptions,
+    selector: &WidgetSelector,
+) -> Result<Vec<DashboardOutcome>> {
+    match (dashboard, suffix) {
+        (Some(dashboard), None) => {
+            // Single dashboard.
+            let mut pacer = Pacer::new(options.retry);
+            let outcome =
+                annotate_single_dashboard(client, &mut pacer, dashboard, specs, options, selector)
+                    .await?;
+            Ok(vec![outcome])
+        }
+        (None, Some(suffix)) => {
+            // All dashboards matching suffix.
+            annotate_dashboards_by_suffix(client, suffix, specs, options, selector).await
+        }
+        (Some(_), Some(_)) => Err(anyhow!(
+            "Please specify either --dashboard OR --dashboard-suffix, not both"
+        )),
+        (None, None) => Err(anyhow!(
+            "Either --dashboard or --dashboard-suffix is required"
+        )),
+    }
+}
+
+/// Dashboard names from a list file: one per line, trimmed, skipping blank
+/// lines and `#` comments.
+pub fn parse_dashboard_names(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// List dashboards whose names start with the given suffix.
+pub async fn list_dashboards_with_suffix(client: &Client, suffix: &str) -> Result<Vec<String>> {
+    list_dashboards_matching(client, |name| name.ends_with(suffix)).await
+}
+
+/// List dashboards whose names `matches` accepts.
+pub async fn list_dashboards_matching(
+    client: &Client,
+    matches: impl Fn(&str) -> bool,
+) -> Result<Vec<String>> {
+    let mut result = Vec::new();
+    let mut next_token: Option<String> = None;
+
+    loop {
+        let mut req = client.list_dashboards();
+        if let Some(ref token) = next_token {
+            req = req.next_token(token);
+        }
+
+        let resp = req.send().await.context("failed to list dashboards")?;
+
+        let entries: &[DashboardEntry] = resp.dashboard_entries();
+
+        for entry in entries {
+            if let Some(name) = entry.dashboard_name() {
+                if matches(name) {
+                    result.push(name.to_string());
+                }
+            }
+        }
+
+        match resp.next_token() {
+            Some(t) if !t.is_empty() => {
+                next_token = Some(t.to_string());
+            }
+            _ => break,
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+    use std::fs;
     use std::sync::{Mutex, OnceLock};
     use tempfile::tempdir;
 
-    // Global mutex for cwd changes.
-    static CWD_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+    // Single-route equivalent of what annotate_single_dashboard does.
+    fn apply_with_selector(body: &mut Value, ann: &Value, selector: &WidgetSelector) -> usize {
+        let routes = [Route {
+            selector: selector.clone(),
+            ..Default::default()
+        }];
+        let widgets = body["widgets"].as_array().cloned().unwrap_or_default();
+        let Ok(targets) = route_widgets(&widgets, &routes) else {
+            return 0;
+        };
+        apply_annotations(
+            body,
+            std::slice::from_ref(ann),
+            &targets,
+            usize::MAX,
+            None,
+            None,
+            "",
+        )
+        .annotated
+        .len()
+    }
+
+    // Global mutex for cwd changes.
+    static CWD_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+
+    fn cwd_lock() -> std::sync::MutexGuard<'static, ()> {
+        CWD_LOCK.get_or_init(|| Mutex::new(())).lock().unwrap()
+    }
+
+    struct EnvVarGuard {
+        key: String,
+        prev: Option<String>,
+    }
+
+    impl EnvVarGuard {
+        fn unset(key: &str) -> Self {
+            let prev = std::env::var(key).ok();
+            std::env::remove_var(key);
+            Self {
+                key: key.to_string(),
+                prev,
+            }
+        }
+    }
+
+    impl Drop for EnvVarGuard {
+        fn drop(&mut self) {
+            match &self.prev {
+                Some(val) => std::env::set_var(&self.key, val),
+                None => std::env::remove_var(&self.key),
+            }
+        }
+    }
+
+    #[test]
+    fn apply_annotation_only_hits_metric_widgets_that_match_selector() {
+        // Dashboard body with:
+        // - metric widget "Overall Latency"
+        // - metric widget "Error Rate"
+        // - text widget
+        let mut body = json!({
+            "widgets": [
+                {
+                    "type": "metric",
+                    "properties": {
+                        "title": "Overall Latency",
+                        "metrics": []
+                    }
+                },
+                {
+                    "type": "metric",
+                    "properties": {
+                        "title": "Error Rate",
+                        "metrics": []
+                    }
+                },
+                {
+                    "type": "text",
+                    "properties": {
+                        "markdown": "# Hello"
+                    }
+                }
+            ]
+        });
+
+        // Only annotate widgets whose title contains "Latency"
+        let selector = WidgetSelector {
+            title_contains: Some("Latency".to_string()),
+            ..Default::default()
+        };
+
+        // Build a fake annotation object.
+        let mut ann_obj = Map::new();
+        ann_obj.insert(
+            "label".to_string(),
+            Value::String("version: 1.2.3".to_string()),
+        );
+        ann_obj.insert(
+            "value".to_string(),
+            Value::String("2025-01-20T12:00:00Z".to_string()),
+        );
+
+        let count = apply_with_selector(&mut body, &Value::Object(ann_obj), &selector);
+        assert_eq!(
+            count, 1,
+            "only one matching metric widget should be annotated"
+        );
+
+        // Check that only the "Overall Latency" widget has vertical annotations.
+        let widgets = body.get("widgets").unwrap().as_array().unwrap();
+
+        // First widget: "Overall Latency" → should have the annotation
+        let w0 = widgets[0].as_object().unwrap();
+        let props0 = w0.get("properties").unwrap().as_object().unwrap();
+        let anns0 = props0.get("annotations").unwrap().as_object().unwrap();
+        let vertical0 = anns0.get("vertical").unwrap().as_array().unwrap();
+        assert_eq!(vertical0.len(), 1);
+        let ann0 = vertical0[0].as_object().unwrap();
+        assert_eq!(
+            ann0.get("label").unwrap(),
+            &Value::String("version: 1.2.3".to_string())
+        );
+        assert_eq!(
+            ann0.get("value").unwrap(),
+            &Value::String("2025-01-20T12:00:00Z".to_string())
+        );
+
+        // Second widget: "Error Rate" → should NOT have annotations
+        let w1 = widgets[1].as_object().unwrap();
+        let props1 = w1.get("properties").unwrap().as_object().unwrap();
+        assert!(
+            !props1.contains_key("annotations"),
+            "non-matching metric widget should not have annotations"
+        );
+
+        // Third widget: type "text" → should not be touched
+        let w2 = widgets[2].as_object().unwrap();
+        assert_eq!(w2.get("type").unwrap().as_str(), Some("text"));
+        let props2 = w2.get("properties").unwrap().as_object().unwrap();
+        assert!(
+            !props2.contains_key("annotations"),
+            "non-metric widget should not have annotations"
+        );
+    }
+
+    // Real-world body with one widget of every type, and the same body after
+    // `golden_annotation()` was applied to the line graphs.
+    const GOLDEN_BODY: &str = include_str!("../testdata/dashboards/all_widget_types.json");
+    const GOLDEN_ANNOTATED: &str =
+        include_str!("../testdata/dashboards/all_widget_types.annotated.json");
+
+    fn golden_annotation() -> Value {
+        AnnotationSpec {
+            label: "version".to_string(),
+            value: "1.9.0".to_string(),
+            time: Some("2025-01-20T12:00:00Z".to_string()),
+            end_time: None,
+            run_id: "20250120T120000Z-aaaaaaaa".to_string(),
+        }
+        .to_annotation()
+    }
+
+    #[test]
+    fn skip_if_exists_matches_label_value_and_tolerance() {
+        let ann = golden_annotation();
+        let rerun = |time: &str| {
+            AnnotationSpec {
+                label: "version".to_string(),
+                value: "1.9.0".to_string(),
+                time: Some(time.to_string()),
+                end_time: None,
+                run_id: "20250120T130000Z-bbbbbbbb".to_string(),
+            }
+            .to_annotation()
+        };
+        let vertical = vec![ann.clone()];
+
+        let any_time = SkipIfExists::default();
+        assert!(any_time.exists(&vertical, &rerun("2025-01-21T00:00:00Z")));
+        assert!(!any_time.exists(&[], &ann));
+
+        let within = SkipIfExists {
+            tolerance: Some(Duration::minutes(10)),
+        };
+        assert!(within.exists(&vertical, &rerun("2025-01-20T12:05:00Z")));
+        assert!(!within.exists(&vertical, &rerun("2025-01-20T12:30:00Z")));
+
+        // Same label, other value: a new release.
+        let mut other = ann.clone();
+        other["label"] = Value::String("version: 1.9.1".to_string());
+        assert!(!any_time.exists(&vertical, &other));
+
+        let mut body: Value = serde_json::from_str(GOLDEN_BODY).unwrap();
+        let routes = [Route::default()];
+        let widgets = body["widgets"].as_array().cloned().unwrap();
+        let targets = route_widgets(&widgets, &routes).unwrap();
+        apply_annotations(
+            &mut body,
+            std::slice::from_ref(&ann),
+            &targets,
+            usize::MAX,
+            None,
+            None,
+            "",
+        );
+        let once = body.clone();
+        apply_annotations(
+            &mut body,
+            &[ann],
+            &targets,
+            usize::MAX,
+            Some(any_time),
+            None,
+            "",
+        );
+        assert_eq!(body, once);
+    }
+
+    #[test]
+    fn keep_last_trims_each_label_to_the_newest_entries() {
+        let mut body = json!({
+            "widgets": [{
+                "type": "metric",
+                "properties": {
+                    "title": "Latency",
+                    "annotations": { "vertical": [
+                        { "label": "version: 1.9.0", "value": "2025-01-20T12:00:00Z" },
+                        { "label": "version: 1.7.0", "value": "2025-01-10T12:00:00Z" },
+                        { "label": "incident: INC-1", "value": "2025-01-01T12:00:00Z" },
+                        { "label": "version: 1.8.0", "value": "2025-01-15T12:00:00Z" },
+                        { "label": "version: 1.9.1", "value": "2025-01-21T12:00:00Z" }
+                    ] }
+                }
+            }]
+        });
+
+        let dropped = keep_last(&mut body, &[0], &["version"], 2);
+        let labels = |anns: Vec<&Value>| -> Vec<String> {
+            anns.iter()
+                .map(|a| a["label"].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(
+            labels(dropped.iter().map(|(_, a)| a).collect()),
+            vec!["version: 1.7.0", "version: 1.8.0"]
+        );
+        assert_eq!(
+            labels(
+                body["widgets"][0]["properties"]["annotations"]["vertical"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .collect()
+            ),
+            vec!["version: 1.9.0", "incident: INC-1", "version: 1.9.1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn concurrent_edits_are_retried_a_bounded_number_of_times() {
+        let mut calls = 0;
+        let result = retry_concurrent_edits("D", || {
+            calls += 1;
+            let edited = calls < 3;
+            async move {
+                if edited {
+                    Err(ConcurrentEdit("D".to_string()).into())
+                } else {
+                    Ok(calls)
+                }
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let err = retry_concurrent_edits("D", || {
+            calls += 1;
+            async { Err::<(), _>(ConcurrentEdit("D".to_string()).into()) }
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(calls, MAX_CONCURRENT_EDIT_RETRIES + 1);
+        assert!(err.to_string().contains("not overwriting"), "{err}");
+    }
+
+    #[test]
+    fn verification_finds_widgets_missing_the_run_annotations() {
+        let ann = |label: &str, run: &str| json!({ "label": label, "value": "2025-01-20T12:00:00Z", "cwnoteRunId": run });
+        let widget = |title: &str, vertical: Vec<Value>| {
+            json!({ "type": "metric", "properties": {
+                "title": title, "annotations": { "vertical": vertical }
+            } })
+        };
+        let written = json!({ "widgets": [
+            widget("A", vec![ann("old", "R0"), ann("new", "R1")]),
+            widget("B", vec![ann("new", "R1")]),
+            widget("C", vec![ann("new", "R1")])
+        ] });
+        let fetched = json!({ "widgets": [
+            widget("A", vec![ann("new", "R1")]),
+            widget("B", vec![]),
+            widget("C", vec![ann("changed", "R1")])
+        ] });
+
+        assert_eq!(
+            unverified_widgets(&written, &fetched, &[0, 1, 2], "R1"),
+            vec!["B", "C"]
+        );
+        assert!(unverified_widgets(&written, &written, &[0, 1, 2], "R1").is_empty());
+    }
+
+    #[test]
+    fn annotate_file_writes_the_annotated_body_without_aws() {
+        let dir = tempdir().unwrap();
+        let input = dir.path().join("orders.json");
+        let output = dir.path().join("out.json");
+        fs::write(
+            &input,
+            "{\n  \"widgets\": [\n    { \"type\": \"metric\", \"properties\": { \"title\": \"Latency\" } },\n    { \"type\": \"text\", \"properties\": { \"markdown\": \"# Orders\" } }\n  ]\n}\n",
+        )
+        .unwrap();
+        let spec = AnnotationSpec {
+            label: "version".to_string(),
+            value: "1.9.0".to_string(),
+            time: Some("2025-01-20T12:00:00Z".to_string()),
+            end_time: None,
+            run_id: "RUN".to_string(),
+        };
+
+        let outcome = annotate_file(
+            &input,
+            Some(&output),
+            std::slice::from_ref(&spec),
+            &AnnotateOptions::default(),
+            &WidgetSelector::default(),
+            "",
+        )
+        .unwrap();
+        assert_eq!(outcome.dashboard, "orders");
+        assert_eq!(outcome.status, OutcomeStatus::Annotated);
+        assert_eq!(outcome.widgets, vec!["Latency"]);
+
+        let written = fs::read_to_string(&output).unwrap();
+        assert!(written.starts_with("{\n  \"widgets\""), "{written}");
+        let body: Value = serde_json::from_str(&written).unwrap();
+        assert_eq!(
+            body["widgets"][0]["properties"]["annotations"]["vertical"][0]["label"],
+            json!("version: 1.9.0")
+        );
+        assert!(body["widgets"][1]["properties"]
+            .get("annotations")
+            .is_none());
+    }
+
+    #[test]
+    fn several_specs_count_each_widget_once() {
+        let spec = |value: &str| AnnotationSpec {
+            label: "version".to_string(),
+            value: value.to_string(),
+            time: Some("2025-01-20T12:00:00Z".to_string()),
+            end_time: None,
+            run_id: "RUN".to_string(),
+        };
+        let specs = [spec("1.9.0"), spec("1.9.1")];
+        // Latency already has the first marker, Errors has neither.
+        let mut body = json!({
+            "widgets": [
+                { "type": "metric", "properties": { "title": "Latency", "annotations": {
+                    "vertical": [specs[0].to_annotation()]
+                } } },
+                { "type": "metric", "properties": { "title": "Errors" } }
+            ]
+        });
+        let options = AnnotateOptions {
+            skip_if_exists: Some(SkipIfExists { tolerance: None }),
+            ..Default::default()
+        };
+
+        let BodyAnnotation::Annotated(annotated) = annotate_body(
+            "orders",
+            &mut body,
+            &specs,
+            &options,
+            &WidgetSelector::default(),
+            "",
+        )
+        .unwrap() else {
+            panic!("expected an annotated body");
+        };
+        assert_eq!(annotated.annotated, vec![0, 1]);
+        assert_eq!(annotated.outcome.widgets, vec!["Latency", "Errors"]);
+        assert_eq!(
+            body["widgets"][1]["properties"]["annotations"]["vertical"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn written_files_keep_their_indentation() {
+        let body = json!({ "widgets": [{ "type": "text" }] });
+        assert_eq!(
+            to_string_like(&body, "{\n    \"widgets\": []\n}\n").unwrap(),
+            "{\n    \"widgets\": [\n        {\n            \"type\": \"text\"\n        }\n    ]\n}\n"
+        );
+        assert_eq!(
+            to_string_like(&body, "{\n\t\"widgets\": []\n}").unwrap(),
+            "{\n\t\"widgets\": [\n\t\t{\n\t\t\t\"type\": \"text\"\n\t\t}\n\t]\n}"
+        );
+        assert_eq!(
+            to_string_like(&body, r#"{"widgets":[]}"#).unwrap(),
+            r#"{"widgets":[{"type":"text"}]}"#
+        );
+    }
+
+    #[test]
+    fn sort_vertical_orders_by_start_time() {
+        let mut body = json!({
+            "widgets": [
+                { "type": "metric", "properties": { "annotations": { "vertical": [
+                    { "label": "b", "value": "2025-01-20T12:00:00Z" },
+                    { "label": "untimed", "value": "soon" },
+                    [{ "label": "band", "value": "2025-01-19T00:00:00Z" }, { "value": "2025-01-21T00:00:00Z" }],
+                    { "label": "a", "value": "2025-01-20T13:00:00+02:00" }
+                ] } } },
+                { "type": "metric", "properties": { "annotations": { "vertical": [
+                    { "label": "x", "value": "2025-01-20T12:00:00Z" }
+                ] } } }
+            ]
+        });
 
-    fn cwd_lock() -> std::sync::MutexGuard<'static, ()> {
-        CWD_LOCK.get_or_init(|| Mutex::new(())).lock().unwrap()
+        assert_eq!(sort_vertical(&mut body, &[0, 1]), 1);
+        let order: Vec<&str> = body["widgets"][0]["properties"]["annotations"]["vertical"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|ann| annotation_head(ann).unwrap()["label"].as_str().unwrap())
+            .collect();
+        assert_eq!(order, vec!["band", "a", "b", "untimed"]);
     }
 
-    struct EnvVarGuard {
-        key: String,
-        prev: Option<String>,
+    #[test]
+    fn dashboard_body_round_trips_byte_for_byte() {
+        let body: Value = serde_json::from_str(GOLDEN_BODY).unwrap();
+        assert_eq!(
+            serde_json::to_string_pretty(&body).unwrap() + "\n",
+            GOLDEN_BODY
+        );
     }
 
-    impl EnvVarGuard {
-        fn unset(key: &str) -> Self {
-            let prev = std::env::var(key).ok();
-            std::env::remove_var(key);
-            Self {
-                key: key.to_string(),
-                prev,
-            }
-        }
+    #[test]
+    fn annotating_only_appends_to_vertical_annotations() {
+        let mut body: Value = serde_json::from_str(GOLDEN_BODY).unwrap();
+
+        let count =
+            apply_with_selector(&mut body, &golden_annotation(), &WidgetSelector::default());
+        assert_eq!(count, 2);
+        assert_eq!(
+            serde_json::to_string_pretty(&body).unwrap() + "\n",
+            GOLDEN_ANNOTATED
+        );
     }
 
-    impl Drop for EnvVarGuard {
-        fn drop(&mut self) {
-            match &self.prev {
-                Some(val) => std::env::set_var(&self.key, val),
-                None => std::env::remove_var(&self.key),
+    #[test]
+    fn widgets_over_the_size_limit_are_left_out() {
+        // A grows by a whole annotations block, B is malformed and C already
+        // has an (empty) vertical array, so C grows the least.
+        let mut body = json!({
+            "widgets": [
+                { "type": "metric", "properties": { "title": "A" } },
+                { "type": "metric", "properties": { "title": "B", "annotations": [] } },
+                { "type": "metric", "properties": { "title": "C", "annotations": { "vertical": [] } } }
+            ]
+        });
+        let routes = [Route::default()];
+        let widgets = body["widgets"].as_array().cloned().unwrap();
+        let targets = route_widgets(&widgets, &routes).unwrap();
+        let ann = golden_annotation();
+
+        // Room for C annotated and tagged, not for A.
+        let mut expected = body.clone();
+        apply_annotations(
+            &mut expected,
+            std::slice::from_ref(&ann),
+            &targets[2..],
+            usize::MAX,
+            None,
+            None,
+            "",
+        );
+        title_tag::tag_widgets(&mut expected, "v1.9.0", &[2]);
+        let limit = body_size::json_len(&expected);
+
+        let applied =
+            apply_annotations(&mut body, &[ann], &targets, limit, None, Some("v1.9.0"), "");
+        assert_eq!(
+            applied,
+            Applied {
+                annotated: vec![2],
+                left_out: vec![0],
             }
-        }
+        );
+        title_tag::tag_widgets(&mut body, "v1.9.0", &applied.annotated);
+        assert_eq!(body, expected);
     }
 
     #[test]
-    fn widget_selector_matches_without_filter() {
-        let selector = WidgetSelector {
-            title_contains: None,
+    fn widget_region_is_rendered_per_widget() {
+        let mut body = json!({
+            "widgets": [
+                { "type": "metric", "properties": { "title": "A", "region": "us-east-1" } },
+                { "type": "metric", "properties": { "title": "B" } }
+            ]
+        });
+        let specs = [AnnotationSpec {
+            label: "deploy ({widget_region})".to_string(),
+            value: "1.9.0".to_string(),
+            time: Some("2025-01-20T12:00:00Z".to_string()),
+            end_time: None,
+            run_id: "RUN".to_string(),
+        }];
+        let options = AnnotateOptions {
+            skip_if_exists: Some(SkipIfExists { tolerance: None }),
+            ..Default::default()
+        };
+        let annotate = |body: &mut Value, default_region: &str| {
+            annotate_body(
+                "orders",
+                body,
+                &specs,
+                &options,
+                &WidgetSelector::default(),
+                default_region,
+            )
         };
 
-        // Widget without title, but since no filter, it should match.
-        let widget = json!({
-            "type": "metric",
-            "properties": {
-                "metrics": []
-            }
+        assert!(annotate(&mut body.clone(), "").is_err());
+        assert!(matches!(
+            annotate(&mut body, "eu-central-1").unwrap(),
+            BodyAnnotation::Annotated(_)
+        ));
+        let label = |i: usize| {
+            body["widgets"][i]["properties"]["annotations"]["vertical"][0]["label"].clone()
+        };
+        assert_eq!(label(0), json!("deploy (us-east-1): 1.9.0"));
+        assert_eq!(label(1), json!("deploy (eu-central-1): 1.9.0"));
+        // A re-run finds the rendered labels.
+        assert!(matches!(
+            annotate(&mut body, "eu-central-1").unwrap(),
+            BodyAnnotation::Skipped(_)
+        ));
+
+        assert_eq!(
+            render_per_widget(
+                &body,
+                &[0, 1, 0],
+                "deploy ({widget_region})",
+                "eu-central-1"
+            ),
+            ["deploy (us-east-1)", "deploy (eu-central-1)"]
+        );
+        assert_eq!(
+            render_per_widget(&body, &[0, 1], "deploy", "eu-central-1"),
+            ["deploy"]
+        );
+    }
+
+    #[test]
+    fn annotating_leaves_malformed_annotations_alone() {
+        let mut body = json!({
+            "widgets": [
+                { "type": "metric", "properties": { "annotations": { "vertical": null } } },
+                { "type": "metric", "properties": { "annotations": [] } }
+            ]
         });
+        let before = body.clone();
 
-        let widget_obj = widget.as_object().unwrap();
-        assert!(selector.matches(widget_obj));
+        apply_with_selector(&mut body, &golden_annotation(), &WidgetSelector::default());
+        assert_eq!(body, before);
     }
 
     #[test]
-    fn widget_selector_matches_when_title_contains_substring() {
+    fn apply_annotation_with_row_of_hits_widgets_in_the_same_row() {
+        let mut body = json!({
+            "widgets": [
+                { "type": "metric", "x": 0, "y": 0, "width": 8, "height": 6,
+                  "properties": { "title": "Overall Latency" } },
+                { "type": "metric", "x": 8, "y": 0, "width": 8, "height": 6,
+                  "properties": { "title": "p99 (svc-a)" } },
+                { "type": "metric", "x": 0, "y": 6, "width": 8, "height": 6,
+                  "properties": { "title": "Overall Errors" } }
+            ]
+        });
+
         let selector = WidgetSelector {
-            title_contains: Some("Latency".to_string()),
+            row_of: Some("Overall Latency".to_string()),
+            ..Default::default()
         };
 
-        let widget = json!({
-            "type": "metric",
-            "properties": {
-                "title": "Overall Latency P95",
-                "metrics": []
-            }
-        });
+        let mut ann_obj = Map::new();
+        ann_obj.insert(
+            "label".to_string(),
+            Value::String("version: 1.2.3".to_string()),
+        );
 
-        let widget_obj = widget.as_object().unwrap();
-        assert!(selector.matches(widget_obj));
+        let count = apply_with_selector(&mut body, &Value::Object(ann_obj), &selector);
+        assert_eq!(count, 2);
+
+        let widgets = body.get("widgets").unwrap().as_array().unwrap();
+        assert!(widgets[0]["properties"].get("annotations").is_some());
+        assert!(widgets[1]["properties"].get("annotations").is_some());
+        assert!(widgets[2]["properties"].get("annotations").is_none());
     }
 
     #[test]
-    fn widget_selector_does_not_match_when_title_does_not_contain_substring() {
+    fn apply_annotation_with_unknown_row_anchor_returns_zero() {
+        let mut body = json!({
+            "widgets": [
+                { "type": "metric", "x": 0, "y": 0, "width": 8, "height": 6,
+                  "properties": { "title": "Overall Latency" } }
+            ]
+        });
+
         let selector = WidgetSelector {
-            title_contains: Some("Latency".to_string()),
+            row_of: Some("Missing".to_string()),
+            ..Default::default()
         };
 
-        let widget = json!({
-            "type": "metric",
-            "properties": {
-                "title": "Error Rate",
-                "metrics": []
-            }
-        });
-
-        let widget_obj = widget.as_object().unwrap();
-        assert!(!selector.matches(widget_obj));
+        let count = apply_with_selector(&mut body, &Value::Object(Map::new()), &selector);
+        assert_eq!(count, 0);
     }
 
     #[test]
-    fn apply_annotation_only_hits_metric_widgets_that_match_selector() {
-        // Dashboard body with:
-        // - metric widget "Overall Latency"
-        // - metric widget "Error Rate"
-        // - text widget
+    fn apply_annotation_with_no_matching_widgets_returns_zero() {
         let mut body = json!({
             "widgets": [
-                {
-                    "type": "metric",
-                    "properties": {
-                        "title": "Overall Latency",
-                        "metrics": []
-                    }
-                },
                 {
                     "type": "metric",
                     "properties": {
                         "title": "Error Rate",
                         "metrics": []
                     }
-                },
-                {
-                    "type": "text",
-                    "properties": {
-                        "markdown": "# Hello"
-                    }
                 }
             ]
         });
 
-        // Only annotate widgets whose title contains "Latency"
         let selector = WidgetSelector {
             title_contains: Some("Latency".to_string()),
+            ..Default::default()
         };
 
-        // Build a fake annotation object.
         let mut ann_obj = Map::new();
         ann_obj.insert(
             "label".to_string(),
@@ -448,86 +2953,297 @@ mod tests {
             Value::String("2025-01-20T12:00:00Z".to_string()),
         );
 
-        let count = apply_annotation_to_body(&mut body, &ann_obj, &selector);
-        assert_eq!(
-            count, 1,
-            "only one matching metric widget should be annotated"
-        );
+        let count = apply_with_selector(&mut body, &Value::Object(ann_obj), &selector);
+        assert_eq!(count, 0);
 
-        // Check that only the "Overall Latency" widget has vertical annotations.
         let widgets = body.get("widgets").unwrap().as_array().unwrap();
-
-        // First widget: "Overall Latency" → should have the annotation
         let w0 = widgets[0].as_object().unwrap();
         let props0 = w0.get("properties").unwrap().as_object().unwrap();
-        let anns0 = props0.get("annotations").unwrap().as_object().unwrap();
-        let vertical0 = anns0.get("vertical").unwrap().as_array().unwrap();
-        assert_eq!(vertical0.len(), 1);
-        let ann0 = vertical0[0].as_object().unwrap();
+        assert!(
+            !props0.contains_key("annotations"),
+            "widget should remain unannotated when selector doesn't match"
+        );
+    }
+
+    #[test]
+    fn annotation_spec_carries_run_id() {
+        let spec = AnnotationSpec {
+            label: "version".to_string(),
+            value: "1.2.3".to_string(),
+            time: Some("2025-01-20T12:00:00Z".to_string()),
+            run_id: "20250120T120000Z-aaaaaaaa".to_string(),
+            end_time: None,
+        };
+
+        let ann_obj = spec.to_json();
         assert_eq!(
-            ann0.get("label").unwrap(),
+            ann_obj.get(JSON_KEY_LABEL).unwrap(),
             &Value::String("version: 1.2.3".to_string())
         );
         assert_eq!(
-            ann0.get("value").unwrap(),
+            ann_obj.get(JSON_KEY_VALUE).unwrap(),
             &Value::String("2025-01-20T12:00:00Z".to_string())
         );
-
-        // Second widget: "Error Rate" → should NOT have annotations
-        let w1 = widgets[1].as_object().unwrap();
-        let props1 = w1.get("properties").unwrap().as_object().unwrap();
-        assert!(
-            !props1.contains_key("annotations"),
-            "non-matching metric widget should not have annotations"
+        assert_eq!(
+            ann_obj.get(JSON_KEY_RUN_ID).unwrap(),
+            &Value::String("20250120T120000Z-aaaaaaaa".to_string())
         );
+    }
 
-        // Third widget: type "text" → should not be touched
-        let w2 = widgets[2].as_object().unwrap();
-        assert_eq!(w2.get("type").unwrap().as_str(), Some("text"));
-        let props2 = w2.get("properties").unwrap().as_object().unwrap();
-        assert!(
-            !props2.contains_key("annotations"),
-            "non-metric widget should not have annotations"
+    #[test]
+    fn annotation_spec_with_end_time_builds_a_band() {
+        let spec = AnnotationSpec {
+            label: "batch".to_string(),
+            value: "nightly".to_string(),
+            time: Some("2025-01-20T01:00:00Z".to_string()),
+            end_time: Some("2025-01-20T02:30:00Z".to_string()),
+            run_id: "20250120T120000Z-aaaaaaaa".to_string(),
+        };
+
+        let ann = spec.to_annotation();
+        assert_eq!(
+            ann,
+            json!([
+                {
+                    "label": "batch: nightly",
+                    "value": "2025-01-20T01:00:00Z",
+                    "cwnoteRunId": "20250120T120000Z-aaaaaaaa"
+                },
+                { "value": "2025-01-20T02:30:00Z" }
+            ])
+        );
+        assert_eq!(
+            annotation_head(&ann).unwrap().get(JSON_KEY_LABEL).unwrap(),
+            "batch: nightly"
         );
     }
 
     #[test]
-    fn apply_annotation_with_no_matching_widgets_returns_zero() {
-        let mut body = json!({
+    fn validate_start_accepts_relative_and_absolute_times() {
+        assert!(validate_start("-PT3H").is_ok());
+        assert!(validate_start("-P7D").is_ok());
+        assert!(validate_start("2025-01-20T12:00:00Z").is_ok());
+        assert!(validate_start("PT3H").is_err());
+        assert!(validate_start("-P").is_err());
+        assert!(validate_start("-3h").is_err());
+    }
+
+    #[test]
+    fn crowded_widgets_reports_metric_widgets_at_budget() {
+        let line = json!({ "label": "version: 1", "value": "2025-01-20T12:00:00Z" });
+        let body = json!({
             "widgets": [
                 {
                     "type": "metric",
                     "properties": {
-                        "title": "Error Rate",
-                        "metrics": []
+                        "title": "Latency",
+                        "annotations": { "vertical": [line.clone(), line.clone(), line.clone()] }
+                    }
+                },
+                {
+                    "type": "metric",
+                    "properties": {
+                        "title": "Errors",
+                        "annotations": { "vertical": [line.clone()] }
+                    }
+                },
+                {
+                    "type": "text",
+                    "properties": {
+                        "annotations": { "vertical": [line.clone(), line.clone(), line] }
                     }
                 }
             ]
         });
 
-        let selector = WidgetSelector {
-            title_contains: Some("Latency".to_string()),
-        };
+        assert_eq!(
+            crowded_widgets(&body, 3, &[0, 1]),
+            vec![("Latency".to_string(), 3)]
+        );
+        assert!(crowded_widgets(&body, 0, &[0, 1]).is_empty());
+    }
 
-        let mut ann_obj = Map::new();
-        ann_obj.insert(
-            "label".to_string(),
-            Value::String("version: 1.2.3".to_string()),
+    #[test]
+    fn has_widgets_rejects_missing_null_and_empty_arrays() {
+        assert!(!has_widgets(&json!({})));
+        assert!(!has_widgets(&json!({ "widgets": null })));
+        assert!(!has_widgets(&json!({ "widgets": [] })));
+        assert!(has_widgets(&json!({ "widgets": [{ "type": "text" }] })));
+    }
+
+    #[test]
+    fn clamp_time_rejects_far_future_unless_allowed() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+        let utc = Timezone::default();
+
+        assert_eq!(
+            clamp_time("2025-01-20T11:00:00Z", now, &utc, true, false).unwrap(),
+            "2025-01-20T11:00:00Z"
         );
-        ann_obj.insert(
-            "value".to_string(),
-            Value::String("2025-01-20T12:00:00Z".to_string()),
+        // Within the skew: clamped to now.
+        assert_eq!(
+            clamp_time("2025-01-20T12:02:00Z", now, &utc, true, false).unwrap(),
+            now.to_rfc3339()
+        );
+        // An hour ahead, e.g. local time passed as UTC.
+        assert!(clamp_time("2025-01-20T13:00:00Z", now, &utc, true, false).is_err());
+        assert_eq!(
+            clamp_time("2025-01-20T13:00:00Z", now, &utc, true, true).unwrap(),
+            "2025-01-20T13:00:00Z"
+        );
+        assert!(clamp_time("yesterday", now, &utc, true, true).is_err());
+        assert_eq!(
+            clamp_time("now-1h", now, &utc, true, false).unwrap(),
+            "2025-01-20T11:00:00+00:00"
+        );
+        assert!(clamp_time("now+1h", now, &utc, true, false).is_err());
+        assert_eq!(
+            clamp_time("1737370800", now, &utc, true, false).unwrap(),
+            "2025-01-20T11:00:00+00:00"
+        );
+        // Not clamping: future times are kept as given.
+        assert_eq!(
+            clamp_time("2025-01-20T12:02:00Z", now, &utc, false, false).unwrap(),
+            "2025-01-20T12:02:00Z"
+        );
+        assert_eq!(
+            clamp_time("2025-01-20T13:00:00Z", now, &utc, false, false).unwrap(),
+            "2025-01-20T13:00:00Z"
+        );
+        // Local wall-clock time, read in its zone rather than as UTC.
+        let berlin: Timezone = "Europe/Berlin".parse().unwrap();
+        assert_eq!(
+            clamp_time("2025-01-20 12:30", now, &berlin, true, false).unwrap(),
+            "2025-01-20T11:30:00+00:00"
         );
+    }
 
-        let count = apply_annotation_to_body(&mut body, &ann_obj, &selector);
-        assert_eq!(count, 0);
+    #[test]
+    fn band_end_from_end_time_or_duration() {
+        let start = "2025-01-20T01:00:00Z";
 
-        let widgets = body.get("widgets").unwrap().as_array().unwrap();
-        let w0 = widgets[0].as_object().unwrap();
-        let props0 = w0.get("properties").unwrap().as_object().unwrap();
-        assert!(
-            !props0.contains_key("annotations"),
-            "widget should remain unannotated when selector doesn't match"
+        assert_eq!(band_end(start, None, None).unwrap(), None);
+        assert_eq!(
+            band_end(start, None, Some(chrono::Duration::minutes(30)))
+                .unwrap()
+                .as_deref(),
+            Some("2025-01-20T01:30:00+00:00")
+        );
+        assert_eq!(
+            band_end(start, Some("2025-01-20T02:30:00Z"), None)
+                .unwrap()
+                .as_deref(),
+            Some("2025-01-20T02:30:00+00:00")
+        );
+        assert!(band_end(start, Some("2025-01-20T00:30:00Z"), None).is_err());
+        assert!(band_end(start, Some("tomorrow"), None).is_err());
+    }
+
+    #[test]
+    fn describe_time_uses_display_format() {
+        let spec = AnnotationSpec {
+            label: "batch".to_string(),
+            value: "nightly".to_string(),
+            time: Some("2025-01-20T01:00:00Z".to_string()),
+            end_time: Some("2025-01-20T02:00:00Z".to_string()),
+            run_id: "20250120T120000Z-aaaaaaaa".to_string(),
+        };
+        assert_eq!(
+            spec.describe_time(TimeDisplay::Unix),
+            "1737334800 until 1737338400"
+        );
+    }
+
+    #[test]
+    fn routes_style_their_widgets_and_first_match_wins() {
+        let mut body = json!({
+            "widgets": [
+                { "type": "metric", "properties": { "title": "API Latency" } },
+                { "type": "metric", "properties": { "title": "API Errors" } }
+            ]
+        });
+        let routes = vec![
+            Route {
+                selector: WidgetSelector {
+                    title_contains: Some("Latency".to_string()),
+                    ..Default::default()
+                },
+                style: AnnotationStyle {
+                    color: Some("#ff7f0e".to_string()),
+                    fill: Some("after".to_string()),
+                },
+            },
+            Route {
+                selector: WidgetSelector {
+                    title_contains: Some("API".to_string()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ];
+        let ann = json!({ "label": "deploy: 1.9.0", "value": "2025-01-20T12:00:00Z" });
+
+        let widgets = body["widgets"].as_array().cloned().unwrap();
+        let targets = route_widgets(&widgets, &routes).unwrap();
+        assert_eq!(targets.len(), 2);
+        apply_annotations(&mut body, &[ann], &targets, usize::MAX, None, None, "");
+
+        let latency = &body["widgets"][0]["properties"]["annotations"]["vertical"];
+        assert_eq!(latency.as_array().unwrap().len(), 1);
+        assert_eq!(latency[0]["fill"], json!("after"));
+        assert_eq!(latency[0]["color"], json!("#ff7f0e"));
+
+        let errors = &body["widgets"][1]["properties"]["annotations"]["vertical"];
+        assert!(errors[0].get("fill").is_none());
+    }
+
+    #[test]
+    fn dashboard_names_skip_blanks_and_comments() {
+        let text = "# from terraform output\norders-prod\n\n  payments-prod  \r\n";
+        assert_eq!(
+            parse_dashboard_names(text),
+            ["orders-prod", "payments-prod"]
+        );
+    }
+
+    #[test]
+    fn summary_names_the_result_per_dashboard() {
+        let failed = DashboardOutcome::failed("orders", &anyhow!("AccessDenied"));
+        assert_eq!(failed.summary(), "orders: FAILED: AccessDenied");
+        let skipped = DashboardOutcome::skipped("payments", OutcomeStatus::NoWidgets);
+        assert_eq!(skipped.summary(), "payments: skipped, no widgets");
+        assert_eq!(failed_count(&[failed, skipped]), 1);
+    }
+
+    #[test]
+    fn summary_table_aligns_one_row_per_dashboard() {
+        let annotated = DashboardOutcome {
+            dashboard: "orders-prod".to_string(),
+            status: OutcomeStatus::Annotated,
+            widgets_matched: 3,
+            widgets: vec!["Latency".to_string(), "Errors".to_string()],
+            changes: Vec::new(),
+            error: None,
+        };
+        let failed = DashboardOutcome::failed("payments", &anyhow!("AccessDenied"));
+
+        assert_eq!(
+            summary_table(&[annotated.clone(), failed], false),
+            [
+                "DASHBOARD    MATCHED  ANNOTATED  STATUS   NOTE",
+                "orders-prod  3        2          updated",
+                "payments     0        0          failed   AccessDenied",
+            ]
+        );
+        assert!(summary_table(&[annotated], true)[1].ends_with("dry-run"));
+    }
+
+    #[test]
+    fn describe_view_reports_unset_settings() {
+        let body = json!({ "start": "-PT6H", "periodOverride": "auto", "widgets": [] });
+        assert_eq!(
+            describe_view(&body),
+            "start=-PT6H, end=<unset>, periodOverride=auto"
         );
     }
 
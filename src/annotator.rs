@@ -0,0 +1,154 @@
+use anyhow::{anyhow, Result};
+use aws_sdk_cloudwatch::Client;
+use chrono::{DateTime, Utc};
+
+use crate::annotate::{self, AnnotateOptions, AnnotationSpec};
+use crate::provenance::Provenance;
+use crate::run_id;
+use crate::selector::WidgetSelector;
+
+/// Which dashboards an [`Annotator`] writes to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// A single dashboard by exact name.
+    Dashboard(String),
+    /// Every dashboard whose name matches, like `--dashboard-suffix`.
+    Suffix(String),
+}
+
+/// One marker to draw: a line at `time`, or a band up to `end_time`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    pub label: String,
+    pub value: String,
+    /// `None` means "now".
+    pub time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+}
+
+impl Annotation {
+    /// A line annotation at the current time.
+    pub fn new(label: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            value: value.into(),
+            time: None,
+            end_time: None,
+        }
+    }
+
+    pub fn at(mut self, time: DateTime<Utc>) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Turn the annotation into a band ending at `end_time`.
+    pub fn until(mut self, end_time: DateTime<Utc>) -> Self {
+        self.end_time = Some(end_time);
+        self
+    }
+}
+
+/// Stable entry point for embedding cwnote, e.g. in a deployment controller.
+///
+/// ```no_run
+/// # async fn run(client: aws_sdk_cloudwatch::Client) -> anyhow::Result<()> {
+/// use cwnote::{Annotation, Annotator, Target, WidgetSelector};
+///
+/// let annotator = Annotator::new(client);
+/// annotator
+///     .annotate(
+///         &Target::Dashboard("Service-Dashboard".to_string()),
+///         &WidgetSelector::default(),
+///         &Annotation::new("deploy", "1.9.0"),
+///     )
+///     .await?;
+/// println!("undo with: cwnote remove --run-id {}", annotator.run_id());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Annotator {
+    client: Client,
+    run_id: String,
+    dry_run: bool,
+    provenance: Provenance,
+}
+
+impl Annotator {
+    /// Annotator with a fresh run id, writing for real.
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            run_id: run_id::generate(),
+            dry_run: false,
+            provenance: Provenance::default(),
+        }
+    }
+
+    /// Use a caller-provided run id, e.g. one derived from a rollout name.
+    pub fn with_run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = run_id.into();
+        self
+    }
+
+    /// Creator, scope, ttl and link to store on every annotation.
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Only log what would change.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Id stamped on every annotation this annotator writes.
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Write `annotation` onto the widgets `selector` picks on `target`.
+    /// Fails if any dashboard could not be annotated, after trying them all.
+    pub async fn annotate(
+        &self,
+        target: &Target,
+        selector: &WidgetSelector,
+        annotation: &Annotation,
+    ) -> Result<()> {
+        let spec = AnnotationSpec {
+            label: annotation.label.clone(),
+            value: annotation.value.clone(),
+            time: annotation.time.map(|t| t.to_rfc3339()),
+            end_time: annotation.end_time.map(|t| t.to_rfc3339()),
+            run_id: self.run_id.clone(),
+        };
+        let options = AnnotateOptions {
+            dry_run: self.dry_run,
+            provenance: self.provenance.clone(),
+            ..Default::default()
+        };
+
+        let (dashboard, suffix) = match target {
+            Target::Dashboard(name) => (Some(name.as_str()), None),
+            Target::Suffix(suffix) => (None, Some(suffix.as_str())),
+        };
+        let outcomes = annotate::annotate_target(
+            &self.client,
+            dashboard,
+            suffix,
+            std::slice::from_ref(&spec),
+            &options,
+            selector,
+        )
+        .await?;
+        match annotate::failed_count(&outcomes) {
+            0 => Ok(()),
+            failed => Err(anyhow!(
+                "{failed} of {} dashboard(s) failed to annotate",
+                outcomes.len()
+            )),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::{Context, Result};
+use aws_sdk_appconfig::Client;
+use chrono::{DateTime, Duration, Utc};
+use log::info;
+
+use crate::annotate::AnnotationSpec;
+use crate::aws_client;
+
+/// One AppConfig configuration deployment, reduced to what an annotation needs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDeployment {
+    pub number: i32,
+    pub configuration_name: String,
+    pub configuration_version: String,
+    pub started_at: DateTime<Utc>,
+}
+
+impl ConfigDeployment {
+    /// Annotation marking the start of this deployment, e.g. `config: flags@7 (#12)`.
+    pub fn to_spec(&self, label: &str, run_id: &str) -> AnnotationSpec {
+        AnnotationSpec {
+            label: label.to_string(),
+            value: format!(
+                "{}@{} (#{})",
+                self.configuration_name, self.configuration_version, self.number
+            ),
+            time: Some(self.started_at.to_rfc3339()),
+            end_time: None,
+            run_id: run_id.to_string(),
+        }
+    }
+}
+
+/// List the deployments of an application/environment started within `since`.
+pub async fn list_deployments(
+    client: &Client,
+    application: &str,
+    environment: &str,
+    since: Duration,
+) -> Result<Vec<ConfigDeployment>> {
+    let cutoff = Utc::now() - since;
+    let mut result = Vec::new();
+    let mut next_token: Option<String> = None;
+
+    loop {
+        let mut req = client
+            .list_deployments()
+            .application_id(application)
+            .environment_id(environment);
+        if let Some(ref token) = next_token {
+            req = req.next_token(token);
+        }
+
+        let resp = req.send().await.with_context(|| {
+            format!("failed to list AppConfig deployments for {application}/{environment}")
+        })?;
+
+        for item in resp.items() {
+            let Some(started_at) = item.started_at().and_then(aws_client::to_chrono) else {
+                continue;
+            };
+            if started_at < cutoff {
+                continue;
+            }
+            result.push(ConfigDeployment {
+                number: item.deployment_number(),
+                configuration_name: item.configuration_name().unwrap_or_default().to_string(),
+                configuration_version: item.configuration_version().unwrap_or_default().to_string(),
+                started_at,
+            });
+        }
+
+        match resp.next_token() {
+            Some(t) if !t.is_empty() => {
+                next_token = Some(t.to_string());
+            }
+            _ => break,
+        }
+    }
+
+    info!(
+        "Found {} AppConfig deployment(s) for {}/{}",
+        result.len(),
+        application,
+        environment
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn deployment_becomes_annotation_at_start_time() {
+        let deployment = ConfigDeployment {
+            number: 12,
+            configuration_name: "feature-flags".to_string(),
+            configuration_version: "7".to_string(),
+            started_at: Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap(),
+        };
+
+        let spec = deployment.to_spec("config", "20250120T120000Z-aaaaaaaa");
+        assert_eq!(spec.label, "config");
+        assert_eq!(spec.value, "feature-flags@7 (#12)");
+        assert_eq!(spec.time.as_deref(), Some("2025-01-20T12:00:00+00:00"));
+    }
+}
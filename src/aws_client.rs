@@ -1,10 +1,62 @@
 // src/aws_client.rs
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use aws_config::meta::region::RegionProviderChain;
-use aws_config::BehaviorVersion;
+use aws_config::retry::RetryConfig;
+use aws_config::sts::AssumeRoleProvider;
 use aws_config::Region;
+use aws_config::SdkConfig;
+use aws_config::{BehaviorVersion, ConfigLoader};
+use aws_sdk_cloudwatch::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_cloudwatch::operation::delete_dashboards::DeleteDashboardsError;
+use aws_sdk_cloudwatch::operation::get_dashboard::GetDashboardError;
+use aws_sdk_cloudwatch::operation::list_dashboards::ListDashboardsError;
+use aws_sdk_cloudwatch::operation::put_dashboard::PutDashboardError;
+use aws_sdk_cloudwatch::primitives::DateTime as SmithyDateTime;
 use aws_sdk_cloudwatch::Client;
+use chrono::{DateTime, Utc};
+use log::warn;
+
+const ENDPOINT_URL_ENV: &str = "CWNOTE_ENDPOINT_URL";
+
+/// Regions enabled by default in every commercial account, the candidates of
+/// `--all-regions`. Opt-in regions are left out.
+pub const DEFAULT_REGIONS: [&str; 17] = [
+    "us-east-1",
+    "us-east-2",
+    "us-west-1",
+    "us-west-2",
+    "ca-central-1",
+    "sa-east-1",
+    "eu-central-1",
+    "eu-west-1",
+    "eu-west-2",
+    "eu-west-3",
+    "eu-north-1",
+    "ap-south-1",
+    "ap-northeast-1",
+    "ap-northeast-2",
+    "ap-northeast-3",
+    "ap-southeast-1",
+    "ap-southeast-2",
+];
+
+/// The [`DEFAULT_REGIONS`] where the credentials can list dashboards.
+/// Regions that refuse (e.g. blocked by an SCP) are skipped with a warning.
+pub async fn discover_regions(
+    profile: Option<&str>,
+    endpoint_url: Option<&str>,
+) -> Result<Vec<String>> {
+    let mut regions = Vec::new();
+    for region in DEFAULT_REGIONS {
+        let client = make_client(Some(region), profile, endpoint_url).await?;
+        match client.list_dashboards().send().await {
+            Ok(_) => regions.push(region.to_string()),
+            Err(err) => warn!("Skipping region {region}: {err}"),
+        }
+    }
+    Ok(regions)
+}
 
 /// Build a CloudWatch client, optionally overriding the region.
 ///
@@ -14,7 +66,237 @@ use aws_sdk_cloudwatch::Client;
 /// - IMDS, etc.
 ///
 /// If `region` is `Some("eu-central-1")`, that wins.
-pub async fn make_client(region: Option<&str>) -> Result<Client> {
+///
+/// `profile` names the shared config/credentials profile to use instead of
+/// AWS_PROFILE (or `default`).
+///
+/// `endpoint_url` (else `CWNOTE_ENDPOINT_URL`) replaces the CloudWatch
+/// endpoint, e.g. `http://localhost:4566` for LocalStack or a VPC endpoint.
+pub async fn make_client(
+    region: Option<&str>,
+    profile: Option<&str>,
+    endpoint_url: Option<&str>,
+) -> Result<Client> {
+    let endpoint_url = resolve_endpoint_url(endpoint_url)?;
+    let config = load_config_as(region, profile, endpoint_url.as_deref(), None).await;
+
+    Ok(Client::new(&config))
+}
+
+/// CloudWatch client for runs that make no AWS calls (`--input-file`). It
+/// resolves no region, credentials or endpoint; calls on it fail.
+pub fn offline_client() -> Client {
+    Client::new(
+        &SdkConfig::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .build(),
+    )
+}
+
+/// Role to assume for a run in another account (`--role-arn`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssumeRole {
+    pub role_arn: String,
+    pub external_id: Option<String>,
+    /// STS session name, shown in the target account's CloudTrail.
+    pub session_name: String,
+}
+
+impl AssumeRole {
+    /// Account id of the role, from its ARN (`arn:aws:iam::123456789012:role/x`).
+    pub fn account_id(&self) -> Option<&str> {
+        self.role_arn.split(':').nth(4).filter(|id| !id.is_empty())
+    }
+}
+
+/// Build a CloudWatch client like [`make_client`] that acts as `role`,
+/// assumed via STS with the credentials of `profile` (or the default ones).
+pub async fn make_client_as(
+    region: Option<&str>,
+    profile: Option<&str>,
+    endpoint_url: Option<&str>,
+    role: &AssumeRole,
+) -> Result<Client> {
+    let endpoint_url = resolve_endpoint_url(endpoint_url)?;
+    let config = load_config_as(region, profile, endpoint_url.as_deref(), Some(role)).await;
+
+    Ok(Client::new(&config))
+}
+
+/// AWS config every client is built from: the region and `profile`
+/// resolved as for [`make_client`], talking to `endpoint_url` if set, and
+/// acting as `role` (assumed via STS with the credentials of `profile`) if
+/// set, so a run in another account reads and writes there only.
+pub async fn load_config_as(
+    region: Option<&str>,
+    profile: Option<&str>,
+    endpoint_url: Option<&str>,
+    role: Option<&AssumeRole>,
+) -> SdkConfig {
+    let mut loader = config_loader(region, profile);
+    if let Some(url) = endpoint_url {
+        loader = loader.endpoint_url(url);
+    }
+    if let Some(role) = role {
+        // STS stays at its regular endpoint, with the base credentials.
+        let base = config_loader(region, profile).load().await;
+        let mut provider = AssumeRoleProvider::builder(&role.role_arn)
+            .session_name(&role.session_name)
+            .configure(&base);
+        if let Some(ref external_id) = role.external_id {
+            provider = provider.external_id(external_id);
+        }
+        loader = loader.credentials_provider(provider.build().await);
+    }
+    loader.load().await
+}
+
+/// Build a CloudWatch Logs client with the same region resolution as [`make_client`].
+pub async fn make_logs_client(
+    region: Option<&str>,
+    profile: Option<&str>,
+    role: Option<&AssumeRole>,
+) -> Result<aws_sdk_cloudwatchlogs::Client> {
+    let config = load_config_as(region, profile, None, role).await;
+
+    Ok(aws_sdk_cloudwatchlogs::Client::new(&config))
+}
+
+/// Build an S3 client (for `--backup-s3`) with the same region resolution as
+/// [`make_client`].
+pub async fn make_s3_client(
+    region: Option<&str>,
+    profile: Option<&str>,
+    role: Option<&AssumeRole>,
+) -> Result<aws_sdk_s3::Client> {
+    let config = load_config_as(region, profile, None, role).await;
+
+    Ok(aws_sdk_s3::Client::new(&config))
+}
+
+/// Build an SSM client with the same region resolution as [`make_client`].
+pub async fn make_ssm_client(
+    region: Option<&str>,
+    profile: Option<&str>,
+    role: Option<&AssumeRole>,
+) -> Result<aws_sdk_ssm::Client> {
+    let config = load_config_as(region, profile, None, role).await;
+
+    Ok(aws_sdk_ssm::Client::new(&config))
+}
+
+/// Build an AppConfig client with the same region resolution as [`make_client`].
+pub async fn make_appconfig_client(
+    region: Option<&str>,
+    profile: Option<&str>,
+    role: Option<&AssumeRole>,
+) -> Result<aws_sdk_appconfig::Client> {
+    let config = load_config_as(region, profile, None, role).await;
+
+    Ok(aws_sdk_appconfig::Client::new(&config))
+}
+
+/// Build a Step Functions client with the same region resolution as [`make_client`].
+pub async fn make_sfn_client(
+    region: Option<&str>,
+    profile: Option<&str>,
+    role: Option<&AssumeRole>,
+) -> Result<aws_sdk_sfn::Client> {
+    let config = load_config_as(region, profile, None, role).await;
+
+    Ok(aws_sdk_sfn::Client::new(&config))
+}
+
+/// Build a CloudWatch Synthetics client with the same region resolution as [`make_client`].
+pub async fn make_synthetics_client(
+    region: Option<&str>,
+    profile: Option<&str>,
+    role: Option<&AssumeRole>,
+) -> Result<aws_sdk_synthetics::Client> {
+    let config = load_config_as(region, profile, None, role).await;
+
+    Ok(aws_sdk_synthetics::Client::new(&config))
+}
+
+/// Build an IAM client with the same region resolution as [`make_client`].
+pub async fn make_iam_client(
+    region: Option<&str>,
+    profile: Option<&str>,
+    role: Option<&AssumeRole>,
+) -> Result<aws_sdk_iam::Client> {
+    let config = load_config_as(region, profile, None, role).await;
+
+    Ok(aws_sdk_iam::Client::new(&config))
+}
+
+/// Build an STS client with the same region resolution as [`make_client`].
+pub async fn make_sts_client(
+    region: Option<&str>,
+    profile: Option<&str>,
+    role: Option<&AssumeRole>,
+) -> Result<aws_sdk_sts::Client> {
+    let config = load_config_as(region, profile, None, role).await;
+
+    Ok(aws_sdk_sts::Client::new(&config))
+}
+
+/// Convert an AWS SDK timestamp into a chrono UTC timestamp.
+pub fn to_chrono(dt: &SmithyDateTime) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(dt.secs(), dt.subsec_nanos())
+}
+
+/// `true` if `err` was caused by an AWS API call (denied, throttled past the
+/// retries, timed out, unreachable, ...) rather than by cwnote or its input.
+pub fn is_aws_error(err: &anyhow::Error) -> bool {
+    err.chain().any(is_sdk_error)
+}
+
+// Whether `cause` is the `SdkError` of one of the AWS calls cwnote makes;
+// a new call needs its operation error listed here.
+fn is_sdk_error(cause: &(dyn std::error::Error + 'static)) -> bool {
+    macro_rules! any_of {
+        ($($operation:ty),+ $(,)?) => {
+            $(cause.is::<SdkError<$operation>>())||+
+        };
+    }
+    any_of!(
+        GetDashboardError,
+        PutDashboardError,
+        ListDashboardsError,
+        DeleteDashboardsError,
+        aws_sdk_cloudwatch::operation::get_metric_data::GetMetricDataError,
+        aws_sdk_cloudwatchlogs::operation::start_query::StartQueryError,
+        aws_sdk_cloudwatchlogs::operation::get_query_results::GetQueryResultsError,
+        aws_sdk_ssm::operation::get_parameter::GetParameterError,
+        aws_sdk_ssm::operation::get_document::GetDocumentError,
+        aws_sdk_iam::operation::list_account_aliases::ListAccountAliasesError,
+        aws_sdk_sts::operation::get_caller_identity::GetCallerIdentityError,
+        aws_sdk_s3::operation::put_object::PutObjectError,
+        aws_sdk_sfn::operation::list_executions::ListExecutionsError,
+        aws_sdk_synthetics::operation::get_canary_runs::GetCanaryRunsError,
+        aws_sdk_appconfig::operation::list_deployments::ListDeploymentsError,
+    )
+}
+
+/// Error code CloudWatch answered a dashboard call with (`Throttling`,
+/// `ResourceNotFound`, ...), found anywhere in the chain of `err`.
+pub fn dashboard_error_code(err: &anyhow::Error) -> Option<&str> {
+    err.chain().find_map(|cause| {
+        if let Some(err) = cause.downcast_ref::<GetDashboardError>() {
+            ProvideErrorMetadata::code(err)
+        } else if let Some(err) = cause.downcast_ref::<PutDashboardError>() {
+            ProvideErrorMetadata::code(err)
+        } else if let Some(err) = cause.downcast_ref::<ListDashboardsError>() {
+            ProvideErrorMetadata::code(err)
+        } else if let Some(err) = cause.downcast_ref::<DeleteDashboardsError>() {
+            ProvideErrorMetadata::code(err)
+        } else {
+            None
+        }
+    })
+}
+
+fn config_loader(region: Option<&str>, profile: Option<&str>) -> ConfigLoader {
     let region_provider = match region {
         Some(explicit) => {
             // Prefer explicit region, but still fall back to default provider if something’s off
@@ -23,23 +305,42 @@ pub async fn make_client(region: Option<&str>) -> Result<Client> {
         None => RegionProviderChain::default_provider(),
     };
 
-    let config = aws_config::defaults(BehaviorVersion::latest())
+    // Adaptive retries rate-limit the client itself once CloudWatch starts
+    // throttling, on top of the backoff between dashboards in bulk runs.
+    let loader = aws_config::defaults(BehaviorVersion::latest())
         .region(region_provider)
-        .load()
-        .await;
+        .retry_config(RetryConfig::adaptive());
+    match profile {
+        Some(profile) => loader.profile_name(profile),
+        None => loader,
+    }
+}
 
-    Ok(Client::new(&config))
+/// The CloudWatch endpoint to use instead of AWS's: `flag`, else
+/// `CWNOTE_ENDPOINT_URL`.
+fn resolve_endpoint_url(flag: Option<&str>) -> Result<Option<String>> {
+    let url = flag
+        .map(str::to_string)
+        .or_else(|| std::env::var(ENDPOINT_URL_ENV).ok())
+        .filter(|url| !url.trim().is_empty());
+    match url {
+        Some(url) if !(url.starts_with("http://") || url.starts_with("https://")) => Err(anyhow!(
+            "endpoint URL '{url}' must start with http:// or https://"
+        )),
+        url => Ok(url),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use anyhow::Context;
 
     const TEST_REGION: &str = "eu-central-1";
 
     #[tokio::test]
     async fn explicit_region_override_wins() {
-        let client = make_client(Some(TEST_REGION))
+        let client = make_client(Some(TEST_REGION), None, None)
             .await
             .expect("client should be created");
 
@@ -51,4 +352,50 @@ mod tests {
 
         assert_eq!(region, TEST_REGION);
     }
+
+    #[tokio::test]
+    async fn custom_endpoint_url_is_accepted() {
+        make_client(Some(TEST_REGION), None, Some("http://localhost:4566"))
+            .await
+            .expect("client should be created");
+
+        let err = resolve_endpoint_url(Some("localhost:4566")).unwrap_err();
+        assert!(err.to_string().contains("http://"));
+    }
+
+    #[test]
+    fn account_id_comes_from_the_role_arn() {
+        let role = |arn: &str| AssumeRole {
+            role_arn: arn.to_string(),
+            external_id: None,
+            session_name: "cwnote".to_string(),
+        };
+        assert_eq!(
+            role("arn:aws:iam::123456789012:role/cwnote").account_id(),
+            Some("123456789012")
+        );
+        assert_eq!(role("cwnote").account_id(), None);
+    }
+
+    #[test]
+    fn sdk_errors_are_told_apart_from_input_errors() {
+        let timed_out = Err::<(), _>(SdkError::<GetDashboardError>::timeout_error(
+            "request has timed out",
+        ))
+        .context("failed to get dashboard orders")
+        .unwrap_err();
+        assert!(is_aws_error(&timed_out));
+        // Only SDK errors count, not messages that look like them.
+        assert!(!is_aws_error(&anyhow!("service error")));
+        assert!(!is_aws_error(&anyhow!("invalid --time 'soon'")));
+    }
+
+    #[test]
+    fn sdk_timestamps_convert_to_chrono() {
+        let dt = SmithyDateTime::from_secs(1_737_374_400);
+        assert_eq!(
+            to_chrono(&dt).unwrap().to_rfc3339(),
+            "2025-01-20T12:00:00+00:00"
+        );
+    }
 }
@@ -0,0 +1,156 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Error, Result};
+use aws_sdk_s3::primitives::ByteStream;
+use chrono::{DateTime, Utc};
+
+use crate::annotate::{self, TS_FORMAT};
+
+const META_DASHBOARD: &str = "dashboard-name";
+const META_RUN_ID: &str = "run-id";
+const META_TIMESTAMP: &str = "timestamp";
+
+/// File name of a backup, like the exports:
+/// `2025-01-20-12-00-00-service-dashboard.json`.
+fn backup_name(dashboard_name: &str, taken: DateTime<Utc>) -> String {
+    format!(
+        "{}-{}.json",
+        taken.format(TS_FORMAT),
+        annotate::export_name(dashboard_name)
+    )
+}
+
+/// Write the body a dashboard had before cwnote changed it to `dir` and
+/// return its path. Reverting is a PutDashboard of the file's content.
+pub fn save_local(dir: &Path, dashboard_name: &str, original_body: &str) -> Result<PathBuf> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("could not create backup directory {}", dir.display()))?;
+    let path = dir.join(backup_name(dashboard_name, Utc::now()));
+    fs::write(&path, original_body)
+        .with_context(|| format!("could not write backup {}", path.display()))?;
+    Ok(path)
+}
+
+/// Bucket and key prefix backups are uploaded to, e.g.
+/// `s3://team-backups/cwnote`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Location {
+    pub bucket: String,
+    /// Key prefix without a trailing `/`; empty for the bucket root.
+    pub prefix: String,
+}
+
+impl S3Location {
+    /// Key of the backup of `dashboard_name` taken at `taken`.
+    pub fn key(&self, dashboard_name: &str, taken: DateTime<Utc>) -> String {
+        let name = backup_name(dashboard_name, taken);
+        if self.prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{name}", self.prefix)
+        }
+    }
+}
+
+impl FromStr for S3Location {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("S3 location '{s}' must look like s3://bucket/prefix");
+        let rest = s.strip_prefix("s3://").ok_or_else(invalid)?;
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+        })
+    }
+}
+
+impl fmt::Display for S3Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s3://{}/{}", self.bucket, self.prefix)
+    }
+}
+
+/// Uploads pre-change bodies to S3 so the whole team can restore them.
+#[derive(Debug, Clone)]
+pub struct S3Backup {
+    pub client: aws_sdk_s3::Client,
+    pub location: S3Location,
+}
+
+impl S3Backup {
+    /// Upload the body a dashboard had before `run_id` changed it, with the
+    /// dashboard name, run id and time as object metadata. Returns the
+    /// object's `s3://` URI.
+    pub async fn save(
+        &self,
+        dashboard_name: &str,
+        run_id: &str,
+        original_body: &str,
+    ) -> Result<String> {
+        let taken = Utc::now();
+        let key = self.location.key(dashboard_name, taken);
+        self.client
+            .put_object()
+            .bucket(&self.location.bucket)
+            .key(&key)
+            .content_type("application/json")
+            .metadata(META_DASHBOARD, dashboard_name)
+            .metadata(META_RUN_ID, run_id)
+            .metadata(META_TIMESTAMP, taken.to_rfc3339())
+            .body(ByteStream::from(original_body.as_bytes().to_vec()))
+            .send()
+            .await
+            .with_context(|| {
+                format!(
+                    "could not upload backup to s3://{}/{key}",
+                    self.location.bucket
+                )
+            })?;
+        Ok(format!("s3://{}/{key}", self.location.bucket))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_keeps_the_original_body_under_name_and_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let backups = dir.path().join("backups");
+        let body = r#"{"widgets":[]}"#;
+
+        let path = save_local(&backups, "Orders/Prod", body).unwrap();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(name.ends_with("-orders-prod.json"), "{name}");
+        assert_eq!(name.len(), "2025-01-20-12-00-00-orders-prod.json".len());
+        assert_eq!(fs::read_to_string(&path).unwrap(), body);
+    }
+
+    #[test]
+    fn s3_locations_parse_and_name_keys() {
+        let taken = "2025-01-20T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let location: S3Location = "s3://team-backups/cwnote/dashboards/".parse().unwrap();
+        assert_eq!(location.bucket, "team-backups");
+        assert_eq!(
+            location.key("Orders", taken),
+            "cwnote/dashboards/2025-01-20-12-00-00-orders.json"
+        );
+
+        let root: S3Location = "s3://team-backups".parse().unwrap();
+        assert_eq!(root.key("Orders", taken), "2025-01-20-12-00-00-orders.json");
+
+        for bad in ["team-backups/cwnote", "s3://", "s3:///cwnote"] {
+            assert!(bad.parse::<S3Location>().is_err(), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use serde_json::Value;
+
+/// Unchanged lines shown around each change.
+pub const CONTEXT_LINES: usize = 3;
+
+// Above this many line pairs (after trimming the common start and end), the
+// changed region is shown as removed then added instead of aligned.
+const MAX_ALIGNED_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op<'a> {
+    Equal(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+/// Unified diff of two dashboard bodies, pretty-printed so that every key of
+/// an annotation is on its own line. Empty if the bodies are equal.
+pub fn diff(before: &Value, after: &Value) -> String {
+    let before = serde_json::to_string_pretty(before).unwrap_or_default();
+    let after = serde_json::to_string_pretty(after).unwrap_or_default();
+    diff_lines(&before, &after, CONTEXT_LINES)
+}
+
+/// Unified diff of two texts, line by line, with `context` unchanged lines
+/// around each change.
+pub fn diff_lines(before: &str, after: &str, context: usize) -> String {
+    let before: Vec<&str> = before.lines().collect();
+    let after: Vec<&str> = after.lines().collect();
+    let ops = align(&before, &after);
+    if ops.iter().all(|op| matches!(op, Op::Equal(_))) {
+        return String::new();
+    }
+
+    let changed: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, Op::Equal(_)))
+        .map(|(i, _)| i)
+        .collect();
+    // Merge changes whose context would overlap into one hunk.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changed {
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = Vec::new();
+    for (start, end) in hunks {
+        let (old_start, new_start) = positions(&ops[..start]);
+        let (old_len, new_len) = positions(&ops[start..end]);
+        out.push(format!(
+            "@@ -{},{old_len} +{},{new_len} @@",
+            old_start + 1,
+            new_start + 1
+        ));
+        out.extend(ops[start..end].iter().map(|op| match op {
+            Op::Equal(line) => format!(" {line}"),
+            Op::Delete(line) => format!("-{line}"),
+            Op::Insert(line) => format!("+{line}"),
+        }));
+    }
+    out.join("\n")
+}
+
+// Lines of the old and the new text covered by `ops`.
+fn positions(ops: &[Op]) -> (usize, usize) {
+    ops.iter().fold((0, 0), |(old, new), op| match op {
+        Op::Equal(_) => (old + 1, new + 1),
+        Op::Delete(_) => (old + 1, new),
+        Op::Insert(_) => (old, new + 1),
+    })
+}
+
+/// Longest common subsequence alignment of the lines. Annotations are
+/// inserted into otherwise unchanged bodies, so the common start and end are
+/// trimmed first and only the region between them is aligned.
+fn align<'a>(before: &[&'a str], after: &[&'a str]) -> Vec<Op<'a>> {
+    let prefix = before.iter().zip(after).take_while(|(a, b)| a == b).count();
+    let suffix = before[prefix..]
+        .iter()
+        .rev()
+        .zip(after[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old = &before[prefix..before.len() - suffix];
+    let new = &after[prefix..after.len() - suffix];
+
+    let mut ops: Vec<Op> = before[..prefix].iter().map(|l| Op::Equal(l)).collect();
+    if old.len().saturating_mul(new.len()) > MAX_ALIGNED_CELLS {
+        ops.extend(old.iter().map(|l| Op::Delete(l)));
+        ops.extend(new.iter().map(|l| Op::Insert(l)));
+    } else {
+        // lengths[i][j]: LCS length of old[i..] and new[j..].
+        let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                lengths[i][j] = if old[i] == new[j] {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() && j < new.len() {
+            if old[i] == new[j] {
+                ops.push(Op::Equal(old[i]));
+                i += 1;
+                j += 1;
+            } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+                ops.push(Op::Delete(old[i]));
+                i += 1;
+            } else {
+                ops.push(Op::Insert(new[j]));
+                j += 1;
+            }
+        }
+        ops.extend(old[i..].iter().map(|l| Op::Delete(l)));
+        ops.extend(new[j..].iter().map(|l| Op::Insert(l)));
+    }
+    ops.extend(before[before.len() - suffix..].iter().map(|l| Op::Equal(l)));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn diff_shows_inserted_annotation_with_context() {
+        let before = json!({
+            "widgets": [{
+                "type": "metric",
+                "properties": { "title": "Latency", "annotations": { "vertical": [] } }
+            }]
+        });
+        let after = json!({
+            "widgets": [{
+                "type": "metric",
+                "properties": {
+                    "title": "Latency",
+                    "annotations": { "vertical": [{ "label": "version: 1.9.0", "value": "2025-01-20T12:00:00Z" }] }
+                }
+            }]
+        });
+
+        let diff = diff(&before, &after);
+        assert!(diff.starts_with("@@ -"), "{diff}");
+        assert!(diff.contains("-          \"vertical\": []"), "{diff}");
+        assert!(
+            diff.contains("+              \"label\": \"version: 1.9.0\","),
+            "{diff}"
+        );
+        assert!(diff.contains("   \"title\": \"Latency\""), "{diff}");
+        assert!(super::diff(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn distant_changes_get_separate_hunks() {
+        let before = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj";
+        let after = "a\nB\nc\nd\ne\nf\ng\nh\nI\nj";
+
+        assert_eq!(
+            diff_lines(before, after, 1),
+            "@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n@@ -8,3 +8,3 @@\n h\n-i\n+I\n j"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+use crate::annotate::{
+    annotation_head, JSON_KEY_ANNOTATIONS, JSON_KEY_PROPERTIES, JSON_KEY_RUN_ID, JSON_KEY_VERTICAL,
+};
+
+/// Largest dashboard body (serialized, in bytes) cwnote will write: the
+/// 100 KB PutDashboard rejects bodies above.
+pub const MAX_BODY_BYTES: usize = 100_000;
+
+/// Body size (in bytes) above which a write warns that the dashboard is
+/// getting close to [`MAX_BODY_BYTES`].
+pub const DEFAULT_WARN_BYTES: usize = 80_000;
+
+/// Fail with a readable error, instead of a rejected PutDashboard, when a
+/// body of `size` bytes would not fit.
+pub fn ensure_fits(dashboard_name: &str, size: usize) -> Result<()> {
+    if size > MAX_BODY_BYTES {
+        return Err(anyhow!(
+            "{dashboard_name}: body would be {size} bytes, over the {MAX_BODY_BYTES} byte limit; \
+             prune old annotations first, e.g. `cwnote prune --older-than 30d`"
+        ));
+    }
+    Ok(())
+}
+
+/// Serialized size of `value` as written by PutDashboard.
+pub fn json_len(value: &Value) -> usize {
+    serde_json::to_string(value).map(|s| s.len()).unwrap_or(0)
+}
+
+/// Remove cwnote annotations of the oldest runs (never `keep_run_id`) until
+/// at least `bytes` have been freed. Run ids start with their UTC start time,
+/// so sorting them sorts runs by age. Returns the removed annotations with the
+/// index of the widget they were on.
+pub fn prune_oldest_runs(body: &mut Value, keep_run_id: &str, bytes: usize) -> Vec<(usize, Value)> {
+    let Some(widgets) = body.get_mut("widgets").and_then(|w| w.as_array_mut()) else {
+        return Vec::new();
+    };
+
+    let mut run_ids: Vec<String> = widgets
+        .iter()
+        .filter_map(|w| vertical(w))
+        .flatten()
+        .filter_map(|ann| annotation_head(ann)?.get(JSON_KEY_RUN_ID)?.as_str())
+        .filter(|run_id| *run_id != keep_run_id)
+        .map(str::to_string)
+        .collect();
+    run_ids.sort_unstable();
+    run_ids.dedup();
+
+    let mut freed = 0usize;
+    let mut removed = Vec::new();
+    for run_id in run_ids {
+        if freed >= bytes {
+            break;
+        }
+        for (i, widget) in widgets.iter_mut().enumerate() {
+            let Some(arr) = widget
+                .get_mut(JSON_KEY_PROPERTIES)
+                .and_then(|p| p.get_mut(JSON_KEY_ANNOTATIONS))
+                .and_then(|a| a.get_mut(JSON_KEY_VERTICAL))
+                .and_then(|v| v.as_array_mut())
+            else {
+                continue;
+            };
+            arr.retain(|ann| {
+                let of_run = annotation_head(ann)
+                    .and_then(|h| h.get(JSON_KEY_RUN_ID))
+                    .and_then(|r| r.as_str())
+                    == Some(run_id.as_str());
+                if of_run {
+                    // The annotation plus its separating comma.
+                    freed += json_len(ann) + 1;
+                    removed.push((i, ann.clone()));
+                }
+                !of_run
+            });
+        }
+    }
+    removed
+}
+
+fn vertical(widget: &Value) -> Option<&Vec<Value>> {
+    widget
+        .get(JSON_KEY_PROPERTIES)?
+        .get(JSON_KEY_ANNOTATIONS)?
+        .get(JSON_KEY_VERTICAL)?
+        .as_array()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn ensure_fits_rejects_bodies_over_the_limit() {
+        assert!(ensure_fits("D", MAX_BODY_BYTES).is_ok());
+        let err = ensure_fits("D", MAX_BODY_BYTES + 1).unwrap_err();
+        assert!(err.to_string().contains("byte limit"), "{err}");
+    }
+
+    #[test]
+    fn prune_removes_oldest_runs_first_and_keeps_the_current_one() {
+        let mut body = json!({
+            "widgets": [
+                {
+                    "type": "metric",
+                    "properties": {
+                        "annotations": {
+                            "vertical": [
+                                { "label": "version: 1", "value": "2025-01-01T00:00:00Z", "cwnoteRunId": "20250101T000000Z-aaaaaaaa" },
+                                { "label": "manual", "value": "2024-01-01T00:00:00Z" },
+                                { "label": "version: 2", "value": "2025-01-02T00:00:00Z", "cwnoteRunId": "20250102T000000Z-bbbbbbbb" },
+                                { "label": "version: 3", "value": "2025-01-03T00:00:00Z", "cwnoteRunId": "20250103T000000Z-cccccccc" }
+                            ]
+                        }
+                    }
+                }
+            ]
+        });
+
+        let pruned = prune_oldest_runs(&mut body, "20250103T000000Z-cccccccc", 1);
+        assert_eq!(pruned.len(), 1);
+        assert_eq!(pruned[0].0, 0);
+        assert_eq!(pruned[0].1["label"], json!("version: 1"));
+        let labels: Vec<_> = body["widgets"][0]["properties"]["annotations"]["vertical"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a["label"].as_str().unwrap())
+            .collect();
+        assert_eq!(labels, vec!["manual", "version: 2", "version: 3"]);
+
+        assert_eq!(
+            prune_oldest_runs(&mut body, "20250103T000000Z-cccccccc", usize::MAX).len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use anyhow::{anyhow, Result};
+use aws_sdk_cloudwatch::Client;
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+
+use crate::annotate::{self, AnnotateOptions, AnnotationSpec, DashboardOutcome};
+use crate::interrupt;
+use crate::selector::WidgetSelector;
+
+const MAX_PHASE_PERCENT: u8 = 100;
+
+/// Check that traffic-shift phases are 1..=100 percent and strictly increasing.
+pub fn validate_phases(phases: &[u8]) -> Result<()> {
+    if phases.is_empty() {
+        return Err(anyhow!("--phases needs at least one percentage"));
+    }
+    if let Some(bad) = phases.iter().find(|p| **p == 0 || **p > MAX_PHASE_PERCENT) {
+        return Err(anyhow!("phase {bad}% is outside 1..=100"));
+    }
+    if phases.windows(2).any(|w| w[0] >= w[1]) {
+        return Err(anyhow!("--phases must be strictly increasing"));
+    }
+    Ok(())
+}
+
+/// Annotation for a single phase, e.g. `deploy 50%: 1.9.0`.
+pub fn phase_spec(
+    percent: u8,
+    at: DateTime<Utc>,
+    label: &str,
+    value: &str,
+    run_id: &str,
+) -> AnnotationSpec {
+    AnnotationSpec {
+        label: format!("{label} {percent}%"),
+        value: value.to_string(),
+        time: Some(at.to_rfc3339()),
+        end_time: None,
+        run_id: run_id.to_string(),
+    }
+}
+
+/// Annotations for all phases, the first at `start` and each following one
+/// `interval` later.
+pub fn planned_specs(
+    phases: &[u8],
+    start: DateTime<Utc>,
+    interval: Duration,
+    label: &str,
+    value: &str,
+    run_id: &str,
+) -> Vec<AnnotationSpec> {
+    let mut at = start;
+    phases
+        .iter()
+        .map(|percent| {
+            let spec = phase_spec(*percent, at, label, value, run_id);
+            at += interval;
+            spec
+        })
+        .collect()
+}
+
+/// What a live canary run writes to.
+pub struct LiveTarget<'a> {
+    pub dashboard: Option<&'a str>,
+    pub suffix: Option<&'a str>,
+    pub selector: &'a WidgetSelector,
+    pub options: &'a AnnotateOptions,
+}
+
+/// Write one annotation per phase as the rollout happens: annotate at "now",
+/// then wait `interval` before moving on to the next phase.
+pub async fn run_live(
+    client: &Client,
+    target: &LiveTarget<'_>,
+    phases: &[u8],
+    interval: Duration,
+    label: &str,
+    value: &str,
+    run_id: &str,
+) -> Result<Vec<DashboardOutcome>> {
+    let wait = interval
+        .to_std()
+        .map_err(|_| anyhow!("--interval must be positive"))?;
+
+    let mut outcomes = Vec::new();
+    for (i, percent) in phases.iter().enumerate() {
+        info!("Canary phase {percent}% ({}/{})", i + 1, phases.len());
+        let spec = phase_spec(*percent, Utc::now(), label, value, run_id);
+        let phase_outcomes = annotate::annotate_target(
+            client,
+            target.dashboard,
+            target.suffix,
+            std::slice::from_ref(&spec),
+            target.options,
+            target.selector,
+        )
+        .await?;
+        outcomes.extend(phase_outcomes);
+
+        if i + 1 < phases.len() && !interrupt::sleep(wait).await {
+            warn!(
+                "Interrupted: canary phases not annotated: {:?}",
+                &phases[i + 1..]
+            );
+            break;
+        }
+    }
+
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const TEST_RUN_ID: &str = "20250120T120000Z-aaaaaaaa";
+
+    #[test]
+    fn phases_must_be_increasing_percentages() {
+        assert!(validate_phases(&[10, 50, 100]).is_ok());
+        assert!(validate_phases(&[]).is_err());
+        assert!(validate_phases(&[0, 50]).is_err());
+        assert!(validate_phases(&[10, 150]).is_err());
+        assert!(validate_phases(&[50, 10]).is_err());
+        assert!(validate_phases(&[50, 50]).is_err());
+    }
+
+    #[test]
+    fn planned_phases_are_spaced_by_interval() {
+        let start = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+        let specs = planned_specs(
+            &[10, 50, 100],
+            start,
+            Duration::minutes(10),
+            "deploy",
+            "1.9.0",
+            TEST_RUN_ID,
+        );
+
+        let labels: Vec<_> = specs.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, vec!["deploy 10%", "deploy 50%", "deploy 100%"]);
+
+        let times: Vec<_> = specs.iter().map(|s| s.time.clone().unwrap()).collect();
+        assert_eq!(
+            times,
+            vec![
+                "2025-01-20T12:00:00+00:00",
+                "2025-01-20T12:10:00+00:00",
+                "2025-01-20T12:20:00+00:00"
+            ]
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::{anyhow, Context, Result};
+use aws_sdk_ssm::Client;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use log::{info, warn};
+
+use crate::annotate::AnnotationSpec;
+use crate::timezone::Timezone;
+
+/// One event of an SSM Change Calendar, e.g. a change freeze.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarEvent {
+    pub summary: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl CalendarEvent {
+    /// Band annotation covering the event, e.g. `freeze: Black Friday`.
+    pub fn to_spec(&self, label: &str, run_id: &str) -> AnnotationSpec {
+        AnnotationSpec {
+            label: label.to_string(),
+            value: self.summary.clone(),
+            time: Some(self.start.to_rfc3339()),
+            end_time: Some(self.end.to_rfc3339()),
+            run_id: run_id.to_string(),
+        }
+    }
+}
+
+/// Read the events of the Change Calendar `calendar` (name or ARN).
+pub async fn fetch_events(client: &Client, calendar: &str) -> Result<Vec<CalendarEvent>> {
+    let resp = client
+        .get_document()
+        .name(calendar)
+        .send()
+        .await
+        .with_context(|| format!("failed to get change calendar {calendar}"))?;
+    let content = resp
+        .content()
+        .with_context(|| format!("change calendar {calendar} has no content"))?;
+
+    let events = parse_events(content)
+        .with_context(|| format!("change calendar {calendar} is not valid iCalendar"))?;
+    info!(
+        "Found {} event(s) in change calendar {calendar}",
+        events.len()
+    );
+    Ok(events)
+}
+
+/// The `VEVENT`s of an iCalendar document, as Change Calendar stores them.
+///
+/// Recurring events are only drawn at their first occurrence; a warning
+/// names them.
+pub fn parse_events(ics: &str) -> Result<Vec<CalendarEvent>> {
+    let mut events = Vec::new();
+    let mut current: Option<Vec<(String, String)>> = None;
+
+    for line in unfold(ics) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        match (name, value) {
+            ("BEGIN", "VEVENT") => current = Some(Vec::new()),
+            ("END", "VEVENT") => {
+                let props = current.take().context("END:VEVENT without BEGIN")?;
+                events.push(event(&props)?);
+            }
+            _ => {
+                if let Some(ref mut props) = current {
+                    props.push((name.to_string(), value.to_string()));
+                }
+            }
+        }
+    }
+    Ok(events)
+}
+
+// Long iCalendar lines continue on lines starting with a space or tab.
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in ics.lines() {
+        match (raw.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(raw.to_string()),
+        }
+    }
+    lines
+}
+
+fn event(props: &[(String, String)]) -> Result<CalendarEvent> {
+    // Property names may carry parameters, e.g. `DTSTART;TZID=Europe/Berlin`.
+    let find = |wanted: &str| {
+        props
+            .iter()
+            .find(|(name, _)| name.split(';').next() == Some(wanted))
+    };
+
+    let summary = find("SUMMARY")
+        .map(|(_, value)| unescape(value))
+        .unwrap_or_else(|| "change freeze".to_string());
+    let (name, value) =
+        find("DTSTART").with_context(|| format!("event '{summary}' has no DTSTART"))?;
+    let start = parse_time(name, value)?;
+    let end = match find("DTEND") {
+        Some((name, value)) => parse_time(name, value)?,
+        None => return Err(anyhow!("event '{summary}' has no DTEND")),
+    };
+    if find("RRULE").is_some() {
+        warn!("Event '{summary}' repeats: only its first occurrence is annotated");
+    }
+
+    Ok(CalendarEvent {
+        summary,
+        start,
+        end,
+    })
+}
+
+/// Parse a DATE-TIME (`20250120T120000Z`, or local with `TZID=`) or an
+/// all-day DATE (`20250120`, taken as UTC midnight).
+fn parse_time(name: &str, value: &str) -> Result<DateTime<Utc>> {
+    let tzid = name
+        .split(';')
+        .find_map(|param| param.strip_prefix("TZID="));
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .with_context(|| format!("invalid time '{value}'"))?;
+        return Ok(naive.and_utc());
+    }
+    if let Ok(naive) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return match tzid {
+            Some(tzid) => tzid.parse::<Timezone>()?.resolve(naive),
+            None => Ok(naive.and_utc()),
+        };
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y%m%d")
+        .with_context(|| format!("invalid time '{value}'"))?;
+    Ok(date.and_time(chrono::NaiveTime::MIN).and_utc())
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("\\n", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//AWS//Change Calendar 1.0//EN\r
+BEGIN:VEVENT\r
+UID:1\r
+SUMMARY:Black Friday\\, all regions\r
+DTSTART;TZID=America/New_York:20251128T000000\r
+DTEND;TZID=America/New_York:20251201T000000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:2\r
+SUMMARY:Year-end freeze with a very long description that the exporter\r
+  folded\r
+DTSTART;VALUE=DATE:20251222\r
+DTEND;VALUE=DATE:20260102\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn parses_events_into_bands() {
+        let events = parse_events(CALENDAR).unwrap();
+        assert_eq!(events.len(), 2);
+
+        let spec = events[0].to_spec("freeze", "20250120T120000Z-aaaaaaaa");
+        assert_eq!(spec.value, "Black Friday, all regions");
+        assert_eq!(spec.time.as_deref(), Some("2025-11-28T05:00:00+00:00"));
+        assert_eq!(spec.end_time.as_deref(), Some("2025-12-01T05:00:00+00:00"));
+
+        assert_eq!(
+            events[1].summary,
+            "Year-end freeze with a very long description that the exporter folded"
+        );
+        assert_eq!(events[1].start.to_rfc3339(), "2025-12-22T00:00:00+00:00");
+    }
+
+    #[test]
+    fn rejects_events_without_end() {
+        let ics = "BEGIN:VEVENT\nSUMMARY:x\nDTSTART:20250120T120000Z\nEND:VEVENT\n";
+        assert!(parse_events(ics).is_err());
+    }
+}
@@ -1,16 +1,79 @@
-use clap::{ArgGroup, Parser};
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use clap::{ArgAction, ArgGroup, Args, Parser};
+use regex::Regex;
+use std::path::PathBuf;
+
+use cwnote::annotate::{DryRunLevel, VerticalFill, DEFAULT_ANNOTATION_BUDGET};
+use cwnote::backup::S3Location;
+use cwnote::body_size::DEFAULT_WARN_BYTES;
+use cwnote::color;
+use cwnote::consistency::Member;
+use cwnote::duration;
+use cwnote::glob::Glob;
+use cwnote::horizontal::{Fill, YAxis};
+use cwnote::iac::{IacGuard, IacMarker, DEFAULT_IAC_MARKER};
+use cwnote::list::{ListFilter, OutputFormat};
+use cwnote::logging::LogFormat;
+use cwnote::pacing::{RetryPolicy, DEFAULT_RETRY_ATTEMPTS, DEFAULT_RETRY_BASE_DELAY};
+use cwnote::provenance::{Provenance, ProvenanceFilter};
+use cwnote::remove::Direction;
+use cwnote::schema::SchemaKind;
+use cwnote::select_expr::SelectExpr;
+use cwnote::selector::{GridPosition, WidgetSelector};
+use cwnote::selftest;
+use cwnote::time_display::TimeDisplay;
+use cwnote::timezone::Timezone;
+use cwnote::widget_support;
 
 const APP_NAME: &str = "cwnote";
 const ABOUT_TEXT: &str = "Add annotation to CloudWatch dashboards.";
 const DEFAULT_LABEL: &str = "version";
+const DEFAULT_LOGS_SINCE: &str = "1d";
+const DEFAULT_APPCONFIG_LABEL: &str = "config";
+const DEFAULT_APPCONFIG_SINCE: &str = "7d";
+const DEFAULT_SFN_LABEL: &str = "batch";
+const DEFAULT_SFN_SINCE: &str = "1d";
+// More parallel PutDashboard calls than this only buy throttling.
+const MAX_CONCURRENCY: u64 = 32;
+const DEFAULT_SYNTHETICS_LABEL: &str = "synthetics";
+const DEFAULT_SYNTHETICS_SINCE: &str = "1d";
+const DEFAULT_CHANGE_CALENDAR_LABEL: &str = "freeze";
+const DEFAULT_CHANGE_CALENDAR_SINCE: &str = "30d";
+const DEFAULT_CANARY_LABEL: &str = "canary";
+const DEFAULT_CANARY_INTERVAL: &str = "10m";
+const ARG_GROUP_CANARY_MODE: &str = "canary_mode";
+const ARG_START: &str = "start";
+const ARG_LIVE: &str = "live";
 const ARG_GROUP_TARGET: &str = "target";
 const ARG_DASHBOARD: &str = "dashboard";
 const ARG_DASHBOARD_SUFFIX: &str = "dashboard_suffix";
+const ARG_DASHBOARD_PREFIX: &str = "dashboard_prefix";
+const ARG_DASHBOARD_REGEX: &str = "dashboard_regex";
+const ARG_DASHBOARD_GLOB: &str = "dashboard_glob";
+const ARG_DASHBOARDS_FILE: &str = "dashboards_file";
+const ARG_DASHBOARDS_STDIN: &str = "dashboards_stdin";
+const ARG_SERVICES_FILE: &str = "services_file";
+const ARG_INPUT_FILE: &str = "input_file";
+const ARG_GROUP_VALUE: &str = "value_source";
+const ARG_VALUE: &str = "value";
+const ARG_GROUP_REMOVE_FILTER: &str = "remove_filter";
+const ARG_RUN_ID: &str = "run_id";
+const ARG_LABEL: &str = "label";
+const ARG_UNTAG_TITLE: &str = "untag_title";
+const ARG_CREATOR: &str = "creator";
+const ARG_SCOPE: &str = "scope";
+const ARG_EXPIRED: &str = "expired";
+const ARG_VALUE_FROM: &str = "value_from";
+const ARG_GROUP_MAINTENANCE: &str = "maintenance_policy";
+const ARG_KEEP: &str = "keep";
+const ARG_MAX_SIZE_MB: &str = "max_size_mb";
+const ARG_GZIP: &str = "gzip";
 
 /**
 CloudWatch dashoard vertical annotator.
 */
-#[derive(Debug, Parser)]
+#[derive(Debug, Clone, Parser)]
 #[command(name = APP_NAME)]
 #[command(version, about = ABOUT_TEXT, long_about = None)]
 pub struct Cli {
@@ -18,168 +81,2770 @@ pub struct Cli {
     #[arg(long)]
     pub region: Option<String>,
 
+    /// Named AWS profile from ~/.aws/config to use instead of AWS_PROFILE.
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Send CloudWatch calls to this endpoint instead of AWS's, e.g.
+    /// http://localhost:4566 for LocalStack (or set CWNOTE_ENDPOINT_URL).
+    #[arg(long)]
+    pub endpoint_url: Option<String>,
+
+    /// Run in each of these regions, one after the other, e.g.: "eu-central-1,us-east-1".
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["region", "all_regions"])]
+    pub regions: Vec<String>,
+
+    /// Run in every region enabled by default that the credentials can use.
+    #[arg(long, conflicts_with = "region")]
+    pub all_regions: bool,
+
+    /// Assume this IAM role (via STS) and run in its account. Repeatable, to
+    /// run in several accounts one after the other.
+    #[arg(long, value_delimiter = ',')]
+    pub role_arn: Vec<String>,
+
+    /// External id required by the trust policy of the --role-arn roles.
+    #[arg(long, requires = "role_arn")]
+    pub external_id: Option<String>,
+
+    /// With several accounts or regions, stop at the first one that fails
+    /// instead of going on with the others.
+    #[arg(long)]
+    pub fail_fast: bool,
+
+    /// How times are shown in output (annotations themselves are always stored as UTC).
+    #[arg(long, global = true, value_enum, default_value_t = TimeDisplay::Utc)]
+    pub time_display: TimeDisplay,
+
+    /// Timezone for timestamps given without an offset, e.g. `2025-03-01 14:00`
+    /// (an IANA name like Europe/Berlin, UTC or local).
+    #[arg(long, global = true, default_value = "UTC")]
+    pub timezone: Timezone,
+
+    /// Never assume a terminal (no colors, nothing waits for input). Detected
+    /// automatically when stdin or stderr is not a TTY, e.g. in CI.
+    #[arg(long, global = true)]
+    pub non_interactive: bool,
+
+    /// More log output: -v for cwnote's debug lines, -vv adds the AWS SDK's
+    /// requests and responses, -vvv everything. RUST_LOG wins if set.
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Write log lines as text or as one JSON object per line.
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Print the result of the run to stdout, one line or JSON object per
+    /// target: changed, unchanged, skipped or failed, with the reason.
+    #[arg(long, global = true, value_enum)]
+    pub result_format: Option<OutputFormat>,
+
+    /// Exit with 6 when the run changed something and 0 when it didn't
+    /// (1 is still a failure), like `terraform plan -detailed-exitcode`.
+    #[arg(long, global = true)]
+    pub detailed_exitcode: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
-#[derive(Debug, Parser)]
+/// Widget selection shared by every subcommand, so annotate and remove (and
+/// the syncs) always pick widgets the same way.
+#[derive(Debug, Clone, Default, Args)]
+pub struct SelectorArgs {
+    /// Only touch widgets whose title contains this substring.
+    #[arg(long)]
+    pub widget_title_contains: Option<String>,
+
+    /// Only touch widgets whose title matches this regex, e.g.: "^(p95|p99) latency".
+    #[arg(long, value_parser = Regex::new)]
+    pub widget_title_regex: Option<Regex>,
+
+    /// Never touch widgets whose title contains this substring, e.g.: "Billing". Repeatable.
+    #[arg(long)]
+    pub exclude_widget_title_contains: Vec<String>,
+
+    /// Never touch widgets whose title matches this regex, e.g.: "^(Billing|Quota)".
+    #[arg(long, value_parser = Regex::new)]
+    pub exclude_widget_title_regex: Option<Regex>,
+
+    /// Only touch widgets of this type or metric view: metric, timeSeries, bar or gauge.
+    /// Repeatable, e.g.: "--widget-type gauge --widget-type bar".
+    #[arg(long, value_parser = widget_support::parse_kind)]
+    pub widget_type: Vec<String>,
+
+    /// Only touch the widgets at these places (0-based) of the dashboard's widgets list,
+    /// e.g.: "0,3,5". For dashboards whose widgets have no titles.
+    #[arg(long, value_delimiter = ',')]
+    pub widget_index: Vec<usize>,
+
+    /// Only touch the widget whose top-left corner is at this grid position, e.g.: "0,6".
+    /// Repeatable.
+    #[arg(long)]
+    pub widget_position: Vec<GridPosition>,
+
+    /// Only touch widgets satisfying this expression, combining tests on title, type,
+    /// region, namespace, metric and account with and/or/not, e.g.:
+    /// '(title ~ "Latency" or title ~ "Errors") and not region == "us-west-2"'.
+    #[arg(long)]
+    pub select: Option<SelectExpr>,
+
+    /// Only touch widgets in the same row (y range) as the widget with this exact title.
+    #[arg(long)]
+    pub row_of: Option<String>,
+
+    /// Only touch widgets plotting a metric from this namespace, e.g.: "AWS/DynamoDB".
+    #[arg(long)]
+    pub metric_namespace: Option<String>,
+
+    /// Only touch widgets plotting a metric of this name, e.g.: "Errors". With
+    /// --metric-namespace, the same metric must be in that namespace.
+    #[arg(long)]
+    pub metric_name: Option<String>,
+
+    /// Only touch widgets whose region property is this region, e.g.: "us-east-1".
+    /// Widgets without a region property are skipped.
+    #[arg(long)]
+    pub widget_region: Option<String>,
+
+    /// Only touch widgets showing metrics of this AWS account (the accountId of a
+    /// cross-account metric), e.g.: "123456789012".
+    #[arg(long)]
+    pub widget_account_id: Option<String>,
+
+    /// Only touch widgets below the text widget (section header) containing this string,
+    /// up to the next text widget, e.g.: "Checkout".
+    #[arg(long)]
+    pub section: Option<String>,
+}
+
+impl SelectorArgs {
+    pub fn widget_selector(&self) -> WidgetSelector {
+        WidgetSelector {
+            title_contains: self.widget_title_contains.clone(),
+            title_regex: self.widget_title_regex.clone(),
+            exclude_title_contains: self.exclude_widget_title_contains.clone(),
+            exclude_title_regex: self.exclude_widget_title_regex.clone(),
+            widget_types: self.widget_type.clone(),
+            indices: self.widget_index.clone(),
+            positions: self.widget_position.clone(),
+            expression: self.select.clone(),
+            row_of: self.row_of.clone(),
+            metric_namespace: self.metric_namespace.clone(),
+            metric_name: self.metric_name.clone(),
+            region: self.widget_region.clone(),
+            account_id: self.widget_account_id.clone(),
+            section: self.section.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// What to do about dashboards managed by infrastructure as code.
+#[derive(Debug, Clone, Default, Args)]
+pub struct IacArgs {
+    /// Annotate dashboards carrying the IaC marker anyway (the next apply may revert it).
+    #[arg(long)]
+    pub allow_iac_managed: bool,
+
+    /// key=value marking a dashboard as IaC-managed, found as a top-level body key
+    /// or a line of a text widget, e.g.: "managedBy=terraform".
+    #[arg(long, default_value = DEFAULT_IAC_MARKER)]
+    pub iac_marker: IacMarker,
+}
+
+impl IacArgs {
+    pub fn guard(&self) -> IacGuard {
+        IacGuard {
+            marker: self.iac_marker.clone(),
+            allow: self.allow_iac_managed,
+        }
+    }
+}
+
+/// Retries of throttled dashboard reads and writes.
+#[derive(Debug, Clone, Args)]
+pub struct RetryArgs {
+    /// How often to try a dashboard while CloudWatch throttles (1 disables retries).
+    #[arg(long, default_value_t = DEFAULT_RETRY_ATTEMPTS as u64, value_parser = clap::value_parser!(u64).range(1..))]
+    pub retry_attempts: u64,
+
+    /// Pause after the first throttle in milliseconds, doubled on every further one.
+    #[arg(long, default_value_t = DEFAULT_RETRY_BASE_DELAY.as_millis() as u64)]
+    pub retry_base_delay_ms: u64,
+
+    /// Wait exactly the backoff instead of a random 50-100% of it.
+    #[arg(long)]
+    pub no_retry_jitter: bool,
+}
+
+impl Default for RetryArgs {
+    fn default() -> Self {
+        let policy = RetryPolicy::default();
+        Self {
+            retry_attempts: policy.attempts as u64,
+            retry_base_delay_ms: policy.base_delay.as_millis() as u64,
+            no_retry_jitter: !policy.jitter,
+        }
+    }
+}
+
+impl RetryArgs {
+    pub fn policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            attempts: self.retry_attempts as usize,
+            base_delay: std::time::Duration::from_millis(self.retry_base_delay_ms),
+            jitter: !self.no_retry_jitter,
+        }
+    }
+}
+
+/// Provenance written next to the run id on every annotation, so `list` and
+/// `remove` can find annotations by who made them and why.
+#[derive(Debug, Clone, Default, Args)]
+pub struct ProvenanceArgs {
+    /// Who creates the annotations (default: the CI actor, else $USER).
+    #[arg(long)]
+    pub creator: Option<String>,
+
+    /// Owner of the annotations, e.g.: "payments", to find them again with --scope.
+    #[arg(long)]
+    pub scope: Option<String>,
+
+    /// How long the annotations are meant to stay, e.g.: "30d";
+    /// `remove --expired` deletes them once it has passed.
+    #[arg(long, value_parser = parse_ttl)]
+    pub ttl: Option<String>,
+
+    /// Link to what creates the annotations (default: the CI job, if any).
+    #[arg(long)]
+    pub url: Option<String>,
+}
+
+impl ProvenanceArgs {
+    pub fn provenance(&self) -> Provenance {
+        let detected = Provenance::detect(|key| std::env::var(key).ok());
+        Provenance {
+            creator: self.creator.clone().or(detected.creator),
+            scope: self.scope.clone(),
+            ttl: self.ttl.clone(),
+            url: self.url.clone().or(detected.url),
+        }
+    }
+}
+
+fn parse_ttl(ttl: &str) -> anyhow::Result<String> {
+    duration::parse_duration(ttl).map(|_| ttl.to_string())
+}
+
+fn parse_dedupe_window(window: &str) -> anyhow::Result<chrono::Duration> {
+    let duration = duration::parse_duration(window)?;
+    if duration <= chrono::Duration::zero() {
+        return Err(anyhow!(
+            "a dedupe window of '{window}' would collapse nothing"
+        ));
+    }
+    Ok(duration)
+}
+
+/// How the event syncs (`logs-sync`, `sfn-sync`, ..., `canary`) write their
+/// annotations: the selection and write flags they share with `annotate`.
+#[derive(Debug, Clone, Args)]
+pub struct SyncArgs {
+    /// Dry run: don’t actually update dashboards, just show what would change.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    #[command(flatten)]
+    pub selector: SelectorArgs,
+
+    #[command(flatten)]
+    pub iac: IacArgs,
+
+    #[command(flatten)]
+    pub provenance: ProvenanceArgs,
+
+    #[command(flatten)]
+    pub retry: RetryArgs,
+
+    /// Update up to this many dashboards at once in multi-dashboard runs.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..=MAX_CONCURRENCY))]
+    pub concurrency: u64,
+
+    /// Warn when a widget carries at least this many vertical annotations (0 disables).
+    #[arg(long, default_value_t = DEFAULT_ANNOTATION_BUDGET)]
+    pub annotation_budget: usize,
+
+    /// Warn when the dashboard body reaches this many bytes; the limit is 100 KB (0 disables).
+    #[arg(long, default_value_t = DEFAULT_WARN_BYTES)]
+    pub body_size_warning: usize,
+
+    /// Save each dashboard's original body to this directory before writing it.
+    #[arg(long)]
+    pub backup_dir: Option<PathBuf>,
+
+    /// Upload each dashboard's original body to this S3 location before writing it.
+    #[arg(long)]
+    pub backup_s3: Option<S3Location>,
+
+    /// After writing, read each dashboard back and fail unless the annotations are on the
+    /// expected widgets.
+    #[arg(long)]
+    pub verify: bool,
+}
+
+/// Time range on annotation timestamps, shared by `list`, `current` and `check`.
+#[derive(Debug, Clone, Default, Args)]
+pub struct TimeRangeArgs {
+    /// Only annotations at or after this time: RFC3339, a time in --timezone,
+    /// or a duration ago, e.g.: "90d".
+    #[arg(long)]
+    pub since: Option<String>,
+
+    /// Only annotations at or before this time: RFC3339, a time in --timezone,
+    /// or a duration ago, e.g.: "1d".
+    #[arg(long)]
+    pub until: Option<String>,
+}
+
+impl TimeRangeArgs {
+    /// `filter` restricted to the range, relative times counted back from `now`.
+    pub fn restrict(
+        &self,
+        filter: ListFilter,
+        now: DateTime<Utc>,
+        zone: &Timezone,
+    ) -> anyhow::Result<ListFilter> {
+        let parse = |flag: &str, time: &Option<String>| {
+            time.as_deref()
+                .map(|t| {
+                    duration::parse_time_or_ago(t, now, zone)
+                        .with_context(|| format!("invalid {flag}"))
+                })
+                .transpose()
+        };
+        let since = parse("--since", &self.since)?;
+        let until = parse("--until", &self.until)?;
+        if let (Some(since), Some(until)) = (since, until) {
+            if since > until {
+                return Err(anyhow!("--since ({since}) is after --until ({until})"));
+            }
+        }
+        Ok(ListFilter {
+            since,
+            until,
+            ..filter
+        })
+    }
+}
+
+/// Provenance criteria shared by `list` and `remove`.
+#[derive(Debug, Clone, Default, Args)]
+pub struct ProvenanceFilterArgs {
+    /// Only annotations created by this creator (see `annotate --creator`).
+    #[arg(long)]
+    pub creator: Option<String>,
+
+    /// Only annotations with this scope (see `annotate --scope`), e.g.: "payments".
+    #[arg(long)]
+    pub scope: Option<String>,
+
+    /// Only annotations whose --ttl has passed.
+    #[arg(long)]
+    pub expired: bool,
+}
+
+impl ProvenanceFilterArgs {
+    pub fn filter(&self, now: DateTime<Utc>) -> ProvenanceFilter {
+        ProvenanceFilter {
+            creator: self.creator.clone(),
+            scope: self.scope.clone(),
+            expired_at: self.expired.then_some(now),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Parser)]
 pub enum Commands {
     /// Add vertical annotation to dasboard(s) / widget(s).
-    Annotate(AnnotateOpts),
+    Annotate(Box<AnnotateOpts>),
+    /// Add a horizontal (threshold) annotation, e.g. an SLO target.
+    AnnotateHorizontal(AnnotateHorizontalOpts),
+    /// Remove annotations created by a previous cwnote run.
+    Remove(RemoveOpts),
+    /// Collapse duplicate annotations (same label and value), e.g. from retried pipelines.
+    Dedup(DedupOpts),
+    /// Remove vertical annotations older than a given age, e.g. stale deploy markers.
+    Prune(PruneOpts),
+    /// Print the annotations already on dashboard(s), e.g. for an audit.
+    List(ListOpts),
+    /// Print the latest value per label on a dashboard, e.g. the deployed version.
+    Current(CurrentOpts),
+    /// Count the vertical annotations per widget and flag crowded widgets.
+    Stats(StatsOpts),
+    /// Report markers that some dashboards of a group (e.g. one per region) lack,
+    /// without changing anything.
+    Check(CheckOpts),
+    /// Turn the rows of a CloudWatch Logs Insights query into annotations.
+    LogsSync(LogsSyncOpts),
+    /// Annotate AWS AppConfig configuration deployments (e.g. feature-flag flips).
+    AppconfigSync(AppconfigSyncOpts),
+    /// Draw Step Functions executions (e.g. nightly batch jobs) as range annotations.
+    SfnSync(SfnSyncOpts),
+    /// Mark CloudWatch Synthetics canary runs, e.g. only the failed ones.
+    SyntheticsSync(SyntheticsSyncOpts),
+    /// Draw the events of an SSM Change Calendar (e.g. change freezes) as range annotations.
+    ChangeCalendarSync(ChangeCalendarSyncOpts),
+    /// Write one annotation per canary traffic-shift phase.
+    Canary(CanaryOpts),
+    /// Helpers for picking dashboards (e.g. from shell completion).
+    Dashboards(DashboardsOpts),
+    /// Try the widget selector flags offline.
+    Selector(SelectorOpts),
+    /// Print the JSON Schema of a file cwnote reads or writes, e.g. to validate it in CI.
+    Schema(SchemaOpts),
+    /// Expire, trim and gzip the exported dashboard files.
+    Maintenance(MaintenanceOpts),
+    /// Annotate a sandbox dashboard, check the annotation and remove it again,
+    /// to confirm permissions and setup before touching real dashboards.
+    Selftest(SelftestOpts),
 }
 
-#[derive(Debug, Parser)]
+#[derive(Debug, Clone, Parser)]
 #[command(
     group(
         ArgGroup::new(ARG_GROUP_TARGET)
             .required(true)
-            .args(&[ARG_DASHBOARD, ARG_DASHBOARD_SUFFIX]),
+            .args(&[
+                ARG_DASHBOARD,
+                ARG_DASHBOARD_SUFFIX,
+                ARG_DASHBOARD_REGEX,
+                ARG_DASHBOARD_GLOB,
+                ARG_DASHBOARDS_FILE,
+                ARG_DASHBOARDS_STDIN,
+                ARG_SERVICES_FILE,
+                ARG_INPUT_FILE,
+            ]),
+    ),
+    group(
+        ArgGroup::new(ARG_GROUP_VALUE)
+            .required(true)
+            .args(&[ARG_VALUE, ARG_VALUE_FROM]),
     )
 )]
 pub struct AnnotateOpts {
-    /// Single dashboard name to update.
-    #[arg(long)]
-    pub dashboard: Option<String>,
+    /// Dashboard name to update. Repeatable or comma-separated, e.g.: "orders,payments".
+    #[arg(long, value_delimiter = ',')]
+    pub dashboard: Vec<String>,
 
     /// Prefx of dashboard names to update.
     #[arg(long)]
     pub dashboard_suffix: Option<String>,
 
+    /// Regex that dashboard names to update must match, e.g.: "^(orders|payments)-prod-".
+    #[arg(long, value_parser = Regex::new)]
+    pub dashboard_regex: Option<Regex>,
+
+    /// Shell-style glob that whole dashboard names to update must match, e.g.: "prod-*-api".
+    #[arg(long)]
+    pub dashboard_glob: Option<Glob>,
+
+    /// File with the dashboard names to update, one per line (blank lines and
+    /// lines starting with '#' are ignored), e.g. from Terraform outputs.
+    #[arg(long)]
+    pub dashboards_file: Option<PathBuf>,
+
+    /// Read the dashboard names to update from stdin, like --dashboards-file.
+    #[arg(long)]
+    pub dashboards_stdin: bool,
+
+    /// JSON file of services, each with its own dashboards, widgets and label,
+    /// all annotated with the same value in one run.
+    #[arg(long)]
+    pub services_file: Option<PathBuf>,
+
+    /// Annotate a dashboard body in this local JSON file instead of a dashboard in
+    /// CloudWatch, e.g. one kept in git for Terraform. Makes no AWS calls.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "verify",
+            "backup_dir",
+            "backup_s3",
+            "preview",
+            "expect_change",
+            "github_deployment",
+        ]
+    )]
+    pub input_file: Option<PathBuf>,
+
+    /// Where to write the annotated body of --input-file (default: stdout).
+    #[arg(
+        long,
+        conflicts_with_all = [
+            ARG_DASHBOARD,
+            ARG_DASHBOARD_SUFFIX,
+            ARG_DASHBOARD_REGEX,
+            ARG_DASHBOARD_GLOB,
+            ARG_DASHBOARDS_FILE,
+            ARG_DASHBOARDS_STDIN,
+            ARG_SERVICES_FILE,
+        ]
+    )]
+    pub output_file: Option<PathBuf>,
+
+    /// Only annotate these services of --services-file, e.g.: "payments,checkout".
+    // Not `requires`: clap counts any member of the target group as present.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        conflicts_with_all = [
+            ARG_DASHBOARD,
+            ARG_DASHBOARD_SUFFIX,
+            ARG_DASHBOARD_REGEX,
+            ARG_DASHBOARD_GLOB,
+            ARG_DASHBOARDS_FILE,
+            ARG_DASHBOARDS_STDIN,
+            ARG_INPUT_FILE,
+        ]
+    )]
+    pub only: Vec<String>,
+
+    /// Skip dashboards starting with this prefix although the target matches them,
+    /// e.g.: "sandbox-". Repeatable.
+    #[arg(long, conflicts_with = ARG_DASHBOARD)]
+    pub exclude_prefix: Vec<String>,
+
+    /// Skip dashboards matching this regex although the target matches them,
+    /// e.g.: "-copy$". Repeatable.
+    #[arg(long, value_parser = Regex::new, conflicts_with = ARG_DASHBOARD, allow_hyphen_values = true)]
+    pub exclude_regex: Vec<Regex>,
+
     /// Annotation label, e.g.: "version", "incident", "deploy", "alarm".
+    /// May contain template variables, e.g.: "deploy ({account_alias})".
     #[arg(long, default_value = DEFAULT_LABEL)]
     pub label: String,
 
     /// Annotation value e.g.: "0.0.0-49u4ref" or "INC-1234", or "SOME-EVENT".
+    /// May contain template variables, e.g.: "{account_alias}".
     #[arg(long)]
-    pub value: String,
+    pub value: Option<String>,
+
+    /// Read the annotation value at run time, e.g.: "ssm:/my/service/version".
+    #[arg(long)]
+    pub value_from: Option<String>,
 
-    /// Annotation time (ISO8601 / RFC3339). If omitted, uses current UTC time.
+    /// Clean up the value before templating, e.g.: "url-decode,semver-core,truncate:20".
+    /// Available: semver-core, short-sha, url-decode, truncate:<n>.
+    #[arg(long, value_delimiter = ',')]
+    pub value_transform: Vec<String>,
+
+    /// Annotation time: ISO8601 / RFC3339, Unix epoch seconds or milliseconds, or
+    /// relative to now (UTC), e.g.: "-15m", "now-1d".
+    /// If omitted, uses current UTC time.
     #[arg(long)]
     pub time: Option<String>,
 
+    /// End of a band annotation (any format --time takes), shading --time (or now) until then,
+    /// e.g. a maintenance or incident window.
+    #[arg(long, conflicts_with = "duration")]
+    pub end_time: Option<String>,
+
+    /// Length of a band annotation starting at --time (or now), e.g.: "30m", "2h".
+    #[arg(long)]
+    pub duration: Option<String>,
+
+    /// Clamp a --time a few minutes ahead (clock skew) to now and reject one further in the
+    /// future. `--clamp-to-now false` keeps future times as given, with a warning.
+    #[arg(long, value_name = "BOOL", default_value_t = true, action = ArgAction::Set)]
+    pub clamp_to_now: bool,
+
+    /// Accept a --time in the future. By default it is rejected (or clamped to now within a few minutes of skew).
+    #[arg(long)]
+    pub allow_future: bool,
+
     /// Dry run: don’t actually update dashboards, just show what would change.
+    /// `--dry-run=read-only` works on the latest export instead of calling
+    /// GetDashboard, `--dry-run=mutate-local` also writes the annotated body
+    /// to an export file.
+    #[arg(long, value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "full")]
+    pub dry_run: Option<DryRunLevel>,
+
+    #[command(flatten)]
+    pub selector: SelectorArgs,
+
+    #[command(flatten)]
+    pub iac: IacArgs,
+
+    #[command(flatten)]
+    pub provenance: ProvenanceArgs,
+
+    #[command(flatten)]
+    pub retry: RetryArgs,
+
+    /// Don't log running totals after every dashboard of a multi-dashboard run.
     #[arg(long)]
-    pub dry_run: bool,
+    pub no_progress: bool,
+
+    /// Don't ask before updating the dashboards a suffix, regex or glob matched.
+    #[arg(long, short = 'y')]
+    pub yes: bool,
 
-    /// Only annotate widgets whose title contains this substring.
+    /// Don't add an annotation to a widget that already has one with the same label and
+    /// value, e.g. when a deploy pipeline is re-run.
     #[arg(long)]
-    pub widget_title_contains: Option<String>,
-}
+    pub skip_if_exists: bool,
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use clap::Parser;
+    /// With --skip-if-exists, only count existing annotations within this of the new
+    /// annotation's time, e.g.: "15m".
+    #[arg(long, value_parser = duration::parse_duration, requires = "skip_if_exists")]
+    pub skip_if_exists_within: Option<chrono::Duration>,
 
-    const CMD_ANNOTATE: &str = "annotate";
+    /// Exit with 3 instead of 0 when the target matches no dashboards.
+    #[arg(long)]
+    pub fail_if_no_dashboards: bool,
 
-    #[test]
-    fn parse_minimal_annotate_with_dashboard() {
-        // cwnote annotate --dashboard TestDash --value 1.2.3
-        let cli = Cli::try_parse_from([
-            APP_NAME,
-            CMD_ANNOTATE,
-            "--dashboard",
-            "TestDash",
-            "--value",
-            "1.2.3",
-        ])
-        .expect("failed to parse args");
+    /// Exit with 4 instead of 0 when no dashboard has a widget the selector picks.
+    #[arg(long)]
+    pub fail_if_no_match: bool,
 
-        assert!(cli.region.is_none());
+    /// Update up to this many dashboards at once in multi-dashboard runs.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..=MAX_CONCURRENCY))]
+    pub concurrency: u64,
 
-        match cli.command {
-            Commands::Annotate(opts) => {
-                assert_eq!(opts.dashboard.as_deref(), Some("TestDash"));
-                assert!(opts.dashboard_suffix.is_none());
-                assert_eq!(opts.label, DEFAULT_LABEL); // default
-                assert_eq!(opts.value, "1.2.3");
-                assert!(opts.time.is_none());
-                assert!(!opts.dry_run);
-                assert!(opts.widget_title_contains.is_none());
-            }
-        }
-    }
+    /// Also set the dashboard's visible window start in the same write, e.g.: "-PT3H".
+    #[arg(long, allow_hyphen_values = true)]
+    pub set_start: Option<String>,
 
-    #[test]
-    fn parse_with_dashboard_suffix() {
-        // cwnote annotate --dashboard-suffix TestService- --value foo
-        let cli = Cli::try_parse_from([
-            APP_NAME,
-            CMD_ANNOTATE,
-            "--dashboard-suffix",
-            "TestService-",
-            "--value",
-            "foo",
-        ])
-        .expect("failed to parse args");
+    /// Warn when a widget carries at least this many vertical annotations (0 disables).
+    #[arg(long, default_value_t = DEFAULT_ANNOTATION_BUDGET)]
+    pub annotation_budget: usize,
 
-        match cli.command {
-            Commands::Annotate(opts) => {
-                assert!(opts.dashboard.is_none());
-                assert_eq!(opts.dashboard_suffix.as_deref(), Some("TestService-"));
-                assert_eq!(opts.label, DEFAULT_LABEL);
-                assert_eq!(opts.value, "foo");
-            }
-        }
-    }
+    /// Warn when the dashboard body reaches this many bytes; the limit is 100 KB (0 disables).
+    #[arg(long, default_value_t = DEFAULT_WARN_BYTES)]
+    pub body_size_warning: usize,
 
-    #[test]
-    fn parse_with_all_optional_extras() {
-        // cwnote annotate --dashboard TestDash --value v \
-        //   --time 2025-01-01T00:00:00Z --dry-run --widget-title-contains Latency
-        let cli = Cli::try_parse_from([
-            APP_NAME,
-            CMD_ANNOTATE,
-            "--dashboard",
-            "TestDash",
-            "--value",
-            "v",
-            "--time",
-            "2025-01-01T00:00:00Z",
-            "--dry-run",
-            "--widget-title-contains",
-            "Latency",
-        ])
-        .expect("failed to parse args");
+    /// When the body would exceed the size limit, drop the annotations of the oldest cwnote
+    /// runs to make room instead of leaving widgets out.
+    #[arg(long)]
+    pub auto_prune: bool,
 
-        match cli.command {
-            Commands::Annotate(opts) => {
-                assert_eq!(opts.dashboard.as_deref(), Some("TestDash"));
-                assert_eq!(opts.value, "v");
-                assert_eq!(opts.time.as_deref(), Some("2025-01-01T00:00:00Z"));
-                assert!(opts.dry_run);
-                assert_eq!(opts.widget_title_contains.as_deref(), Some("Latency"));
-            }
-        }
-    }
+    /// After annotating, keep only the newest N vertical annotations with the same label
+    /// on each annotated widget, e.g.: "10".
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub keep_last: Option<u64>,
 
-    #[test]
-    fn error_when_neither_dashboard_nor_suffix_is_provided() {
-        // cwnote annotate --value v
-        let res = Cli::try_parse_from([APP_NAME, CMD_ANNOTATE, "--value", "v"]);
-        assert!(
-            res.is_err(),
-            "expected clap error when missing dashboard and suffix"
-        );
-    }
+    /// Sort the vertical annotations of annotated widgets by time instead of appending the
+    /// new ones, e.g. for stable body diffs and hover order.
+    #[arg(long)]
+    pub sort: bool,
 
-    #[test]
-    fn error_when_both_dashboard_and_suffix_are_provided() {
-        // cwnote annotate --dashboard A --dashboard-suffix B --value v
-        let res = Cli::try_parse_from([
-            APP_NAME,
-            CMD_ANNOTATE,
-            "--dashboard",
+    /// Save each dashboard's original body to this directory before writing it, e.g.
+    /// "./backups". A failed backup stops the write.
+    #[arg(long)]
+    pub backup_dir: Option<PathBuf>,
+
+    /// Upload each dashboard's original body to this S3 location before writing it, with
+    /// the dashboard name, run id and time as metadata, e.g. "s3://team-backups/cwnote".
+    #[arg(long)]
+    pub backup_s3: Option<S3Location>,
+
+    /// After writing, read each dashboard back and fail unless the annotations are on the
+    /// expected widgets.
+    #[arg(long)]
+    pub verify: bool,
+
+    /// Fail instead of skipping when a target dashboard has no widgets.
+    #[arg(long)]
+    pub fail_on_empty_dashboard: bool,
+
+    /// With --dry-run, draw the selected widget's recent data with the annotation marked.
+    #[arg(long, requires = "dry_run")]
+    pub preview: bool,
+
+    /// Report whether the selected widget's metric shifted around the annotation time.
+    #[arg(long)]
+    pub expect_change: bool,
+
+    /// JSON manifest with several (widget selector -> annotation style) routes,
+    /// all applied in one write per dashboard. Its routes pick the widgets, so it
+    /// can't be combined with the widget selection flags (--widget-title-contains etc.).
+    #[arg(long, conflicts_with = "SelectorArgs")]
+    pub manifest: Option<PathBuf>,
+
+    /// Annotation color: a hex code, e.g.: "#ff9900", or red, orange, green, blue.
+    /// Manifest routes with their own color keep it.
+    #[arg(long, value_parser = color::parse)]
+    pub color: Option<String>,
+
+    /// Shade the graph before or after the line (none: no shading).
+    /// Manifest routes with their own fill keep it.
+    #[arg(long, value_enum)]
+    pub fill: Option<VerticalFill>,
+
+    /// Write a standalone HTML report (matched widgets, changes, console links) to this file.
+    #[arg(long)]
+    pub report_html: Option<PathBuf>,
+
+    /// Print the result to stdout as text (logs only) or as one JSON document
+    /// (per dashboard: widgets matched and annotated, changes, errors).
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// Also record a GitHub Deployment to this environment (e.g.: "production") with a
+    /// status linking the dashboard. Needs GITHUB_TOKEN, GITHUB_REPOSITORY and GITHUB_SHA.
+    #[arg(long)]
+    pub github_deployment: Option<String>,
+
+    /// Also append this tag to the annotated widgets' titles, e.g.: "[v1.2.3]".
+    /// Replaces the tag of a previous run; strip it with `remove --untag-title`.
+    #[arg(long)]
+    pub tag_title: Option<String>,
+}
+
+#[derive(Debug, Clone, Parser)]
+#[command(
+    group(
+        ArgGroup::new(ARG_GROUP_TARGET)
+            .required(true)
+            .args(&[ARG_DASHBOARD, ARG_DASHBOARD_SUFFIX]),
+    )
+)]
+pub struct AnnotateHorizontalOpts {
+    /// Single dashboard name to update.
+    #[arg(long)]
+    pub dashboard: Option<String>,
+
+    /// Suffix of dashboard names to update.
+    #[arg(long)]
+    pub dashboard_suffix: Option<String>,
+
+    /// Threshold on the y axis, e.g.: 250 for a 250 ms latency SLO.
+    #[arg(long, allow_negative_numbers = true)]
+    pub value: f64,
+
+    /// Label shown next to the threshold line, e.g.: "SLO p99".
+    #[arg(long)]
+    pub label: Option<String>,
+
+    /// Shade the area above or below the threshold.
+    #[arg(long, value_enum)]
+    pub fill: Option<Fill>,
+
+    /// Y axis the threshold refers to (CloudWatch defaults to left).
+    #[arg(long, value_enum)]
+    pub y_axis: Option<YAxis>,
+
+    #[command(flatten)]
+    pub selector: SelectorArgs,
+
+    #[command(flatten)]
+    pub iac: IacArgs,
+
+    #[command(flatten)]
+    pub provenance: ProvenanceArgs,
+
+    /// Dry run: don’t actually update dashboards, just show what would change.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Parser)]
+#[command(
+    group(
+        ArgGroup::new(ARG_GROUP_TARGET)
+            .required(false)
+            .args(&[ARG_DASHBOARD, ARG_DASHBOARD_SUFFIX]),
+    ),
+    group(
+        ArgGroup::new(ARG_GROUP_REMOVE_FILTER)
+            .required(true)
+            .multiple(true)
+            .args(&[ARG_RUN_ID, ARG_LABEL, ARG_UNTAG_TITLE, ARG_CREATOR, ARG_SCOPE, ARG_EXPIRED]),
+    )
+)]
+pub struct RemoveOpts {
+    /// Single dashboard name to clean up. If no target is given, all dashboards are scanned.
+    #[arg(long)]
+    pub dashboard: Option<String>,
+
+    /// Suffix of dashboard names to clean up.
+    #[arg(long)]
+    pub dashboard_suffix: Option<String>,
+
+    /// Remove annotations created by this run id (printed at the start of every run).
+    #[arg(long)]
+    pub run_id: Option<String>,
+
+    /// Remove annotations with this label, e.g.: "experiment".
+    #[arg(long)]
+    pub label: Option<String>,
+
+    /// Strip the title tags added by `annotate --tag-title`.
+    #[arg(long)]
+    pub untag_title: bool,
+
+    /// Only clean up this annotation array (default: both vertical and horizontal).
+    #[arg(long, value_enum)]
+    pub direction: Option<Direction>,
+
+    #[command(flatten)]
+    pub provenance: ProvenanceFilterArgs,
+
+    #[command(flatten)]
+    pub selector: SelectorArgs,
+
+    /// Dry run: don’t actually update dashboards, just print every annotation that would be removed.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Format of the dry-run listing.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Clone, Parser)]
+#[command(
+    group(
+        ArgGroup::new(ARG_GROUP_TARGET)
+            .required(true)
+            .args(&[ARG_DASHBOARD, ARG_DASHBOARD_PREFIX, ARG_DASHBOARD_SUFFIX]),
+    )
+)]
+pub struct PruneOpts {
+    /// Single dashboard name to prune.
+    #[arg(long)]
+    pub dashboard: Option<String>,
+
+    /// Prefix of dashboard names to prune, e.g.: "payments-".
+    #[arg(long)]
+    pub dashboard_prefix: Option<String>,
+
+    /// Suffix of dashboard names to prune.
+    #[arg(long)]
+    pub dashboard_suffix: Option<String>,
+
+    /// Remove annotations that ended longer ago than this, e.g.: "30d".
+    #[arg(long, value_parser = duration::parse_duration)]
+    pub older_than: chrono::Duration,
+
+    /// Only prune annotations with this label, e.g.: "deploy".
+    #[arg(long)]
+    pub label: Option<String>,
+
+    #[command(flatten)]
+    pub selector: SelectorArgs,
+
+    /// Dry run: don’t actually update dashboards, just print every annotation that would be removed.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Format of the dry-run listing.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct DedupOpts {
+    /// Single dashboard name to clean up. If no target is given, all dashboards are scanned.
+    #[arg(long, conflicts_with = ARG_DASHBOARD_SUFFIX)]
+    pub dashboard: Option<String>,
+
+    /// Suffix of dashboard names to clean up.
+    #[arg(long)]
+    pub dashboard_suffix: Option<String>,
+
+    /// Only collapse annotations with this label, e.g.: "deploy".
+    #[arg(long)]
+    pub label: Option<String>,
+
+    /// Only treat annotations within this time after the kept (earliest) one as copies,
+    /// e.g.: "1h". Without it, every copy is collapsed however far apart.
+    #[arg(long, value_parser = duration::parse_duration)]
+    pub window: Option<chrono::Duration>,
+
+    #[command(flatten)]
+    pub selector: SelectorArgs,
+
+    /// Dry run: don’t actually update dashboards, just print every annotation that would be removed.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Format of the dry-run listing.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Clone, Parser)]
+#[command(
+    group(
+        ArgGroup::new(ARG_GROUP_TARGET)
+            .required(true)
+            .args(&[ARG_DASHBOARD, ARG_DASHBOARD_SUFFIX]),
+    )
+)]
+pub struct LogsSyncOpts {
+    /// Single dashboard name to update.
+    #[arg(long)]
+    pub dashboard: Option<String>,
+
+    /// Suffix of dashboard names to update.
+    #[arg(long)]
+    pub dashboard_suffix: Option<String>,
+
+    /// Log group to query, e.g.: "/deploys".
+    #[arg(long)]
+    pub log_group: String,
+
+    /// Logs Insights query string.
+    #[arg(long)]
+    pub query: String,
+
+    /// How far back to query, e.g.: "30m", "12h", "7d".
+    #[arg(long, default_value = DEFAULT_LOGS_SINCE)]
+    pub since: String,
+
+    /// Collapse events into one annotation per time bucket of this size, e.g.: "5m".
+    #[arg(long, value_parser = parse_dedupe_window)]
+    pub dedupe_window: Option<chrono::Duration>,
+
+    /// Map result fields onto the annotation, e.g.: "value=version", "label=service", "time=@timestamp".
+    /// Defaults to time=@timestamp and value=@message.
+    #[arg(long)]
+    pub map: Vec<String>,
+
+    /// Annotation label used when no "label" field is mapped.
+    #[arg(long, default_value = DEFAULT_LABEL)]
+    pub label: String,
+
+    #[command(flatten)]
+    pub sync: SyncArgs,
+}
+
+#[derive(Debug, Clone, Parser)]
+#[command(
+    group(
+        ArgGroup::new(ARG_GROUP_TARGET)
+            .required(true)
+            .args(&[ARG_DASHBOARD, ARG_DASHBOARD_SUFFIX]),
+    )
+)]
+pub struct AppconfigSyncOpts {
+    /// Single dashboard name to update.
+    #[arg(long)]
+    pub dashboard: Option<String>,
+
+    /// Suffix of dashboard names to update.
+    #[arg(long)]
+    pub dashboard_suffix: Option<String>,
+
+    /// AppConfig application id.
+    #[arg(long)]
+    pub application: String,
+
+    /// AppConfig environment id.
+    #[arg(long)]
+    pub environment: String,
+
+    /// How far back to look for deployments, e.g.: "12h", "7d".
+    #[arg(long, default_value = DEFAULT_APPCONFIG_SINCE)]
+    pub since: String,
+
+    /// Collapse events into one annotation per time bucket of this size, e.g.: "5m".
+    #[arg(long, value_parser = parse_dedupe_window)]
+    pub dedupe_window: Option<chrono::Duration>,
+
+    /// Annotation label.
+    #[arg(long, default_value = DEFAULT_APPCONFIG_LABEL)]
+    pub label: String,
+
+    #[command(flatten)]
+    pub sync: SyncArgs,
+}
+
+#[derive(Debug, Clone, Parser)]
+#[command(
+    group(
+        ArgGroup::new(ARG_GROUP_TARGET)
+            .required(true)
+            .args(&[ARG_DASHBOARD, ARG_DASHBOARD_SUFFIX]),
+    )
+)]
+pub struct SfnSyncOpts {
+    /// Single dashboard name to update.
+    #[arg(long)]
+    pub dashboard: Option<String>,
+
+    /// Suffix of dashboard names to update.
+    #[arg(long)]
+    pub dashboard_suffix: Option<String>,
+
+    /// ARN of the state machine whose executions to annotate.
+    #[arg(long)]
+    pub state_machine_arn: String,
+
+    /// Only annotate executions whose name contains this substring.
+    #[arg(long)]
+    pub execution_name_contains: Option<String>,
+
+    /// How far back to look for executions, e.g.: "12h", "7d".
+    #[arg(long, default_value = DEFAULT_SFN_SINCE)]
+    pub since: String,
+
+    /// Collapse events into one annotation per time bucket of this size, e.g.: "5m".
+    #[arg(long, value_parser = parse_dedupe_window)]
+    pub dedupe_window: Option<chrono::Duration>,
+
+    /// Annotation label.
+    #[arg(long, default_value = DEFAULT_SFN_LABEL)]
+    pub label: String,
+
+    #[command(flatten)]
+    pub sync: SyncArgs,
+}
+
+#[derive(Debug, Clone, Parser)]
+#[command(
+    group(
+        ArgGroup::new(ARG_GROUP_TARGET)
+            .required(true)
+            .args(&[ARG_DASHBOARD, ARG_DASHBOARD_SUFFIX]),
+    )
+)]
+pub struct SyntheticsSyncOpts {
+    /// Single dashboard name to update.
+    #[arg(long)]
+    pub dashboard: Option<String>,
+
+    /// Suffix of dashboard names to update.
+    #[arg(long)]
+    pub dashboard_suffix: Option<String>,
+
+    /// Name of the Synthetics canary whose runs to annotate.
+    #[arg(long)]
+    pub canary_name: String,
+
+    /// Only annotate failed runs.
+    #[arg(long)]
+    pub failures_only: bool,
+
+    /// How far back to look for runs, e.g.: "12h", "7d".
+    #[arg(long, default_value = DEFAULT_SYNTHETICS_SINCE)]
+    pub since: String,
+
+    /// Collapse events into one annotation per time bucket of this size, e.g.: "5m".
+    #[arg(long, value_parser = parse_dedupe_window)]
+    pub dedupe_window: Option<chrono::Duration>,
+
+    /// Annotation label.
+    #[arg(long, default_value = DEFAULT_SYNTHETICS_LABEL)]
+    pub label: String,
+
+    #[command(flatten)]
+    pub sync: SyncArgs,
+}
+
+#[derive(Debug, Clone, Parser)]
+#[command(
+    group(
+        ArgGroup::new(ARG_GROUP_TARGET)
+            .required(true)
+            .args(&[ARG_DASHBOARD, ARG_DASHBOARD_SUFFIX]),
+    )
+)]
+pub struct ChangeCalendarSyncOpts {
+    /// Single dashboard name to update.
+    #[arg(long)]
+    pub dashboard: Option<String>,
+
+    /// Suffix of dashboard names to update.
+    #[arg(long)]
+    pub dashboard_suffix: Option<String>,
+
+    /// Name or ARN of the Change Calendar document.
+    #[arg(long)]
+    pub calendar: String,
+
+    /// How far back to look for events, e.g.: "7d", "30d". Upcoming events
+    /// are annotated too.
+    #[arg(long, default_value = DEFAULT_CHANGE_CALENDAR_SINCE)]
+    pub since: String,
+
+    /// Collapse events into one annotation per time bucket of this size, e.g.: "1d".
+    #[arg(long, value_parser = parse_dedupe_window)]
+    pub dedupe_window: Option<chrono::Duration>,
+
+    /// Annotation label.
+    #[arg(long, default_value = DEFAULT_CHANGE_CALENDAR_LABEL)]
+    pub label: String,
+
+    #[command(flatten)]
+    pub sync: SyncArgs,
+}
+
+#[derive(Debug, Clone, Parser)]
+#[command(
+    group(
+        ArgGroup::new(ARG_GROUP_TARGET)
+            .required(true)
+            .args(&[ARG_DASHBOARD, ARG_DASHBOARD_SUFFIX]),
+    ),
+    group(
+        ArgGroup::new(ARG_GROUP_CANARY_MODE)
+            .required(true)
+            .args(&[ARG_START, ARG_LIVE]),
+    )
+)]
+pub struct CanaryOpts {
+    /// Single dashboard name to update.
+    #[arg(long)]
+    pub dashboard: Option<String>,
+
+    /// Suffix of dashboard names to update.
+    #[arg(long)]
+    pub dashboard_suffix: Option<String>,
+
+    /// Traffic percentages of the rollout phases, e.g.: "10,50,100".
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub phases: Vec<u8>,
+
+    /// Time between phases, e.g.: "5m", "1h".
+    #[arg(long, default_value = DEFAULT_CANARY_INTERVAL)]
+    pub interval: String,
+
+    /// Time of the first phase (ISO8601 / RFC3339); all phases are written at once.
+    #[arg(long)]
+    pub start: Option<String>,
+
+    /// Annotate each phase as it happens, waiting --interval between phases.
+    #[arg(long)]
+    pub live: bool,
+
+    /// Annotation label; the phase percentage is appended, e.g.: "canary 50%".
+    #[arg(long, default_value = DEFAULT_CANARY_LABEL)]
+    pub label: String,
+
+    /// Annotation value e.g.: "1.9.0".
+    #[arg(long)]
+    pub value: String,
+
+    #[command(flatten)]
+    pub sync: SyncArgs,
+}
+
+#[derive(Debug, Clone, Parser)]
+#[command(group(
+    ArgGroup::new(ARG_GROUP_TARGET)
+        .required(false)
+        .args(&[ARG_DASHBOARD, ARG_DASHBOARD_SUFFIX]),
+))]
+pub struct ListOpts {
+    /// Single dashboard name to list. If no target is given, all dashboards are scanned.
+    #[arg(long)]
+    pub dashboard: Option<String>,
+
+    /// Suffix of dashboard names to list.
+    #[arg(long)]
+    pub dashboard_suffix: Option<String>,
+
+    /// Only annotations with this label, e.g.: "version".
+    #[arg(long)]
+    pub label: Option<String>,
+
+    #[command(flatten)]
+    pub time_range: TimeRangeArgs,
+
+    /// Only list this annotation array (default: both vertical and horizontal).
+    #[arg(long, value_enum)]
+    pub direction: Option<Direction>,
+
+    #[command(flatten)]
+    pub provenance: ProvenanceFilterArgs,
+
+    #[command(flatten)]
+    pub selector: SelectorArgs,
+
+    /// Print tab-separated text or a JSON array.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Clone, Parser)]
+#[command(group(
+    ArgGroup::new(ARG_GROUP_TARGET)
+        .required(false)
+        .args(&[ARG_DASHBOARD, ARG_DASHBOARD_SUFFIX]),
+))]
+pub struct StatsOpts {
+    /// Single dashboard to count. If no target is given, all dashboards are scanned.
+    #[arg(long)]
+    pub dashboard: Option<String>,
+
+    /// Suffix of dashboard names to count.
+    #[arg(long)]
+    pub dashboard_suffix: Option<String>,
+
+    /// Flag widgets carrying at least this many vertical annotations (0 disables).
+    #[arg(long, default_value_t = DEFAULT_ANNOTATION_BUDGET)]
+    pub annotation_budget: usize,
+
+    /// Only count annotations in this range, e.g. the last quarter.
+    #[command(flatten)]
+    pub time_range: TimeRangeArgs,
+
+    #[command(flatten)]
+    pub selector: SelectorArgs,
+
+    /// Print tab-separated text or a JSON array.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct CurrentOpts {
+    /// Dashboard to look at.
+    #[arg(long)]
+    pub dashboard: String,
+
+    /// Only this label, e.g.: "version". Fails if the dashboard has none.
+    #[arg(long)]
+    pub label: Option<String>,
+
+    /// With --until: the values as of that time, e.g. at the end of a quarter.
+    #[command(flatten)]
+    pub time_range: TimeRangeArgs,
+
+    #[command(flatten)]
+    pub selector: SelectorArgs,
+
+    /// Print tab-separated text or a JSON array.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct CheckOpts {
+    /// Dashboard that should carry the same markers as the others, as
+    /// region:dashboard (or just the name for the default region). Repeat for each member.
+    #[arg(long = "member", required = true)]
+    pub members: Vec<Member>,
+
+    /// Only compare annotations with this label, e.g.: "version".
+    #[arg(long)]
+    pub label: Option<String>,
+
+    #[command(flatten)]
+    pub time_range: TimeRangeArgs,
+
+    #[command(flatten)]
+    pub selector: SelectorArgs,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct DashboardsOpts {
+    #[command(subcommand)]
+    pub command: DashboardsCommand,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub enum DashboardsCommand {
+    /// Print dashboard names matching a partial name, one per line.
+    Complete(CompleteOpts),
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct CompleteOpts {
+    /// Start (or any part) of the dashboard name.
+    #[arg(default_value = "")]
+    pub partial: String,
+
+    /// Ignore the cached dashboard list and fetch it again.
+    #[arg(long)]
+    pub refresh: bool,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct SelectorOpts {
+    #[command(subcommand)]
+    pub command: SelectorCommand,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub enum SelectorCommand {
+    /// Explain which widgets of a JSON file the selector flags pick, clause by clause.
+    Test(SelectorTestOpts),
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct SelectorTestOpts {
+    /// Dashboard body or single widget, as JSON (e.g. from an export).
+    #[arg(long)]
+    pub file: PathBuf,
+
+    #[command(flatten)]
+    pub selector: SelectorArgs,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct SelftestOpts {
+    /// Dedicated sandbox dashboard to test against.
+    #[arg(long, default_value = selftest::DEFAULT_DASHBOARD)]
+    pub dashboard: String,
+
+    /// Create the dashboard (one metric widget) if it doesn't exist.
+    #[arg(long)]
+    pub create: bool,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct SchemaOpts {
+    /// Which document: manifest, policy (the cwnotePolicy block), listing (list --output json),
+    /// report (annotate --output json) or result (--result-format json).
+    #[arg(value_enum)]
+    pub kind: SchemaKind,
+}
+
+#[derive(Debug, Clone, Parser)]
+#[command(group(
+    ArgGroup::new(ARG_GROUP_MAINTENANCE)
+        .required(true)
+        .multiple(true)
+        .args(&[ARG_KEEP, ARG_MAX_SIZE_MB, ARG_GZIP]),
+))]
+pub struct MaintenanceOpts {
+    /// Export directory (default: $CWNOTE_EXPORT_DIR). Without either, pass `--dir .`
+    /// to clean up exports in the current directory.
+    #[arg(long)]
+    pub dir: Option<PathBuf>,
+
+    /// Delete exports older than this, e.g.: "30d".
+    #[arg(long)]
+    pub keep: Option<String>,
+
+    /// Delete the oldest exports until all of them fit in this many megabytes.
+    #[arg(long)]
+    pub max_size_mb: Option<u64>,
+
+    /// Gzip exports older than a day.
+    #[arg(long)]
+    pub gzip: bool,
+
+    /// Dry run: only list what would be deleted or compressed.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    const CMD_ANNOTATE: &str = "annotate";
+
+    #[test]
+    fn parse_minimal_annotate_with_dashboard() {
+        // cwnote annotate --dashboard TestDash --value 1.2.3
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            CMD_ANNOTATE,
+            "--dashboard",
+            "TestDash",
+            "--value",
+            "1.2.3",
+        ])
+        .expect("failed to parse args");
+
+        assert!(cli.region.is_none());
+        assert!(cli.profile.is_none());
+
+        match cli.command {
+            Commands::Annotate(opts) => {
+                assert_eq!(opts.dashboard, ["TestDash"]);
+                assert!(opts.dashboard_suffix.is_none());
+                assert_eq!(opts.label, DEFAULT_LABEL); // default
+                assert_eq!(opts.value.as_deref(), Some("1.2.3"));
+                assert!(opts.value_from.is_none());
+                assert!(opts.value_transform.is_empty());
+                assert!(opts.time.is_none());
+                assert!(opts.end_time.is_none());
+                assert!(opts.duration.is_none());
+                assert!(opts.clamp_to_now);
+                assert!(!opts.allow_future);
+                assert!(opts.dry_run.is_none());
+                assert!(opts.selector.widget_title_contains.is_none());
+                assert!(opts.selector.row_of.is_none());
+                assert!(opts.set_start.is_none());
+                assert_eq!(opts.annotation_budget, DEFAULT_ANNOTATION_BUDGET);
+                assert!(!opts.fail_on_empty_dashboard);
+                assert!(!opts.preview);
+                assert!(!opts.expect_change);
+                assert!(opts.tag_title.is_none());
+                assert!(opts.manifest.is_none());
+                assert!(opts.report_html.is_none());
+                assert_eq!(opts.retry.policy(), RetryPolicy::default());
+                assert_eq!(opts.concurrency, 1);
+            }
+            _ => panic!("expected annotate command"),
+        }
+    }
+
+    #[test]
+    fn parse_retry_policy() {
+        let parse = |extra: &[&str]| {
+            let mut argv = vec![APP_NAME, CMD_ANNOTATE, "--dashboard", "D", "--value", "v"];
+            argv.extend_from_slice(extra);
+            Cli::try_parse_from(argv)
+        };
+
+        let cli = parse(&[
+            "--retry-attempts",
+            "8",
+            "--retry-base-delay-ms",
+            "2000",
+            "--no-retry-jitter",
+        ])
+        .expect("failed to parse args");
+        match cli.command {
+            Commands::Annotate(opts) => {
+                let policy = opts.retry.policy();
+                assert_eq!(policy.attempts, 8);
+                assert_eq!(policy.base_delay, std::time::Duration::from_secs(2));
+                assert!(!policy.jitter);
+            }
+            _ => panic!("expected annotate command"),
+        }
+
+        assert!(parse(&["--retry-attempts", "0"]).is_err());
+    }
+
+    #[test]
+    fn concurrency_is_bounded() {
+        let parse = |n: &str| {
+            Cli::try_parse_from([
+                APP_NAME,
+                CMD_ANNOTATE,
+                "--dashboard-suffix",
+                "Service-",
+                "--value",
+                "v",
+                "--concurrency",
+                n,
+            ])
+        };
+        match parse("8").expect("failed to parse args").command {
+            Commands::Annotate(opts) => assert_eq!(opts.concurrency, 8),
+            _ => panic!("expected annotate command"),
+        }
+        assert!(parse("0").is_err());
+        assert!(parse("33").is_err());
+    }
+
+    #[test]
+    fn parse_with_dashboard_suffix() {
+        // cwnote annotate --dashboard-suffix TestService- --value foo
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            CMD_ANNOTATE,
+            "--dashboard-suffix",
+            "TestService-",
+            "--value",
+            "foo",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Annotate(opts) => {
+                assert!(opts.dashboard.is_empty());
+                assert_eq!(opts.dashboard_suffix.as_deref(), Some("TestService-"));
+                assert_eq!(opts.label, DEFAULT_LABEL);
+                assert_eq!(opts.value.as_deref(), Some("foo"));
+            }
+            _ => panic!("expected annotate command"),
+        }
+    }
+
+    #[test]
+    fn parse_with_several_dashboards() {
+        // cwnote annotate --dashboard orders --dashboard payments,shipping --value foo
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--dashboard",
+            "orders",
+            "--dashboard",
+            "payments,shipping",
+            "--value",
+            "foo",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Annotate(opts) => {
+                assert_eq!(opts.dashboard, ["orders", "payments", "shipping"]);
+            }
+            _ => panic!("expected annotate command"),
+        }
+    }
+
+    #[test]
+    fn parse_selftest_defaults_to_sandbox_dashboard() {
+        let cli = Cli::try_parse_from([APP_NAME, "selftest"]).expect("failed to parse args");
+
+        match cli.command {
+            Commands::Selftest(opts) => {
+                assert_eq!(opts.dashboard, "cwnote-selftest");
+                assert!(!opts.create);
+            }
+            _ => panic!("expected selftest command"),
+        }
+    }
+
+    #[test]
+    fn parse_with_dashboards_file() {
+        // cwnote annotate --dashboards-file targets.txt --value foo
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--dashboards-file",
+            "targets.txt",
+            "--value",
+            "foo",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Annotate(opts) => {
+                assert_eq!(opts.dashboards_file, Some(PathBuf::from("targets.txt")));
+                assert!(!opts.dashboards_stdin);
+            }
+            _ => panic!("expected annotate command"),
+        }
+
+        let both = Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--dashboards-stdin",
+            "--dashboard",
+            "orders",
+            "--value",
+            "foo",
+        ]);
+        assert!(both.is_err());
+    }
+
+    #[test]
+    fn parse_services_file_with_only() {
+        // cwnote annotate --services-file services.json --only payments,checkout --value abc123
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--services-file",
+            "services.json",
+            "--only",
+            "payments,checkout",
+            "--value",
+            "abc123",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Annotate(opts) => {
+                assert_eq!(opts.services_file, Some(PathBuf::from("services.json")));
+                assert_eq!(opts.only, ["payments", "checkout"]);
+            }
+            _ => panic!("expected annotate command"),
+        }
+
+        let only_without_file = Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--dashboard",
+            "orders",
+            "--only",
+            "payments",
+            "--value",
+            "abc123",
+        ]);
+        assert!(only_without_file.is_err());
+    }
+
+    #[test]
+    fn parse_yes_short_flag() {
+        // cwnote annotate --dashboard-suffix prod -y --value 1.9.0
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--dashboard-suffix",
+            "prod",
+            "-y",
+            "--value",
+            "1.9.0",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Annotate(opts) => assert!(opts.yes),
+            _ => panic!("expected annotate command"),
+        }
+    }
+
+    #[test]
+    fn parse_regions_for_a_fan_out() {
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "--profile",
+            "prod",
+            "--regions",
+            "eu-central-1,us-east-1",
+            "list",
+            "--dashboard",
+            "orders",
+        ])
+        .expect("failed to parse args");
+        assert_eq!(cli.regions, ["eu-central-1", "us-east-1"]);
+        assert_eq!(cli.profile.as_deref(), Some("prod"));
+        assert!(!cli.all_regions);
+
+        let both = Cli::try_parse_from([
+            APP_NAME,
+            "--region",
+            "eu-central-1",
+            "--all-regions",
+            "list",
+        ]);
+        assert!(both.is_err());
+    }
+
+    #[test]
+    fn time_range_rejects_since_after_until() {
+        let now = DateTime::parse_from_rfc3339("2025-04-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let range = |since: &str, until: &str| {
+            TimeRangeArgs {
+                since: Some(since.to_string()),
+                until: Some(until.to_string()),
+            }
+            .restrict(ListFilter::default(), now, &Timezone::default())
+        };
+
+        let filter = range("2025-01-01T00:00:00Z", "1d").unwrap();
+        assert_eq!(
+            filter.since.unwrap().to_rfc3339(),
+            "2025-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            filter.until.unwrap().to_rfc3339(),
+            "2025-03-31T00:00:00+00:00"
+        );
+        assert!(range("1d", "90d").is_err());
+        assert!(range("yesterday", "1d").is_err());
+    }
+
+    #[test]
+    fn parse_check_members() {
+        // cwnote check --member eu-central-1:orders --member us-east-1:orders --label version
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "check",
+            "--member",
+            "eu-central-1:orders",
+            "--member",
+            "us-east-1:orders",
+            "--label",
+            "version",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Check(opts) => {
+                assert_eq!(opts.members.len(), 2);
+                assert_eq!(opts.members[1].region.as_deref(), Some("us-east-1"));
+                assert_eq!(opts.label.as_deref(), Some("version"));
+            }
+            _ => panic!("expected check command"),
+        }
+        assert!(Cli::try_parse_from([APP_NAME, "check"]).is_err());
+    }
+
+    #[test]
+    fn parse_roles_for_several_accounts() {
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "--role-arn",
+            "arn:aws:iam::111111111111:role/cwnote",
+            "--role-arn",
+            "arn:aws:iam::222222222222:role/cwnote",
+            "--external-id",
+            "deploy",
+            "list",
+        ])
+        .expect("failed to parse args");
+        assert_eq!(cli.role_arn.len(), 2);
+        assert_eq!(cli.external_id.as_deref(), Some("deploy"));
+        assert!(!cli.fail_fast);
+
+        assert!(Cli::try_parse_from([APP_NAME, "--external-id", "deploy", "list"]).is_err());
+    }
+
+    #[test]
+    fn parse_current_for_one_label() {
+        // cwnote current --dashboard orders --label version --output json
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "current",
+            "--dashboard",
+            "orders",
+            "--label",
+            "version",
+            "--output",
+            "json",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Current(opts) => {
+                assert_eq!(opts.dashboard, "orders");
+                assert_eq!(opts.label.as_deref(), Some("version"));
+                assert_eq!(opts.output, OutputFormat::Json);
+            }
+            _ => panic!("expected current command"),
+        }
+        assert!(Cli::try_parse_from([APP_NAME, "current"]).is_err());
+    }
+
+    #[test]
+    fn parse_stats_with_a_budget() {
+        // cwnote stats --dashboard-suffix Service- --annotation-budget 10
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "stats",
+            "--dashboard-suffix",
+            "Service-",
+            "--annotation-budget",
+            "10",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Stats(opts) => {
+                assert!(opts.dashboard.is_none());
+                assert_eq!(opts.dashboard_suffix.as_deref(), Some("Service-"));
+                assert_eq!(opts.annotation_budget, 10);
+                assert!(opts.time_range.since.is_none());
+                assert_eq!(opts.output, OutputFormat::Text);
+            }
+            _ => panic!("expected stats command"),
+        }
+    }
+
+    #[test]
+    fn parse_stats_for_a_time_range() {
+        // cwnote stats --dashboard orders --since 90d --until 2025-03-31T23:59:59Z
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "stats",
+            "--dashboard",
+            "orders",
+            "--since",
+            "90d",
+            "--until",
+            "2025-03-31T23:59:59Z",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Stats(opts) => {
+                assert_eq!(opts.time_range.since.as_deref(), Some("90d"));
+                assert_eq!(
+                    opts.time_range.until.as_deref(),
+                    Some("2025-03-31T23:59:59Z")
+                );
+            }
+            _ => panic!("expected stats command"),
+        }
+    }
+
+    #[test]
+    fn parse_with_dashboard_regex() {
+        // cwnote annotate --dashboard-regex '^(orders|payments)-prod-' --value foo
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            CMD_ANNOTATE,
+            "--dashboard-regex",
+            "^(orders|payments)-prod-",
+            "--value",
+            "foo",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Annotate(opts) => {
+                let regex = opts.dashboard_regex.expect("regex");
+                assert!(regex.is_match("payments-prod-eu"));
+                assert!(!regex.is_match("payments-staging-eu"));
+            }
+            _ => panic!("expected annotate command"),
+        }
+
+        // Exclusive with the other targets, and must compile.
+        for args in [
+            ["--dashboard-regex", "prod", "--dashboard-suffix", "-prod"],
+            ["--dashboard-regex", "(prod", "--value", "foo"],
+        ] {
+            let mut argv = vec![APP_NAME, CMD_ANNOTATE, "--value", "foo"];
+            argv.extend(args);
+            assert!(Cli::try_parse_from(argv).is_err(), "{args:?}");
+        }
+    }
+
+    #[test]
+    fn parse_with_dashboard_glob() {
+        // cwnote annotate --dashboard-glob 'prod-*-api' --value foo
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            CMD_ANNOTATE,
+            "--dashboard-glob",
+            "prod-*-api",
+            "--value",
+            "foo",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Annotate(opts) => {
+                let glob = opts.dashboard_glob.expect("glob");
+                assert!(glob.is_match("prod-orders-api"));
+                assert!(opts.dashboard_suffix.is_none());
+            }
+            _ => panic!("expected annotate command"),
+        }
+
+        assert!(Cli::try_parse_from([
+            APP_NAME,
+            CMD_ANNOTATE,
+            "--dashboard-glob",
+            "prod-*",
+            "--dashboard-regex",
+            "^prod-",
+            "--value",
+            "foo",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn parse_with_exclusions() {
+        // cwnote annotate --dashboard-suffix Service- --exclude-prefix sandbox- --exclude-regex '-copy$' --value foo
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            CMD_ANNOTATE,
+            "--dashboard-suffix",
+            "Service-",
+            "--exclude-prefix",
+            "sandbox-",
+            "--exclude-regex",
+            "-copy$",
+            "--exclude-regex",
+            "^tmp",
+            "--value",
+            "foo",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Annotate(opts) => {
+                assert_eq!(opts.exclude_prefix, vec!["sandbox-".to_string()]);
+                assert_eq!(opts.exclude_regex.len(), 2);
+            }
+            _ => panic!("expected annotate command"),
+        }
+    }
+
+    #[test]
+    fn parse_with_all_optional_extras() {
+        // cwnote annotate --dashboard TestDash --value v \
+        //   --time 2025-01-01T00:00:00Z --dry-run --widget-title-contains Latency
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            CMD_ANNOTATE,
+            "--dashboard",
+            "TestDash",
+            "--value",
+            "v",
+            "--time",
+            "2025-01-01T00:00:00Z",
+            "--dry-run",
+            "--widget-title-contains",
+            "Latency",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Annotate(opts) => {
+                assert_eq!(opts.dashboard, ["TestDash"]);
+                assert_eq!(opts.value.as_deref(), Some("v"));
+                assert_eq!(opts.time.as_deref(), Some("2025-01-01T00:00:00Z"));
+                assert_eq!(opts.dry_run, Some(DryRunLevel::Full));
+                assert_eq!(
+                    opts.selector.widget_title_contains.as_deref(),
+                    Some("Latency")
+                );
+            }
+            _ => panic!("expected annotate command"),
+        }
+    }
+
+    #[test]
+    fn parse_dry_run_levels() {
+        let dry_run = |flag: &str| {
+            let cli = Cli::try_parse_from([
+                APP_NAME,
+                CMD_ANNOTATE,
+                "--dashboard",
+                "TestDash",
+                "--value",
+                "v",
+                flag,
+            ])?;
+            match cli.command {
+                Commands::Annotate(opts) => Ok::<_, clap::Error>(opts.dry_run),
+                _ => panic!("expected annotate command"),
+            }
+        };
+
+        assert_eq!(
+            dry_run("--dry-run=read-only").unwrap(),
+            Some(DryRunLevel::ReadOnly)
+        );
+        assert_eq!(
+            dry_run("--dry-run=mutate-local").unwrap(),
+            Some(DryRunLevel::MutateLocal)
+        );
+        assert!(dry_run("--dry-run=everything").is_err());
+    }
+
+    #[test]
+    fn error_when_neither_dashboard_nor_suffix_is_provided() {
+        // cwnote annotate --value v
+        let res = Cli::try_parse_from([APP_NAME, CMD_ANNOTATE, "--value", "v"]);
+        assert!(
+            res.is_err(),
+            "expected clap error when missing dashboard and suffix"
+        );
+    }
+
+    #[test]
+    fn error_when_both_dashboard_and_suffix_are_provided() {
+        // cwnote annotate --dashboard A --dashboard-suffix B --value v
+        let res = Cli::try_parse_from([
+            APP_NAME,
+            CMD_ANNOTATE,
+            "--dashboard",
             "A",
             "--dashboard-suffix",
-            "B",
+            "B",
+            "--value",
+            "v",
+        ]);
+        assert!(
+            res.is_err(),
+            "expected clap error when both dashboard and suffix are set"
+        );
+    }
+
+    #[test]
+    fn parse_remove_by_run_id_without_target() {
+        // cwnote remove --run-id 20250120T120000Z-aaaaaaaa
+        let cli =
+            Cli::try_parse_from([APP_NAME, "remove", "--run-id", "20250120T120000Z-aaaaaaaa"])
+                .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Remove(opts) => {
+                assert!(opts.dashboard.is_none());
+                assert!(opts.dashboard_suffix.is_none());
+                assert_eq!(opts.run_id.as_deref(), Some("20250120T120000Z-aaaaaaaa"));
+                assert!(opts.label.is_none());
+                assert!(!opts.dry_run);
+            }
+            _ => panic!("expected remove command"),
+        }
+    }
+
+    #[test]
+    fn parse_dedup_with_window() {
+        // cwnote dedup --dashboard-suffix Service- --label deploy --window 1h --dry-run
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "dedup",
+            "--dashboard-suffix",
+            "Service-",
+            "--label",
+            "deploy",
+            "--window",
+            "1h",
+            "--dry-run",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Dedup(opts) => {
+                assert_eq!(opts.dashboard_suffix.as_deref(), Some("Service-"));
+                assert_eq!(opts.label.as_deref(), Some("deploy"));
+                assert_eq!(opts.window, Some(chrono::Duration::hours(1)));
+                assert!(opts.dry_run);
+            }
+            _ => panic!("expected dedup command"),
+        }
+    }
+
+    #[test]
+    fn parse_annotate_input_file_as_target() {
+        // cwnote annotate --input-file orders.json --output-file out.json --value 1.9.0
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--input-file",
+            "orders.json",
+            "--output-file",
+            "out.json",
+            "--value",
+            "1.9.0",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Annotate(opts) => {
+                assert_eq!(opts.input_file, Some(PathBuf::from("orders.json")));
+                assert_eq!(opts.output_file, Some(PathBuf::from("out.json")));
+            }
+            _ => panic!("expected annotate command"),
+        }
+
+        // Reading the dashboard back needs CloudWatch.
+        assert!(Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--input-file",
+            "orders.json",
+            "--value",
+            "1.9.0",
+            "--verify",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn parse_remove_by_provenance() {
+        // cwnote remove --scope payments --expired
+        let cli = Cli::try_parse_from([APP_NAME, "remove", "--scope", "payments", "--expired"])
+            .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Remove(opts) => {
+                assert!(opts.run_id.is_none());
+                assert_eq!(opts.provenance.scope.as_deref(), Some("payments"));
+                assert!(opts.provenance.expired);
+            }
+            _ => panic!("expected remove command"),
+        }
+
+        assert!(Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--dashboard",
+            "D",
+            "--value",
+            "v",
+            "--ttl",
+            "30 days",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn parse_remove_by_label_and_namespace() {
+        // cwnote remove --metric-namespace AWS/DynamoDB --label experiment
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "remove",
+            "--metric-namespace",
+            "AWS/DynamoDB",
+            "--label",
+            "experiment",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Remove(opts) => {
+                assert!(opts.run_id.is_none());
+                assert_eq!(opts.label.as_deref(), Some("experiment"));
+                assert_eq!(
+                    opts.selector.metric_namespace.as_deref(),
+                    Some("AWS/DynamoDB")
+                );
+                assert!(!opts.untag_title);
+                assert!(opts.direction.is_none());
+            }
+            _ => panic!("expected remove command"),
+        }
+    }
+
+    #[test]
+    fn parse_remove_with_row_of_builds_the_same_selector_as_annotate() {
+        // cwnote remove --run-id R --row-of "API Latency"
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "remove",
+            "--run-id",
+            "R",
+            "--row-of",
+            "API Latency",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Remove(opts) => {
+                let selector = opts.selector.widget_selector();
+                assert_eq!(selector.row_of.as_deref(), Some("API Latency"));
+                assert!(selector.title_contains.is_none());
+            }
+            _ => panic!("expected remove command"),
+        }
+    }
+
+    #[test]
+    fn parse_remove_requires_run_id_or_label() {
+        // cwnote remove --metric-namespace AWS/DynamoDB
+        let res = Cli::try_parse_from([APP_NAME, "remove", "--metric-namespace", "AWS/DynamoDB"]);
+        assert!(
+            res.is_err(),
+            "expected clap error without --run-id or --label"
+        );
+    }
+
+    #[test]
+    fn parse_logs_sync_with_repeated_map() {
+        // cwnote logs-sync --dashboard D --log-group /deploys --query q \
+        //   --since 7d --map value=version --map label=service
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "logs-sync",
+            "--dashboard",
+            "D",
+            "--log-group",
+            "/deploys",
+            "--query",
+            "fields @timestamp, version",
+            "--since",
+            "7d",
+            "--map",
+            "value=version",
+            "--map",
+            "label=service",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::LogsSync(opts) => {
+                assert_eq!(opts.dashboard.as_deref(), Some("D"));
+                assert_eq!(opts.log_group, "/deploys");
+                assert_eq!(opts.since, "7d");
+                assert_eq!(opts.map, vec!["value=version", "label=service"]);
+                assert_eq!(opts.label, DEFAULT_LABEL);
+            }
+            _ => panic!("expected logs-sync command"),
+        }
+    }
+
+    #[test]
+    fn parse_annotate_with_value_from() {
+        // cwnote annotate --dashboard D --value-from ssm:/svc/version
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            CMD_ANNOTATE,
+            "--dashboard",
+            "D",
+            "--value-from",
+            "ssm:/svc/version",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Annotate(opts) => {
+                assert!(opts.value.is_none());
+                assert_eq!(opts.value_from.as_deref(), Some("ssm:/svc/version"));
+            }
+            _ => panic!("expected annotate command"),
+        }
+    }
+
+    #[test]
+    fn error_when_value_and_value_from_are_both_missing_or_both_set() {
+        let res = Cli::try_parse_from([APP_NAME, CMD_ANNOTATE, "--dashboard", "D"]);
+        assert!(res.is_err(), "expected clap error without a value");
+
+        let res = Cli::try_parse_from([
+            APP_NAME,
+            CMD_ANNOTATE,
+            "--dashboard",
+            "D",
+            "--value",
+            "v",
+            "--value-from",
+            "ssm:/svc/version",
+        ]);
+        assert!(res.is_err(), "expected clap error with both value sources");
+    }
+
+    #[test]
+    fn parse_appconfig_sync_defaults() {
+        // cwnote appconfig-sync --dashboard-suffix svc --application app --environment env
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "appconfig-sync",
+            "--dashboard-suffix",
+            "svc",
+            "--application",
+            "app",
+            "--environment",
+            "env",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::AppconfigSync(opts) => {
+                assert_eq!(opts.dashboard_suffix.as_deref(), Some("svc"));
+                assert_eq!(opts.application, "app");
+                assert_eq!(opts.environment, "env");
+                assert_eq!(opts.since, DEFAULT_APPCONFIG_SINCE);
+                assert_eq!(opts.label, DEFAULT_APPCONFIG_LABEL);
+            }
+            _ => panic!("expected appconfig-sync command"),
+        }
+    }
+
+    #[test]
+    fn parse_sfn_sync() {
+        // cwnote sfn-sync --dashboard D --state-machine-arn arn --execution-name-contains nightly
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "sfn-sync",
+            "--dashboard",
+            "D",
+            "--state-machine-arn",
+            "arn:aws:states:eu-central-1:123456789012:stateMachine:batch",
+            "--execution-name-contains",
+            "nightly",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::SfnSync(opts) => {
+                assert_eq!(opts.execution_name_contains.as_deref(), Some("nightly"));
+                assert_eq!(opts.since, DEFAULT_SFN_SINCE);
+                assert_eq!(opts.label, DEFAULT_SFN_LABEL);
+            }
+            _ => panic!("expected sfn-sync command"),
+        }
+    }
+
+    #[test]
+    fn parse_synthetics_sync() {
+        // cwnote synthetics-sync --dashboard D --canary-name checkout-canary --failures-only --since 24h
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "synthetics-sync",
+            "--dashboard",
+            "D",
+            "--canary-name",
+            "checkout-canary",
+            "--failures-only",
+            "--since",
+            "24h",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::SyntheticsSync(opts) => {
+                assert_eq!(opts.canary_name, "checkout-canary");
+                assert!(opts.failures_only);
+                assert_eq!(opts.since, "24h");
+                assert_eq!(opts.label, DEFAULT_SYNTHETICS_LABEL);
+            }
+            _ => panic!("expected synthetics-sync command"),
+        }
+    }
+
+    #[test]
+    fn parse_change_calendar_sync() {
+        // cwnote change-calendar-sync --dashboard-suffix Service- --calendar freezes --dedupe-window 1d
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "change-calendar-sync",
+            "--dashboard-suffix",
+            "Service-",
+            "--calendar",
+            "freezes",
+            "--dedupe-window",
+            "1d",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::ChangeCalendarSync(opts) => {
+                assert_eq!(opts.calendar, "freezes");
+                assert_eq!(opts.dedupe_window, Some(chrono::Duration::days(1)));
+                assert_eq!(opts.since, DEFAULT_CHANGE_CALENDAR_SINCE);
+                assert_eq!(opts.label, DEFAULT_CHANGE_CALENDAR_LABEL);
+            }
+            _ => panic!("expected change-calendar-sync command"),
+        }
+    }
+
+    #[test]
+    fn parse_dedupe_window_as_a_duration() {
+        // cwnote appconfig-sync --dashboard D --application app --environment env --dedupe-window 5m
+        let parse = |window: &str| {
+            Cli::try_parse_from([
+                APP_NAME,
+                "appconfig-sync",
+                "--dashboard",
+                "D",
+                "--application",
+                "app",
+                "--environment",
+                "env",
+                "--dedupe-window",
+                window,
+            ])
+        };
+
+        match parse("5m").expect("failed to parse args").command {
+            Commands::AppconfigSync(opts) => {
+                assert_eq!(opts.dedupe_window, Some(chrono::Duration::minutes(5)));
+            }
+            _ => panic!("expected appconfig-sync command"),
+        }
+        assert!(
+            parse("0m").is_err(),
+            "expected clap error for a zero window"
+        );
+        assert!(parse("5").is_err(), "expected clap error without a unit");
+    }
+
+    #[test]
+    fn parse_sync_with_write_flags() {
+        // cwnote sfn-sync --dashboard D --state-machine-arn arn --concurrency 4 --verify --backup-dir ./backups
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "sfn-sync",
+            "--dashboard",
+            "D",
+            "--state-machine-arn",
+            "arn:aws:states:eu-central-1:123456789012:stateMachine:batch",
+            "--concurrency",
+            "4",
+            "--verify",
+            "--backup-dir",
+            "./backups",
+            "--retry-attempts",
+            "2",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::SfnSync(opts) => {
+                assert_eq!(opts.sync.concurrency, 4);
+                assert!(opts.sync.verify);
+                assert_eq!(opts.sync.backup_dir, Some(PathBuf::from("./backups")));
+                assert_eq!(opts.sync.retry.policy().attempts, 2);
+                assert!(!opts.sync.dry_run);
+            }
+            _ => panic!("expected sfn-sync command"),
+        }
+    }
+
+    #[test]
+    fn parse_canary_with_start() {
+        // cwnote canary --dashboard D --phases 10,50,100 --start 2025-01-20T12:00:00Z --value v
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "canary",
+            "--dashboard",
+            "D",
+            "--phases",
+            "10,50,100",
+            "--start",
+            "2025-01-20T12:00:00Z",
+            "--value",
+            "v",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Canary(opts) => {
+                assert_eq!(opts.phases, vec![10, 50, 100]);
+                assert_eq!(opts.start.as_deref(), Some("2025-01-20T12:00:00Z"));
+                assert!(!opts.live);
+                assert_eq!(opts.interval, DEFAULT_CANARY_INTERVAL);
+            }
+            _ => panic!("expected canary command"),
+        }
+    }
+
+    #[test]
+    fn error_when_canary_has_both_start_and_live() {
+        let res = Cli::try_parse_from([
+            APP_NAME,
+            "canary",
+            "--dashboard",
+            "D",
+            "--phases",
+            "10,100",
+            "--start",
+            "2025-01-20T12:00:00Z",
+            "--live",
+            "--value",
+            "v",
+        ]);
+        assert!(res.is_err(), "expected clap error for --start with --live");
+    }
+
+    #[test]
+    fn parse_preview_requires_dry_run() {
+        // cwnote annotate --dashboard D --value v --preview
+        let res = Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--dashboard",
+            "D",
             "--value",
             "v",
+            "--preview",
         ]);
         assert!(
             res.is_err(),
-            "expected clap error when both dashboard and suffix are set"
+            "expected clap error for --preview without --dry-run"
         );
     }
+
+    #[test]
+    fn parse_time_display_after_subcommand() {
+        // cwnote annotate --dashboard D --value v --time-display local
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--dashboard",
+            "D",
+            "--value",
+            "v",
+            "--time-display",
+            "local",
+        ])
+        .expect("failed to parse args");
+
+        assert_eq!(cli.time_display, TimeDisplay::Local);
+    }
+
+    #[test]
+    fn parse_timezone() {
+        // cwnote annotate --dashboard D --value v --time "2025-03-01 14:00" --timezone Europe/Berlin
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--dashboard",
+            "D",
+            "--value",
+            "v",
+            "--time",
+            "2025-03-01 14:00",
+            "--timezone",
+            "Europe/Berlin",
+        ])
+        .expect("failed to parse args");
+        assert_eq!(cli.timezone.to_string(), "Europe/Berlin");
+
+        let cli = Cli::try_parse_from([APP_NAME, "annotate", "--dashboard", "D", "--value", "v"])
+            .expect("failed to parse args");
+        assert_eq!(cli.timezone.to_string(), "UTC");
+
+        assert!(Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--dashboard",
+            "D",
+            "--value",
+            "v",
+            "--timezone",
+            "Berlin",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn parse_named_color() {
+        // cwnote annotate --dashboard Service-Dashboard --value 1.9.0 --color red
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--dashboard",
+            "Service-Dashboard",
+            "--value",
+            "1.9.0",
+            "--color",
+            "red",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Annotate(opts) => assert_eq!(opts.color.as_deref(), Some("#d62728")),
+            _ => panic!("expected annotate command"),
+        }
+    }
+
+    #[test]
+    fn parse_fill_rejects_unknown_values() {
+        let annotate = |fill: &str| {
+            Cli::try_parse_from([
+                APP_NAME,
+                "annotate",
+                "--dashboard",
+                "Service-Dashboard",
+                "--value",
+                "1.9.0",
+                "--fill",
+                fill,
+            ])
+        };
+
+        match annotate("after").expect("failed to parse args").command {
+            Commands::Annotate(opts) => assert_eq!(opts.fill, Some(VerticalFill::After)),
+            _ => panic!("expected annotate command"),
+        }
+        assert!(annotate("none").is_ok());
+        assert!(annotate("above").is_err());
+    }
+
+    #[test]
+    fn parse_iac_marker_and_allow() {
+        // cwnote annotate --dashboard Service-Dashboard --value 1.9.0 --iac-marker owner=cdk --allow-iac-managed
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--dashboard",
+            "Service-Dashboard",
+            "--value",
+            "1.9.0",
+            "--iac-marker",
+            "owner=cdk",
+            "--allow-iac-managed",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Annotate(opts) => {
+                let guard = opts.iac.guard();
+                assert!(guard.allow);
+                assert_eq!(guard.marker.to_string(), "owner=cdk");
+            }
+            _ => panic!("expected annotate command"),
+        }
+
+        assert!(Cli::try_parse_from([
+            APP_NAME,
+            "annotate",
+            "--dashboard",
+            "Service-Dashboard",
+            "--value",
+            "1.9.0",
+            "--iac-marker",
+            "terraform",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn parse_annotate_horizontal() {
+        // cwnote annotate-horizontal --dashboard-suffix Service- --value 250 --label "SLO p99" --fill above --y-axis right
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "annotate-horizontal",
+            "--dashboard-suffix",
+            "Service-",
+            "--value",
+            "250",
+            "--label",
+            "SLO p99",
+            "--fill",
+            "above",
+            "--y-axis",
+            "right",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::AnnotateHorizontal(opts) => {
+                assert_eq!(opts.dashboard_suffix.as_deref(), Some("Service-"));
+                assert_eq!(opts.value, 250.0);
+                assert_eq!(opts.label.as_deref(), Some("SLO p99"));
+                assert_eq!(opts.fill, Some(Fill::Above));
+                assert_eq!(opts.y_axis, Some(YAxis::Right));
+            }
+            _ => panic!("expected annotate-horizontal command"),
+        }
+    }
+
+    #[test]
+    fn parse_list_with_label_and_since() {
+        // cwnote list --dashboard-suffix Service- --label version --since 7d
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "list",
+            "--dashboard-suffix",
+            "Service-",
+            "--label",
+            "version",
+            "--since",
+            "7d",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::List(opts) => {
+                assert_eq!(opts.dashboard_suffix.as_deref(), Some("Service-"));
+                assert_eq!(opts.label.as_deref(), Some("version"));
+                assert_eq!(opts.time_range.since.as_deref(), Some("7d"));
+                assert!(opts.time_range.until.is_none());
+            }
+            _ => panic!("expected list command"),
+        }
+    }
+
+    #[test]
+    fn parse_dashboards_complete() {
+        // cwnote dashboards complete Orders
+        let cli = Cli::try_parse_from([APP_NAME, "dashboards", "complete", "Orders"])
+            .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Dashboards(DashboardsOpts {
+                command: DashboardsCommand::Complete(opts),
+            }) => {
+                assert_eq!(opts.partial, "Orders");
+                assert!(!opts.refresh);
+            }
+            _ => panic!("expected dashboards complete command"),
+        }
+    }
+
+    #[test]
+    fn parse_selector_test() {
+        // cwnote selector test --file dashboard.json --row-of "API Latency"
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            "selector",
+            "test",
+            "--file",
+            "dashboard.json",
+            "--row-of",
+            "API Latency",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Selector(SelectorOpts {
+                command: SelectorCommand::Test(opts),
+            }) => {
+                assert_eq!(opts.file, PathBuf::from("dashboard.json"));
+                assert_eq!(
+                    opts.selector.widget_selector().row_of.as_deref(),
+                    Some("API Latency")
+                );
+            }
+            _ => panic!("expected selector test command"),
+        }
+    }
+
+    #[test]
+    fn manifest_rejects_widget_selection_flags() {
+        let base = [APP_NAME, "annotate", "--dashboard", "D", "--value", "1.2.3"];
+        assert!(Cli::try_parse_from(base.iter().chain(&["--manifest", "routes.json"])).is_ok());
+        for flag in [
+            ["--widget-title-regex", "^p99"],
+            ["--exclude-widget-title-contains", "Billing"],
+            ["--widget-index", "0"],
+            ["--widget-region", "us-east-1"],
+            ["--row-of", "Latency"],
+        ] {
+            let args = base
+                .iter()
+                .chain(&["--manifest", "routes.json"])
+                .chain(&flag);
+            assert!(Cli::try_parse_from(args).is_err(), "{flag:?}");
+        }
+    }
+
+    #[test]
+    fn parse_schema_kind() {
+        let cli =
+            Cli::try_parse_from([APP_NAME, "schema", "manifest"]).expect("failed to parse args");
+        match cli.command {
+            Commands::Schema(opts) => assert_eq!(opts.kind, SchemaKind::Manifest),
+            _ => panic!("expected schema command"),
+        }
+        assert!(Cli::try_parse_from([APP_NAME, "schema", "fleet"]).is_err());
+    }
+
+    #[test]
+    fn parse_maintenance_requires_a_policy() {
+        // cwnote maintenance --keep 30d --gzip
+        let cli = Cli::try_parse_from([APP_NAME, "maintenance", "--keep", "30d", "--gzip"])
+            .expect("failed to parse args");
+        match cli.command {
+            Commands::Maintenance(opts) => {
+                assert_eq!(opts.keep.as_deref(), Some("30d"));
+                assert!(opts.gzip);
+                assert!(opts.max_size_mb.is_none());
+            }
+            _ => panic!("expected maintenance command"),
+        }
+
+        let res = Cli::try_parse_from([APP_NAME, "maintenance", "--dry-run"]);
+        assert!(res.is_err(), "expected clap error without a policy");
+    }
+
+    #[test]
+    fn parse_non_interactive_after_subcommand() {
+        // cwnote remove --run-id R --non-interactive
+        let cli = Cli::try_parse_from([APP_NAME, "remove", "--run-id", "R", "--non-interactive"])
+            .expect("failed to parse args");
+
+        assert!(cli.non_interactive);
+    }
+
+    #[test]
+    fn parse_set_start_with_leading_dash() {
+        // cwnote annotate --dashboard D --value v --set-start -PT3H
+        let cli = Cli::try_parse_from([
+            APP_NAME,
+            CMD_ANNOTATE,
+            "--dashboard",
+            "D",
+            "--value",
+            "v",
+            "--set-start",
+            "-PT3H",
+        ])
+        .expect("failed to parse args");
+
+        match cli.command {
+            Commands::Annotate(opts) => {
+                assert_eq!(opts.set_start.as_deref(), Some("-PT3H"));
+            }
+            _ => panic!("expected annotate command"),
+        }
+    }
 }
@@ -0,0 +1,47 @@
+use anyhow::{anyhow, Result};
+
+/// Friendly names for the colors of CloudWatch's default graph palette.
+const NAMED_COLORS: [(&str, &str); 4] = [
+    ("blue", "#1f77b4"),
+    ("orange", "#ff7f0e"),
+    ("green", "#2ca02c"),
+    ("red", "#d62728"),
+];
+
+/// Annotation color as CloudWatch expects it (`#rrggbb`), from either a hex
+/// code or one of the names in [`NAMED_COLORS`].
+pub fn parse(input: &str) -> Result<String> {
+    let input = input.trim();
+    if let Some((_, hex)) = NAMED_COLORS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(input))
+    {
+        return Ok(hex.to_string());
+    }
+
+    match input.strip_prefix('#') {
+        Some(digits) if digits.len() == 6 && digits.chars().all(|c| c.is_ascii_hexdigit()) => {
+            Ok(input.to_ascii_lowercase())
+        }
+        _ => Err(anyhow!(
+            "color '{input}' must be a hex code like #ff7f0e or one of: {}",
+            NAMED_COLORS.map(|(name, _)| name).join(", ")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_and_hex_codes_are_accepted() {
+        assert_eq!(parse("red").unwrap(), "#d62728");
+        assert_eq!(parse("Green").unwrap(), "#2ca02c");
+        assert_eq!(parse("#FF9900").unwrap(), "#ff9900");
+
+        assert!(parse("purple").is_err());
+        assert!(parse("#ff99").is_err());
+        assert!(parse("ff9900").is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error, Result};
+
+use crate::list::ListedAnnotation;
+use crate::remove::Direction;
+
+/// One dashboard of a group that should carry the same markers, e.g. the
+/// copy of a service dashboard in each region: `eu-central-1:orders`, or
+/// just `orders` for the default region.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    pub region: Option<String>,
+    pub dashboard: String,
+}
+
+impl FromStr for Member {
+    type Err = Error;
+
+    // Dashboard names can't contain ':', so the first one separates the region.
+    fn from_str(s: &str) -> Result<Self> {
+        let (region, dashboard) = match s.split_once(':') {
+            Some((region, dashboard)) => (Some(region.to_string()), dashboard),
+            None => (None, s),
+        };
+        if dashboard.is_empty() || region.as_deref() == Some("") {
+            return Err(anyhow!(
+                "member '{s}' must be a dashboard name or region:dashboard"
+            ));
+        }
+        Ok(Self {
+            region,
+            dashboard: dashboard.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Member {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.region {
+            Some(ref region) => write!(f, "{region}:{}", self.dashboard),
+            None => f.write_str(&self.dashboard),
+        }
+    }
+}
+
+/// A vertical annotation as compared between members: the same label at the
+/// same time (and band end), on whichever widgets.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Marker {
+    pub time: String,
+    pub label: String,
+    pub end: Option<String>,
+}
+
+impl fmt::Display for Marker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' at {}", self.label, self.time)?;
+        if let Some(ref end) = self.end {
+            write!(f, " until {end}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The distinct markers among `listed`.
+pub fn markers(listed: &[ListedAnnotation]) -> BTreeSet<Marker> {
+    listed
+        .iter()
+        .filter(|ann| ann.direction == Direction::Vertical)
+        .map(|ann| Marker {
+            time: ann.value.clone(),
+            label: ann.label.clone(),
+            end: ann.end.clone(),
+        })
+        .collect()
+}
+
+/// A marker that some members carry and others don't.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub marker: Marker,
+    pub present: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: on {}, missing on {}",
+            self.marker,
+            self.present.join(", "),
+            self.missing.join(", ")
+        )
+    }
+}
+
+/// Every marker not on all `members` (name and markers), in time order.
+pub fn divergences(members: &[(String, BTreeSet<Marker>)]) -> Vec<Divergence> {
+    let all: BTreeSet<&Marker> = members.iter().flat_map(|(_, m)| m).collect();
+    all.into_iter()
+        .filter_map(|marker| {
+            let (present, missing): (Vec<_>, Vec<_>) =
+                members.iter().partition(|(_, m)| m.contains(marker));
+            if missing.is_empty() {
+                return None;
+            }
+            let names =
+                |members: Vec<&(String, _)>| members.into_iter().map(|(n, _)| n.clone()).collect();
+            Some(Divergence {
+                marker: marker.clone(),
+                present: names(present),
+                missing: names(missing),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marker(label: &str, time: &str) -> Marker {
+        Marker {
+            time: time.to_string(),
+            label: label.to_string(),
+            end: None,
+        }
+    }
+
+    #[test]
+    fn parses_members_with_and_without_region() {
+        let member: Member = "us-east-1:orders".parse().unwrap();
+        assert_eq!(member.region.as_deref(), Some("us-east-1"));
+        assert_eq!(member.to_string(), "us-east-1:orders");
+        assert_eq!("orders".parse::<Member>().unwrap().region, None);
+        assert!(":orders".parse::<Member>().is_err());
+        assert!("us-east-1:".parse::<Member>().is_err());
+    }
+
+    #[test]
+    fn reports_markers_missing_on_some_members() {
+        let deploy = marker("version: 1.9.0", "2025-01-20T12:00:00Z");
+        let incident = marker("incident: INC-1", "2025-01-21T08:00:00Z");
+        let members = vec![
+            (
+                "eu-central-1:orders".to_string(),
+                BTreeSet::from([deploy.clone(), incident.clone()]),
+            ),
+            (
+                "us-east-1:orders".to_string(),
+                BTreeSet::from([deploy.clone()]),
+            ),
+        ];
+
+        let found = divergences(&members);
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0].to_string(),
+            "'incident: INC-1' at 2025-01-21T08:00:00Z: on eu-central-1:orders, missing on us-east-1:orders"
+        );
+        assert!(divergences(&members[..1]).is_empty());
+    }
+}
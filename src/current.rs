@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+use crate::list::{ListedAnnotation, OutputFormat};
+use crate::remove::Direction;
+use crate::time_display::TimeDisplay;
+
+/// Latest value of one label on a dashboard, e.g. the version marked as
+/// deployed there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurrentValue {
+    pub dashboard: String,
+    pub label: String,
+    pub value: String,
+    /// When it was marked (RFC3339, as stored).
+    pub time: String,
+    pub run_id: Option<String>,
+}
+
+impl CurrentValue {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "dashboard": self.dashboard,
+            "label": self.label,
+            "value": self.value,
+            "time": self.time,
+            "runId": self.run_id,
+        })
+    }
+}
+
+/// Split a stored label, `version: 1.9.0` as cwnote writes it, into label and
+/// value. A label without value (written by hand) keeps an empty value.
+fn split_label(stored: &str) -> (&str, &str) {
+    stored.split_once(": ").unwrap_or((stored, ""))
+}
+
+/// The most recent vertical annotation per dashboard and label in `listed`,
+/// in order of first appearance. The same marker on several widgets counts
+/// once; annotations without a parseable time are ignored.
+pub fn current_values(listed: &[ListedAnnotation]) -> Vec<CurrentValue> {
+    let mut current: Vec<(DateTime<Utc>, CurrentValue)> = Vec::new();
+    for ann in listed.iter().filter(|a| a.direction == Direction::Vertical) {
+        let Ok(time) = DateTime::parse_from_rfc3339(&ann.value) else {
+            continue;
+        };
+        let time = time.with_timezone(&Utc);
+        let (label, value) = split_label(&ann.label);
+        let entry = CurrentValue {
+            dashboard: ann.dashboard.clone(),
+            label: label.to_string(),
+            value: value.to_string(),
+            time: ann.value.clone(),
+            run_id: ann.run_id.clone(),
+        };
+
+        match current
+            .iter_mut()
+            .find(|(_, c)| c.dashboard == entry.dashboard && c.label == entry.label)
+        {
+            Some((latest, c)) if time > *latest => {
+                *latest = time;
+                *c = entry;
+            }
+            Some(_) => {}
+            None => current.push((time, entry)),
+        }
+    }
+    current.into_iter().map(|(_, c)| c).collect()
+}
+
+/// Render `current` for stdout in the given format.
+pub fn render(current: &[CurrentValue], format: OutputFormat, display: TimeDisplay) -> String {
+    match format {
+        OutputFormat::Json => {
+            let entries: Vec<Value> = current.iter().map(CurrentValue::to_json).collect();
+            serde_json::to_string_pretty(&entries).unwrap_or_default()
+        }
+        OutputFormat::Text => {
+            let mut lines = vec!["DASHBOARD\tLABEL\tVALUE\tTIME\tRUN_ID".to_string()];
+            lines.extend(current.iter().map(|c| {
+                format!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    c.dashboard,
+                    c.label,
+                    c.value,
+                    display.format_rfc3339(&c.time),
+                    c.run_id.as_deref().unwrap_or("")
+                )
+            }));
+            lines.join("\n")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provenance::Provenance;
+
+    fn listed(widget: &str, label: &str, time: &str) -> ListedAnnotation {
+        ListedAnnotation {
+            dashboard: "orders".to_string(),
+            widget: widget.to_string(),
+            direction: Direction::Vertical,
+            label: label.to_string(),
+            value: time.to_string(),
+            end: None,
+            run_id: None,
+            provenance: Provenance::default(),
+        }
+    }
+
+    #[test]
+    fn latest_value_wins_per_label() {
+        let listed = vec![
+            listed("Latency", "version: 1.8.0", "2025-01-10T12:00:00Z"),
+            listed("Latency", "incident: INC-1", "2025-01-12T12:00:00Z"),
+            listed("Latency", "version: 1.9.0", "2025-01-20T12:00:00Z"),
+            listed("Errors", "version: 1.9.0", "2025-01-20T12:00:00Z"),
+            listed("Errors", "version: 1.7.0", "2025-01-01T12:00:00Z"),
+        ];
+
+        let current = current_values(&listed);
+        assert_eq!(current.len(), 2);
+        assert_eq!(
+            (current[0].label.as_str(), current[0].value.as_str()),
+            ("version", "1.9.0")
+        );
+        assert_eq!(
+            (current[1].label.as_str(), current[1].value.as_str()),
+            ("incident", "INC-1")
+        );
+        assert_eq!(
+            render(&current[..1], OutputFormat::Text, TimeDisplay::Utc),
+            "DASHBOARD\tLABEL\tVALUE\tTIME\tRUN_ID\norders\tversion\t1.9.0\t2025-01-20T12:00:00+00:00\t"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use aws_sdk_cloudwatch::Client;
+use log::{debug, warn};
+
+use crate::annotate;
+
+const CACHE_DIR_ENV: &str = "CWNOTE_CACHE_DIR";
+// Dashboards are created rarely; a few minutes keeps completion instant
+// without hiding a new dashboard for long.
+const CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Where the dashboard names of `region` are cached: `$CWNOTE_CACHE_DIR`,
+/// else `$XDG_CACHE_HOME/cwnote`, else `~/.cache/cwnote`.
+pub fn cache_path(region: &str) -> Option<PathBuf> {
+    let env_dir = |key: &str| {
+        std::env::var(key)
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(PathBuf::from)
+    };
+    let dir = env_dir(CACHE_DIR_ENV)
+        .or_else(|| env_dir("XDG_CACHE_HOME").map(|d| d.join("cwnote")))
+        .or_else(|| env_dir("HOME").map(|d| d.join(".cache").join("cwnote")))?;
+    Some(dir.join(format!("dashboards-{region}.txt")))
+}
+
+/// Cached names, if the cache file was written less than `CACHE_TTL` before `now`.
+fn read_fresh(path: &Path, now: SystemTime) -> Option<Vec<String>> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    if now.duration_since(modified).unwrap_or_default() > CACHE_TTL {
+        return None;
+    }
+    let text = fs::read_to_string(path).ok()?;
+    Some(text.lines().map(str::to_string).collect())
+}
+
+fn write(path: &Path, names: &[String]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, names.join("\n")).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// All dashboard names in the region, from the cache unless it is stale or
+/// `refresh` is set.
+pub async fn dashboard_names(client: &Client, region: &str, refresh: bool) -> Result<Vec<String>> {
+    let path = cache_path(region);
+    if !refresh {
+        if let Some(names) = path
+            .as_deref()
+            .and_then(|p| read_fresh(p, SystemTime::now()))
+        {
+            debug!("Using cached dashboard names for {region}");
+            return Ok(names);
+        }
+    }
+
+    let names = annotate::list_dashboards_with_suffix(client, "").await?;
+    if let Some(ref path) = path {
+        if let Err(err) = write(path, &names) {
+            warn!("Could not cache dashboard names: {err}");
+        }
+    }
+    Ok(names)
+}
+
+/// Names starting with `partial` first, then names only containing it.
+pub fn complete<'a>(names: &'a [String], partial: &str) -> Vec<&'a str> {
+    let (mut prefixed, mut containing): (Vec<&str>, Vec<&str>) = names
+        .iter()
+        .map(String::as_str)
+        .filter(|n| n.contains(partial))
+        .partition(|n| n.starts_with(partial));
+    prefixed.sort_unstable();
+    containing.sort_unstable();
+    prefixed.append(&mut containing);
+    prefixed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn complete_lists_prefix_matches_before_substring_matches() {
+        let names: Vec<String> = ["Orders-Prod", "Service-Orders", "Orders-Dev", "Billing"]
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+
+        assert_eq!(
+            complete(&names, "Orders"),
+            vec!["Orders-Dev", "Orders-Prod", "Service-Orders"]
+        );
+        assert_eq!(complete(&names, "").len(), 4);
+    }
+
+    #[test]
+    fn cache_is_only_used_while_fresh() {
+        let dir = tempdir().unwrap();
+        let path = dir
+            .path()
+            .join("cwnote")
+            .join("dashboards-eu-central-1.txt");
+        let names = vec!["A".to_string(), "B".to_string()];
+        write(&path, &names).unwrap();
+
+        let now = SystemTime::now();
+        assert_eq!(read_fresh(&path, now), Some(names));
+        assert_eq!(read_fresh(&path, now + CACHE_TTL * 2), None);
+        assert_eq!(read_fresh(&dir.path().join("missing.txt"), now), None);
+    }
+}
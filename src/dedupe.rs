@@ -0,0 +1,124 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::annotate::AnnotationSpec;
+
+/// A kept spec, the (label, bucket) it stands for and how many events it absorbed.
+struct Kept {
+    spec: AnnotationSpec,
+    key: Option<(String, i64)>,
+    absorbed: usize,
+}
+
+fn parse(time: Option<&str>) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time?)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// Collapse bursts of synced events into one annotation per `window`-sized
+/// time bucket and label, e.g. a flapping alarm or a retried job.
+///
+/// The earliest event of a bucket is kept; its value notes how many events it
+/// stands for, and a band is stretched to the latest end in the bucket. Specs
+/// without a parseable time are passed through.
+pub fn dedupe_specs(mut specs: Vec<AnnotationSpec>, window: Duration) -> Vec<AnnotationSpec> {
+    let window_secs = window.num_seconds();
+    if window_secs <= 0 {
+        return specs;
+    }
+    specs.sort_by_key(|s| parse(s.time.as_deref()));
+
+    let mut kept: Vec<Kept> = Vec::new();
+    for spec in specs {
+        let key = parse(spec.time.as_deref())
+            .map(|time| (spec.label.clone(), time.timestamp().div_euclid(window_secs)));
+
+        match kept.iter_mut().find(|k| key.is_some() && k.key == key) {
+            Some(head) => {
+                head.absorbed += 1;
+                let end = parse(spec.end_time.as_deref());
+                if end.is_some() && end > parse(head.spec.end_time.as_deref()) {
+                    head.spec.end_time = spec.end_time;
+                }
+            }
+            None => kept.push(Kept {
+                spec,
+                key,
+                absorbed: 0,
+            }),
+        }
+    }
+
+    kept.into_iter()
+        .map(|mut k| {
+            if k.absorbed > 0 {
+                k.spec.value = format!("{} (+{} more)", k.spec.value, k.absorbed);
+            }
+            k.spec
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(label: &str, value: &str, time: &str, end_time: Option<&str>) -> AnnotationSpec {
+        AnnotationSpec {
+            label: label.to_string(),
+            value: value.to_string(),
+            time: Some(time.to_string()),
+            end_time: end_time.map(str::to_string),
+            run_id: "20250120T120000Z-aaaaaaaa".to_string(),
+        }
+    }
+
+    #[test]
+    fn bursts_collapse_per_bucket_and_label() {
+        let specs = vec![
+            spec("alarm", "ALARM", "2025-01-20T12:03:00Z", None),
+            spec("alarm", "OK", "2025-01-20T12:01:00Z", None),
+            spec("alarm", "ALARM", "2025-01-20T12:04:59Z", None),
+            spec("alarm", "OK", "2025-01-20T12:05:00Z", None),
+            spec("deploy", "1.9.0", "2025-01-20T12:02:00Z", None),
+        ];
+
+        let deduped = dedupe_specs(specs, Duration::minutes(5));
+        let summary: Vec<_> = deduped
+            .iter()
+            .map(|s| (s.label.as_str(), s.value.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("alarm", "OK (+2 more)"),
+                ("deploy", "1.9.0"),
+                ("alarm", "OK"),
+            ]
+        );
+        assert_eq!(deduped[0].time.as_deref(), Some("2025-01-20T12:01:00Z"));
+    }
+
+    #[test]
+    fn bands_stretch_to_the_latest_end() {
+        let specs = vec![
+            spec(
+                "batch",
+                "run-1",
+                "2025-01-20T01:00:00Z",
+                Some("2025-01-20T01:10:00Z"),
+            ),
+            spec(
+                "batch",
+                "run-2",
+                "2025-01-20T01:20:00Z",
+                Some("2025-01-20T01:50:00Z"),
+            ),
+        ];
+
+        let deduped = dedupe_specs(specs, Duration::hours(1));
+        assert_eq!(deduped.len(), 1);
+        assert_eq!(deduped[0].value, "run-1 (+1 more)");
+        assert_eq!(deduped[0].end_time.as_deref(), Some("2025-01-20T01:50:00Z"));
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+
+use chrono::{DateTime, Duration, Utc};
+use serde_json::Value;
+
+use crate::annotate::{self, JSON_KEY_LABEL, JSON_KEY_VALUE};
+use crate::remove::label_matches;
+
+/// Which vertical annotations `dedup` treats as copies of each other, e.g.
+/// the deploy markers of a retried pipeline.
+#[derive(Debug, Clone, Default)]
+pub struct DuplicateFilter {
+    /// Only collapse annotations with this label (cwnote writes `label: value`).
+    pub label: Option<String>,
+    /// Copies further than this after the kept one stay. `None` collapses
+    /// every copy, however far apart.
+    pub window: Option<Duration>,
+}
+
+fn time(ann: &Value) -> Option<DateTime<Utc>> {
+    annotate::annotation_head(ann)?
+        .get(JSON_KEY_VALUE)?
+        .as_str()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc))
+}
+
+impl DuplicateFilter {
+    /// Indices into `vertical` of the entries repeating an earlier one: same
+    /// label and value (cwnote keeps both in the label) and same kind, marker
+    /// or band. The earliest copy is kept.
+    pub fn duplicates(&self, vertical: &[Value]) -> Vec<usize> {
+        // Earliest first; the sort is stable, so copies at the same time keep
+        // their array order.
+        let mut order: Vec<usize> = (0..vertical.len()).collect();
+        order.sort_by_key(|&i| time(&vertical[i]));
+
+        let mut kept: Vec<(&str, bool, Option<DateTime<Utc>>)> = Vec::new();
+        let mut duplicates = Vec::new();
+        for i in order {
+            let ann = &vertical[i];
+            let Some(label) = annotate::annotation_head(ann)
+                .and_then(|head| head.get(JSON_KEY_LABEL))
+                .and_then(|l| l.as_str())
+            else {
+                continue;
+            };
+            if self
+                .label
+                .as_ref()
+                .is_some_and(|wanted| !label_matches(label, wanted))
+            {
+                continue;
+            }
+            let (band, at) = (ann.is_array(), time(ann));
+            let copy = kept.iter().any(|&(kept_label, kept_band, kept_at)| {
+                kept_label == label
+                    && kept_band == band
+                    && match (self.window, kept_at, at) {
+                        (None, _, _) => true,
+                        (Some(window), Some(kept_at), Some(at)) => at - kept_at <= window,
+                        _ => false,
+                    }
+            });
+            if copy {
+                duplicates.push(i);
+            } else {
+                kept.push((label, band, at));
+            }
+        }
+        duplicates.sort_unstable();
+        duplicates
+    }
+}
+
+impl fmt::Display for DuplicateFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("duplicates")?;
+        if let Some(ref label) = self.label {
+            write!(f, " of label '{label}'")?;
+        }
+        if let Some(window) = self.window {
+            write!(f, " within {}s", window.num_seconds())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn marker(label: &str, time: &str) -> Value {
+        json!({ "label": label, "value": time })
+    }
+
+    #[test]
+    fn keeps_the_earliest_copy_within_the_window() {
+        let vertical = [
+            marker("version: 1.9.0", "2025-01-20T12:07:00Z"),
+            marker("version: 1.9.0", "2025-01-20T12:00:00Z"),
+            marker("version: 1.9.1", "2025-01-20T12:03:00Z"),
+            json!([marker("version: 1.9.0", "2025-01-20T12:00:00Z"), { "value": "2025-01-20T13:00:00Z" }]),
+            marker("version: 1.9.0", "2025-01-22T09:00:00Z"),
+            marker("incident: INC-1", "2025-01-20T12:00:00Z"),
+        ];
+        let filter = DuplicateFilter {
+            label: Some("version".to_string()),
+            window: Some(Duration::hours(1)),
+        };
+        assert_eq!(filter.duplicates(&vertical), vec![0]);
+        assert_eq!(
+            filter.to_string(),
+            "duplicates of label 'version' within 3600s"
+        );
+
+        // Without a window the redeploy two days later is a copy too.
+        let everything = DuplicateFilter::default();
+        assert_eq!(everything.duplicates(&vertical), vec![0, 4]);
+    }
+}
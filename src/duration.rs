@@ -0,0 +1,208 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+
+use crate::timezone::Timezone;
+
+/// Parse a short human duration such as `45s`, `15m`, `2h`, `7d` or `2w`.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    let split_at = input
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow!("duration '{input}' is missing a unit (s, m, h, d, w)"))?;
+    let (amount, unit) = input.split_at(split_at);
+
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| anyhow!("duration '{input}' must start with a whole number"))?;
+
+    let duration = match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => {
+            return Err(anyhow!(
+                "duration '{input}' has unknown unit '{unit}' (expected s, m, h, d or w)"
+            ))
+        }
+    };
+
+    duration.ok_or_else(|| anyhow!("duration '{input}' is out of range"))
+}
+
+/// Parse a point in time: an RFC3339 timestamp, a naive timestamp read in
+/// `zone`, or a duration meaning that long before `now` (e.g. `7d`).
+pub fn parse_time_or_ago(
+    input: &str,
+    now: DateTime<Utc>,
+    zone: &Timezone,
+) -> Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(input.trim()) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    if let Some(time) = zone.parse_naive(input.trim()) {
+        return time;
+    }
+    parse_duration(input).map(|ago| now - ago)
+}
+
+// Epoch values above this are milliseconds (as seconds they'd be past the year 5000).
+const MAX_EPOCH_SECONDS: i64 = 99_999_999_999;
+
+/// Parse Unix epoch seconds or milliseconds, e.g. `1737374400` or `1737374400000`.
+fn parse_epoch(input: &str) -> Option<Result<DateTime<Utc>>> {
+    if input.is_empty() || !input.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let parsed = input.parse::<i64>().ok().and_then(|epoch| {
+        if epoch > MAX_EPOCH_SECONDS {
+            DateTime::from_timestamp_millis(epoch)
+        } else {
+            DateTime::from_timestamp(epoch, 0)
+        }
+    });
+    Some(parsed.ok_or_else(|| anyhow!("epoch time '{input}' is out of range")))
+}
+
+/// Parse an absolute or relative point in time, resolved against `now` (UTC):
+/// an RFC3339 timestamp, a naive timestamp like `2025-03-01 14:00` read in
+/// `zone`, Unix epoch seconds or milliseconds, `now`, or an offset such as
+/// `-15m`, `now-1d` or `now+5m`.
+pub fn parse_time_expr(input: &str, now: DateTime<Utc>, zone: &Timezone) -> Result<DateTime<Utc>> {
+    let input = input.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    if let Some(time) = zone.parse_naive(input) {
+        return time;
+    }
+    if let Some(epoch) = parse_epoch(input) {
+        return epoch;
+    }
+
+    let offset = input.strip_prefix("now").unwrap_or(input);
+    if offset.is_empty() && !input.is_empty() {
+        return Ok(now);
+    }
+    let (sign, amount) = match offset.split_at_checked(1) {
+        Some(("-", amount)) => (-1, amount),
+        Some(("+", amount)) => (1, amount),
+        _ => {
+            return Err(anyhow!(
+                "time '{input}' must be RFC3339, YYYY-MM-DD HH:MM, epoch seconds/milliseconds or relative to now, e.g. now, -15m, now-1d"
+            ))
+        }
+    };
+    let offset = parse_duration(amount)
+        .map_err(|err| anyhow!("time '{input}' has a malformed offset: {err}"))?;
+
+    Ok(if sign < 0 { now - offset } else { now + offset })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_all_units() {
+        assert_eq!(parse_duration("45s").unwrap(), Duration::seconds(45));
+        assert_eq!(parse_duration("15m").unwrap(), Duration::minutes(15));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::hours(2));
+        assert_eq!(parse_duration("7d").unwrap(), Duration::days(7));
+        assert_eq!(parse_duration("2w").unwrap(), Duration::weeks(2));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("15").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("15y").is_err());
+        assert!(parse_duration("-15m").is_err());
+    }
+
+    #[test]
+    fn parses_timestamps_and_durations_ago() {
+        let now = DateTime::parse_from_rfc3339("2025-01-20T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let utc = Timezone::default();
+        assert_eq!(
+            parse_time_or_ago("7d", now, &utc).unwrap(),
+            now - Duration::days(7)
+        );
+        assert_eq!(
+            parse_time_or_ago("2025-01-01T00:00:00+01:00", now, &utc)
+                .unwrap()
+                .to_rfc3339(),
+            "2024-12-31T23:00:00+00:00"
+        );
+        assert!(parse_time_or_ago("yesterday", now, &utc).is_err());
+    }
+
+    #[test]
+    fn parses_relative_time_expressions() {
+        let now = DateTime::parse_from_rfc3339("2025-01-20T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let utc = Timezone::default();
+
+        assert_eq!(parse_time_expr("now", now, &utc).unwrap(), now);
+        assert_eq!(
+            parse_time_expr("-15m", now, &utc).unwrap(),
+            now - Duration::minutes(15)
+        );
+        assert_eq!(
+            parse_time_expr("now-1d", now, &utc).unwrap(),
+            now - Duration::days(1)
+        );
+        assert_eq!(
+            parse_time_expr("now+5m", now, &utc).unwrap(),
+            now + Duration::minutes(5)
+        );
+        assert_eq!(
+            parse_time_expr("2025-01-01T00:00:00Z", now, &utc)
+                .unwrap()
+                .to_rfc3339(),
+            "2025-01-01T00:00:00+00:00"
+        );
+
+        for malformed in [
+            "",
+            "today",
+            "15m",
+            "now-",
+            "now-15",
+            "now*2h",
+            "-2y",
+            "1737374400.5",
+        ] {
+            assert!(
+                parse_time_expr(malformed, now, &utc).is_err(),
+                "{malformed}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_epoch_seconds_and_milliseconds() {
+        let now = Utc::now();
+        let utc = Timezone::default();
+        let expected = "2025-01-20T12:00:00+00:00";
+
+        assert_eq!(
+            parse_time_expr("1737374400", now, &utc)
+                .unwrap()
+                .to_rfc3339(),
+            expected
+        );
+        assert_eq!(
+            parse_time_expr("1737374400000", now, &utc)
+                .unwrap()
+                .to_rfc3339(),
+            expected
+        );
+        assert!(parse_time_expr("99999999999999999999", now, &utc).is_err());
+    }
+}
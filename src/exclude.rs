@@ -0,0 +1,68 @@
+use log::info;
+use regex::Regex;
+
+/// Dashboards to skip even though the target (suffix, regex, glob) picks
+/// them, e.g. sandbox copies.
+#[derive(Debug, Clone, Default)]
+pub struct DashboardExclusions {
+    pub prefixes: Vec<String>,
+    pub regexes: Vec<Regex>,
+}
+
+impl DashboardExclusions {
+    /// Why `name` is excluded, or `None` if it is kept.
+    pub fn reason(&self, name: &str) -> Option<String> {
+        if let Some(prefix) = self.prefixes.iter().find(|p| name.starts_with(p.as_str())) {
+            return Some(format!("prefix '{prefix}'"));
+        }
+        self.regexes
+            .iter()
+            .find(|r| r.is_match(name))
+            .map(|regex| format!("regex '{regex}'"))
+    }
+
+    /// The dashboards that are kept, logging every excluded one with its reason.
+    pub fn apply(&self, dashboards: Vec<String>) -> Vec<String> {
+        dashboards
+            .into_iter()
+            .filter(|name| match self.reason(name) {
+                Some(reason) => {
+                    info!("  - {name} excluded ({reason})");
+                    false
+                }
+                None => true,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excludes_by_prefix_then_regex() {
+        let exclusions = DashboardExclusions {
+            prefixes: vec!["sandbox-".to_string()],
+            regexes: vec![Regex::new("-copy$").unwrap()],
+        };
+
+        assert_eq!(
+            exclusions.reason("sandbox-orders").as_deref(),
+            Some("prefix 'sandbox-'")
+        );
+        assert_eq!(
+            exclusions.reason("orders-copy").as_deref(),
+            Some("regex '-copy$'")
+        );
+        assert_eq!(
+            exclusions.apply(vec![
+                "orders".to_string(),
+                "sandbox-orders".to_string(),
+                "orders-copy".to_string(),
+            ]),
+            vec!["orders".to_string()]
+        );
+        assert!(DashboardExclusions::default().reason("orders").is_none());
+    }
+}
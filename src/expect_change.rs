@@ -0,0 +1,139 @@
+use std::fmt;
+
+use anyhow::{anyhow, Context, Result};
+use aws_sdk_cloudwatch::Client;
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{Map, Value};
+
+use crate::preview::{self, WidgetMetric};
+
+// Length of the windows compared on each side of the annotation.
+const CHANGE_WINDOW_MINUTES: i64 = 30;
+// Relative mean shift from which we call a change visible.
+const VISIBLE_SHIFT_RATIO: f64 = 0.1;
+
+/// Mean of a metric before and after an annotation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeanShift {
+    pub before: f64,
+    pub after: f64,
+}
+
+impl MeanShift {
+    /// Split `points` at `at`; `None` unless both sides have data.
+    pub fn from_points(points: &[(DateTime<Utc>, f64)], at: DateTime<Utc>) -> Option<Self> {
+        let mean = |values: Vec<f64>| {
+            (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+        };
+        let (before, after): (Vec<_>, Vec<_>) = points.iter().partition(|(t, _)| *t < at);
+
+        Some(Self {
+            before: mean(before.into_iter().map(|(_, v)| v).collect())?,
+            after: mean(after.into_iter().map(|(_, v)| v).collect())?,
+        })
+    }
+
+    /// Change relative to the "before" mean, e.g. `0.25` for +25%.
+    pub fn ratio(&self) -> f64 {
+        if self.before == 0.0 {
+            if self.after == 0.0 {
+                0.0
+            } else {
+                f64::INFINITY.copysign(self.after)
+            }
+        } else {
+            (self.after - self.before) / self.before.abs()
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.ratio().abs() >= VISIBLE_SHIFT_RATIO
+    }
+}
+
+impl fmt::Display for MeanShift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "mean {:.3} -> {:.3} ({:+.1}%), {}",
+            self.before,
+            self.after,
+            self.ratio() * 100.0,
+            if self.is_visible() {
+                "change visible"
+            } else {
+                "no visible change"
+            }
+        )
+    }
+}
+
+/// Compare a widget's primary metric in the windows before and after `at`.
+pub async fn check_widget(
+    client: &Client,
+    widget_obj: &Map<String, Value>,
+    at: DateTime<Utc>,
+) -> Result<String> {
+    let metric =
+        WidgetMetric::from_widget(widget_obj).context("widget has no plain metric to compare")?;
+
+    let window = Duration::minutes(CHANGE_WINDOW_MINUTES);
+    let end = (at + window).min(Utc::now());
+    if end <= at {
+        return Err(anyhow!(
+            "no data after the annotation yet; rerun later with --time {}",
+            at.to_rfc3339()
+        ));
+    }
+
+    let points = preview::fetch_points(client, &metric, at - window, end).await?;
+    let shift = MeanShift::from_points(&points, at).with_context(|| {
+        format!(
+            "not enough datapoints for {}/{} on both sides of {at}",
+            metric.namespace, metric.metric_name
+        )
+    })?;
+
+    Ok(format!(
+        "{}/{} {}: {shift}",
+        metric.namespace, metric.metric_name, metric.stat
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn mean_shift_splits_at_annotation() {
+        let at = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+        let points = vec![
+            (at - Duration::minutes(10), 10.0),
+            (at - Duration::minutes(5), 10.0),
+            (at, 14.0),
+            (at + Duration::minutes(5), 16.0),
+        ];
+
+        let shift = MeanShift::from_points(&points, at).unwrap();
+        assert_eq!(shift.before, 10.0);
+        assert_eq!(shift.after, 15.0);
+        assert!(shift.is_visible());
+        assert_eq!(
+            shift.to_string(),
+            "mean 10.000 -> 15.000 (+50.0%), change visible"
+        );
+    }
+
+    #[test]
+    fn mean_shift_needs_both_sides() {
+        let at = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+        assert!(MeanShift::from_points(&[(at - Duration::minutes(1), 1.0)], at).is_none());
+
+        let flat = MeanShift {
+            before: 100.0,
+            after: 102.0,
+        };
+        assert!(!flat.is_visible());
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+
+// Set by GitHub Actions for every job.
+const GITHUB_TOKEN_ENV: &str = "GITHUB_TOKEN";
+const GITHUB_REPOSITORY_ENV: &str = "GITHUB_REPOSITORY";
+const GITHUB_SHA_ENV: &str = "GITHUB_SHA";
+const GITHUB_API_URL_ENV: &str = "GITHUB_API_URL";
+const DEFAULT_API_URL: &str = "https://api.github.com";
+const USER_AGENT: &str = concat!("cwnote/", env!("CARGO_PKG_VERSION"));
+const JSON_KEY_ID: &str = "id";
+
+/// A GitHub Deployment created for one cwnote run. The deployment payload
+/// carries the run id, and the run's annotations carry it too, so either
+/// side can be found from the other.
+#[derive(Debug, Clone)]
+pub struct GithubDeployment {
+    api_url: String,
+    repository: String,
+    token: String,
+    id: u64,
+}
+
+/// Request body creating the deployment.
+fn deployment_request(git_ref: &str, environment: &str, description: &str, run_id: &str) -> Value {
+    json!({
+        "ref": git_ref,
+        "environment": environment,
+        "description": description,
+        "auto_merge": false,
+        // Annotating is not gated on commit statuses.
+        "required_contexts": [],
+        "payload": { "cwnoteRunId": run_id }
+    })
+}
+
+/// Request body of a deployment status, linking to the annotated dashboard.
+fn status_request(success: bool, dashboard_url: Option<&str>, description: &str) -> Value {
+    let mut status = json!({
+        "state": if success { "success" } else { "failure" },
+        "description": description,
+    });
+    if let Some(url) = dashboard_url {
+        status["environment_url"] = json!(url);
+        status["log_url"] = json!(url);
+    }
+    status
+}
+
+fn env_var(key: &str) -> Result<String> {
+    std::env::var(key)
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .with_context(|| format!("--github-deployment needs {key} (set by GitHub Actions)"))
+}
+
+async fn post(api_url: &str, token: &str, path: &str, body: &Value) -> Result<Value> {
+    let resp = reqwest::Client::new()
+        .post(format!("{api_url}{path}"))
+        .bearer_auth(token)
+        .header(reqwest::header::USER_AGENT, USER_AGENT)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .with_context(|| format!("failed to call GitHub {path}"))?;
+
+    let status = resp.status();
+    let text = resp.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(anyhow!("GitHub {path} returned {status}: {text}"));
+    }
+    serde_json::from_str(&text).with_context(|| format!("GitHub {path} returned invalid JSON"))
+}
+
+impl GithubDeployment {
+    /// Create a deployment of the current commit to `environment`.
+    pub async fn create(environment: &str, description: &str, run_id: &str) -> Result<Self> {
+        let token = env_var(GITHUB_TOKEN_ENV)?;
+        let repository = env_var(GITHUB_REPOSITORY_ENV)?;
+        let git_ref = env_var(GITHUB_SHA_ENV)?;
+        let api_url = env_var(GITHUB_API_URL_ENV).unwrap_or_else(|_| DEFAULT_API_URL.to_string());
+
+        let resp = post(
+            &api_url,
+            &token,
+            &format!("/repos/{repository}/deployments"),
+            &deployment_request(&git_ref, environment, description, run_id),
+        )
+        .await?;
+        let id = resp
+            .get(JSON_KEY_ID)
+            .and_then(|id| id.as_u64())
+            .context("GitHub deployment response has no id")?;
+
+        Ok(Self {
+            api_url,
+            repository,
+            token,
+            id,
+        })
+    }
+
+    /// Record how the run went on the deployment.
+    pub async fn set_status(
+        &self,
+        success: bool,
+        dashboard_url: Option<&str>,
+        description: &str,
+    ) -> Result<()> {
+        post(
+            &self.api_url,
+            &self.token,
+            &format!(
+                "/repos/{}/deployments/{}/statuses",
+                self.repository, self.id
+            ),
+            &status_request(success, dashboard_url, description),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deployment_request_carries_the_run_id() {
+        let body = deployment_request("abc123", "production", "version: 1.9.0", "RUN");
+        assert_eq!(body["ref"], json!("abc123"));
+        assert_eq!(body["environment"], json!("production"));
+        assert_eq!(body["required_contexts"], json!([]));
+        assert_eq!(body["payload"]["cwnoteRunId"], json!("RUN"));
+    }
+
+    #[test]
+    fn status_request_links_the_dashboard() {
+        let body = status_request(true, Some("https://console/dash"), "done");
+        assert_eq!(body["state"], json!("success"));
+        assert_eq!(body["environment_url"], json!("https://console/dash"));
+
+        let failed = status_request(false, None, "failed");
+        assert_eq!(failed["state"], json!("failure"));
+        assert!(failed.get("environment_url").is_none());
+    }
+}
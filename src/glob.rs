@@ -0,0 +1,98 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error, Result};
+use regex::Regex;
+
+/// Shell-style pattern for dashboard names, e.g. `prod-*-api`: `*` matches
+/// any run of characters, `?` a single one and `[...]` a character class.
+/// The whole name has to match.
+#[derive(Debug, Clone)]
+pub struct Glob {
+    pattern: String,
+    regex: Regex,
+}
+
+impl FromStr for Glob {
+    type Err = Error;
+
+    fn from_str(pattern: &str) -> Result<Self> {
+        let mut regex = String::from("^");
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' => regex.push_str(".*"),
+                '?' => regex.push('.'),
+                '[' => {
+                    let mut class = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(']') if !class.is_empty() && class != "!" => break,
+                            Some(c) => class.push(c),
+                            None => return Err(anyhow!("glob '{pattern}' has an unclosed '['")),
+                        }
+                    }
+                    let (negate, class) = match class.strip_prefix('!') {
+                        Some(rest) => ("^", rest),
+                        None => ("", class.as_str()),
+                    };
+                    // Only ranges keep their meaning; regex class syntax is escaped.
+                    let class: String = class
+                        .chars()
+                        .map(|c| match c {
+                            '\\' | '[' | ']' | '&' | '~' | '^' => format!("\\{c}"),
+                            c => c.to_string(),
+                        })
+                        .collect();
+                    regex.push_str(&format!("[{negate}{class}]"));
+                }
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        regex.push('$');
+
+        let regex =
+            Regex::new(&regex).map_err(|err| anyhow!("glob '{pattern}' is invalid: {err}"))?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex,
+        })
+    }
+}
+
+impl fmt::Display for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+impl Glob {
+    pub fn is_match(&self, name: &str) -> bool {
+        self.regex.is_match(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_whole_names_like_a_shell() {
+        let glob: Glob = "prod-*-api".parse().unwrap();
+        assert!(glob.is_match("prod-orders-api"));
+        assert!(glob.is_match("prod--api"));
+        assert!(!glob.is_match("prod-orders-api-v2"));
+        assert!(!glob.is_match("staging-orders-api"));
+
+        let glob: Glob = "svc-[ab]?.prod".parse().unwrap();
+        assert!(glob.is_match("svc-a1.prod"));
+        assert!(!glob.is_match("svc-c1.prod"));
+        assert!(!glob.is_match("svc-a1xprod"));
+
+        let glob: Glob = "svc-[!a]*".parse().unwrap();
+        assert!(glob.is_match("svc-b"));
+        assert!(!glob.is_match("svc-a"));
+
+        assert!("prod-[".parse::<Glob>().is_err());
+    }
+}
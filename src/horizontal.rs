@@ -0,0 +1,319 @@
+use std::fmt;
+
+use anyhow::{Context, Result};
+use aws_sdk_cloudwatch::Client;
+use clap::ValueEnum;
+use log::{info, warn};
+use serde_json::{Map, Number, Value};
+
+use crate::annotate::{
+    self, annotations_array, Admission, JSON_KEY_FILL, JSON_KEY_HORIZONTAL, JSON_KEY_LABEL,
+    JSON_KEY_RUN_ID, JSON_KEY_VALUE,
+};
+use crate::body_size;
+use crate::iac::IacGuard;
+use crate::interrupt;
+use crate::pacing::Pacer;
+use crate::provenance::Provenance;
+use crate::remove::Direction;
+use crate::run_result::{ResultKind, TargetResult};
+use crate::selector::{selected_widgets, WidgetSelector};
+use crate::widget_support;
+
+const JSON_KEY_Y_AXIS: &str = "yAxis";
+
+/// Which side of a threshold line CloudWatch shades.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Fill {
+    Above,
+    Below,
+}
+
+impl Fill {
+    fn as_str(self) -> &'static str {
+        match self {
+            Fill::Above => "above",
+            Fill::Below => "below",
+        }
+    }
+}
+
+/// Y axis a threshold is drawn against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum YAxis {
+    Left,
+    Right,
+}
+
+impl YAxis {
+    fn as_str(self) -> &'static str {
+        match self {
+            YAxis::Left => "left",
+            YAxis::Right => "right",
+        }
+    }
+}
+
+/// One horizontal (threshold) annotation to add, e.g. an SLO target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HorizontalSpec {
+    pub label: Option<String>,
+    pub value: f64,
+    pub fill: Option<Fill>,
+    /// `None` leaves the choice to CloudWatch (the left axis).
+    pub y_axis: Option<YAxis>,
+    /// Id of the cwnote run creating the annotation, used by `remove --run-id`.
+    pub run_id: String,
+    pub provenance: Provenance,
+}
+
+impl fmt::Display for HorizontalSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.label {
+            Some(ref label) => write!(f, "{label} at {}", self.value)?,
+            None => write!(f, "threshold at {}", self.value)?,
+        }
+        if let Some(fill) = self.fill {
+            write!(f, " (fill {})", fill.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+impl HorizontalSpec {
+    /// Build the entry to push onto `annotations.horizontal`.
+    pub fn to_annotation(&self) -> Result<Value> {
+        let value = Number::from_f64(self.value)
+            .with_context(|| format!("threshold value {} is not a finite number", self.value))?;
+
+        let mut ann_obj = Map::new();
+        if let Some(ref label) = self.label {
+            ann_obj.insert(JSON_KEY_LABEL.to_string(), Value::String(label.clone()));
+        }
+        ann_obj.insert(JSON_KEY_VALUE.to_string(), Value::Number(value));
+        if let Some(fill) = self.fill {
+            ann_obj.insert(
+                JSON_KEY_FILL.to_string(),
+                Value::String(fill.as_str().to_string()),
+            );
+        }
+        if let Some(y_axis) = self.y_axis {
+            ann_obj.insert(
+                JSON_KEY_Y_AXIS.to_string(),
+                Value::String(y_axis.as_str().to_string()),
+            );
+        }
+        ann_obj.insert(
+            JSON_KEY_RUN_ID.to_string(),
+            Value::String(self.run_id.clone()),
+        );
+        self.provenance.write(&mut ann_obj);
+        Ok(Value::Object(ann_obj))
+    }
+}
+
+/// Push `annotation` onto the horizontal annotations of the widgets at
+/// `indices`. Returns how many widgets got it.
+pub fn apply_horizontal(body: &mut Value, annotation: &Value, indices: &[usize]) -> usize {
+    let Some(widgets) = body.get_mut("widgets").and_then(|w| w.as_array_mut()) else {
+        return 0;
+    };
+
+    let mut applied = 0usize;
+    for i in indices {
+        let Some(widget_obj) = widgets.get_mut(*i).and_then(|w| w.as_object_mut()) else {
+            continue;
+        };
+        let title = annotate::widget_title(&Value::Object(widget_obj.clone()));
+        if !widget_support::supports(widget_obj, Direction::Horizontal) {
+            info!(
+                "Skipping widget '{title}': {} widgets show no horizontal annotations",
+                widget_support::describe(widget_obj)
+            );
+            continue;
+        }
+        match annotations_array(widget_obj, JSON_KEY_HORIZONTAL) {
+            Some(arr) => {
+                arr.push(annotation.clone());
+                applied += 1;
+            }
+            None => {
+                warn!("Skipping widget '{title}': its annotations are not in the expected shape")
+            }
+        }
+    }
+    applied
+}
+
+/// Add a threshold annotation to a single dashboard by name.
+pub async fn annotate_dashboard(
+    client: &Client,
+    dashboard_name: &str,
+    spec: &HorizontalSpec,
+    selector: &WidgetSelector,
+    iac_guard: &IacGuard,
+    dry_run: bool,
+) -> Result<TargetResult> {
+    let annotation = spec.to_annotation()?;
+
+    let (_, mut body) = annotate::read_dashboard(client, dashboard_name).await?;
+    let label = spec.label.as_deref().unwrap_or_default();
+    let policy = match annotate::admit(dashboard_name, &body, iac_guard, false, [label])? {
+        Admission::Allowed(policy) => policy,
+        Admission::Skipped(outcome) => return Ok(TargetResult::from(&outcome)),
+    };
+    let selector = policy.restrict(selector);
+
+    let indices = {
+        let widgets = body
+            .get("widgets")
+            .and_then(|w| w.as_array())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        selected_widgets(widgets, &selector)
+            .with_context(|| format!("{dashboard_name}: invalid widget selection"))?
+    };
+
+    let applied = apply_horizontal(&mut body, &annotation, &indices);
+    if applied == 0 {
+        info!("{dashboard_name}: No matching metric widgets (nothing to annotate)");
+        return Ok(TargetResult::new(
+            dashboard_name,
+            ResultKind::Skipped,
+            "no matching widgets",
+        ));
+    }
+
+    body_size::ensure_fits(dashboard_name, body_size::json_len(&body))?;
+
+    if dry_run {
+        info! {
+            target: "dry-run",
+            "{}: would add {} to {} widget(s).",
+            dashboard_name, spec, applied
+        };
+        return Ok(annotated(dashboard_name, applied));
+    }
+
+    let updated_body = annotate::write_dashboard(client, dashboard_name, &body).await?;
+    info!("Added {spec} to {applied} widget(s) of dashboard '{dashboard_name}'");
+    if let Err(err) = annotate::save_to_file(&updated_body, dashboard_name) {
+        warn!("Export failed for '{dashboard_name}': {err}");
+    }
+
+    Ok(annotated(dashboard_name, applied))
+}
+
+fn annotated(dashboard_name: &str, widgets: usize) -> TargetResult {
+    TargetResult::new(
+        dashboard_name,
+        ResultKind::Changed,
+        format!("annotated {widgets} widget(s)"),
+    )
+}
+
+/// Add a threshold annotation to all dashboards whose name matches the suffix.
+pub async fn annotate_dashboards_by_suffix(
+    client: &Client,
+    suffix: &str,
+    spec: &HorizontalSpec,
+    selector: &WidgetSelector,
+    iac_guard: &IacGuard,
+    dry_run: bool,
+) -> Result<Vec<TargetResult>> {
+    let dashboards = annotate::list_dashboards_with_suffix(client, suffix).await?;
+
+    if dashboards.is_empty() {
+        info!("No dashboards found with suffix '{}'", suffix);
+        return Ok(Vec::new());
+    }
+
+    let mut pacer = Pacer::default();
+    let mut results = Vec::with_capacity(dashboards.len());
+    for (i, name) in dashboards.iter().enumerate() {
+        if interrupt::stop_before(&dashboards[i..]) {
+            break;
+        }
+        let result = pacer
+            .run(|| annotate_dashboard(client, name, spec, selector, iac_guard, dry_run))
+            .await?;
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const TEST_RUN_ID: &str = "20250120T120000Z-aaaaaaaa";
+
+    #[test]
+    fn threshold_is_appended_to_horizontal_annotations() {
+        let mut body = json!({
+            "widgets": [
+                {
+                    "type": "metric",
+                    "properties": {
+                        "title": "Latency",
+                        "annotations": {
+                            "horizontal": [{ "label": "old", "value": 100 }]
+                        }
+                    }
+                },
+                { "type": "metric", "properties": { "title": "Errors" } },
+                { "type": "text", "properties": { "markdown": "# Hi" } }
+            ]
+        });
+        let spec = HorizontalSpec {
+            label: Some("SLO p99".to_string()),
+            value: 250.0,
+            fill: Some(Fill::Above),
+            y_axis: Some(YAxis::Right),
+            run_id: TEST_RUN_ID.to_string(),
+            provenance: Provenance {
+                scope: Some("payments".to_string()),
+                ..Provenance::default()
+            },
+        };
+
+        let annotation = spec.to_annotation().unwrap();
+        assert_eq!(apply_horizontal(&mut body, &annotation, &[0, 1]), 2);
+        assert_eq!(
+            body["widgets"][0]["properties"]["annotations"]["horizontal"][1],
+            json!({
+                "label": "SLO p99",
+                "value": 250.0,
+                "fill": "above",
+                "yAxis": "right",
+                "cwnoteRunId": TEST_RUN_ID,
+                "cwnoteScope": "payments"
+            })
+        );
+        assert_eq!(
+            body["widgets"][1]["properties"]["annotations"]["horizontal"]
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(body["widgets"][1]["properties"]["annotations"]
+            .get("vertical")
+            .is_none());
+    }
+
+    #[test]
+    fn non_finite_threshold_is_rejected() {
+        let spec = HorizontalSpec {
+            label: None,
+            value: f64::NAN,
+            fill: None,
+            y_axis: None,
+            run_id: TEST_RUN_ID.to_string(),
+            provenance: Provenance::default(),
+        };
+        assert!(spec.to_annotation().is_err());
+    }
+}
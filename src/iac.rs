@@ -0,0 +1,167 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error, Result};
+use log::warn;
+use serde_json::Value;
+
+use crate::annotate::{JSON_KEY_MARKDOWN, JSON_KEY_PROPERTIES, JSON_KEY_TYPE, WIDGET_TYPE_TEXT};
+
+/// Marker Terraform users commonly put on their dashboards.
+pub const DEFAULT_IAC_MARKER: &str = "managedBy=terraform";
+
+/// `key=value` pair that flags a dashboard as owned by infrastructure as code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IacMarker {
+    pub key: String,
+    pub value: String,
+}
+
+impl FromStr for IacMarker {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("IaC marker '{s}' must look like key=value"))?;
+        let (key, value) = (key.trim(), value.trim());
+        if key.is_empty() || value.is_empty() {
+            return Err(anyhow!("IaC marker '{s}' must look like key=value"));
+        }
+        Ok(Self {
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for IacMarker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.key, self.value)
+    }
+}
+
+impl Default for IacMarker {
+    fn default() -> Self {
+        DEFAULT_IAC_MARKER
+            .parse()
+            .expect("default IaC marker is valid")
+    }
+}
+
+impl IacMarker {
+    /// `true` if a markdown line reads like the marker, e.g.
+    /// `"managedBy": "terraform"`, `managedBy: terraform` or `managedBy=terraform`.
+    fn matches_line(&self, line: &str) -> bool {
+        let clean = |s: &str| {
+            s.trim()
+                .trim_end_matches(',')
+                .trim_matches(|c| matches!(c, '"' | '\'' | '`' | '*' | '_' | ' '))
+                .to_string()
+        };
+        line.split_once([':', '=']).is_some_and(|(key, value)| {
+            clean(key).eq_ignore_ascii_case(&self.key)
+                && clean(value).eq_ignore_ascii_case(&self.value)
+        })
+    }
+
+    /// `true` if the dashboard carries the marker, either as a top-level key of
+    /// the body or on a line of a text widget.
+    pub fn is_on(&self, body: &Value) -> bool {
+        if body.get(&self.key).and_then(|v| v.as_str()) == Some(self.value.as_str()) {
+            return true;
+        }
+
+        body.get("widgets")
+            .and_then(|w| w.as_array())
+            .into_iter()
+            .flatten()
+            .filter(|w| w.get(JSON_KEY_TYPE).and_then(|t| t.as_str()) == Some(WIDGET_TYPE_TEXT))
+            .filter_map(|w| w.get(JSON_KEY_PROPERTIES)?.get(JSON_KEY_MARKDOWN)?.as_str())
+            .any(|markdown| markdown.lines().any(|line| self.matches_line(line)))
+    }
+}
+
+/// Whether dashboards managed by infrastructure as code may be annotated.
+///
+/// Such dashboards lose cwnote's annotations on the next `apply`, so by
+/// default they are skipped with a warning.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IacGuard {
+    pub marker: IacMarker,
+    /// Annotate marked dashboards anyway (`--allow-iac-managed`).
+    pub allow: bool,
+}
+
+impl IacGuard {
+    /// `true` if `body` must be left alone; logs why.
+    pub fn skips(&self, dashboard_name: &str, body: &Value) -> bool {
+        if !self.marker.is_on(body) {
+            return false;
+        }
+        if self.allow {
+            warn!(
+                "{dashboard_name}: marked {} - annotating anyway, the next apply may revert it",
+                self.marker
+            );
+            return false;
+        }
+        warn!(
+            "{dashboard_name}: marked {} - skipping, the next apply would revert annotations \
+             (use --allow-iac-managed to annotate anyway)",
+            self.marker
+        );
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn marker_is_found_in_text_widgets_and_top_level_keys() {
+        let marker = IacMarker::default();
+
+        let json_style = json!({
+            "widgets": [
+                { "type": "text", "properties": { "markdown": "Owned by platform\n\"managedBy\": \"terraform\"" } },
+                { "type": "metric", "properties": { "title": "Latency" } }
+            ]
+        });
+        assert!(marker.is_on(&json_style));
+
+        let yaml_style = json!({
+            "widgets": [{ "type": "text", "properties": { "markdown": "**managedBy**: Terraform" } }]
+        });
+        assert!(marker.is_on(&yaml_style));
+
+        assert!(marker.is_on(&json!({ "widgets": [], "managedBy": "terraform" })));
+
+        let other = json!({
+            "widgets": [
+                { "type": "text", "properties": { "markdown": "managedBy: pulumi" } },
+                { "type": "metric", "properties": { "title": "managedBy: terraform" } }
+            ]
+        });
+        assert!(!marker.is_on(&other));
+    }
+
+    #[test]
+    fn guard_skips_marked_dashboards_unless_allowed() {
+        let body = json!({
+            "widgets": [{ "type": "text", "properties": { "markdown": "owner=cdk" } }]
+        });
+        let mut guard = IacGuard {
+            marker: "owner=cdk".parse().unwrap(),
+            allow: false,
+        };
+        assert!(guard.skips("Service-Dashboard", &body));
+
+        guard.allow = true;
+        assert!(!guard.skips("Service-Dashboard", &body));
+
+        assert!("managedBy".parse::<IacMarker>().is_err());
+    }
+}
@@ -0,0 +1,49 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use anyhow::{Context, Result};
+
+/// Whether cwnote may assume a human is watching the terminal (colors, and
+/// anything that would wait for input). `--non-interactive` forces `false`;
+/// otherwise both stdin and stderr have to be terminals, which is never the
+/// case in a CI container or when output is piped to a file.
+pub fn is_interactive(non_interactive: bool) -> bool {
+    !non_interactive && std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// Ask `question` on stderr and wait for an answer on stdin. Only "y" or
+/// "yes" (any case) count as yes; an empty answer is no.
+pub fn confirm(question: &str) -> Result<bool> {
+    let mut stderr = io::stderr();
+    write!(stderr, "{question} [y/N] ").context("failed to write the prompt")?;
+    stderr.flush().context("failed to write the prompt")?;
+
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .context("failed to read the answer")?;
+    Ok(is_yes(&answer))
+}
+
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_y_or_yes_confirms() {
+        assert!(is_yes("y\n"));
+        assert!(is_yes(" YES \n"));
+        assert!(!is_yes("\n"));
+        assert!(!is_yes("no\n"));
+        assert!(!is_yes("yep\n"));
+    }
+
+    #[test]
+    fn non_interactive_flag_wins_over_terminal_detection() {
+        assert!(!is_interactive(true));
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use log::warn;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Trap Ctrl-C: the first one lets the dashboard in flight finish its
+/// GetDashboard/PutDashboard and stops before the next one; a second one
+/// exits immediately.
+pub fn install() {
+    tokio::spawn(async {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            if REQUESTED.swap(true, Ordering::SeqCst) {
+                warn!("Second Ctrl-C, exiting now");
+                std::process::exit(130);
+            }
+            warn!("Ctrl-C: finishing the current dashboard, then stopping (press again to abort)");
+        }
+    });
+}
+
+/// Whether Ctrl-C was pressed.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// `true` (after logging what is left) if the run has to stop before the
+/// first of the `remaining` dashboards.
+pub fn stop_before(remaining: &[String]) -> bool {
+    if !requested() || remaining.is_empty() {
+        return false;
+    }
+    warn!(
+        "Interrupted: {} dashboard(s) not processed: {}",
+        remaining.len(),
+        remaining.join(", ")
+    );
+    true
+}
+
+/// Sleep for `wait` unless Ctrl-C is pressed first. Returns `false` if it was.
+pub async fn sleep(wait: Duration) -> bool {
+    tokio::select! {
+        _ = tokio::time::sleep(wait) => !requested(),
+        _ = tokio::signal::ctrl_c() => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops_only_when_requested_and_work_is_left() {
+        let remaining = vec!["Orders-Dashboard".to_string()];
+        assert!(!stop_before(&remaining));
+
+        REQUESTED.store(true, Ordering::SeqCst);
+        assert!(stop_before(&remaining));
+        assert!(!stop_before(&[]));
+        REQUESTED.store(false, Ordering::SeqCst);
+    }
+}
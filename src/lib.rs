@@ -0,0 +1,118 @@
+//! Add annotations to CloudWatch dashboards.
+//!
+//! [`Annotator`] is the embedding API (e.g. for deployment controllers) and
+//! follows semver on its own. The other modules back the `cwnote` CLI and may
+//! change in any release.
+
+mod annotator;
+
+#[doc(hidden)]
+pub mod account;
+#[doc(hidden)]
+pub mod annotate;
+#[doc(hidden)]
+pub mod appconfig_sync;
+#[doc(hidden)]
+pub mod aws_client;
+#[doc(hidden)]
+pub mod backup;
+#[doc(hidden)]
+pub mod body_diff;
+#[doc(hidden)]
+pub mod body_size;
+#[doc(hidden)]
+pub mod canary;
+#[doc(hidden)]
+pub mod change_calendar;
+#[doc(hidden)]
+pub mod color;
+#[doc(hidden)]
+pub mod consistency;
+#[doc(hidden)]
+pub mod current;
+#[doc(hidden)]
+pub mod dashboard_cache;
+#[doc(hidden)]
+pub mod dedupe;
+#[doc(hidden)]
+pub mod duplicate_filter;
+#[doc(hidden)]
+pub mod duration;
+#[doc(hidden)]
+pub mod exclude;
+#[doc(hidden)]
+pub mod expect_change;
+#[doc(hidden)]
+pub mod github_deployment;
+#[doc(hidden)]
+pub mod glob;
+#[doc(hidden)]
+pub mod horizontal;
+#[doc(hidden)]
+pub mod iac;
+#[doc(hidden)]
+pub mod interactive;
+#[doc(hidden)]
+pub mod interrupt;
+#[doc(hidden)]
+pub mod list;
+#[doc(hidden)]
+pub mod logging;
+#[doc(hidden)]
+pub mod logs_sync;
+#[doc(hidden)]
+pub mod maintenance;
+#[doc(hidden)]
+pub mod manifest;
+#[doc(hidden)]
+pub mod pacing;
+#[doc(hidden)]
+pub mod policy;
+#[doc(hidden)]
+pub mod preview;
+#[doc(hidden)]
+pub mod progress;
+#[doc(hidden)]
+pub mod provenance;
+#[doc(hidden)]
+pub mod remove;
+#[doc(hidden)]
+pub mod report;
+#[doc(hidden)]
+pub mod run_id;
+#[doc(hidden)]
+pub mod run_result;
+#[doc(hidden)]
+pub mod schema;
+#[doc(hidden)]
+pub mod select_expr;
+#[doc(hidden)]
+pub mod selector;
+#[doc(hidden)]
+pub mod selftest;
+#[doc(hidden)]
+pub mod services;
+#[doc(hidden)]
+pub mod sfn_sync;
+#[doc(hidden)]
+pub mod stats;
+#[doc(hidden)]
+pub mod synthetics_sync;
+#[doc(hidden)]
+pub mod template;
+#[doc(hidden)]
+pub mod time_display;
+#[doc(hidden)]
+pub mod timezone;
+#[doc(hidden)]
+pub mod title_tag;
+#[doc(hidden)]
+pub mod value_source;
+#[doc(hidden)]
+pub mod value_transform;
+#[doc(hidden)]
+pub mod widget_support;
+
+pub use annotator::{Annotation, Annotator, Target};
+pub use provenance::Provenance;
+pub use selector::WidgetSelector;
@@ -0,0 +1,336 @@
+use anyhow::{Context, Result};
+use aws_sdk_cloudwatch::Client;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+use crate::annotate::{
+    self, annotation_head, widget_title, JSON_KEY_ANNOTATIONS, JSON_KEY_LABEL, JSON_KEY_PROPERTIES,
+    JSON_KEY_RUN_ID, JSON_KEY_VALUE,
+};
+use crate::provenance::{Provenance, ProvenanceFilter};
+use crate::remove::{label_matches, Direction};
+use crate::selector::{selected_widgets, WidgetSelector};
+use crate::time_display::TimeDisplay;
+
+/// Which existing annotations `list` prints. Every criterion that is set has
+/// to match.
+#[derive(Debug, Clone, Default)]
+pub struct ListFilter {
+    /// Only annotations with this label (cwnote writes `label: value`).
+    pub label: Option<String>,
+    /// Only annotations at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only annotations at or before this time.
+    pub until: Option<DateTime<Utc>>,
+    /// Only this annotation array. `None` lists both.
+    pub direction: Option<Direction>,
+    pub provenance: ProvenanceFilter,
+}
+
+/// One annotation found on a dashboard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedAnnotation {
+    pub dashboard: String,
+    pub widget: String,
+    pub direction: Direction,
+    pub label: String,
+    /// Timestamp (vertical) or threshold (horizontal), as stored.
+    pub value: String,
+    /// End of a band, if the annotation is one.
+    pub end: Option<String>,
+    pub run_id: Option<String>,
+    pub provenance: Provenance,
+}
+
+impl ListFilter {
+    fn matches(&self, ann: &ListedAnnotation) -> bool {
+        if let Some(ref label) = self.label {
+            if !label_matches(&ann.label, label) {
+                return false;
+            }
+        }
+        if self.since.is_some() || self.until.is_some() {
+            // Thresholds have no time, so a time range only keeps vertical annotations.
+            let Ok(time) = DateTime::parse_from_rfc3339(&ann.value) else {
+                return false;
+            };
+            let time = time.with_timezone(&Utc);
+            if self.since.is_some_and(|since| time < since)
+                || self.until.is_some_and(|until| time > until)
+            {
+                return false;
+            }
+        }
+        self.provenance.matches(&ann.provenance, &ann.value)
+    }
+}
+
+fn text(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(s) => Some(s.clone()),
+        Value::Null => None,
+        other => Some(other.to_string()),
+    }
+}
+
+/// Describe one annotation entry (object or band) of the widget titled `widget`.
+pub fn listed_annotation(
+    dashboard: &str,
+    widget: &str,
+    direction: Direction,
+    ann: &Value,
+) -> Option<ListedAnnotation> {
+    let head = annotation_head(ann)?;
+    let end = ann
+        .as_array()
+        .and_then(|band| band.get(1))
+        .and_then(|tail| text(tail.get(JSON_KEY_VALUE)));
+    Some(ListedAnnotation {
+        dashboard: dashboard.to_string(),
+        widget: widget.to_string(),
+        direction,
+        label: text(head.get(JSON_KEY_LABEL)).unwrap_or_default(),
+        value: text(head.get(JSON_KEY_VALUE)).unwrap_or_default(),
+        end,
+        run_id: text(head.get(JSON_KEY_RUN_ID)),
+        provenance: Provenance::read(head),
+    })
+}
+
+/// How listed annotations are printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Tab-separated lines with a header.
+    #[default]
+    Text,
+    /// One JSON array of objects.
+    Json,
+}
+
+impl ListedAnnotation {
+    /// The annotation as a JSON object, with times as stored (UTC).
+    pub fn to_json(&self) -> Value {
+        json!({
+            "dashboard": self.dashboard,
+            "widget": self.widget,
+            "direction": self.direction.json_key(),
+            "label": self.label,
+            "value": self.value,
+            "end": self.end,
+            "runId": self.run_id,
+            "creator": self.provenance.creator,
+            "scope": self.provenance.scope,
+            "ttl": self.provenance.ttl,
+            "url": self.provenance.url,
+        })
+    }
+}
+
+/// Render `listed` for stdout in the given format.
+pub fn render(listed: &[ListedAnnotation], format: OutputFormat, display: TimeDisplay) -> String {
+    match format {
+        OutputFormat::Json => {
+            let entries: Vec<Value> = listed.iter().map(ListedAnnotation::to_json).collect();
+            serde_json::to_string_pretty(&entries).unwrap_or_default()
+        }
+        OutputFormat::Text => {
+            let time = |v: &str| display.format_rfc3339(v);
+            let mut lines = vec![
+                "DASHBOARD\tWIDGET\tDIRECTION\tVALUE\tEND\tLABEL\tRUN_ID\tCREATOR\tSCOPE"
+                    .to_string(),
+            ];
+            lines.extend(listed.iter().map(|ann| {
+                format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    ann.dashboard,
+                    ann.widget,
+                    ann.direction,
+                    time(&ann.value),
+                    ann.end.as_deref().map(time).unwrap_or_default(),
+                    ann.label,
+                    ann.run_id.as_deref().unwrap_or(""),
+                    ann.provenance.creator.as_deref().unwrap_or(""),
+                    ann.provenance.scope.as_deref().unwrap_or("")
+                )
+            }));
+            lines.join("\n")
+        }
+    }
+}
+
+/// Annotations on the widgets `selector` picks, in widget order.
+pub fn list_in_body(
+    dashboard: &str,
+    body: &Value,
+    filter: &ListFilter,
+    selector: &WidgetSelector,
+) -> Result<Vec<ListedAnnotation>> {
+    let widgets = body
+        .get("widgets")
+        .and_then(|w| w.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let directions = match filter.direction {
+        Some(direction) => vec![direction],
+        None => Direction::ALL.to_vec(),
+    };
+
+    let mut listed = Vec::new();
+    for i in selected_widgets(widgets, selector)? {
+        let widget = &widgets[i];
+        let annotations = widget
+            .get(JSON_KEY_PROPERTIES)
+            .and_then(|p| p.get(JSON_KEY_ANNOTATIONS));
+
+        for &direction in &directions {
+            let anns = annotations
+                .and_then(|a| a.get(direction.json_key()))
+                .and_then(|v| v.as_array())
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+
+            let title = widget_title(widget);
+            listed.extend(
+                anns.iter()
+                    .filter_map(|ann| listed_annotation(dashboard, &title, direction, ann))
+                    .filter(|entry| filter.matches(entry)),
+            );
+        }
+    }
+    Ok(listed)
+}
+
+/// Annotations of a single dashboard by name.
+pub async fn list_dashboard(
+    client: &Client,
+    dashboard_name: &str,
+    filter: &ListFilter,
+    selector: &WidgetSelector,
+) -> Result<Vec<ListedAnnotation>> {
+    let resp = client
+        .get_dashboard()
+        .dashboard_name(dashboard_name)
+        .send()
+        .await
+        .with_context(|| format!("failed to get dashboard {dashboard_name}"))?;
+
+    let body_str = resp
+        .dashboard_body()
+        .with_context(|| format!("dashboard {dashboard_name} has no body"))?;
+    let body: Value =
+        serde_json::from_str(body_str).context("failed to parse dashboard body JSON")?;
+
+    list_in_body(dashboard_name, &body, filter, selector)
+        .with_context(|| format!("{dashboard_name}: cannot select widgets"))
+}
+
+/// Annotations of every dashboard whose name matches the suffix (all
+/// dashboards for an empty suffix).
+pub async fn list_dashboards_by_suffix(
+    client: &Client,
+    suffix: &str,
+    filter: &ListFilter,
+    selector: &WidgetSelector,
+) -> Result<Vec<ListedAnnotation>> {
+    let mut listed = Vec::new();
+    for name in annotate::list_dashboards_with_suffix(client, suffix).await? {
+        listed.extend(list_dashboard(client, &name, filter, selector).await?);
+    }
+    Ok(listed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn body() -> Value {
+        json!({
+            "widgets": [
+                {
+                    "type": "metric",
+                    "properties": {
+                        "title": "Latency",
+                        "annotations": {
+                            "vertical": [
+                                { "label": "version: 1.8.0", "value": "2025-01-10T12:00:00Z", "cwnoteRunId": "R1" },
+                                [
+                                    { "label": "batch: nightly", "value": "2025-01-20T01:00:00Z" },
+                                    { "value": "2025-01-20T02:00:00Z" }
+                                ]
+                            ],
+                            "horizontal": [
+                                { "label": "SLO", "value": 0.8 }
+                            ]
+                        }
+                    }
+                },
+                { "type": "text", "properties": { "markdown": "# Hi" } }
+            ]
+        })
+    }
+
+    #[test]
+    fn lists_vertical_bands_and_horizontal_annotations() {
+        let listed = list_in_body(
+            "D",
+            &body(),
+            &ListFilter::default(),
+            &WidgetSelector::default(),
+        )
+        .unwrap();
+
+        assert_eq!(listed.len(), 3);
+        assert_eq!(listed[0].run_id.as_deref(), Some("R1"));
+        assert_eq!(listed[1].end.as_deref(), Some("2025-01-20T02:00:00Z"));
+        assert_eq!(listed[2].direction, Direction::Horizontal);
+        assert_eq!(listed[2].value, "0.8");
+        assert!(listed.iter().all(|a| a.widget == "Latency"));
+    }
+
+    #[test]
+    fn filters_by_label_and_time_range() {
+        let by_label = ListFilter {
+            label: Some("version".to_string()),
+            ..Default::default()
+        };
+        let listed = list_in_body("D", &body(), &by_label, &WidgetSelector::default()).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].label, "version: 1.8.0");
+
+        let recent = ListFilter {
+            since: Some(Utc.with_ymd_and_hms(2025, 1, 15, 0, 0, 0).unwrap()),
+            ..Default::default()
+        };
+        let listed = list_in_body("D", &body(), &recent, &WidgetSelector::default()).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].label, "batch: nightly");
+    }
+
+    #[test]
+    fn renders_text_and_json() {
+        let listed = list_in_body(
+            "D",
+            &body(),
+            &ListFilter::default(),
+            &WidgetSelector::default(),
+        )
+        .unwrap();
+
+        let text = render(&listed[..1], OutputFormat::Text, TimeDisplay::Utc);
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("DASHBOARD\tWIDGET"));
+        assert!(lines[1].starts_with("D\tLatency\tvertical\t"));
+        assert!(lines[1].ends_with("\tversion: 1.8.0\tR1\t\t"));
+
+        let json: Value =
+            serde_json::from_str(&render(&listed, OutputFormat::Json, TimeDisplay::Utc)).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 3);
+        assert_eq!(json[1]["end"], json!("2025-01-20T02:00:00Z"));
+        assert_eq!(json[2]["direction"], json!("horizontal"));
+        assert_eq!(json[2]["runId"], Value::Null);
+    }
+}
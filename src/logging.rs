@@ -0,0 +1,70 @@
+use clap::ValueEnum;
+use serde_json::json;
+
+/// How log lines are written to stderr.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// `[time level target run=<id>] message`.
+    #[default]
+    Text,
+    /// One JSON object per line, for log pipelines.
+    Json,
+}
+
+/// env_logger filter for `-v` repeated `verbose` times, used unless
+/// `RUST_LOG` is set: cwnote's debug lines with `-v`, the AWS SDK's
+/// requests and responses with `-vv`, everything with `-vvv`.
+pub fn filter(verbose: u8) -> &'static str {
+    match verbose {
+        0 => "info",
+        1 => "info,cwnote=debug",
+        2 => "debug",
+        _ => "trace",
+    }
+}
+
+/// One log line in the JSON format.
+pub fn json_line(
+    timestamp: &str,
+    level: &str,
+    target: &str,
+    run_id: &str,
+    message: &str,
+) -> String {
+    json!({
+        "timestamp": timestamp,
+        "level": level,
+        "target": target,
+        "runId": run_id,
+        "message": message,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbosity_widens_the_filter() {
+        assert_eq!(filter(0), "info");
+        assert_eq!(filter(1), "info,cwnote=debug");
+        assert_eq!(filter(2), "debug");
+        assert_eq!(filter(9), "trace");
+    }
+
+    #[test]
+    fn json_lines_carry_the_run_id() {
+        let line = json_line(
+            "2025-01-20T12:00:00Z",
+            "INFO",
+            "cwnote::annotate",
+            "20250120T120000Z-aaaaaaaa",
+            "orders: \"Latency\" annotated",
+        );
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["runId"], "20250120T120000Z-aaaaaaaa");
+        assert_eq!(value["message"], "orders: \"Latency\" annotated");
+        assert!(!line.contains('\n'));
+    }
+}
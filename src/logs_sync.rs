@@ -0,0 +1,243 @@
+use anyhow::{anyhow, Context, Result};
+use aws_sdk_cloudwatchlogs::types::QueryStatus;
+use aws_sdk_cloudwatchlogs::Client;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use log::{info, warn};
+use std::collections::BTreeMap;
+
+use crate::annotate::AnnotationSpec;
+
+const MAP_KEY_TIME: &str = "time";
+const MAP_KEY_VALUE: &str = "value";
+const MAP_KEY_LABEL: &str = "label";
+const DEFAULT_TIME_FIELD: &str = "@timestamp";
+const DEFAULT_VALUE_FIELD: &str = "@message";
+// Logs Insights renders @timestamp as e.g. "2025-01-20 12:34:56.789" (UTC).
+const LOGS_TS_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// One Logs Insights result row, keyed by field name.
+pub type ResultRow = BTreeMap<String, String>;
+
+/// Which result fields become the annotation time, value and (optionally) label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldMap {
+    pub time: String,
+    pub value: String,
+    /// If unset, every annotation uses the `--label` given on the command line.
+    pub label: Option<String>,
+}
+
+impl Default for FieldMap {
+    fn default() -> Self {
+        Self {
+            time: DEFAULT_TIME_FIELD.to_string(),
+            value: DEFAULT_VALUE_FIELD.to_string(),
+            label: None,
+        }
+    }
+}
+
+impl FieldMap {
+    /// Build a field map from `--map key=field` pairs, e.g. `value=version`.
+    pub fn from_pairs(pairs: &[String]) -> Result<Self> {
+        let mut map = Self::default();
+        for pair in pairs {
+            let (key, field) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("--map '{pair}' must look like key=field"))?;
+            let field = field.trim().to_string();
+            match key.trim() {
+                MAP_KEY_TIME => map.time = field,
+                MAP_KEY_VALUE => map.value = field,
+                MAP_KEY_LABEL => map.label = Some(field),
+                other => {
+                    return Err(anyhow!(
+                        "--map key '{other}' is not one of: time, value, label"
+                    ))
+                }
+            }
+        }
+        Ok(map)
+    }
+}
+
+/// Convert a Logs Insights timestamp (or an RFC3339 string) into RFC3339.
+fn parse_logs_timestamp(raw: &str) -> Result<String> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(dt.with_timezone(&Utc).to_rfc3339());
+    }
+    let naive = NaiveDateTime::parse_from_str(raw, LOGS_TS_FORMAT)
+        .with_context(|| format!("unrecognised timestamp '{raw}'"))?;
+    Ok(naive.and_utc().to_rfc3339())
+}
+
+/// Turn one result row into an annotation.
+pub fn row_to_spec(
+    row: &ResultRow,
+    map: &FieldMap,
+    default_label: &str,
+    run_id: &str,
+) -> Result<AnnotationSpec> {
+    let field = |name: &str| {
+        row.get(name)
+            .map(String::as_str)
+            .ok_or_else(|| anyhow!("result row has no field '{name}'"))
+    };
+
+    let time = parse_logs_timestamp(field(&map.time)?)?;
+    let value = field(&map.value)?.to_string();
+    let label = match map.label {
+        Some(ref name) => field(name)?.to_string(),
+        None => default_label.to_string(),
+    };
+
+    Ok(AnnotationSpec {
+        label,
+        value,
+        time: Some(time),
+        end_time: None,
+        run_id: run_id.to_string(),
+    })
+}
+
+/// Turn all result rows into annotations, skipping (and reporting) rows that don't map.
+pub fn rows_to_specs(
+    rows: &[ResultRow],
+    map: &FieldMap,
+    default_label: &str,
+    run_id: &str,
+) -> Vec<AnnotationSpec> {
+    rows.iter()
+        .enumerate()
+        .filter_map(
+            |(i, row)| match row_to_spec(row, map, default_label, run_id) {
+                Ok(spec) => Some(spec),
+                Err(err) => {
+                    warn!("Skipping result row {i}: {err}");
+                    None
+                }
+            },
+        )
+        .collect()
+}
+
+/// Run a Logs Insights query over the last `since` and wait for its results.
+pub async fn run_query(
+    client: &Client,
+    log_group: &str,
+    query: &str,
+    since: Duration,
+) -> Result<Vec<ResultRow>> {
+    let end = Utc::now();
+    let start = end - since;
+
+    let started = client
+        .start_query()
+        .log_group_name(log_group)
+        .query_string(query)
+        .start_time(start.timestamp())
+        .end_time(end.timestamp())
+        .send()
+        .await
+        .with_context(|| format!("failed to start Logs Insights query on {log_group}"))?;
+
+    let query_id = started
+        .query_id()
+        .context("Logs Insights did not return a query id")?
+        .to_string();
+    info!("Started Logs Insights query {query_id} on {log_group}");
+
+    loop {
+        let resp = client
+            .get_query_results()
+            .query_id(&query_id)
+            .send()
+            .await
+            .with_context(|| format!("failed to get results of query {query_id}"))?;
+
+        match resp.status() {
+            Some(QueryStatus::Complete) => {
+                let rows = resp
+                    .results()
+                    .iter()
+                    .map(|fields| {
+                        fields
+                            .iter()
+                            .filter_map(|f| Some((f.field()?.to_string(), f.value()?.to_string())))
+                            .collect()
+                    })
+                    .collect();
+                return Ok(rows);
+            }
+            Some(
+                status @ (QueryStatus::Failed | QueryStatus::Cancelled | QueryStatus::Timeout),
+            ) => {
+                return Err(anyhow!("query {query_id} ended with status {status:?}"));
+            }
+            _ => tokio::time::sleep(POLL_INTERVAL).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_RUN_ID: &str = "20250120T120000Z-aaaaaaaa";
+
+    fn row(pairs: &[(&str, &str)]) -> ResultRow {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn field_map_defaults_and_overrides() {
+        assert_eq!(FieldMap::from_pairs(&[]).unwrap(), FieldMap::default());
+
+        let map = FieldMap::from_pairs(&["value=version".to_string(), "label=service".to_string()])
+            .unwrap();
+        assert_eq!(map.time, DEFAULT_TIME_FIELD);
+        assert_eq!(map.value, "version");
+        assert_eq!(map.label.as_deref(), Some("service"));
+
+        assert!(FieldMap::from_pairs(&["value".to_string()]).is_err());
+        assert!(FieldMap::from_pairs(&["color=red".to_string()]).is_err());
+    }
+
+    #[test]
+    fn row_maps_to_annotation() {
+        let map = FieldMap::from_pairs(&["value=version".to_string()]).unwrap();
+        let spec = row_to_spec(
+            &row(&[
+                ("@timestamp", "2025-01-20 12:34:56.789"),
+                ("version", "1.9.0"),
+            ]),
+            &map,
+            "deploy",
+            TEST_RUN_ID,
+        )
+        .unwrap();
+
+        assert_eq!(spec.label, "deploy");
+        assert_eq!(spec.value, "1.9.0");
+        assert_eq!(spec.time.as_deref(), Some("2025-01-20T12:34:56.789+00:00"));
+        assert_eq!(spec.run_id, TEST_RUN_ID);
+    }
+
+    #[test]
+    fn rows_missing_fields_are_skipped() {
+        let map = FieldMap::default();
+        let rows = vec![
+            row(&[("@timestamp", "2025-01-20 12:00:00.000"), ("@message", "a")]),
+            row(&[("@message", "no timestamp")]),
+            row(&[("@timestamp", "garbage"), ("@message", "b")]),
+        ];
+
+        let specs = rows_to_specs(&rows, &map, "deploy", TEST_RUN_ID);
+        assert_eq!(specs.len(), 1);
+        assert_eq!(specs[0].value, "a");
+    }
+}
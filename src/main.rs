@@ -1,120 +1,1741 @@
-mod annotate;
-mod aws_client;
 mod cli;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use clap::Parser;
-use cli::{Cli, Commands};
+use cli::{
+    AnnotateOpts, Cli, Commands, DashboardsCommand, DashboardsOpts, SelectorCommand, SelectorOpts,
+    SyncArgs,
+};
+use cwnote::github_deployment::GithubDeployment;
+use cwnote::list::OutputFormat;
+use cwnote::logging::{self, LogFormat};
+use cwnote::run_result::{ResultKind, RunResult, TargetResult};
+use cwnote::time_display::TimeDisplay;
+use cwnote::{
+    account, annotate, appconfig_sync, aws_client, backup, canary, change_calendar, consistency,
+    current, dashboard_cache, dedupe, duplicate_filter, duration, exclude, horizontal, interactive,
+    interrupt, list, logs_sync, maintenance, manifest, remove, report, run_id, run_result, schema,
+    selector, selftest, services, sfn_sync, stats, synthetics_sync, template, value_source,
+    value_transform,
+};
+use env_logger::WriteStyle;
+use log::{info, warn};
+use std::io::Write;
+use std::process::ExitCode;
 
-const DEFAULT_LOG_LEVEL: &str = "info";
 #[tokio::main]
-async fn main() -> Result<()> {
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(DEFAULT_LOG_LEVEL))
-        .init();
+async fn main() -> ExitCode {
+    let run_id = run_id::generate();
 
     let args = Cli::parse();
+    let interactive = interactive::is_interactive(args.non_interactive);
 
-    let client = aws_client::make_client(args.region.as_deref()).await?;
+    // Every log line carries the run id so output can be correlated with
+    // the annotations this run creates.
+    let log_run_id = run_id.clone();
+    let log_format = args.log_format;
+    env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or(logging::filter(args.verbose)),
+    )
+    .write_style(if interactive && log_format == LogFormat::Text {
+        WriteStyle::Auto
+    } else {
+        WriteStyle::Never
+    })
+    .format(move |buf, record| match log_format {
+        LogFormat::Text => writeln!(
+            buf,
+            "[{} {} {} run={}] {}",
+            buf.timestamp(),
+            record.level(),
+            record.target(),
+            log_run_id,
+            record.args()
+        ),
+        LogFormat::Json => writeln!(
+            buf,
+            "{}",
+            logging::json_line(
+                &buf.timestamp().to_string(),
+                record.level().as_str(),
+                record.target(),
+                &log_run_id,
+                &record.args().to_string(),
+            )
+        ),
+    })
+    .init();
 
-    run_with_client(&client, args).await
+    interrupt::install();
+    let (result_format, detailed_exitcode) = (args.result_format, args.detailed_exitcode);
+    let result = run(args, &run_id).await;
+    if let Some(format) = result_format {
+        let printed = match result {
+            Ok(ref result) => result.render(format, &run_id),
+            Err(ref err) => RunResult::single("run", ResultKind::Failed, format!("{err:#}"))
+                .render(format, &run_id),
+        };
+        println!("{printed}");
+    }
+
+    match finish(result, &run_id, detailed_exitcode) {
+        Ok(code) => code,
+        Err(err) => {
+            // Same as returning the error from main, but with our exit code.
+            eprintln!("Error: {err:?}");
+            ExitCode::from(run_result::exit_code_of(&err))
+        }
+    }
+}
+
+/// Turn the result of the run into the exit code, or the error to report.
+fn finish(result: Result<RunResult>, run_id: &str, detailed_exitcode: bool) -> Result<ExitCode> {
+    let result = result?;
+    if interrupt::requested() {
+        return Err(anyhow!(
+            "interrupted by Ctrl-C, only part of the run was applied \
+             (undo it with `cwnote remove --run-id {run_id}`)"
+        ));
+    }
+    let failed = result.failed();
+    if failed > 0 {
+        return Err(run_result::TargetsFailed {
+            failed,
+            total: result.targets.len(),
+            run_id: run_id.to_string(),
+            exit_code: result.failure_exit_code.unwrap_or(run_result::EXIT_FAILED),
+        }
+        .into());
+    }
+    Ok(ExitCode::from(result.exit_code(detailed_exitcode)))
+}
+
+/// Run the subcommand once, or in every account/region of a fan-out.
+async fn run(args: Cli, run_id: &str) -> Result<RunResult> {
+    if matches!(args.command, Commands::Annotate(ref opts) if opts.input_file.is_some()) {
+        if args.all_regions || !args.regions.is_empty() || !args.role_arn.is_empty() {
+            return Err(anyhow!(
+                "--input-file annotates one local file, it can't run in several regions or accounts"
+            ));
+        }
+        if let Commands::Annotate(ref opts) = args.command {
+            if opts.output_file.is_none()
+                && (opts.output == OutputFormat::Json || args.result_format.is_some())
+            {
+                return Err(anyhow!(
+                    "--input-file prints the annotated body to stdout, \
+                     add --output-file to use --output json or --result-format"
+                ));
+            }
+        }
+        return run_with_client(&aws_client::offline_client(), None, args, run_id).await;
+    }
+
+    let regions = if args.all_regions {
+        let regions =
+            aws_client::discover_regions(args.profile.as_deref(), args.endpoint_url.as_deref())
+                .await?;
+        if regions.is_empty() {
+            return Err(anyhow!(
+                "--all-regions: no region accepts these credentials"
+            ));
+        }
+        regions
+    } else {
+        args.regions.clone()
+    };
+    if regions.is_empty() && args.role_arn.is_empty() {
+        let client = aws_client::make_client(
+            args.region.as_deref(),
+            args.profile.as_deref(),
+            args.endpoint_url.as_deref(),
+        )
+        .await?;
+        run_with_client(&client, None, args, run_id).await
+    } else {
+        let targets = RunTarget::all(&args, &regions, run_id);
+        run_in_targets(args, &targets, run_id).await
+    }
+}
+
+/// Apply `--dedupe-window` (if given) to the specs of a sync.
+fn dedupe(
+    specs: Vec<annotate::AnnotationSpec>,
+    window: Option<chrono::Duration>,
+) -> Vec<annotate::AnnotationSpec> {
+    let Some(window) = window else {
+        return specs;
+    };
+    let events = specs.len();
+    let specs = dedupe::dedupe_specs(specs, window);
+    if specs.len() < events {
+        info!(
+            "Deduplicated {events} event(s) into {} annotation(s) ({}s buckets)",
+            specs.len(),
+            window.num_seconds()
+        );
+    }
+    specs
+}
+
+/// The dashboards an event sync (`logs-sync`, `sfn-sync`, ..., `canary`)
+/// writes to, and how.
+struct SyncTarget<'a> {
+    dashboard: Option<&'a str>,
+    suffix: Option<&'a str>,
+    selector: selector::WidgetSelector,
+    options: annotate::AnnotateOptions,
+}
+
+impl<'a> SyncTarget<'a> {
+    async fn new(
+        dashboard: Option<&'a str>,
+        suffix: Option<&'a str>,
+        sync: &SyncArgs,
+        time_display: TimeDisplay,
+        region: Option<&str>,
+        profile: Option<&str>,
+    ) -> Result<Self> {
+        Ok(Self {
+            dashboard,
+            suffix,
+            selector: sync.selector.widget_selector(),
+            options: annotate::AnnotateOptions {
+                dry_run: sync.dry_run,
+                annotation_budget: sync.annotation_budget,
+                body_size_warning: sync.body_size_warning,
+                time_display,
+                iac_guard: sync.iac.guard(),
+                provenance: sync.provenance.provenance(),
+                retry: sync.retry.policy(),
+                concurrency: sync.concurrency as usize,
+                verify: sync.verify,
+                backup_dir: sync.backup_dir.clone(),
+                backup_s3: s3_backup(sync.backup_s3.as_ref(), region, profile).await?,
+                ..Default::default()
+            },
+        })
+    }
+}
+
+/// Write the `specs` an event sync fetched to `target`, collapsed into
+/// `dedupe_window` buckets first. Without any, the target is left unchanged
+/// and `nothing` says why.
+async fn sync_annotate(
+    client: &aws_sdk_cloudwatch::Client,
+    target: &SyncTarget<'_>,
+    specs: Vec<annotate::AnnotationSpec>,
+    dedupe_window: Option<chrono::Duration>,
+    nothing: &str,
+) -> Result<RunResult> {
+    let specs = dedupe(specs, dedupe_window);
+    if specs.is_empty() {
+        info!("Nothing to annotate: {nothing}");
+        return Ok(RunResult::single(
+            target_name(target.dashboard, target.suffix),
+            ResultKind::Unchanged,
+            nothing,
+        ));
+    }
+    info!("Annotating {} event(s)", specs.len());
+
+    let outcomes = annotate::annotate_target(
+        client,
+        target.dashboard,
+        target.suffix,
+        &specs,
+        &target.options,
+        &target.selector,
+    )
+    .await?;
+    Ok(RunResult::from_outcomes(&outcomes))
+}
+
+/// The `--backup-s3` upload target. One bucket holds the backups of every
+/// account, so it is written with the caller's own credentials, not the role's.
+async fn s3_backup(
+    location: Option<&backup::S3Location>,
+    region: Option<&str>,
+    profile: Option<&str>,
+) -> Result<Option<backup::S3Backup>> {
+    Ok(match location {
+        Some(location) => Some(backup::S3Backup {
+            client: aws_client::make_s3_client(region, profile, None).await?,
+            location: location.clone(),
+        }),
+        None => None,
+    })
+}
+
+/// One pass of a fanned-out run: a region (`--regions`/`--all-regions`)
+/// and/or an account (`--role-arn`).
+struct RunTarget {
+    region: Option<String>,
+    profile: Option<String>,
+    endpoint_url: Option<String>,
+    role: Option<aws_client::AssumeRole>,
+}
+
+impl RunTarget {
+    /// Every account in every region. Without regions, each account uses
+    /// the default region; without roles, the default credentials are used.
+    fn all(args: &Cli, regions: &[String], run_id: &str) -> Vec<Self> {
+        let regions: Vec<Option<String>> = if regions.is_empty() {
+            vec![args.region.clone()]
+        } else {
+            regions.iter().cloned().map(Some).collect()
+        };
+        let roles: Vec<Option<aws_client::AssumeRole>> = if args.role_arn.is_empty() {
+            vec![None]
+        } else {
+            args.role_arn
+                .iter()
+                .map(|role_arn| {
+                    Some(aws_client::AssumeRole {
+                        role_arn: role_arn.clone(),
+                        external_id: args.external_id.clone(),
+                        session_name: format!("cwnote-{run_id}"),
+                    })
+                })
+                .collect()
+        };
+
+        roles
+            .iter()
+            .flat_map(|role| {
+                regions.iter().map(|region| Self {
+                    region: region.clone(),
+                    profile: args.profile.clone(),
+                    endpoint_url: args.endpoint_url.clone(),
+                    role: role.clone(),
+                })
+            })
+            .collect()
+    }
+
+    async fn client(&self) -> Result<aws_sdk_cloudwatch::Client> {
+        let region = self.region.as_deref();
+        let profile = self.profile.as_deref();
+        let endpoint_url = self.endpoint_url.as_deref();
+        match self.role {
+            Some(ref role) => aws_client::make_client_as(region, profile, endpoint_url, role).await,
+            None => aws_client::make_client(region, profile, endpoint_url).await,
+        }
+    }
+}
+
+impl std::fmt::Display for RunTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(ref role) = self.role {
+            match role.account_id() {
+                Some(account) => write!(f, "account {account}")?,
+                None => write!(f, "role {}", role.role_arn)?,
+            }
+            if self.region.is_some() {
+                f.write_str(" in ")?;
+            }
+        }
+        f.write_str(self.region.as_deref().unwrap_or(""))
+    }
+}
+
+/// Run the command once per target, going on after a target fails (unless
+/// `--fail-fast`), then log how each target fared.
+async fn run_in_targets(args: Cli, targets: &[RunTarget], run_id: &str) -> Result<RunResult> {
+    if matches!(args.command, Commands::Annotate(ref opts) if opts.dashboards_stdin)
+        && targets.len() > 1
+    {
+        return Err(anyhow!(
+            "--dashboards-stdin can only be read once, use --dashboards-file with several regions or accounts"
+        ));
+    }
+
+    let mut results = Vec::with_capacity(targets.len());
+    for target in targets {
+        if interrupt::requested() {
+            break;
+        }
+        info!("Running in {target}");
+        let mut target_args = args.clone();
+        target_args.region = target.region.clone().or(target_args.region);
+        let result = match target.client().await {
+            Ok(client) => run_with_client(&client, target.role.as_ref(), target_args, run_id).await,
+            Err(err) => Err(err),
+        };
+        // A target fails as a whole (e.g. bad credentials) or on some dashboards.
+        let failed = result.as_ref().map_or(true, |r| r.failed() > 0);
+        if let Err(ref err) = result {
+            warn!("{target}: {err:#}");
+        }
+        results.push((target, result));
+        if failed && args.fail_fast {
+            warn!("Stopping at the first failure (--fail-fast)");
+            break;
+        }
+    }
+
+    info!("Summary:");
+    let mut merged = RunResult::default();
+    for (target, result) in results {
+        match result {
+            Ok(result) => {
+                match result.failed() {
+                    0 => info!("  - {target}: ok"),
+                    failed => info!(
+                        "  - {target}: {failed} of {} target(s) FAILED",
+                        result.targets.len()
+                    ),
+                }
+                merged
+                    .targets
+                    .extend(result.targets.into_iter().map(|t| TargetResult {
+                        target: format!("{target}: {}", t.target),
+                        ..t
+                    }));
+            }
+            Err(err) => {
+                info!("  - {target}: FAILED: {err:#}");
+                merged
+                    .failure_exit_code
+                    .get_or_insert(run_result::exit_code_of(&err));
+                merged.targets.push(TargetResult::new(
+                    target.to_string(),
+                    ResultKind::Failed,
+                    format!("{err:#}"),
+                ));
+            }
+        }
+    }
+    Ok(merged)
+}
+
+/// Explicit list of dashboards to annotate: from `--dashboards-file`,
+/// `--dashboards-stdin` or several `--dashboard` values. `None` for a single
+/// dashboard or a pattern target.
+fn dashboard_list(opts: &AnnotateOpts) -> Result<Option<Vec<String>>> {
+    let (text, source) = if let Some(ref path) = opts.dashboards_file {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        (text, path.display().to_string())
+    } else if opts.dashboards_stdin {
+        let text = std::io::read_to_string(std::io::stdin()).context("failed to read stdin")?;
+        (text, "stdin".to_string())
+    } else if opts.dashboard.len() > 1 {
+        return Ok(Some(opts.dashboard.clone()));
+    } else {
+        return Ok(None);
+    };
+
+    let dashboards = annotate::parse_dashboard_names(&text);
+    if dashboards.is_empty() {
+        return Err(anyhow!("no dashboard names in {source}"));
+    }
+    info!("{} dashboard(s) listed in {source}", dashboards.len());
+    Ok(Some(dashboards))
+}
+
+/// Annotate each service of a services file with its own dashboards, widgets
+/// and label, then log a combined summary. A service that fails as a whole
+/// (e.g. its dashboards can't be listed) doesn't stop the others.
+async fn annotate_services(
+    client: &aws_sdk_cloudwatch::Client,
+    services: &[services::Service],
+    spec: &annotate::AnnotationSpec,
+    vars: &template::TemplateVars,
+    options: &annotate::AnnotateOptions,
+    selector: &selector::WidgetSelector,
+) -> Result<Vec<annotate::DashboardOutcome>> {
+    let mut outcomes = Vec::new();
+    let mut results = Vec::with_capacity(services.len());
+    for service in services {
+        if interrupt::requested() {
+            break;
+        }
+        info!("Annotating service {}", service.name);
+        let spec = annotate::AnnotationSpec {
+            label: match service.label {
+                Some(ref label) => template::render(label, vars),
+                None => spec.label.clone(),
+            },
+            ..spec.clone()
+        };
+        let specs = std::slice::from_ref(&spec);
+        // Selector fields of the service win over the CLI flags.
+        let selector = selector::WidgetSelector {
+            title_contains: service
+                .selector
+                .title_contains
+                .clone()
+                .or_else(|| selector.title_contains.clone()),
+            row_of: service
+                .selector
+                .row_of
+                .clone()
+                .or_else(|| selector.row_of.clone()),
+            metric_namespace: service
+                .selector
+                .metric_namespace
+                .clone()
+                .or_else(|| selector.metric_namespace.clone()),
+            metric_name: service
+                .selector
+                .metric_name
+                .clone()
+                .or_else(|| selector.metric_name.clone()),
+            section: service
+                .selector
+                .section
+                .clone()
+                .or_else(|| selector.section.clone()),
+            ..selector.clone()
+        };
+        let result = match service.dashboards {
+            services::ServiceDashboards::Names(ref names) => {
+                annotate::annotate_dashboards_by_name(client, names, specs, options, &selector)
+                    .await
+            }
+            services::ServiceDashboards::Suffix(ref suffix) => {
+                annotate::annotate_target(client, None, Some(suffix), specs, options, &selector)
+                    .await
+            }
+        };
+        match result {
+            Ok(service_outcomes) => {
+                let failed = annotate::failed_count(&service_outcomes);
+                results.push((service, Ok((service_outcomes.len(), failed))));
+                outcomes.extend(service_outcomes);
+            }
+            Err(err) => {
+                warn!("Service {}: {err:#}", service.name);
+                results.push((service, Err(err)));
+            }
+        }
+    }
+
+    info!("Summary:");
+    for (service, result) in &results {
+        match result {
+            Ok((total, 0)) => info!("  - {}: {total} dashboard(s) ok", service.name),
+            Ok((total, failed)) => info!(
+                "  - {}: {failed} of {total} dashboard(s) failed",
+                service.name
+            ),
+            Err(err) => info!("  - {}: FAILED: {err:#}", service.name),
+        }
+    }
+    let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+    if failed > 0 {
+        return Err(anyhow!(
+            "{failed} of {} service(s) failed in run {}",
+            services.len(),
+            spec.run_id
+        ));
+    }
+    Ok(outcomes)
 }
 
 // Extracted so we can unit test decision logic without going through Clap/#[tokio::main].
-async fn run_with_client(client: &aws_sdk_cloudwatch::Client, args: Cli) -> Result<()> {
-    match args.command {
+///
+/// `role` is the `--role-arn` role the run acts as: every other AWS client
+/// (event sources, SSM, ...) assumes it too, like `client` does.
+async fn run_with_client(
+    client: &aws_sdk_cloudwatch::Client,
+    role: Option<&aws_client::AssumeRole>,
+    args: Cli,
+    run_id: &str,
+) -> Result<RunResult> {
+    let result = match args.command {
         Commands::Annotate(opts) => {
-            let time_override = opts.time.as_deref();
+            info!("Starting run {run_id} (use `cwnote remove --run-id {run_id}` to undo)");
+
+            let value = match (opts.value.clone(), opts.value_from.as_deref()) {
+                (Some(value), None) => value,
+                (None, Some(source)) => {
+                    value_source::ValueSource::parse(source)?
+                        .resolve(args.region.as_deref(), args.profile.as_deref(), role)
+                        .await?
+                }
+                _ => return Err(anyhow!("Please specify either --value OR --value-from")),
+            };
+            let transforms = opts
+                .value_transform
+                .iter()
+                .map(|t| value_transform::ValueTransform::parse(t))
+                .collect::<Result<Vec<_>>>()?;
+            let value = value_transform::apply_all(&value, &transforms);
+
+            // Templates need the account alias up front; otherwise it is looked
+            // up for the report once the run is done. Either way it is the alias
+            // of the account this target runs in, once per target.
+            let mut vars = template::TemplateVars::new();
+            let mut account_alias = None;
+            if template::uses(&opts.label, template::VAR_ACCOUNT_ALIAS)
+                || template::uses(&value, template::VAR_ACCOUNT_ALIAS)
+            {
+                let alias = account::resolve_account_alias(
+                    args.region.as_deref(),
+                    args.profile.as_deref(),
+                    role,
+                )
+                .await?;
+                vars.insert(template::VAR_ACCOUNT_ALIAS, alias.clone());
+                account_alias = Some(alias);
+            }
+
+            let now = chrono::Utc::now();
+            let mut time = opts
+                .time
+                .as_deref()
+                .map(|t| {
+                    annotate::clamp_time(
+                        t,
+                        now,
+                        &args.timezone,
+                        opts.clamp_to_now,
+                        opts.allow_future,
+                    )
+                })
+                .transpose()?;
+            let duration = opts
+                .duration
+                .as_deref()
+                .map(duration::parse_duration)
+                .transpose()?;
+            let end_time = if opts.end_time.is_some() || duration.is_some() {
+                // A band needs a fixed start to measure from.
+                let start = time.get_or_insert_with(|| now.to_rfc3339());
+                let end_time = opts
+                    .end_time
+                    .as_deref()
+                    .map(|t| {
+                        duration::parse_time_expr(t, now, &args.timezone)
+                            .context("invalid --end-time")
+                    })
+                    .transpose()?
+                    .map(|t| t.to_rfc3339());
+                annotate::band_end(start, end_time.as_deref(), duration)?
+            } else {
+                None
+            };
+
+            let spec = annotate::AnnotationSpec {
+                label: template::render(&opts.label, &vars),
+                value: template::render(&value, &vars),
+                time,
+                end_time,
+                run_id: run_id.to_string(),
+            };
+
+            if let Some(ref start) = opts.set_start {
+                annotate::validate_start(start)?;
+            }
+            let options = annotate::AnnotateOptions {
+                dry_run: opts.dry_run.is_some(),
+                dry_run_level: opts.dry_run.unwrap_or_default(),
+                set_start: opts.set_start.clone(),
+                annotation_budget: opts.annotation_budget,
+                body_size_warning: opts.body_size_warning,
+                auto_prune: opts.auto_prune,
+                fail_on_empty_dashboard: opts.fail_on_empty_dashboard,
+                preview: opts.preview,
+                expect_change: opts.expect_change,
+                tag_title: opts.tag_title.clone(),
+                time_display: args.time_display,
+                routes: match opts.manifest {
+                    Some(ref path) => manifest::load_routes(path)?,
+                    None => Vec::new(),
+                },
+                iac_guard: opts.iac.guard(),
+                provenance: opts.provenance.provenance(),
+                color: opts.color.clone(),
+                fill: opts.fill,
+                exclusions: exclude::DashboardExclusions {
+                    prefixes: opts.exclude_prefix.clone(),
+                    regexes: opts.exclude_regex.clone(),
+                },
+                retry: opts.retry.policy(),
+                concurrency: opts.concurrency as usize,
+                progress: !opts.no_progress,
+                confirm: opts.dry_run.is_none()
+                    && !opts.yes
+                    && interactive::is_interactive(args.non_interactive),
+                skip_if_exists: opts.skip_if_exists.then_some(annotate::SkipIfExists {
+                    tolerance: opts.skip_if_exists_within,
+                }),
+                keep_last: opts.keep_last.map(|n| n as usize),
+                sort: opts.sort,
+                verify: opts.verify,
+                backup_dir: opts.backup_dir.clone(),
+                backup_s3: s3_backup(
+                    opts.backup_s3.as_ref(),
+                    args.region.as_deref(),
+                    args.profile.as_deref(),
+                )
+                .await?,
+            };
 
             // Build widget selector from CLI flags.
-            let selector = annotate::WidgetSelector {
-                title_contains: opts.widget_title_contains.clone(),
+            let selector = opts.selector.widget_selector();
+
+            let deployment = match opts.github_deployment {
+                Some(ref environment) if opts.dry_run.is_none() => {
+                    let description = format!("{}: {}", spec.label, spec.value);
+                    let deployment =
+                        GithubDeployment::create(environment, &description, run_id).await?;
+                    info!("Created GitHub deployment {}", deployment.id());
+                    Some(deployment)
+                }
+                Some(_) => {
+                    info!(target: "dry-run", "Skipping GitHub deployment in a dry run");
+                    None
+                }
+                None => None,
             };
 
-            match (opts.dashboard.as_deref(), opts.dashboard_suffix.as_deref()) {
-                (Some(dashboard), None) => {
-                    // Single dashboard.
-                    annotate::annotate_single_dashboard(
+            let specs = std::slice::from_ref(&spec);
+            let result = match (
+                &opts.input_file,
+                &opts.services_file,
+                &opts.dashboard_regex,
+                &opts.dashboard_glob,
+                dashboard_list(&opts)?,
+            ) {
+                (Some(input), _, _, _, _) => annotate::annotate_file(
+                    input,
+                    opts.output_file.as_deref(),
+                    specs,
+                    &options,
+                    &selector,
+                    args.region.as_deref().unwrap_or_default(),
+                )
+                .map(|outcome| vec![outcome]),
+                (None, Some(path), _, _, _) => {
+                    let services = services::select(services::load_services(path)?, &opts.only)?;
+                    annotate_services(client, &services, &spec, &vars, &options, &selector).await
+                }
+                (None, None, Some(regex), _, _) => {
+                    annotate::annotate_dashboards_by_regex(
+                        client, regex, specs, &options, &selector,
+                    )
+                    .await
+                }
+                (None, None, None, Some(glob), _) => {
+                    annotate::annotate_dashboards_by_glob(client, glob, specs, &options, &selector)
+                        .await
+                }
+                (None, None, None, None, Some(dashboards)) => {
+                    annotate::annotate_dashboards_by_name(
                         client,
-                        dashboard,
-                        &opts.label,
-                        &opts.value,
-                        time_override,
-                        opts.dry_run,
+                        &dashboards,
+                        specs,
+                        &options,
                         &selector,
                     )
-                    .await?;
+                    .await
+                }
+                (None, None, None, None, None) => {
+                    annotate::annotate_target(
+                        client,
+                        opts.dashboard.first().map(String::as_str),
+                        opts.dashboard_suffix.as_deref(),
+                        specs,
+                        &options,
+                        &selector,
+                    )
+                    .await
+                }
+            };
+            let region = client.config().region().map(|r| r.to_string());
+
+            if let Some(ref deployment) = deployment {
+                let (success, dashboard_url, description) = match result {
+                    Ok(ref outcomes) => {
+                        let annotated: Vec<&str> = outcomes
+                            .iter()
+                            .filter(|o| o.status == annotate::OutcomeStatus::Annotated)
+                            .map(|o| o.dashboard.as_str())
+                            .collect();
+                        (
+                            annotate::failed_count(outcomes) == 0,
+                            region
+                                .as_deref()
+                                .and_then(|region| report::console_url_of_all(region, &annotated)),
+                            format!(
+                                "cwnote run {run_id} annotated {} dashboard(s): {}",
+                                annotated.len(),
+                                annotated.join(", ")
+                            ),
+                        )
+                    }
+                    Err(ref err) => (false, None, format!("cwnote run {run_id} failed: {err}")),
+                };
+                // GitHub rejects longer descriptions.
+                let description: String = description.chars().take(140).collect();
+                if let Err(err) = deployment
+                    .set_status(success, dashboard_url.as_deref(), &description)
+                    .await
+                {
+                    warn!(
+                        "Could not update GitHub deployment {}: {err}",
+                        deployment.id()
+                    );
+                }
+            }
+            let outcomes = result?;
+            // A local --input-file run talks to no AWS account. Without the
+            // IAM permissions the report just goes without the alias.
+            if account_alias.is_none() && opts.input_file.is_none() {
+                match account::resolve_account_alias(
+                    args.region.as_deref(),
+                    args.profile.as_deref(),
+                    role,
+                )
+                .await
+                {
+                    Ok(alias) => account_alias = Some(alias),
+                    Err(err) => warn!("Could not resolve the account alias: {err:#}"),
+                }
+            }
+            if opts.output == OutputFormat::Json {
+                println!(
+                    "{}",
+                    report::render_json(
+                        run_id,
+                        opts.dry_run.is_some(),
+                        account_alias.as_deref(),
+                        &outcomes
+                    )
+                );
+            }
+
+            if let Some(ref path) = opts.report_html {
+                report::write_html(
+                    path,
+                    run_id,
+                    opts.dry_run.is_some(),
+                    region.as_deref(),
+                    account_alias.as_deref(),
+                    &outcomes,
+                )?;
+                info!("Wrote report to {}", path.display());
+            }
+
+            if annotate::failed_count(&outcomes) == 0 {
+                let target = target_name(
+                    opts.dashboard.first().map(String::as_str),
+                    opts.dashboard_suffix.as_deref(),
+                );
+                if opts.fail_if_no_dashboards && outcomes.is_empty() {
+                    return Err(run_result::NothingMatched::Dashboards(target).into());
                 }
+                let matched_nothing = outcomes.iter().all(|o| {
+                    matches!(
+                        o.status,
+                        annotate::OutcomeStatus::NoWidgets
+                            | annotate::OutcomeStatus::NoMatchingWidgets
+                    )
+                });
+                if opts.fail_if_no_match && !outcomes.is_empty() && matched_nothing {
+                    return Err(run_result::NothingMatched::Widgets(target).into());
+                }
+                match account_alias {
+                    Some(alias) => info!("Run {run_id} finished for account {alias}"),
+                    None => info!("Run {run_id} finished"),
+                }
+            }
+            RunResult::from_outcomes(&outcomes)
+        }
+        Commands::AnnotateHorizontal(opts) => {
+            info!("Starting run {run_id} (use `cwnote remove --run-id {run_id}` to undo)");
+
+            let spec = horizontal::HorizontalSpec {
+                label: opts.label.clone(),
+                value: opts.value,
+                fill: opts.fill,
+                y_axis: opts.y_axis,
+                run_id: run_id.to_string(),
+                provenance: opts.provenance.provenance(),
+            };
+            let selector = opts.selector.widget_selector();
+            let iac_guard = opts.iac.guard();
+
+            let targets = match (opts.dashboard.as_deref(), opts.dashboard_suffix.as_deref()) {
+                (Some(dashboard), None) => vec![
+                    horizontal::annotate_dashboard(
+                        client,
+                        dashboard,
+                        &spec,
+                        &selector,
+                        &iac_guard,
+                        opts.dry_run,
+                    )
+                    .await?,
+                ],
                 (None, Some(suffix)) => {
-                    // All dashboards matching suffix.
-                    annotate::annotate_dashboards_by_suffix(
+                    horizontal::annotate_dashboards_by_suffix(
                         client,
                         suffix,
-                        &opts.label,
-                        &opts.value,
-                        time_override,
+                        &spec,
+                        &selector,
+                        &iac_guard,
+                        opts.dry_run,
+                    )
+                    .await?
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "Please specify either --dashboard OR --dashboard-suffix"
+                    ));
+                }
+            };
+            RunResult {
+                targets,
+                failure_exit_code: None,
+            }
+        }
+        Commands::Remove(opts) => {
+            let filter = remove::RemoveFilter {
+                run_id: opts.run_id.clone(),
+                label: opts.label.clone(),
+                untag_title: opts.untag_title,
+                direction: opts.direction,
+                provenance: opts.provenance.filter(chrono::Utc::now()),
+                older_than: None,
+                duplicates: None,
+            };
+            let selector = opts.selector.widget_selector();
+
+            let removed = match (opts.dashboard.as_deref(), opts.dashboard_suffix.as_deref()) {
+                (Some(dashboard), None) => {
+                    remove::remove_from_dashboard(
+                        client,
+                        dashboard,
+                        &filter,
+                        &selector,
                         opts.dry_run,
+                    )
+                    .await?
+                }
+                (None, suffix) => {
+                    // No target means every dashboard the run could have touched.
+                    remove::remove_from_dashboards_by_suffix(
+                        client,
+                        suffix.unwrap_or(""),
+                        &filter,
                         &selector,
+                        opts.dry_run,
                     )
-                    .await?;
+                    .await?
                 }
                 (Some(_), Some(_)) => {
                     return Err(anyhow!(
                         "Please specify either --dashboard OR --dashboard-suffix, not both"
                     ));
                 }
-                (None, None) => {
-                    return Err(anyhow!(
-                        "Either --dashboard or --dashboard-suffix is required"
-                    ));
+            };
+
+            // Change reviews need to see exactly what a removal deletes.
+            if opts.dry_run {
+                println!("{}", list::render(&removed, opts.output, args.time_display));
+            }
+            removal_result(
+                &removed,
+                opts.dashboard.as_deref(),
+                opts.dashboard_suffix.as_deref(),
+            )
+        }
+        Commands::Prune(opts) => {
+            let filter = remove::RemoveFilter {
+                label: opts.label.clone(),
+                direction: Some(remove::Direction::Vertical),
+                older_than: Some(chrono::Utc::now() - opts.older_than),
+                ..Default::default()
+            };
+            let selector = opts.selector.widget_selector();
+
+            let removed = match (
+                opts.dashboard.as_deref(),
+                opts.dashboard_prefix.as_deref(),
+                opts.dashboard_suffix.as_deref(),
+            ) {
+                (Some(dashboard), _, _) => {
+                    remove::remove_from_dashboard(
+                        client,
+                        dashboard,
+                        &filter,
+                        &selector,
+                        opts.dry_run,
+                    )
+                    .await?
+                }
+                (None, Some(prefix), _) => {
+                    remove::remove_from_dashboards_by_prefix(
+                        client,
+                        prefix,
+                        &filter,
+                        &selector,
+                        opts.dry_run,
+                    )
+                    .await?
+                }
+                (None, None, suffix) => {
+                    remove::remove_from_dashboards_by_suffix(
+                        client,
+                        suffix.unwrap_or(""),
+                        &filter,
+                        &selector,
+                        opts.dry_run,
+                    )
+                    .await?
+                }
+            };
+
+            if opts.dry_run {
+                println!("{}", list::render(&removed, opts.output, args.time_display));
+            }
+            let prefix_target = opts.dashboard_prefix.as_ref().map(|p| format!("{p}*"));
+            removal_result(
+                &removed,
+                opts.dashboard.as_deref().or(prefix_target.as_deref()),
+                opts.dashboard_suffix.as_deref(),
+            )
+        }
+        Commands::Dedup(opts) => {
+            let filter = remove::RemoveFilter {
+                direction: Some(remove::Direction::Vertical),
+                duplicates: Some(duplicate_filter::DuplicateFilter {
+                    label: opts.label.clone(),
+                    window: opts.window,
+                }),
+                ..Default::default()
+            };
+            let selector = opts.selector.widget_selector();
+
+            let removed = match opts.dashboard.as_deref() {
+                Some(dashboard) => {
+                    remove::remove_from_dashboard(
+                        client,
+                        dashboard,
+                        &filter,
+                        &selector,
+                        opts.dry_run,
+                    )
+                    .await?
+                }
+                None => {
+                    remove::remove_from_dashboards_by_suffix(
+                        client,
+                        opts.dashboard_suffix.as_deref().unwrap_or(""),
+                        &filter,
+                        &selector,
+                        opts.dry_run,
+                    )
+                    .await?
                 }
+            };
+
+            if opts.dry_run {
+                println!("{}", list::render(&removed, opts.output, args.time_display));
             }
+            removal_result(
+                &removed,
+                opts.dashboard.as_deref(),
+                opts.dashboard_suffix.as_deref(),
+            )
+        }
+        Commands::List(opts) => {
+            let now = chrono::Utc::now();
+            let filter = list::ListFilter {
+                label: opts.label.clone(),
+                direction: opts.direction,
+                provenance: opts.provenance.filter(now),
+                ..list::ListFilter::default()
+            };
+            let filter = opts.time_range.restrict(filter, now, &args.timezone)?;
+            let selector = opts.selector.widget_selector();
+
+            let listed = match opts.dashboard.as_deref() {
+                Some(dashboard) => {
+                    list::list_dashboard(client, dashboard, &filter, &selector).await?
+                }
+                None => {
+                    list::list_dashboards_by_suffix(
+                        client,
+                        opts.dashboard_suffix.as_deref().unwrap_or(""),
+                        &filter,
+                        &selector,
+                    )
+                    .await?
+                }
+            };
+
+            println!("{}", list::render(&listed, opts.output, args.time_display));
+            info!("Listed {} annotation(s)", listed.len());
+            RunResult::single(
+                target_name(opts.dashboard.as_deref(), opts.dashboard_suffix.as_deref()),
+                ResultKind::Unchanged,
+                format!("listed {} annotation(s)", listed.len()),
+            )
         }
+        Commands::Stats(opts) => {
+            let filter = list::ListFilter {
+                direction: Some(remove::Direction::Vertical),
+                ..list::ListFilter::default()
+            };
+            let filter = opts
+                .time_range
+                .restrict(filter, chrono::Utc::now(), &args.timezone)?;
+            let selector = opts.selector.widget_selector();
+            let listed = match opts.dashboard.as_deref() {
+                Some(dashboard) => {
+                    list::list_dashboard(client, dashboard, &filter, &selector).await?
+                }
+                None => {
+                    list::list_dashboards_by_suffix(
+                        client,
+                        opts.dashboard_suffix.as_deref().unwrap_or(""),
+                        &filter,
+                        &selector,
+                    )
+                    .await?
+                }
+            };
+
+            let stats = stats::widget_stats(&listed, opts.annotation_budget);
+            println!("{}", stats::render(&stats, opts.output));
+            let crowded: Vec<&stats::WidgetStats> = stats.iter().filter(|s| s.crowded).collect();
+            for s in &crowded {
+                warn!(
+                    "{}: widget '{}' has {} vertical annotations (budget {}); \
+                     consider pruning old runs with `cwnote prune --older-than 30d`",
+                    s.dashboard, s.widget, s.annotations, opts.annotation_budget
+                );
+            }
+            RunResult::single(
+                target_name(opts.dashboard.as_deref(), opts.dashboard_suffix.as_deref()),
+                ResultKind::Unchanged,
+                format!(
+                    "{} widget(s) with annotations, {} crowded",
+                    stats.len(),
+                    crowded.len()
+                ),
+            )
+        }
+        Commands::Current(opts) => {
+            let filter = list::ListFilter {
+                label: opts.label.clone(),
+                direction: Some(remove::Direction::Vertical),
+                ..list::ListFilter::default()
+            };
+            let filter = opts
+                .time_range
+                .restrict(filter, chrono::Utc::now(), &args.timezone)?;
+            let selector = opts.selector.widget_selector();
+            let listed = list::list_dashboard(client, &opts.dashboard, &filter, &selector).await?;
+            let current = current::current_values(&listed);
+            if let (Some(label), true) = (&opts.label, current.is_empty()) {
+                return Err(anyhow!(
+                    "dashboard '{}' has no '{label}' annotation",
+                    opts.dashboard
+                ));
+            }
+
+            println!(
+                "{}",
+                current::render(&current, opts.output, args.time_display)
+            );
+            RunResult::single(
+                opts.dashboard.as_str(),
+                ResultKind::Unchanged,
+                format!("{} current value(s)", current.len()),
+            )
+        }
+        Commands::Check(opts) => {
+            if opts.members.len() < 2 {
+                return Err(anyhow!("check needs at least two --member dashboards"));
+            }
+            let filter = list::ListFilter {
+                label: opts.label.clone(),
+                direction: Some(remove::Direction::Vertical),
+                ..list::ListFilter::default()
+            };
+            let filter = opts
+                .time_range
+                .restrict(filter, chrono::Utc::now(), &args.timezone)?;
+            let selector = opts.selector.widget_selector();
+
+            let mut members = Vec::with_capacity(opts.members.len());
+            for member in &opts.members {
+                let region = member.region.as_deref().or(args.region.as_deref());
+                let profile = args.profile.as_deref();
+                let endpoint_url = args.endpoint_url.as_deref();
+                let member_client = match role {
+                    Some(role) => {
+                        aws_client::make_client_as(region, profile, endpoint_url, role).await?
+                    }
+                    None => aws_client::make_client(region, profile, endpoint_url).await?,
+                };
+                let listed =
+                    list::list_dashboard(&member_client, &member.dashboard, &filter, &selector)
+                        .await
+                        .with_context(|| format!("cannot read member {member}"))?;
+                let markers = consistency::markers(&listed);
+                info!("{member}: {} marker(s)", markers.len());
+                members.push((member.to_string(), markers));
+            }
+
+            let divergences = consistency::divergences(&members);
+            for divergence in &divergences {
+                println!("{divergence}");
+            }
+            if !divergences.is_empty() {
+                return Err(anyhow!(
+                    "{} marker(s) are not on all {} member(s)",
+                    divergences.len(),
+                    members.len()
+                ));
+            }
+            info!("All {} member(s) carry the same markers", members.len());
+            RunResult::single(
+                members
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                ResultKind::Unchanged,
+                "all members carry the same markers",
+            )
+        }
+        Commands::LogsSync(opts) => {
+            info!("Starting run {run_id} (use `cwnote remove --run-id {run_id}` to undo)");
+
+            let since = duration::parse_duration(&opts.since)?;
+            let map = logs_sync::FieldMap::from_pairs(&opts.map)?;
+            let target = SyncTarget::new(
+                opts.dashboard.as_deref(),
+                opts.dashboard_suffix.as_deref(),
+                &opts.sync,
+                args.time_display,
+                args.region.as_deref(),
+                args.profile.as_deref(),
+            )
+            .await?;
+
+            let logs_client =
+                aws_client::make_logs_client(args.region.as_deref(), args.profile.as_deref(), role)
+                    .await?;
+            let rows =
+                logs_sync::run_query(&logs_client, &opts.log_group, &opts.query, since).await?;
+            sync_annotate(
+                client,
+                &target,
+                logs_sync::rows_to_specs(&rows, &map, &opts.label, run_id),
+                opts.dedupe_window,
+                "query returned no usable rows",
+            )
+            .await?
+        }
+        Commands::AppconfigSync(opts) => {
+            info!("Starting run {run_id} (use `cwnote remove --run-id {run_id}` to undo)");
+
+            let since = duration::parse_duration(&opts.since)?;
+            let target = SyncTarget::new(
+                opts.dashboard.as_deref(),
+                opts.dashboard_suffix.as_deref(),
+                &opts.sync,
+                args.time_display,
+                args.region.as_deref(),
+                args.profile.as_deref(),
+            )
+            .await?;
+
+            let appconfig_client = aws_client::make_appconfig_client(
+                args.region.as_deref(),
+                args.profile.as_deref(),
+                role,
+            )
+            .await?;
+            let deployments = appconfig_sync::list_deployments(
+                &appconfig_client,
+                &opts.application,
+                &opts.environment,
+                since,
+            )
+            .await?;
+            sync_annotate(
+                client,
+                &target,
+                deployments
+                    .iter()
+                    .map(|d| d.to_spec(&opts.label, run_id))
+                    .collect(),
+                opts.dedupe_window,
+                "no AppConfig deployments",
+            )
+            .await?
+        }
+        Commands::SfnSync(opts) => {
+            info!("Starting run {run_id} (use `cwnote remove --run-id {run_id}` to undo)");
+
+            let since = duration::parse_duration(&opts.since)?;
+            let target = SyncTarget::new(
+                opts.dashboard.as_deref(),
+                opts.dashboard_suffix.as_deref(),
+                &opts.sync,
+                args.time_display,
+                args.region.as_deref(),
+                args.profile.as_deref(),
+            )
+            .await?;
+
+            let sfn_client =
+                aws_client::make_sfn_client(args.region.as_deref(), args.profile.as_deref(), role)
+                    .await?;
+            let executions = sfn_sync::list_executions(
+                &sfn_client,
+                &opts.state_machine_arn,
+                opts.execution_name_contains.as_deref(),
+                since,
+            )
+            .await?;
+            sync_annotate(
+                client,
+                &target,
+                executions
+                    .iter()
+                    .map(|e| e.to_spec(&opts.label, run_id))
+                    .collect(),
+                opts.dedupe_window,
+                "no matching executions",
+            )
+            .await?
+        }
+        Commands::SyntheticsSync(opts) => {
+            info!("Starting run {run_id} (use `cwnote remove --run-id {run_id}` to undo)");
+
+            let since = duration::parse_duration(&opts.since)?;
+            let target = SyncTarget::new(
+                opts.dashboard.as_deref(),
+                opts.dashboard_suffix.as_deref(),
+                &opts.sync,
+                args.time_display,
+                args.region.as_deref(),
+                args.profile.as_deref(),
+            )
+            .await?;
+
+            let synthetics_client = aws_client::make_synthetics_client(
+                args.region.as_deref(),
+                args.profile.as_deref(),
+                role,
+            )
+            .await?;
+            let runs = synthetics_sync::list_runs(
+                &synthetics_client,
+                &opts.canary_name,
+                opts.failures_only,
+                since,
+            )
+            .await?;
+            sync_annotate(
+                client,
+                &target,
+                runs.iter()
+                    .map(|r| r.to_spec(&opts.label, run_id))
+                    .collect(),
+                opts.dedupe_window,
+                "no matching canary runs",
+            )
+            .await?
+        }
+        Commands::ChangeCalendarSync(opts) => {
+            info!("Starting run {run_id} (use `cwnote remove --run-id {run_id}` to undo)");
+
+            let cutoff = chrono::Utc::now() - duration::parse_duration(&opts.since)?;
+            let target = SyncTarget::new(
+                opts.dashboard.as_deref(),
+                opts.dashboard_suffix.as_deref(),
+                &opts.sync,
+                args.time_display,
+                args.region.as_deref(),
+                args.profile.as_deref(),
+            )
+            .await?;
+
+            let ssm_client =
+                aws_client::make_ssm_client(args.region.as_deref(), args.profile.as_deref(), role)
+                    .await?;
+            let events = change_calendar::fetch_events(&ssm_client, &opts.calendar).await?;
+            sync_annotate(
+                client,
+                &target,
+                events
+                    .iter()
+                    .filter(|e| e.end >= cutoff)
+                    .map(|e| e.to_spec(&opts.label, run_id))
+                    .collect(),
+                opts.dedupe_window,
+                "no change calendar events in range",
+            )
+            .await?
+        }
+        Commands::Canary(opts) => {
+            info!("Starting run {run_id} (use `cwnote remove --run-id {run_id}` to undo)");
+
+            canary::validate_phases(&opts.phases)?;
+            let interval = duration::parse_duration(&opts.interval)?;
+            let target = SyncTarget::new(
+                opts.dashboard.as_deref(),
+                opts.dashboard_suffix.as_deref(),
+                &opts.sync,
+                args.time_display,
+                args.region.as_deref(),
+                args.profile.as_deref(),
+            )
+            .await?;
+
+            if opts.live {
+                let live = canary::LiveTarget {
+                    dashboard: target.dashboard,
+                    suffix: target.suffix,
+                    selector: &target.selector,
+                    options: &target.options,
+                };
+                let outcomes = canary::run_live(
+                    client,
+                    &live,
+                    &opts.phases,
+                    interval,
+                    &opts.label,
+                    &opts.value,
+                    run_id,
+                )
+                .await?;
+                RunResult::from_outcomes(&outcomes)
+            } else {
+                let start = opts.start.as_deref().unwrap_or_default();
+                let start = chrono::DateTime::parse_from_rfc3339(start)
+                    .with_context(|| format!("--start '{start}' is not RFC3339"))?
+                    .with_timezone(&chrono::Utc);
+                let specs = canary::planned_specs(
+                    &opts.phases,
+                    start,
+                    interval,
+                    &opts.label,
+                    &opts.value,
+                    run_id,
+                );
+                sync_annotate(client, &target, specs, None, "no canary phases").await?
+            }
+        }
+        Commands::Dashboards(DashboardsOpts {
+            command: DashboardsCommand::Complete(opts),
+        }) => {
+            let region = args
+                .region
+                .clone()
+                .or_else(|| client.config().region().map(|r| r.to_string()))
+                .unwrap_or_default();
+            let names = dashboard_cache::dashboard_names(client, &region, opts.refresh).await?;
+            let completions = dashboard_cache::complete(&names, &opts.partial);
+            for name in &completions {
+                println!("{name}");
+            }
+            RunResult::single(
+                opts.partial.as_str(),
+                ResultKind::Unchanged,
+                format!("{} completion(s)", completions.len()),
+            )
+        }
+        Commands::Selector(SelectorOpts {
+            command: SelectorCommand::Test(opts),
+        }) => {
+            // Offline: only reads the file.
+            let text = std::fs::read_to_string(&opts.file)
+                .with_context(|| format!("failed to read {}", opts.file.display()))?;
+            let json: serde_json::Value = serde_json::from_str(&text)
+                .with_context(|| format!("{} is not valid JSON", opts.file.display()))?;
+            let widgets = match json.get("widgets").and_then(|w| w.as_array()) {
+                Some(widgets) => widgets.clone(),
+                None => vec![json],
+            };
+
+            let explained = selector::explain(&widgets, &opts.selector.widget_selector());
+            for explanation in &explained {
+                println!("{explanation}");
+            }
+            let summary = format!(
+                "{} of {} widget(s) selected",
+                explained.iter().filter(|e| e.selected()).count(),
+                explained.len()
+            );
+            info!("{summary}");
+            RunResult::single(
+                opts.file.display().to_string(),
+                ResultKind::Unchanged,
+                summary,
+            )
+        }
+        Commands::Schema(opts) => {
+            print!("{}", schema::schema(opts.kind));
+            RunResult::single("schema", ResultKind::Unchanged, "printed the schema")
+        }
+        Commands::Selftest(opts) => {
+            info!("Running selftest on dashboard '{}'", opts.dashboard);
+            let report = selftest::run(client, &opts.dashboard, opts.create, run_id).await;
+            if !report.passed() {
+                return Err(anyhow!(
+                    "selftest failed on dashboard '{}' (see FAIL above)",
+                    opts.dashboard
+                ));
+            }
+            info!("Selftest passed: cwnote can annotate dashboards with these credentials");
+            // It wrote and removed an annotation (and maybe created the dashboard).
+            RunResult::single(
+                opts.dashboard.as_str(),
+                ResultKind::Changed,
+                "selftest passed",
+            )
+        }
+        Commands::Maintenance(opts) => {
+            // Deleting files, so never fall back to the current directory unasked.
+            let dir = opts
+                .dir
+                .clone()
+                .or_else(annotate::export_dir)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "no export directory: pass --dir (e.g. --dir . for the current directory) \
+                     or set {}",
+                        annotate::EXPORT_DIR_ENV
+                    )
+                })?;
+            let policy = maintenance::Policy {
+                keep: opts
+                    .keep
+                    .as_deref()
+                    .map(duration::parse_duration)
+                    .transpose()?,
+                max_bytes: opts.max_size_mb.map(|mb| mb * 1024 * 1024),
+                gzip: opts.gzip,
+            };
+
+            let exports = maintenance::list_exports(&dir)?;
+            let actions = maintenance::plan(&exports, &policy, chrono::Utc::now());
+            for action in &actions {
+                if opts.dry_run {
+                    info!(target: "dry-run", "would {action}");
+                } else {
+                    maintenance::apply(action)?;
+                }
+            }
+            info!(
+                "{}: {} export(s), {} deleted or compressed",
+                dir.display(),
+                exports.len(),
+                actions.len()
+            );
+            RunResult {
+                targets: actions
+                    .iter()
+                    .map(|action| {
+                        TargetResult::new(
+                            dir.display().to_string(),
+                            ResultKind::Changed,
+                            action.to_string(),
+                        )
+                    })
+                    .collect(),
+                failure_exit_code: None,
+            }
+        }
+    };
+    Ok(result)
+}
+
+/// What `--dashboard`/`--dashboard-suffix` targets, for results that aren't
+/// per dashboard.
+fn target_name(dashboard: Option<&str>, suffix: Option<&str>) -> String {
+    match (dashboard, suffix) {
+        (Some(dashboard), _) => dashboard.to_string(),
+        (None, Some(suffix)) => format!("*{suffix}"),
+        (None, None) => "all dashboards".to_string(),
     }
+}
 
-    Ok(())
+/// One changed result per dashboard annotations were removed from.
+fn removal_result(
+    removed: &[list::ListedAnnotation],
+    dashboard: Option<&str>,
+    suffix: Option<&str>,
+) -> RunResult {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for ann in removed {
+        match counts.iter_mut().find(|(name, _)| *name == ann.dashboard) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((&ann.dashboard, 1)),
+        }
+    }
+    if counts.is_empty() {
+        return RunResult::single(
+            target_name(dashboard, suffix),
+            ResultKind::Unchanged,
+            "no matching annotations",
+        );
+    }
+    RunResult {
+        targets: counts
+            .into_iter()
+            .map(|(name, count)| {
+                TargetResult::new(
+                    name,
+                    ResultKind::Changed,
+                    format!("removed {count} annotation(s)"),
+                )
+            })
+            .collect(),
+        failure_exit_code: None,
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::aws_client;
-    use crate::cli::{AnnotateOpts, Cli, Commands};
+    use crate::cli::{
+        AnnotateOpts, Cli, Commands, IacArgs, ProvenanceArgs, RetryArgs, SelectorArgs,
+    };
+    use cwnote::aws_client;
+    use cwnote::time_display::TimeDisplay;
+    use cwnote::timezone::Timezone;
 
     const TEST_REGION: &str = "eu-central-1";
     const TEST_DASHBOARD: &str = "DashA";
     const TEST_SUFFIX: &str = "suffixB";
     const TEST_LABEL: &str = "version";
     const TEST_VALUE: &str = "1.2.3";
+    const TEST_RUN_ID: &str = "20250120T120000Z-aaaaaaaa";
 
     // Helper: build a dummy client once for these tests.
     // It won't actually talk to AWS as long as we only hit the error paths
     // (we return before calling annotate::*).
     async fn make_dummy_client() -> aws_sdk_cloudwatch::Client {
-        aws_client::make_client(Some(TEST_REGION))
+        aws_client::make_client(Some(TEST_REGION), None, None)
             .await
             .expect("failed to create dummy client")
     }
 
+    #[test]
+    fn run_targets_cover_every_account_in_every_region() {
+        let args = Cli::try_parse_from([
+            "cwnote",
+            "--role-arn",
+            "arn:aws:iam::111111111111:role/cwnote,arn:aws:iam::222222222222:role/cwnote",
+            "list",
+        ])
+        .unwrap();
+        let regions = [TEST_REGION.to_string(), "us-east-1".to_string()];
+
+        let targets: Vec<String> = RunTarget::all(&args, &regions, TEST_RUN_ID)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            targets,
+            [
+                "account 111111111111 in eu-central-1",
+                "account 111111111111 in us-east-1",
+                "account 222222222222 in eu-central-1",
+                "account 222222222222 in us-east-1",
+            ]
+        );
+
+        let targets = RunTarget::all(&args, &[], TEST_RUN_ID);
+        assert_eq!(targets[1].to_string(), "account 222222222222");
+        assert_eq!(
+            targets[1].role.as_ref().unwrap().session_name,
+            "cwnote-20250120T120000Z-aaaaaaaa"
+        );
+    }
+
     #[tokio::test]
     async fn run_with_client_errors_when_both_dashboard_and_suffix_are_set() {
         let client = make_dummy_client().await;
 
         let opts = AnnotateOpts {
-            dashboard: Some(TEST_DASHBOARD.to_string()),
+            dashboard: vec![TEST_DASHBOARD.to_string()],
             dashboard_suffix: Some(TEST_SUFFIX.to_string()),
+            dashboard_regex: None,
+            dashboard_glob: None,
+            dashboards_file: None,
+            dashboards_stdin: false,
+            services_file: None,
+            input_file: None,
+            output_file: None,
+            only: Vec::new(),
+            exclude_prefix: Vec::new(),
+            exclude_regex: Vec::new(),
             label: TEST_LABEL.to_string(),
-            value: TEST_VALUE.to_string(),
+            value: Some(TEST_VALUE.to_string()),
+            value_from: None,
+            value_transform: Vec::new(),
             time: None,
-            dry_run: false,
-            widget_title_contains: None,
+            end_time: None,
+            duration: None,
+            clamp_to_now: true,
+            allow_future: false,
+            dry_run: None,
+            selector: SelectorArgs::default(),
+            iac: IacArgs::default(),
+            provenance: ProvenanceArgs::default(),
+            retry: RetryArgs::default(),
+            no_progress: false,
+            yes: false,
+            skip_if_exists: false,
+            skip_if_exists_within: None,
+            fail_if_no_dashboards: false,
+            fail_if_no_match: false,
+            concurrency: 1,
+            set_start: None,
+            annotation_budget: annotate::DEFAULT_ANNOTATION_BUDGET,
+            body_size_warning: cwnote::body_size::DEFAULT_WARN_BYTES,
+            auto_prune: false,
+            keep_last: None,
+            sort: false,
+            verify: false,
+            backup_dir: None,
+            backup_s3: None,
+            fail_on_empty_dashboard: false,
+            preview: false,
+            expect_change: false,
+            tag_title: None,
+            manifest: None,
+            color: None,
+            fill: None,
+            report_html: None,
+            output: OutputFormat::Text,
+            github_deployment: None,
         };
 
         let args = Cli {
             region: None,
-            command: Commands::Annotate(opts),
+            profile: None,
+            endpoint_url: None,
+            regions: Vec::new(),
+            all_regions: false,
+            role_arn: Vec::new(),
+            external_id: None,
+            fail_fast: false,
+            time_display: TimeDisplay::Utc,
+            timezone: Timezone::default(),
+            non_interactive: false,
+            verbose: 0,
+            log_format: LogFormat::Text,
+            result_format: None,
+            detailed_exitcode: false,
+            command: Commands::Annotate(Box::new(opts)),
         };
 
-        let result = run_with_client(&client, args).await;
+        let result = run_with_client(&client, None, args, TEST_RUN_ID).await;
 
         assert!(
             result.is_err(),
@@ -133,21 +1754,80 @@ mod tests {
         let client = make_dummy_client().await;
 
         let opts = AnnotateOpts {
-            dashboard: None,
+            dashboard: Vec::new(),
             dashboard_suffix: None,
+            dashboard_regex: None,
+            dashboard_glob: None,
+            dashboards_file: None,
+            dashboards_stdin: false,
+            services_file: None,
+            input_file: None,
+            output_file: None,
+            only: Vec::new(),
+            exclude_prefix: Vec::new(),
+            exclude_regex: Vec::new(),
             label: TEST_LABEL.to_string(),
-            value: TEST_VALUE.to_string(),
+            value: Some(TEST_VALUE.to_string()),
+            value_from: None,
+            value_transform: Vec::new(),
             time: None,
-            dry_run: false,
-            widget_title_contains: None,
+            end_time: None,
+            duration: None,
+            clamp_to_now: true,
+            allow_future: false,
+            dry_run: None,
+            selector: SelectorArgs::default(),
+            iac: IacArgs::default(),
+            provenance: ProvenanceArgs::default(),
+            retry: RetryArgs::default(),
+            no_progress: false,
+            yes: false,
+            skip_if_exists: false,
+            skip_if_exists_within: None,
+            fail_if_no_dashboards: false,
+            fail_if_no_match: false,
+            concurrency: 1,
+            set_start: None,
+            annotation_budget: annotate::DEFAULT_ANNOTATION_BUDGET,
+            body_size_warning: cwnote::body_size::DEFAULT_WARN_BYTES,
+            auto_prune: false,
+            keep_last: None,
+            sort: false,
+            verify: false,
+            backup_dir: None,
+            backup_s3: None,
+            fail_on_empty_dashboard: false,
+            preview: false,
+            expect_change: false,
+            tag_title: None,
+            manifest: None,
+            color: None,
+            fill: None,
+            report_html: None,
+            output: OutputFormat::Text,
+            github_deployment: None,
         };
 
         let args = Cli {
             region: None,
-            command: Commands::Annotate(opts),
+            profile: None,
+            endpoint_url: None,
+            regions: Vec::new(),
+            all_regions: false,
+            role_arn: Vec::new(),
+            external_id: None,
+            fail_fast: false,
+            time_display: TimeDisplay::Utc,
+            timezone: Timezone::default(),
+            non_interactive: false,
+            verbose: 0,
+            log_format: LogFormat::Text,
+            result_format: None,
+            detailed_exitcode: false,
+            command: Commands::Annotate(Box::new(opts)),
         };
 
-        let result = run_with_client(&client, args).await;
+        let result = run_with_client(&client, None, args, TEST_RUN_ID).await;
 
         assert!(
             result.is_err(),
@@ -0,0 +1,314 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::annotate::{self, TS_FORMAT};
+
+const EXT_JSON: &str = ".json";
+const EXT_GZIP: &str = ".json.gz";
+// Length of a `TS_FORMAT` timestamp, e.g. `2025-01-20-12-00-00`.
+const TS_LEN: usize = 19;
+// Recent exports stay plain JSON so they can still be diffed by hand.
+const COMPRESS_AFTER_DAYS: i64 = 1;
+
+/// What `cwnote maintenance` may do to the exported dashboards.
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    /// Delete exports older than this.
+    pub keep: Option<Duration>,
+    /// Delete the oldest exports until all of them fit in this many bytes.
+    pub max_bytes: Option<u64>,
+    /// Gzip exports older than a day.
+    pub gzip: bool,
+}
+
+/// One exported dashboard body, e.g. `2025-01-20-12-00-00-service-dashboard.json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Export {
+    pub path: PathBuf,
+    pub taken: DateTime<Utc>,
+    pub size: u64,
+    pub compressed: bool,
+}
+
+impl Export {
+    /// Reads the export time from the file name; files not named like
+    /// [`annotate::save_to_file`] names them yield `None`.
+    fn from_file_name(path: PathBuf, size: u64) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        let compressed = name.ends_with(EXT_GZIP);
+        if !compressed && !name.ends_with(EXT_JSON) {
+            return None;
+        }
+        let taken = NaiveDateTime::parse_from_str(name.get(..TS_LEN)?, TS_FORMAT)
+            .ok()?
+            .and_utc();
+        let export = Self {
+            path,
+            taken,
+            size,
+            compressed,
+        };
+        // Sanitized by `annotate::export_name`: lowercase ASCII letters, digits and `-`.
+        let dashboard = export.dashboard()?;
+        let is_export_name = !dashboard.is_empty()
+            && dashboard
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        is_export_name.then_some(export)
+    }
+
+    /// Dashboard name as written into the file name, e.g. `service-dashboard`.
+    pub fn dashboard(&self) -> Option<&str> {
+        let name = self.path.file_name()?.to_str()?;
+        let rest = name.get(TS_LEN..)?.strip_prefix('-')?;
+        let rest = rest
+            .strip_suffix(EXT_GZIP)
+            .or_else(|| rest.strip_suffix(EXT_JSON))?;
+        Some(
+            rest.strip_suffix(annotate::DRY_RUN_EXPORT_SUFFIX)
+                .unwrap_or(rest),
+        )
+    }
+
+    /// `true` for a body computed by `--dry-run=mutate-local` rather than
+    /// read from or written to CloudWatch.
+    pub fn is_dry_run(&self) -> bool {
+        self.path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| {
+                name.strip_suffix(EXT_GZIP)
+                    .or_else(|| name.strip_suffix(EXT_JSON))
+            })
+            .is_some_and(|stem| stem.ends_with(annotate::DRY_RUN_EXPORT_SUFFIX))
+    }
+
+    /// The exported dashboard body, unzipped if needed.
+    pub fn read(&self) -> Result<String> {
+        let file = File::open(&self.path)
+            .with_context(|| format!("failed to open {}", self.path.display()))?;
+        let mut body = String::new();
+        if self.compressed {
+            GzDecoder::new(file).read_to_string(&mut body)
+        } else {
+            io::BufReader::new(file).read_to_string(&mut body)
+        }
+        .with_context(|| format!("failed to read {}", self.path.display()))?;
+        Ok(body)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Delete(PathBuf),
+    Compress(PathBuf),
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Delete(path) => write!(f, "delete {}", path.display()),
+            Action::Compress(path) => write!(f, "gzip {}", path.display()),
+        }
+    }
+}
+
+/// Exports in `dir`, oldest first.
+pub fn list_exports(dir: &Path) -> Result<Vec<Export>> {
+    let mut exports = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_file() {
+            exports.extend(Export::from_file_name(entry.path(), meta.len()));
+        }
+    }
+    exports.sort_by_key(|e| e.taken);
+    Ok(exports)
+}
+
+/// The most recent export of `dashboard` in `dir`, `None` if there is none
+/// (or no such directory). Bodies of `--dry-run=mutate-local` don't count.
+pub fn latest_export(dir: &Path, dashboard: &str) -> Result<Option<Export>> {
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+    let wanted = annotate::export_name(dashboard);
+    Ok(list_exports(dir)?
+        .into_iter()
+        .rev()
+        .find(|e| !e.is_dry_run() && e.dashboard() == Some(wanted.as_str())))
+}
+
+/// What to do with `exports` (oldest first) under `policy` at `now`.
+pub fn plan(exports: &[Export], policy: &Policy, now: DateTime<Utc>) -> Vec<Action> {
+    let mut actions = Vec::new();
+    let mut kept: Vec<&Export> = Vec::new();
+
+    for export in exports {
+        match policy.keep {
+            Some(keep) if now - export.taken > keep => {
+                actions.push(Action::Delete(export.path.clone()))
+            }
+            _ => kept.push(export),
+        }
+    }
+
+    if let Some(max_bytes) = policy.max_bytes {
+        let mut total: u64 = kept.iter().map(|e| e.size).sum();
+        let mut oldest = 0;
+        while total > max_bytes && oldest < kept.len() {
+            total -= kept[oldest].size;
+            actions.push(Action::Delete(kept[oldest].path.clone()));
+            oldest += 1;
+        }
+        kept.drain(..oldest);
+    }
+
+    if policy.gzip {
+        let compress_after = Duration::days(COMPRESS_AFTER_DAYS);
+        actions.extend(
+            kept.iter()
+                .filter(|e| !e.compressed && now - e.taken > compress_after)
+                .map(|e| Action::Compress(e.path.clone())),
+        );
+    }
+
+    actions
+}
+
+/// Gzip `path` to `path.gz` and remove the original.
+fn compress(path: &Path) -> Result<()> {
+    let mut gz_path = path.as_os_str().to_owned();
+    gz_path.push(".gz");
+
+    let mut input =
+        File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut encoder = GzEncoder::new(File::create(&gz_path)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+
+    fs::remove_file(path).with_context(|| format!("failed to remove {}", path.display()))
+}
+
+pub fn apply(action: &Action) -> Result<()> {
+    match action {
+        Action::Delete(path) => {
+            fs::remove_file(path).with_context(|| format!("failed to remove {}", path.display()))
+        }
+        Action::Compress(path) => compress(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+    use tempfile::tempdir;
+
+    fn export(name: &str, size: u64) -> Export {
+        Export::from_file_name(PathBuf::from(name), size).unwrap()
+    }
+
+    #[test]
+    fn export_time_comes_from_the_file_name() {
+        let e = export("2025-01-20-12-00-00-service-dashboard.json.gz", 10);
+        assert_eq!(
+            e.taken,
+            Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap()
+        );
+        assert!(e.compressed);
+
+        assert!(Export::from_file_name(PathBuf::from("notes.json"), 1).is_none());
+        assert!(Export::from_file_name(PathBuf::from("2025-01-20-12-00-00-x.txt"), 1).is_none());
+        // Timestamped, but not named by cwnote.
+        for name in [
+            "2025-01-20-12-00-00_orders.json",
+            "2025-01-20-12-00-00-.json",
+            "2025-01-20-12-00-00-Orders Backup.json",
+            "2025-01-20-12-00-00-package.lock.json",
+        ] {
+            assert!(
+                Export::from_file_name(PathBuf::from(name), 1).is_none(),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn latest_export_of_a_dashboard() {
+        let dir = tempdir().unwrap();
+        for (name, body) in [
+            ("2025-01-20-12-00-00-orders.json", "old"),
+            ("2025-01-21-12-00-00-orders.json", "new"),
+            ("2025-01-22-12-00-00-eu-orders.json", "other dashboard"),
+            ("2025-01-23-12-00-00-orders.dry-run.json", "simulated"),
+        ] {
+            fs::write(dir.path().join(name), body).unwrap();
+        }
+
+        let latest = latest_export(dir.path(), "Orders").unwrap().unwrap();
+        assert_eq!(latest.dashboard(), Some("orders"));
+        assert_eq!(latest.read().unwrap(), "new");
+        assert!(latest_export(dir.path(), "payments").unwrap().is_none());
+        assert!(latest_export(&dir.path().join("missing"), "orders")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn plan_expires_then_trims_to_size_then_compresses() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap();
+        let exports = vec![
+            export("2025-01-01-00-00-00-a.json", 100),
+            export("2025-02-20-00-00-00-a.json", 100),
+            export("2025-02-25-00-00-00-a.json", 100),
+            export("2025-02-28-23-00-00-a.json", 100),
+        ];
+        let policy = Policy {
+            keep: Some(Duration::days(30)),
+            max_bytes: Some(250),
+            gzip: true,
+        };
+
+        assert_eq!(
+            plan(&exports, &policy, now),
+            vec![
+                Action::Delete(PathBuf::from("2025-01-01-00-00-00-a.json")),
+                Action::Delete(PathBuf::from("2025-02-20-00-00-00-a.json")),
+                Action::Compress(PathBuf::from("2025-02-25-00-00-00-a.json")),
+            ]
+        );
+        assert!(plan(&exports, &Policy::default(), now).is_empty());
+    }
+
+    #[test]
+    fn compress_replaces_the_file_with_a_gzip_copy() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("2025-01-20-12-00-00-d.json");
+        fs::write(&path, "{\"widgets\":[]}").unwrap();
+
+        apply(&Action::Compress(path.clone())).unwrap();
+        assert!(!path.exists());
+
+        let exports = list_exports(dir.path()).unwrap();
+        assert_eq!(exports.len(), 1);
+        assert!(exports[0].compressed);
+
+        let mut body = String::new();
+        GzDecoder::new(File::open(&exports[0].path).unwrap())
+            .read_to_string(&mut body)
+            .unwrap();
+        assert_eq!(body, "{\"widgets\":[]}");
+    }
+}
@@ -0,0 +1,128 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{Map, Value};
+
+use crate::annotate::{AnnotationStyle, Route};
+use crate::color;
+use crate::selector::WidgetSelector;
+
+const JSON_KEY_ROUTES: &str = "routes";
+const JSON_KEY_WIDGET_TITLE_CONTAINS: &str = "widgetTitleContains";
+const JSON_KEY_ROW_OF: &str = "rowOf";
+const JSON_KEY_METRIC_NAMESPACE: &str = "metricNamespace";
+const JSON_KEY_METRIC_NAME: &str = "metricName";
+const JSON_KEY_COLOR: &str = "color";
+const JSON_KEY_FILL: &str = "fill";
+const FILL_VALUES: [&str; 2] = ["before", "after"];
+
+/// Read the routes of a manifest file, e.g.
+///
+/// ```json
+/// { "routes": [
+///     { "widgetTitleContains": "Latency", "fill": "after", "color": "#ff7f0e" },
+///     { "widgetTitleContains": "Errors" }
+/// ] }
+/// ```
+pub fn load_routes(path: &Path) -> Result<Vec<Route>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read manifest {}", path.display()))?;
+    let manifest: Value = serde_json::from_str(&text)
+        .with_context(|| format!("manifest {} is not valid JSON", path.display()))?;
+
+    parse_routes(&manifest).with_context(|| format!("invalid manifest {}", path.display()))
+}
+
+/// Routes of an already parsed manifest, in file order.
+pub fn parse_routes(manifest: &Value) -> Result<Vec<Route>> {
+    let routes = manifest
+        .get(JSON_KEY_ROUTES)
+        .and_then(|r| r.as_array())
+        .with_context(|| format!("'{JSON_KEY_ROUTES}' must be a list"))?;
+    if routes.is_empty() {
+        return Err(anyhow!("'{JSON_KEY_ROUTES}' needs at least one route"));
+    }
+
+    routes
+        .iter()
+        .enumerate()
+        .map(|(i, route)| {
+            let route = route
+                .as_object()
+                .with_context(|| format!("route {i} must be an object"))?;
+            parse_route(route).with_context(|| format!("route {i}"))
+        })
+        .collect()
+}
+
+fn parse_route(route: &Map<String, Value>) -> Result<Route> {
+    let text = |key: &str| -> Result<Option<String>> {
+        match route.get(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s.clone())),
+            Some(_) => Err(anyhow!("'{key}' must be a string")),
+        }
+    };
+
+    let fill = text(JSON_KEY_FILL)?;
+    if let Some(ref fill) = fill {
+        if !FILL_VALUES.contains(&fill.as_str()) {
+            return Err(anyhow!("'{JSON_KEY_FILL}' must be \"before\" or \"after\""));
+        }
+    }
+
+    Ok(Route {
+        selector: WidgetSelector {
+            title_contains: text(JSON_KEY_WIDGET_TITLE_CONTAINS)?,
+            row_of: text(JSON_KEY_ROW_OF)?,
+            metric_namespace: text(JSON_KEY_METRIC_NAMESPACE)?,
+            metric_name: text(JSON_KEY_METRIC_NAME)?,
+            ..Default::default()
+        },
+        style: AnnotationStyle {
+            color: text(JSON_KEY_COLOR)?
+                .map(|c| color::parse(&c))
+                .transpose()?,
+            fill,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_routes_in_order() {
+        let manifest = json!({
+            "routes": [
+                { "widgetTitleContains": "Latency", "fill": "after", "color": "#ff7f0e" },
+                { "metricNamespace": "AWS/Lambda" }
+            ]
+        });
+
+        let routes = parse_routes(&manifest).unwrap();
+        assert_eq!(routes.len(), 2);
+        assert_eq!(
+            routes[0].selector.title_contains.as_deref(),
+            Some("Latency")
+        );
+        assert_eq!(routes[0].style.fill.as_deref(), Some("after"));
+        assert_eq!(routes[0].style.color.as_deref(), Some("#ff7f0e"));
+        assert_eq!(
+            routes[1].selector.metric_namespace.as_deref(),
+            Some("AWS/Lambda")
+        );
+        assert_eq!(routes[1].style, AnnotationStyle::default());
+    }
+
+    #[test]
+    fn rejects_bad_style_and_empty_routes() {
+        assert!(parse_routes(&json!({ "routes": [{ "fill": "below" }] })).is_err());
+        assert!(parse_routes(&json!({ "routes": [{ "color": "purple" }] })).is_err());
+        assert!(parse_routes(&json!({ "routes": [] })).is_err());
+        assert!(parse_routes(&json!({})).is_err());
+    }
+}
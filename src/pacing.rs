@@ -0,0 +1,264 @@
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use log::{info, warn};
+
+use crate::aws_client;
+
+// Pause added after each successful update while slowed down (additive decrease
+// of the pause, i.e. additive increase of the rate).
+const RECOVERY_STEP: Duration = Duration::from_millis(250);
+pub const DEFAULT_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+const MAX_PAUSE: Duration = Duration::from_secs(30);
+pub const DEFAULT_RETRY_ATTEMPTS: usize = 5;
+// Error code of CloudWatch's "Rate exceeded" answers.
+const THROTTLING_CODE: &str = "Throttling";
+
+/// How throttled dashboard updates are retried (`--retry-*`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How often one dashboard is tried while being throttled before the run
+    /// fails (1 disables retries).
+    pub attempts: usize,
+    /// First pause after CloudWatch throttles; doubled on every further throttle.
+    pub base_delay: Duration,
+    /// Wait a random 50-100% of the pause, so parallel runs (e.g. several CI
+    /// jobs in one account) don't retry in lockstep.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: DEFAULT_RETRY_ATTEMPTS,
+            base_delay: DEFAULT_RETRY_BASE_DELAY,
+            jitter: true,
+        }
+    }
+}
+
+/// AIMD pacing of the dashboard updates in a bulk run: the pause between
+/// dashboards doubles whenever CloudWatch throttles and shrinks by a fixed
+/// step after every update that goes through, so a run settles on the rate
+/// the account allows without a knob to guess.
+///
+/// Clones share the pause, so updates running concurrently slow down
+/// together when the account is throttled.
+#[derive(Debug, Clone, Default)]
+pub struct Pacer {
+    pause: Arc<Mutex<Duration>>,
+    policy: RetryPolicy,
+}
+
+impl Pacer {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self {
+            pause: Arc::default(),
+            policy,
+        }
+    }
+
+    /// Current pause before each dashboard update.
+    pub fn pause(&self) -> Duration {
+        *self.pause.lock().expect("pacer lock poisoned")
+    }
+
+    /// Back off after a throttled request.
+    pub fn on_throttled(&mut self) {
+        let mut pause = self.pause.lock().expect("pacer lock poisoned");
+        *pause = if pause.is_zero() {
+            self.policy.base_delay.min(MAX_PAUSE)
+        } else {
+            (*pause * 2).min(MAX_PAUSE)
+        };
+        warn!(
+            "CloudWatch is throttling: slowing down to one dashboard every {:.1}s",
+            pause.as_secs_f64()
+        );
+    }
+
+    /// Speed up again after an update went through.
+    pub fn on_success(&mut self) {
+        let mut pause = self.pause.lock().expect("pacer lock poisoned");
+        if pause.is_zero() {
+            return;
+        }
+        *pause = pause.saturating_sub(RECOVERY_STEP);
+        if pause.is_zero() {
+            info!("No more throttling: back to full speed");
+        }
+    }
+
+    /// Sleep for the current pause (no-op at full speed).
+    pub async fn wait(&self) {
+        let pause = self.pause();
+        if pause.is_zero() {
+            return;
+        }
+        let pause = if self.policy.jitter {
+            jittered(pause)
+        } else {
+            pause
+        };
+        tokio::time::sleep(pause).await;
+    }
+
+    /// Run one dashboard update at the current pace. A throttled update is
+    /// tried again more slowly: a throttled GetDashboard/PutDashboard changed
+    /// nothing, so repeating it is safe.
+    pub async fn run<T, F, Fut>(&mut self, mut update: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            self.wait().await;
+            match update().await {
+                Ok(value) => {
+                    self.on_success();
+                    return Ok(value);
+                }
+                Err(err) if is_throttling(&err) && attempt < self.policy.attempts => {
+                    self.on_throttled();
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+// A random duration between half of `pause` and `pause`.
+fn jittered(pause: Duration) -> Duration {
+    let random = RandomState::new().build_hasher().finish();
+    let fraction = 0.5 + (random % 1000) as f64 / 2000.0;
+    pause.mul_f64(fraction)
+}
+
+/// `true` if `err` (or anything it wraps) is CloudWatch throttling the
+/// caller, after the SDK's own retries gave up.
+pub fn is_throttling(err: &anyhow::Error) -> bool {
+    aws_client::dashboard_error_code(err) == Some(THROTTLING_CODE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Context};
+    use aws_sdk_cloudwatch::error::ErrorMetadata;
+    use aws_sdk_cloudwatch::operation::put_dashboard::PutDashboardError;
+
+    fn put_error(code: &str) -> anyhow::Error {
+        let meta = ErrorMetadata::builder()
+            .code(code)
+            .message("Rate exceeded")
+            .build();
+        anyhow::Error::new(PutDashboardError::generic(meta))
+    }
+
+    #[test]
+    fn pause_doubles_on_throttling_and_recovers_step_by_step() {
+        let mut pacer = Pacer::default();
+        pacer.on_success();
+        assert_eq!(pacer.pause(), Duration::ZERO);
+
+        pacer.on_throttled();
+        pacer.on_throttled();
+        assert_eq!(pacer.pause(), Duration::from_secs(1));
+
+        for _ in 0..3 {
+            pacer.on_success();
+        }
+        assert_eq!(pacer.pause(), Duration::from_millis(250));
+        pacer.on_success();
+        assert_eq!(pacer.pause(), Duration::ZERO);
+
+        for _ in 0..20 {
+            pacer.on_throttled();
+        }
+        assert_eq!(pacer.pause(), MAX_PAUSE);
+    }
+
+    #[tokio::test]
+    async fn throttled_updates_are_retried_more_slowly() {
+        let mut pacer = Pacer::default();
+        let mut calls = 0;
+        let value = pacer
+            .run(|| {
+                calls += 1;
+                let throttled = calls == 1;
+                async move {
+                    if throttled {
+                        Err(put_error(THROTTLING_CODE))
+                    } else {
+                        Ok(calls)
+                    }
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(value, 2);
+        // Backed off to 0.5s, then the success took a step off.
+        assert_eq!(pacer.pause(), Duration::from_millis(250));
+
+        let mut calls = 0;
+        let err = pacer
+            .run(|| {
+                calls += 1;
+                async { Err::<(), _>(anyhow!("AccessDenied")) }
+            })
+            .await;
+        assert!(err.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test]
+    async fn retry_policy_limits_attempts() {
+        let mut pacer = Pacer::new(RetryPolicy {
+            attempts: 2,
+            base_delay: Duration::from_millis(10),
+            jitter: false,
+        });
+        let mut calls = 0;
+        let err = pacer
+            .run(|| {
+                calls += 1;
+                async { Err::<(), _>(put_error(THROTTLING_CODE)) }
+            })
+            .await;
+        assert!(err.is_err());
+        assert_eq!(calls, 2);
+        assert_eq!(pacer.pause(), Duration::from_millis(10));
+
+        for _ in 0..100 {
+            let pause = jittered(Duration::from_secs(2));
+            assert!(pause >= Duration::from_secs(1) && pause <= Duration::from_secs(2));
+        }
+    }
+
+    #[test]
+    fn clones_share_the_pause() {
+        let mut pacer = Pacer::default();
+        let mut other = pacer.clone();
+        other.on_throttled();
+        assert_eq!(pacer.pause(), DEFAULT_RETRY_BASE_DELAY);
+        pacer.on_success();
+        assert_eq!(other.pause(), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn recognizes_throttling_anywhere_in_the_chain() {
+        let throttled = Err::<(), _>(put_error(THROTTLING_CODE))
+            .context("failed to put updated dashboard orders")
+            .unwrap_err();
+        assert!(is_throttling(&throttled));
+        assert!(!is_throttling(&put_error("AccessDenied")));
+        // Only the error code counts, not what a message happens to say.
+        assert!(!is_throttling(&anyhow!("Throttling: Rate exceeded")));
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+
+use crate::annotate::AnnotationSpec;
+use crate::selector::WidgetSelector;
+
+/// Top-level dashboard body key dashboard owners can use to restrict cwnote, e.g.
+/// `"cwnotePolicy": {"maxAnnotations": 30, "allowedLabels": ["deploy"], "protectedWidgets": ["SLO"]}`.
+pub const JSON_KEY_POLICY: &str = "cwnotePolicy";
+const JSON_KEY_MAX_ANNOTATIONS: &str = "maxAnnotations";
+const JSON_KEY_ALLOWED_LABELS: &str = "allowedLabels";
+const JSON_KEY_PROTECTED_WIDGETS: &str = "protectedWidgets";
+const JSON_KEY_PRUNE_OLDEST: &str = "pruneOldest";
+
+/// Rules a dashboard carries for itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DashboardPolicy {
+    /// Most vertical annotations any widget may carry after a write.
+    pub max_annotations: Option<usize>,
+    /// Labels cwnote may write. `None` allows every label.
+    pub allowed_labels: Option<Vec<String>>,
+    /// Titles of widgets cwnote must never touch.
+    pub protected_widgets: Vec<String>,
+    /// Drop annotations of the oldest cwnote runs when a write would exceed
+    /// the dashboard size limit.
+    pub prune_oldest: bool,
+}
+
+impl DashboardPolicy {
+    /// Read the policy block from a dashboard body, if there is one.
+    pub fn from_body(body: &Value) -> Result<Option<Self>> {
+        let Some(block) = body.get(JSON_KEY_POLICY) else {
+            return Ok(None);
+        };
+        let block = block
+            .as_object()
+            .with_context(|| format!("{JSON_KEY_POLICY} must be an object"))?;
+
+        let max_annotations = match block.get(JSON_KEY_MAX_ANNOTATIONS) {
+            None => None,
+            Some(v) => Some(
+                v.as_u64()
+                    .with_context(|| format!("{JSON_KEY_MAX_ANNOTATIONS} must be a number"))?
+                    as usize,
+            ),
+        };
+
+        Ok(Some(Self {
+            max_annotations,
+            allowed_labels: block
+                .get(JSON_KEY_ALLOWED_LABELS)
+                .map(|v| string_list(v, JSON_KEY_ALLOWED_LABELS))
+                .transpose()?,
+            protected_widgets: block
+                .get(JSON_KEY_PROTECTED_WIDGETS)
+                .map(|v| string_list(v, JSON_KEY_PROTECTED_WIDGETS))
+                .transpose()?
+                .unwrap_or_default(),
+            prune_oldest: match block.get(JSON_KEY_PRUNE_OLDEST) {
+                None => false,
+                Some(v) => v
+                    .as_bool()
+                    .with_context(|| format!("{JSON_KEY_PRUNE_OLDEST} must be true or false"))?,
+            },
+        }))
+    }
+
+    /// Fail if any annotation uses a label the policy doesn't allow.
+    pub fn check_labels(&self, specs: &[AnnotationSpec]) -> Result<()> {
+        specs.iter().try_for_each(|s| self.check_label(&s.label))
+    }
+
+    /// Fail if the policy doesn't allow `label`.
+    pub fn check_label(&self, label: &str) -> Result<()> {
+        match self.allowed_labels {
+            Some(ref allowed) if !allowed.iter().any(|a| a == label) => Err(anyhow!(
+                "label '{label}' is not in {JSON_KEY_ALLOWED_LABELS} ({})",
+                allowed.join(", ")
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// `selector` narrowed so it never picks protected widgets.
+    pub fn restrict(&self, selector: &WidgetSelector) -> WidgetSelector {
+        let mut restricted = selector.clone();
+        restricted
+            .exclude_titles
+            .extend(self.protected_widgets.iter().cloned());
+        restricted
+    }
+}
+
+fn string_list(value: &Value, key: &str) -> Result<Vec<String>> {
+    value
+        .as_array()
+        .and_then(|items| {
+            items
+                .iter()
+                .map(|i| i.as_str().map(str::to_string))
+                .collect::<Option<Vec<_>>>()
+        })
+        .with_context(|| format!("{key} must be a list of strings"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::title_tag;
+    use serde_json::json;
+
+    const TEST_RUN_ID: &str = "20250120T120000Z-aaaaaaaa";
+
+    fn spec(label: &str) -> AnnotationSpec {
+        AnnotationSpec {
+            label: label.to_string(),
+            value: "1.9.0".to_string(),
+            time: None,
+            end_time: None,
+            run_id: TEST_RUN_ID.to_string(),
+        }
+    }
+
+    #[test]
+    fn policy_is_read_from_the_body() {
+        let body = json!({
+            "widgets": [],
+            "cwnotePolicy": {
+                "maxAnnotations": 30,
+                "allowedLabels": ["deploy"],
+                "protectedWidgets": ["SLO"],
+                "pruneOldest": true
+            }
+        });
+
+        let policy = DashboardPolicy::from_body(&body).unwrap().unwrap();
+        assert_eq!(policy.max_annotations, Some(30));
+        assert!(policy.prune_oldest);
+        assert!(policy.check_labels(&[spec("deploy")]).is_ok());
+        assert!(policy.check_labels(&[spec("experiment")]).is_err());
+        assert_eq!(
+            policy.restrict(&WidgetSelector::default()).exclude_titles,
+            vec!["SLO".to_string()]
+        );
+
+        assert!(DashboardPolicy::from_body(&json!({ "widgets": [] }))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn protected_widgets_stay_protected_once_tagged() {
+        let policy = DashboardPolicy {
+            protected_widgets: vec!["SLO".to_string()],
+            ..Default::default()
+        };
+        let selector = policy.restrict(&WidgetSelector::default());
+        let mut widget = json!({ "type": "metric", "properties": { "title": "SLO" } });
+        title_tag::tag_title(widget.as_object_mut().unwrap(), "[v1.2.3]");
+
+        assert!(!selector.matches(widget.as_object().unwrap()));
+    }
+
+    #[test]
+    fn malformed_policy_is_an_error() {
+        let body = json!({ "cwnotePolicy": { "allowedLabels": "deploy" } });
+        assert!(DashboardPolicy::from_body(&body).is_err());
+    }
+}
@@ -0,0 +1,277 @@
+use anyhow::{anyhow, Context, Result};
+use aws_sdk_cloudwatch::primitives::DateTime as SmithyDateTime;
+use aws_sdk_cloudwatch::types::{Dimension, Metric, MetricDataQuery, MetricStat};
+use aws_sdk_cloudwatch::Client;
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{Map, Value};
+
+use crate::annotate::{JSON_KEY_METRICS, JSON_KEY_PROPERTIES, JSON_KEY_TITLE};
+use crate::aws_client;
+
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const PREVIEW_WIDTH: i64 = 60;
+const PREVIEW_WINDOW_HOURS: i64 = 3;
+// Part of the window after the annotation, so both sides of it are visible.
+const PREVIEW_LEAD_MINUTES: i64 = 30;
+const DEFAULT_PERIOD_SECS: i64 = 300;
+const DEFAULT_STAT: &str = "Average";
+const JSON_KEY_STAT: &str = "stat";
+const JSON_KEY_PERIOD: &str = "period";
+const METRIC_QUERY_ID: &str = "preview";
+
+/// The metric a widget plots, as far as a preview needs it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WidgetMetric {
+    pub namespace: String,
+    pub metric_name: String,
+    pub dimensions: Vec<(String, String)>,
+    pub stat: String,
+    pub period: i64,
+}
+
+impl WidgetMetric {
+    /// First metric of a widget's `properties.metrics`, e.g.
+    /// `["AWS/Lambda", "Errors", "FunctionName", "api", {"stat": "Sum"}]`.
+    ///
+    /// Returns `None` for math expressions and rows using the `.`/`...` shorthand.
+    pub fn from_widget(widget_obj: &Map<String, Value>) -> Option<Self> {
+        let props = widget_obj.get(JSON_KEY_PROPERTIES)?;
+        let row = props
+            .get(JSON_KEY_METRICS)?
+            .as_array()?
+            .first()?
+            .as_array()?;
+
+        let names: Vec<&str> = row.iter().filter_map(|v| v.as_str()).collect();
+        let [namespace, metric_name, dimensions @ ..] = names.as_slice() else {
+            return None;
+        };
+        if names.iter().any(|n| n.starts_with('.')) {
+            return None;
+        }
+
+        // Per-metric options win over the widget-wide settings.
+        let row_options = row.last().and_then(|v| v.as_object());
+        let setting = |key: &str| {
+            row_options
+                .and_then(|o| o.get(key))
+                .or_else(|| props.get(key))
+        };
+
+        Some(Self {
+            namespace: namespace.to_string(),
+            metric_name: metric_name.to_string(),
+            dimensions: dimensions
+                .chunks_exact(2)
+                .map(|kv| (kv[0].to_string(), kv[1].to_string()))
+                .collect(),
+            stat: setting(JSON_KEY_STAT)
+                .and_then(|s| s.as_str())
+                .unwrap_or(DEFAULT_STAT)
+                .to_string(),
+            period: setting(JSON_KEY_PERIOD)
+                .and_then(|p| p.as_i64())
+                .filter(|p| *p > 0)
+                .unwrap_or(DEFAULT_PERIOD_SECS),
+        })
+    }
+}
+
+/// Time range shown around an annotation at `at`: mostly before it, a little
+/// after, but never past `now` for annotations in the past.
+pub fn window(at: DateTime<Utc>, now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let end = (at + Duration::minutes(PREVIEW_LEAD_MINUTES)).min(now.max(at));
+    (end - Duration::hours(PREVIEW_WINDOW_HOURS), end)
+}
+
+/// Sparkline of `points` over `[start, end)` plus a second line with a `^`
+/// under the column of `marker`.
+pub fn render(
+    points: &[(DateTime<Utc>, f64)],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    period: i64,
+    marker: DateTime<Utc>,
+) -> (String, String) {
+    let span = (end - start).num_seconds().max(1);
+    let columns = (span / period.max(1)).clamp(1, PREVIEW_WIDTH);
+    let column_of = |t: DateTime<Utc>| {
+        ((t - start).num_seconds() * columns / span).clamp(0, columns - 1) as usize
+    };
+
+    // Average all datapoints falling into the same column.
+    let mut buckets = vec![(0.0f64, 0usize); columns as usize];
+    for (t, v) in points.iter().filter(|(t, _)| *t >= start && *t < end) {
+        let bucket = &mut buckets[column_of(*t)];
+        bucket.0 += v;
+        bucket.1 += 1;
+    }
+    let averages: Vec<Option<f64>> = buckets
+        .iter()
+        .map(|(sum, n)| (*n > 0).then(|| sum / *n as f64))
+        .collect();
+
+    let (min, max) = averages
+        .iter()
+        .flatten()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+            (lo.min(*v), hi.max(*v))
+        });
+    let top = (SPARK_LEVELS.len() - 1) as f64;
+
+    let spark = averages
+        .iter()
+        .map(|avg| match avg {
+            None => ' ',
+            Some(_) if max <= min => SPARK_LEVELS[SPARK_LEVELS.len() / 2],
+            Some(v) => SPARK_LEVELS[(((v - min) / (max - min)) * top).round() as usize],
+        })
+        .collect();
+
+    let marker_line = format!("{}^", " ".repeat(column_of(marker)));
+    (spark, marker_line)
+}
+
+/// Fetch `metric` over `[start, end)` with GetMetricData.
+pub async fn fetch_points(
+    client: &Client,
+    metric: &WidgetMetric,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<(DateTime<Utc>, f64)>> {
+    let mut builder = Metric::builder()
+        .namespace(&metric.namespace)
+        .metric_name(&metric.metric_name);
+    for (name, value) in &metric.dimensions {
+        builder = builder.dimensions(Dimension::builder().name(name).value(value).build());
+    }
+
+    let query = MetricDataQuery::builder()
+        .id(METRIC_QUERY_ID)
+        .metric_stat(
+            MetricStat::builder()
+                .metric(builder.build())
+                .period(metric.period as i32)
+                .stat(&metric.stat)
+                .build(),
+        )
+        .build();
+
+    let resp = client
+        .get_metric_data()
+        .metric_data_queries(query)
+        .start_time(SmithyDateTime::from_secs(start.timestamp()))
+        .end_time(SmithyDateTime::from_secs(end.timestamp()))
+        .send()
+        .await
+        .with_context(|| {
+            format!(
+                "failed to get metric data for {}/{}",
+                metric.namespace, metric.metric_name
+            )
+        })?;
+
+    Ok(resp
+        .metric_data_results()
+        .iter()
+        .flat_map(|r| r.timestamps().iter().zip(r.values()))
+        .filter_map(|(t, v)| aws_client::to_chrono(t).map(|t| (t, *v)))
+        .collect())
+}
+
+/// Preview lines for a widget with an annotation at `at`.
+pub async fn render_widget(
+    client: &Client,
+    widget_obj: &Map<String, Value>,
+    at: DateTime<Utc>,
+) -> Result<Vec<String>> {
+    let title = widget_obj
+        .get(JSON_KEY_PROPERTIES)
+        .and_then(|p| p.get(JSON_KEY_TITLE))
+        .and_then(|t| t.as_str())
+        .unwrap_or("<untitled>");
+    let metric = WidgetMetric::from_widget(widget_obj)
+        .with_context(|| format!("widget '{title}' has no plain metric to preview"))?;
+
+    let (start, end) = window(at, Utc::now());
+    let points = fetch_points(client, &metric, start, end).await?;
+    if points.is_empty() {
+        return Err(anyhow!(
+            "no datapoints for {}/{} between {start} and {end}",
+            metric.namespace,
+            metric.metric_name
+        ));
+    }
+
+    let (spark, marker_line) = render(&points, start, end, metric.period, at);
+    Ok(vec![
+        format!(
+            "preview of '{title}' ({}/{} {}):",
+            metric.namespace, metric.metric_name, metric.stat
+        ),
+        format!("|{spark}|"),
+        format!(" {marker_line} annotation"),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn widget_metric_reads_first_row_with_options() {
+        let widget = json!({
+            "type": "metric",
+            "properties": {
+                "period": 60,
+                "metrics": [
+                    ["AWS/Lambda", "Errors", "FunctionName", "api", { "stat": "Sum" }],
+                    ["...", "Throttles"]
+                ]
+            }
+        });
+
+        let metric = WidgetMetric::from_widget(widget.as_object().unwrap()).unwrap();
+        assert_eq!(metric.namespace, "AWS/Lambda");
+        assert_eq!(metric.metric_name, "Errors");
+        assert_eq!(
+            metric.dimensions,
+            vec![("FunctionName".to_string(), "api".to_string())]
+        );
+        assert_eq!(metric.stat, "Sum");
+        assert_eq!(metric.period, 60);
+    }
+
+    #[test]
+    fn widget_metric_skips_expressions() {
+        let widget = json!({
+            "properties": { "metrics": [[{ "expression": "SUM(METRICS())" }]] }
+        });
+        assert!(WidgetMetric::from_widget(widget.as_object().unwrap()).is_none());
+    }
+
+    #[test]
+    fn render_marks_annotation_column() {
+        let start = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+        let end = start + Duration::minutes(4);
+        let points = vec![
+            (start, 1.0),
+            (start + Duration::minutes(1), 2.0),
+            (start + Duration::minutes(3), 8.0),
+        ];
+
+        let (spark, marker) = render(&points, start, end, 60, start + Duration::minutes(3));
+        assert_eq!(spark, "▁▂ █");
+        assert_eq!(marker, "   ^");
+    }
+
+    #[test]
+    fn window_stops_at_now_for_past_annotations() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 20, 12, 0, 0).unwrap();
+        let (start, end) = window(now - Duration::minutes(10), now);
+        assert_eq!(end, now);
+        assert_eq!(start, now - Duration::hours(PREVIEW_WINDOW_HOURS));
+    }
+}
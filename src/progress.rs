@@ -0,0 +1,81 @@
+use log::info;
+
+use crate::annotate::{DashboardOutcome, OutcomeStatus};
+
+/// Running totals of a multi-dashboard run, logged after every dashboard so
+/// a long suffix run shows how far it got.
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    total: usize,
+    done: usize,
+    widgets: usize,
+    failed: usize,
+    enabled: bool,
+}
+
+impl Progress {
+    /// Progress over `total` dashboards; with `enabled` false (`--no-progress`)
+    /// nothing is logged.
+    pub fn new(total: usize, enabled: bool) -> Self {
+        Self {
+            total,
+            enabled,
+            ..Self::default()
+        }
+    }
+
+    /// Count one finished dashboard and log the totals so far.
+    pub fn record(&mut self, outcome: &DashboardOutcome) {
+        match outcome.status {
+            OutcomeStatus::Annotated => self.widgets += outcome.widgets.len(),
+            OutcomeStatus::Failed => self.failed += 1,
+            _ => {}
+        }
+        self.finish_one();
+    }
+
+    /// Count a dashboard whose update returned an error.
+    pub fn record_failure(&mut self) {
+        self.failed += 1;
+        self.finish_one();
+    }
+
+    fn finish_one(&mut self) {
+        self.done += 1;
+        if self.enabled && self.total > 1 {
+            info!("{}", self.line());
+        }
+    }
+
+    /// e.g. `Progress: 3/80 dashboards, 12 widget(s) annotated, 1 failed`.
+    pub fn line(&self) -> String {
+        format!(
+            "Progress: {}/{} dashboards, {} widget(s) annotated, {} failed",
+            self.done, self.total, self.widgets, self.failed
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_dashboards_widgets_and_failures() {
+        let mut progress = Progress::new(3, false);
+        progress.record(&DashboardOutcome {
+            dashboard: "orders".to_string(),
+            status: OutcomeStatus::Annotated,
+            error: None,
+            widgets_matched: 2,
+            widgets: vec!["Latency".to_string(), "Errors".to_string()],
+            changes: Vec::new(),
+        });
+        progress.record_failure();
+
+        assert_eq!(
+            progress.line(),
+            "Progress: 2/3 dashboards, 2 widget(s) annotated, 1 failed"
+        );
+    }
+}
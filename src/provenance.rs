@@ -0,0 +1,208 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+
+use crate::duration;
+
+pub const JSON_KEY_CREATOR: &str = "cwnoteCreator";
+pub const JSON_KEY_SCOPE: &str = "cwnoteScope";
+pub const JSON_KEY_TTL: &str = "cwnoteTtl";
+pub const JSON_KEY_URL: &str = "cwnoteUrl";
+
+// Who runs cwnote: the CI actor if there is one, else the local user.
+const CREATOR_ENVS: [&str; 4] = ["GITHUB_ACTOR", "GITLAB_USER_LOGIN", "USER", "USERNAME"];
+// Direct links to the CI job (GitLab, Jenkins); GitHub's is assembled below.
+const URL_ENVS: [&str; 2] = ["CI_JOB_URL", "BUILD_URL"];
+const GITHUB_SERVER_URL_ENV: &str = "GITHUB_SERVER_URL";
+const GITHUB_REPOSITORY_ENV: &str = "GITHUB_REPOSITORY";
+const GITHUB_RUN_ID_ENV: &str = "GITHUB_RUN_ID";
+
+/// Where an annotation came from, stored as extra keys next to `cwnoteRunId`
+/// instead of in the label. CloudWatch ignores them; `list` and `remove` can
+/// filter on them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+    /// Who created the annotation, e.g. the CI actor.
+    pub creator: Option<String>,
+    /// Free-form owner, e.g. a team or service.
+    pub scope: Option<String>,
+    /// How long the annotation is meant to stay, counted from its time, e.g. `30d`.
+    pub ttl: Option<String>,
+    /// Link to what created it, e.g. the CI job.
+    pub url: Option<String>,
+}
+
+impl Provenance {
+    /// Creator and URL of the current CI job (or local user), from `var`.
+    pub fn detect(var: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |key: &str| var(key).filter(|v| !v.trim().is_empty());
+        let github_run = || {
+            Some(format!(
+                "{}/{}/actions/runs/{}",
+                var(GITHUB_SERVER_URL_ENV)?,
+                var(GITHUB_REPOSITORY_ENV)?,
+                var(GITHUB_RUN_ID_ENV)?
+            ))
+        };
+        Self {
+            creator: CREATOR_ENVS.iter().find_map(|key| var(key)),
+            url: github_run().or_else(|| URL_ENVS.iter().find_map(|key| var(key))),
+            ..Self::default()
+        }
+    }
+
+    fn fields(&self) -> [(&'static str, &Option<String>); 4] {
+        [
+            (JSON_KEY_CREATOR, &self.creator),
+            (JSON_KEY_SCOPE, &self.scope),
+            (JSON_KEY_TTL, &self.ttl),
+            (JSON_KEY_URL, &self.url),
+        ]
+    }
+
+    /// Add the fields that are set to an annotation object.
+    pub fn write(&self, obj: &mut Map<String, Value>) {
+        for (key, value) in self.fields() {
+            if let Some(value) = value {
+                obj.insert(key.to_string(), Value::String(value.clone()));
+            }
+        }
+    }
+
+    /// Read the fields back from an annotation object.
+    pub fn read(obj: &Map<String, Value>) -> Self {
+        let field = |key: &str| obj.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        Self {
+            creator: field(JSON_KEY_CREATOR),
+            scope: field(JSON_KEY_SCOPE),
+            ttl: field(JSON_KEY_TTL),
+            url: field(JSON_KEY_URL),
+        }
+    }
+
+    /// When an annotation at `time` (RFC3339) with this ttl expires. `None`
+    /// without a ttl, or if either doesn't parse.
+    pub fn expires_at(&self, time: &str) -> Option<DateTime<Utc>> {
+        let ttl = duration::parse_duration(self.ttl.as_deref()?).ok()?;
+        let time = DateTime::parse_from_rfc3339(time).ok()?;
+        Some(time.with_timezone(&Utc) + ttl)
+    }
+}
+
+/// Provenance criteria of `list` and `remove`. Every criterion that is set
+/// has to match.
+#[derive(Debug, Clone, Default)]
+pub struct ProvenanceFilter {
+    /// Only annotations created by this creator.
+    pub creator: Option<String>,
+    /// Only annotations with this scope.
+    pub scope: Option<String>,
+    /// Only annotations whose ttl ran out by this time.
+    pub expired_at: Option<DateTime<Utc>>,
+}
+
+impl ProvenanceFilter {
+    pub fn is_set(&self) -> bool {
+        self.creator.is_some() || self.scope.is_some() || self.expired_at.is_some()
+    }
+
+    /// `true` if an annotation at `time` with this provenance matches.
+    pub fn matches(&self, provenance: &Provenance, time: &str) -> bool {
+        if self.creator.is_some() && provenance.creator != self.creator {
+            return false;
+        }
+        if self.scope.is_some() && provenance.scope != self.scope {
+            return false;
+        }
+        match self.expired_at {
+            Some(now) => provenance.expires_at(time).is_some_and(|at| at <= now),
+            None => true,
+        }
+    }
+}
+
+impl fmt::Display for ProvenanceFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(ref creator) = self.creator {
+            parts.push(format!("creator '{creator}'"));
+        }
+        if let Some(ref scope) = self.scope {
+            parts.push(format!("scope '{scope}'"));
+        }
+        if self.expired_at.is_some() {
+            parts.push("expired ttl".to_string());
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn detects_ci_creator_and_link() {
+        let github = |key: &str| {
+            match key {
+                "GITHUB_ACTOR" => Some("octocat"),
+                "USER" => Some("runner"),
+                "GITHUB_SERVER_URL" => Some("https://github.com"),
+                "GITHUB_REPOSITORY" => Some("acme/shop"),
+                "GITHUB_RUN_ID" => Some("42"),
+                _ => None,
+            }
+            .map(str::to_string)
+        };
+        let provenance = Provenance::detect(github);
+        assert_eq!(provenance.creator.as_deref(), Some("octocat"));
+        assert_eq!(
+            provenance.url.as_deref(),
+            Some("https://github.com/acme/shop/actions/runs/42")
+        );
+
+        let local = |key: &str| (key == "USER").then(|| "alice".to_string());
+        assert_eq!(
+            Provenance::detect(local),
+            Provenance {
+                creator: Some("alice".to_string()),
+                ..Provenance::default()
+            }
+        );
+    }
+
+    #[test]
+    fn round_trips_and_filters() {
+        let provenance = Provenance {
+            creator: Some("octocat".to_string()),
+            scope: Some("payments".to_string()),
+            ttl: Some("7d".to_string()),
+            url: None,
+        };
+        let mut obj = Map::new();
+        provenance.write(&mut obj);
+        assert_eq!(
+            Value::Object(obj.clone()),
+            json!({ "cwnoteCreator": "octocat", "cwnoteScope": "payments", "cwnoteTtl": "7d" })
+        );
+        assert_eq!(Provenance::read(&obj), provenance);
+
+        let time = "2025-01-20T12:00:00Z";
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let filter = ProvenanceFilter {
+            scope: Some("payments".to_string()),
+            expired_at: Some(at("2025-01-27T12:00:00Z")),
+            ..ProvenanceFilter::default()
+        };
+        assert!(filter.matches(&provenance, time));
+
+        let not_yet = ProvenanceFilter {
+            expired_at: Some(at("2025-01-27T11:59:59Z")),
+            ..ProvenanceFilter::default()
+        };
+        assert!(!not_yet.matches(&provenance, time));
+        assert!(!filter.matches(&Provenance::default(), time));
+    }
+}
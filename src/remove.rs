@@ -0,0 +1,678 @@
+use std::fmt;
+
+use anyhow::{Context, Result};
+use aws_sdk_cloudwatch::Client;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use log::{info, warn};
+use serde_json::Value;
+
+use crate::annotate::{
+    self, JSON_KEY_ANNOTATIONS, JSON_KEY_HORIZONTAL, JSON_KEY_LABEL, JSON_KEY_PROPERTIES,
+    JSON_KEY_RUN_ID, JSON_KEY_VALUE, JSON_KEY_VERTICAL,
+};
+use crate::duplicate_filter::DuplicateFilter;
+use crate::interrupt;
+use crate::list::{listed_annotation, ListedAnnotation};
+use crate::pacing::Pacer;
+use crate::provenance::{Provenance, ProvenanceFilter};
+use crate::selector::{selected_widgets, WidgetSelector};
+use crate::title_tag;
+
+/// `true` if an annotation label is `label`, or `label: value` as cwnote writes it.
+pub fn label_matches(ann_label: &str, label: &str) -> bool {
+    ann_label == label
+        || ann_label
+            .strip_prefix(label)
+            .is_some_and(|rest| rest.starts_with(':'))
+}
+
+/// Which annotation array of a widget to look at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Direction {
+    /// Time markers and bands (what cwnote writes).
+    Vertical,
+    /// Threshold lines and bands on the value axis.
+    Horizontal,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 2] = [Direction::Vertical, Direction::Horizontal];
+
+    pub fn json_key(self) -> &'static str {
+        match self {
+            Direction::Vertical => JSON_KEY_VERTICAL,
+            Direction::Horizontal => JSON_KEY_HORIZONTAL,
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.json_key())
+    }
+}
+
+/// Controls which existing annotations get removed. Every criterion that is
+/// set has to match.
+#[derive(Debug, Clone, Default)]
+pub struct RemoveFilter {
+    /// Only remove annotations created by this cwnote run.
+    pub run_id: Option<String>,
+    /// Only remove annotations with this label (cwnote writes `label: value`).
+    pub label: Option<String>,
+    /// Also strip the title tags added by `annotate --tag-title`.
+    pub untag_title: bool,
+    /// Only look at this annotation array. `None` cleans up both.
+    pub direction: Option<Direction>,
+    pub provenance: ProvenanceFilter,
+    /// Only remove vertical annotations that ended before this time (a
+    /// band's end, a marker's time), e.g. for `prune --older-than`.
+    pub older_than: Option<DateTime<Utc>>,
+    /// Also remove the vertical annotations repeating an earlier one on the
+    /// same widget, for `dedup`. Unlike the criteria above, this doesn't
+    /// narrow what else is removed.
+    pub duplicates: Option<DuplicateFilter>,
+}
+
+impl RemoveFilter {
+    /// Returns `true` if the given annotation (object or band) should be removed.
+    ///
+    /// A filter without run id, label, provenance or age criteria matches
+    /// nothing, so `--untag-title` on its own leaves annotations alone.
+    pub fn matches(&self, ann: &Value) -> bool {
+        if self.run_id.is_none()
+            && self.label.is_none()
+            && !self.provenance.is_set()
+            && self.older_than.is_none()
+        {
+            return false;
+        }
+        let Some(head) = annotate::annotation_head(ann) else {
+            return false;
+        };
+        let field = |key: &str| head.get(key).and_then(|v| v.as_str());
+
+        if let Some(ref run_id) = self.run_id {
+            if field(JSON_KEY_RUN_ID) != Some(run_id.as_str()) {
+                return false;
+            }
+        }
+        if let Some(ref label) = self.label {
+            if !label_matches(field(JSON_KEY_LABEL).unwrap_or(""), label) {
+                return false;
+            }
+        }
+        if let Some(cutoff) = self.older_than {
+            // Horizontal annotations carry no time and never count as old.
+            let last = ann
+                .as_array()
+                .and_then(|band| band.get(1))
+                .and_then(|tail| tail.get(JSON_KEY_VALUE))
+                .or_else(|| head.get(JSON_KEY_VALUE))
+                .and_then(|v| v.as_str())
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok());
+            if last.is_none_or(|last| last >= cutoff) {
+                return false;
+            }
+        }
+        self.provenance
+            .matches(&Provenance::read(head), field(JSON_KEY_VALUE).unwrap_or(""))
+    }
+
+    fn directions(&self) -> Vec<Direction> {
+        match self.direction {
+            Some(direction) => vec![direction],
+            None => Direction::ALL.to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for RemoveFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.run_id, &self.label) {
+            (Some(run_id), Some(label)) => write!(f, "run '{run_id}' with label '{label}'")?,
+            (Some(run_id), None) => write!(f, "run '{run_id}'")?,
+            (None, Some(label)) => write!(f, "label '{label}'")?,
+            (None, None) if self.provenance.is_set() => write!(f, "{}", self.provenance)?,
+            (None, None) if self.older_than.is_some() => write!(f, "any run")?,
+            (None, None) if self.duplicates.is_some() && !self.provenance.is_set() => {}
+            (None, None) => write!(f, "no run")?,
+        }
+        if self.provenance.is_set() && (self.run_id.is_some() || self.label.is_some()) {
+            write!(f, " with {}", self.provenance)?;
+        }
+        if let Some(cutoff) = self.older_than {
+            write!(f, " older than {}", cutoff.to_rfc3339())?;
+        }
+        if let Some(ref duplicates) = self.duplicates {
+            let matches_some = self.run_id.is_some()
+                || self.label.is_some()
+                || self.provenance.is_set()
+                || self.older_than.is_some();
+            if matches_some {
+                f.write_str(" and ")?;
+            }
+            write!(f, "{duplicates}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Internal helper: drop every annotation matching the filter (vertical and/or
+/// horizontal) from the widgets picked by `selector`. Returns the annotations
+/// removed.
+fn remove_from_body(
+    dashboard_name: &str,
+    body: &mut Value,
+    filter: &RemoveFilter,
+    selector: &WidgetSelector,
+) -> Vec<ListedAnnotation> {
+    let mut removed = Vec::new();
+
+    if let Some(widgets) = body.get_mut("widgets").and_then(|w| w.as_array_mut()) {
+        let selected = match selected_widgets(widgets, selector) {
+            Ok(selected) => selected,
+            Err(err) => {
+                warn!("{err}");
+                return removed;
+            }
+        };
+
+        for i in selected {
+            let title = annotate::widget_title(&widgets[i]);
+            let Some(anns_obj) = widgets[i]
+                .get_mut(JSON_KEY_PROPERTIES)
+                .and_then(|p| p.get_mut(JSON_KEY_ANNOTATIONS))
+                .and_then(|a| a.as_object_mut())
+            else {
+                continue;
+            };
+
+            for direction in filter.directions() {
+                if let Some(arr) = anns_obj
+                    .get_mut(direction.json_key())
+                    .and_then(|v| v.as_array_mut())
+                {
+                    let duplicates = match filter.duplicates {
+                        Some(ref duplicates) if direction == Direction::Vertical => {
+                            duplicates.duplicates(arr)
+                        }
+                        _ => Vec::new(),
+                    };
+                    let mut index = 0;
+                    arr.retain(|ann| {
+                        index += 1;
+                        if !filter.matches(ann) && duplicates.binary_search(&(index - 1)).is_err() {
+                            return true;
+                        }
+                        removed.extend(listed_annotation(dashboard_name, &title, direction, ann));
+                        false
+                    });
+                }
+            }
+        }
+    }
+
+    removed
+}
+
+/// Remove matching annotations from a single dashboard by name. Returns the
+/// annotations removed (or, in a dry run, the ones that would be).
+pub async fn remove_from_dashboard(
+    client: &Client,
+    dashboard_name: &str,
+    filter: &RemoveFilter,
+    selector: &WidgetSelector,
+    dry_run: bool,
+) -> Result<Vec<ListedAnnotation>> {
+    let resp = client
+        .get_dashboard()
+        .dashboard_name(dashboard_name)
+        .send()
+        .await
+        .with_context(|| format!("failed to get dashboard {dashboard_name}"))?;
+
+    let body_str = resp
+        .dashboard_body()
+        .with_context(|| format!("dashboard {dashboard_name} has no body"))?;
+
+    let mut body: Value =
+        serde_json::from_str(body_str).context("failed to parse dashboard body JSON")?;
+
+    let removed = remove_from_body(dashboard_name, &mut body, filter, selector);
+    let untagged = if filter.untag_title {
+        title_tag::untag_selected(&mut body, selector)
+    } else {
+        0
+    };
+
+    if removed.is_empty() && untagged == 0 {
+        info!("{dashboard_name}: No matching annotations found (nothing to remove)");
+        return Ok(removed);
+    }
+
+    if dry_run {
+        info! {
+            target: "dry-run",
+            "{}: would remove {} annotation(s) of {} and untag {} widget title(s).",
+            dashboard_name, removed.len(), filter, untagged
+        };
+        for ann in &removed {
+            info! {
+                target: "dry-run",
+                "{}: would remove {} '{}' at {} from widget '{}'.",
+                dashboard_name, ann.direction, ann.label, ann.value, ann.widget
+            };
+        }
+        return Ok(removed);
+    }
+
+    let updated_body =
+        serde_json::to_string(&body).context("failed to serialize updated dashboard body")?;
+
+    client
+        .put_dashboard()
+        .dashboard_name(dashboard_name)
+        .dashboard_body(&updated_body)
+        .send()
+        .await
+        .with_context(|| format!("failed to put updated dashboard {dashboard_name}"))?;
+
+    info!(
+        "Removed {} annotation(s) of {} and {} title tag(s) from dashboard '{}'",
+        removed.len(),
+        filter,
+        untagged,
+        dashboard_name
+    );
+    if let Err(err) = annotate::save_to_file(&updated_body, dashboard_name) {
+        warn!("Export failed for '{dashboard_name}': {err}");
+    }
+
+    Ok(removed)
+}
+
+/// Remove matching annotations from all dashboards whose name matches the suffix.
+///
+/// An empty suffix matches every dashboard in the account/region, which is
+/// what `remove` uses when no target is given.
+pub async fn remove_from_dashboards_by_suffix(
+    client: &Client,
+    suffix: &str,
+    filter: &RemoveFilter,
+    selector: &WidgetSelector,
+    dry_run: bool,
+) -> Result<Vec<ListedAnnotation>> {
+    let dashboards = annotate::list_dashboards_with_suffix(client, suffix).await?;
+
+    if dashboards.is_empty() {
+        info!("No dashboards found with suffix '{}'", suffix);
+        return Ok(Vec::new());
+    }
+    remove_from_dashboards(client, &dashboards, filter, selector, dry_run).await
+}
+
+/// Remove matching annotations from all dashboards whose name starts with `prefix`.
+pub async fn remove_from_dashboards_by_prefix(
+    client: &Client,
+    prefix: &str,
+    filter: &RemoveFilter,
+    selector: &WidgetSelector,
+    dry_run: bool,
+) -> Result<Vec<ListedAnnotation>> {
+    let dashboards =
+        annotate::list_dashboards_matching(client, |name| name.starts_with(prefix)).await?;
+
+    if dashboards.is_empty() {
+        info!("No dashboards found with prefix '{}'", prefix);
+        return Ok(Vec::new());
+    }
+    remove_from_dashboards(client, &dashboards, filter, selector, dry_run).await
+}
+
+/// Remove matching annotations from each of `dashboards`, one after the other.
+async fn remove_from_dashboards(
+    client: &Client,
+    dashboards: &[String],
+    filter: &RemoveFilter,
+    selector: &WidgetSelector,
+    dry_run: bool,
+) -> Result<Vec<ListedAnnotation>> {
+    info!("Scanning {} dashboard(s) for annotations", dashboards.len());
+
+    let mut removed = Vec::new();
+    let mut pacer = Pacer::default();
+    for (i, name) in dashboards.iter().enumerate() {
+        if interrupt::stop_before(&dashboards[i..]) {
+            break;
+        }
+        removed.extend(
+            pacer
+                .run(|| remove_from_dashboard(client, name, filter, selector, dry_run))
+                .await?,
+        );
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+    use serde_json::json;
+
+    const RUN_A: &str = "20250120T120000Z-aaaaaaaa";
+    const RUN_B: &str = "20250120T130000Z-bbbbbbbb";
+
+    #[test]
+    fn remove_only_drops_annotations_of_the_given_run() {
+        let mut body = json!({
+            "widgets": [
+                {
+                    "type": "metric",
+                    "properties": {
+                        "title": "Latency",
+                        "annotations": {
+                            "vertical": [
+                                { "label": "version: 1", "value": "2025-01-20T12:00:00Z", "cwnoteRunId": RUN_A },
+                                { "label": "version: 2", "value": "2025-01-20T13:00:00Z", "cwnoteRunId": RUN_B },
+                                { "label": "manual", "value": "2025-01-20T14:00:00Z" },
+                                [
+                                    { "label": "batch: nightly", "value": "2025-01-20T01:00:00Z", "cwnoteRunId": RUN_A },
+                                    { "value": "2025-01-20T02:00:00Z" }
+                                ]
+                            ]
+                        }
+                    }
+                },
+                {
+                    "type": "text",
+                    "properties": { "markdown": "# Hello" }
+                }
+            ]
+        });
+
+        let filter = RemoveFilter {
+            run_id: Some(RUN_A.to_string()),
+            ..Default::default()
+        };
+
+        let removed = remove_from_body("D", &mut body, &filter, &WidgetSelector::default());
+        assert_eq!(removed.len(), 2);
+        assert_eq!(removed[0].widget, "Latency");
+        assert_eq!(removed[0].label, "version: 1");
+        assert_eq!(removed[0].value, "2025-01-20T12:00:00Z");
+
+        let vertical = body["widgets"][0]["properties"]["annotations"]["vertical"]
+            .as_array()
+            .unwrap();
+        let labels: Vec<_> = vertical
+            .iter()
+            .map(|a| a.get(JSON_KEY_LABEL).unwrap().as_str().unwrap())
+            .collect();
+        assert_eq!(labels, vec!["version: 2", "manual"]);
+    }
+
+    #[test]
+    fn remove_by_scope_and_expired_ttl() {
+        let mut body = json!({
+            "widgets": [
+                {
+                    "type": "metric",
+                    "properties": {
+                        "annotations": {
+                            "vertical": [
+                                { "label": "deploy: 1", "value": "2025-01-01T12:00:00Z", "cwnoteScope": "payments", "cwnoteTtl": "7d" },
+                                { "label": "deploy: 2", "value": "2025-01-19T12:00:00Z", "cwnoteScope": "payments", "cwnoteTtl": "7d" },
+                                { "label": "deploy: 3", "value": "2025-01-01T12:00:00Z", "cwnoteScope": "search", "cwnoteTtl": "7d" },
+                                { "label": "manual", "value": "2025-01-01T12:00:00Z" }
+                            ]
+                        }
+                    }
+                }
+            ]
+        });
+
+        let filter = RemoveFilter {
+            provenance: ProvenanceFilter {
+                scope: Some("payments".to_string()),
+                expired_at: DateTime::parse_from_rfc3339("2025-01-20T12:00:00Z")
+                    .ok()
+                    .map(|t| t.with_timezone(&Utc)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(filter.to_string(), "scope 'payments', expired ttl");
+
+        let removed = remove_from_body("D", &mut body, &filter, &WidgetSelector::default());
+        let labels: Vec<_> = removed.iter().map(|a| a.label.as_str()).collect();
+        assert_eq!(labels, vec!["deploy: 1"]);
+        assert_eq!(removed[0].provenance.ttl.as_deref(), Some("7d"));
+    }
+
+    #[test]
+    fn remove_with_unknown_run_leaves_body_untouched() {
+        let mut body = json!({
+            "widgets": [
+                {
+                    "type": "metric",
+                    "properties": {
+                        "annotations": {
+                            "vertical": [
+                                { "label": "version: 1", "value": "2025-01-20T12:00:00Z", "cwnoteRunId": RUN_A }
+                            ]
+                        }
+                    }
+                }
+            ]
+        });
+        let before = body.clone();
+
+        let filter = RemoveFilter {
+            run_id: Some(RUN_B.to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            remove_from_body("D", &mut body, &filter, &WidgetSelector::default()).len(),
+            0
+        );
+        assert_eq!(body, before);
+    }
+
+    #[test]
+    fn prune_drops_annotations_that_ended_before_the_cutoff() {
+        let mut body = json!({
+            "widgets": [
+                {
+                    "type": "metric",
+                    "properties": {
+                        "annotations": {
+                            "vertical": [
+                                { "label": "version: 1.0.0", "value": "2024-11-01T12:00:00Z" },
+                                { "label": "version: 1.9.0", "value": "2025-01-20T12:00:00Z" },
+                                [
+                                    { "label": "freeze", "value": "2024-12-20T00:00:00Z" },
+                                    { "value": "2025-01-05T00:00:00Z" }
+                                ],
+                                { "label": "note", "value": "not a time" }
+                            ],
+                            "horizontal": [{ "label": "SLO", "value": 800 }]
+                        }
+                    }
+                }
+            ]
+        });
+        let cutoff = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let filter = RemoveFilter {
+            older_than: Some(cutoff),
+            ..Default::default()
+        };
+
+        let removed = remove_from_body("D", &mut body, &filter, &WidgetSelector::default());
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].label, "version: 1.0.0");
+        assert_eq!(
+            filter.to_string(),
+            "any run older than 2025-01-01T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn remove_cleans_horizontal_annotations_unless_direction_excludes_them() {
+        let body = json!({
+            "widgets": [
+                {
+                    "type": "metric",
+                    "properties": {
+                        "annotations": {
+                            "vertical": [
+                                { "label": "threshold: v1", "value": "2025-01-20T12:00:00Z" }
+                            ],
+                            "horizontal": [
+                                { "label": "threshold: 500ms", "value": 500 },
+                                { "label": "SLO", "value": 800 }
+                            ]
+                        }
+                    }
+                }
+            ]
+        });
+        let annotations = |body: &Value, key: &str| {
+            body["widgets"][0]["properties"]["annotations"][key]
+                .as_array()
+                .unwrap()
+                .len()
+        };
+
+        let mut both = body.clone();
+        let filter = RemoveFilter {
+            label: Some("threshold".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            remove_from_body("D", &mut both, &filter, &WidgetSelector::default()).len(),
+            2
+        );
+        assert_eq!(annotations(&both, "vertical"), 0);
+        assert_eq!(annotations(&both, "horizontal"), 1);
+
+        let mut horizontal_only = body.clone();
+        let filter = RemoveFilter {
+            label: Some("threshold".to_string()),
+            direction: Some(Direction::Horizontal),
+            ..Default::default()
+        };
+        assert_eq!(
+            remove_from_body(
+                "D",
+                &mut horizontal_only,
+                &filter,
+                &WidgetSelector::default()
+            )
+            .len(),
+            1
+        );
+        assert_eq!(annotations(&horizontal_only, "vertical"), 1);
+    }
+
+    #[test]
+    fn remove_by_label_is_scoped_to_selected_widgets() {
+        let mut body = json!({
+            "widgets": [
+                {
+                    "type": "metric",
+                    "properties": {
+                        "metrics": [["AWS/DynamoDB", "ThrottledRequests"]],
+                        "annotations": {
+                            "vertical": [
+                                { "label": "experiment: cache-off", "value": "2025-01-20T12:00:00Z" },
+                                { "label": "experiments: other", "value": "2025-01-20T12:00:00Z" },
+                                { "label": "version: 2", "value": "2025-01-20T13:00:00Z" }
+                            ]
+                        }
+                    }
+                },
+                {
+                    "type": "metric",
+                    "properties": {
+                        "metrics": [["AWS/Lambda", "Errors"]],
+                        "annotations": {
+                            "vertical": [
+                                { "label": "experiment: cache-off", "value": "2025-01-20T12:00:00Z" }
+                            ]
+                        }
+                    }
+                }
+            ]
+        });
+
+        let filter = RemoveFilter {
+            label: Some("experiment".to_string()),
+            ..Default::default()
+        };
+        let selector = WidgetSelector {
+            metric_namespace: Some("AWS/DynamoDB".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            remove_from_body("D", &mut body, &filter, &selector).len(),
+            1
+        );
+        assert_eq!(
+            body["widgets"][0]["properties"]["annotations"]["vertical"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            body["widgets"][1]["properties"]["annotations"]["vertical"]
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn duplicates_are_removed_alongside_the_filter() {
+        let marker = |label: &str, time: &str| json!({ "label": label, "value": time });
+        let mut body = json!({
+            "widgets": [{
+                "type": "metric",
+                "properties": {
+                    "title": "Latency",
+                    "annotations": { "vertical": [
+                        marker("version: 1.9.0", "2025-01-20T12:07:00Z"),
+                        marker("version: 1.9.0", "2025-01-20T12:00:00Z"),
+                        marker("version: 1.9.1", "2025-01-20T12:03:00Z")
+                    ] }
+                }
+            }]
+        });
+        let filter = RemoveFilter {
+            direction: Some(Direction::Vertical),
+            duplicates: Some(DuplicateFilter::default()),
+            ..Default::default()
+        };
+        assert_eq!(filter.to_string(), "duplicates");
+
+        let removed = remove_from_body("D", &mut body, &filter, &WidgetSelector::default());
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].value, "2025-01-20T12:07:00Z");
+        assert_eq!(
+            body["widgets"][0]["properties"]["annotations"]["vertical"],
+            json!([
+                marker("version: 1.9.0", "2025-01-20T12:00:00Z"),
+                marker("version: 1.9.1", "2025-01-20T12:03:00Z")
+            ])
+        );
+    }
+}
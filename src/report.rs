@@ -0,0 +1,234 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::annotate::{DashboardOutcome, OutcomeStatus};
+
+/// CloudWatch console link to a dashboard.
+pub fn console_url(region: &str, dashboard: &str) -> String {
+    format!(
+        "https://{region}.console.aws.amazon.com/cloudwatch/home?region={region}#dashboards/dashboard/{dashboard}"
+    )
+}
+
+/// CloudWatch console link covering all of `dashboards`: the dashboard
+/// itself for one, the dashboard list for several, `None` for none.
+pub fn console_url_of_all(region: &str, dashboards: &[&str]) -> Option<String> {
+    match dashboards {
+        [] => None,
+        [dashboard] => Some(console_url(region, dashboard)),
+        _ => Some(format!(
+            "https://{region}.console.aws.amazon.com/cloudwatch/home?region={region}#dashboards:"
+        )),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Standalone HTML page describing a run, one section per dashboard.
+pub fn render_html(
+    run_id: &str,
+    dry_run: bool,
+    region: Option<&str>,
+    account_alias: Option<&str>,
+    outcomes: &[DashboardOutcome],
+) -> String {
+    let mode = if dry_run { "Dry run" } else { "Applied" };
+    let account = account_alias
+        .map(|alias| format!(" in account {}", escape_html(alias)))
+        .unwrap_or_default();
+    let mut html = String::new();
+
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>cwnote run {run}</title>\n\
+         <style>body{{font-family:sans-serif;margin:2em}}\
+         .skipped{{color:#777}}pre{{background:#f4f4f4;padding:.5em}}\
+         .add{{color:#1a7f37}}.failed{{color:#cf222e}}</style>\n</head>\n<body>\n\
+         <h1>cwnote run {run}</h1>\n<p>{mode}, {count} dashboard(s){account}. Undo with \
+         <code>cwnote remove --run-id {run}</code>.</p>\n",
+        run = escape_html(run_id),
+        count = outcomes.len(),
+    );
+
+    for outcome in outcomes {
+        let name = escape_html(&outcome.dashboard);
+        let heading = match region {
+            Some(region) => format!(
+                "<a href=\"{}\">{name}</a>",
+                escape_html(&console_url(region, &outcome.dashboard))
+            ),
+            None => name,
+        };
+        let _ = writeln!(html, "<h2>{heading}</h2>");
+
+        match outcome.status {
+            OutcomeStatus::NoWidgets => {
+                let _ = writeln!(html, "<p class=\"skipped\">Dashboard has no widgets.</p>");
+            }
+            OutcomeStatus::NoMatchingWidgets => {
+                let _ = writeln!(html, "<p class=\"skipped\">No matching metric widgets.</p>");
+            }
+            OutcomeStatus::AlreadyAnnotated => {
+                let _ = writeln!(html, "<p class=\"skipped\">Already annotated.</p>");
+            }
+            OutcomeStatus::IacManaged => {
+                let _ = writeln!(
+                    html,
+                    "<p class=\"skipped\">Managed by infrastructure as code, skipped.</p>"
+                );
+            }
+            OutcomeStatus::Failed => {
+                let _ = writeln!(
+                    html,
+                    "<p class=\"failed\">Failed: {}</p>",
+                    escape_html(outcome.error.as_deref().unwrap_or("unknown error"))
+                );
+            }
+            OutcomeStatus::Annotated => {
+                let _ = writeln!(html, "<p>Matched widgets:</p>\n<ul>");
+                for widget in &outcome.widgets {
+                    let _ = writeln!(html, "<li>{}</li>", escape_html(widget));
+                }
+                let _ = writeln!(html, "</ul>\n<pre>");
+                for change in &outcome.changes {
+                    let class = if change.starts_with('+') {
+                        " class=\"add\""
+                    } else {
+                        ""
+                    };
+                    let _ = writeln!(html, "<span{class}>{}</span>", escape_html(change));
+                }
+                let _ = writeln!(html, "</pre>");
+            }
+        }
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// JSON document describing a run, one object per dashboard.
+pub fn render_json(
+    run_id: &str,
+    dry_run: bool,
+    account_alias: Option<&str>,
+    outcomes: &[DashboardOutcome],
+) -> String {
+    let dashboards: Vec<Value> = outcomes.iter().map(DashboardOutcome::to_json).collect();
+    let document = json!({
+        "runId": run_id,
+        "dryRun": dry_run,
+        "accountAlias": account_alias,
+        "dashboards": dashboards,
+    });
+    serde_json::to_string_pretty(&document).unwrap_or_default()
+}
+
+/// Write the HTML report of a run to `path`.
+pub fn write_html(
+    path: &Path,
+    run_id: &str,
+    dry_run: bool,
+    region: Option<&str>,
+    account_alias: Option<&str>,
+    outcomes: &[DashboardOutcome],
+) -> Result<()> {
+    fs::write(
+        path,
+        render_html(run_id, dry_run, region, account_alias, outcomes),
+    )
+    .with_context(|| format!("failed to write report {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lists_widgets_changes_and_links() {
+        let outcomes = vec![
+            DashboardOutcome {
+                dashboard: "Service-Dashboard".to_string(),
+                status: OutcomeStatus::Annotated,
+                widgets_matched: 2,
+                widgets: vec!["Latency <p99>".to_string()],
+                changes: vec!["+ version: 1.9.0 at now".to_string()],
+                error: None,
+            },
+            DashboardOutcome {
+                dashboard: "Empty".to_string(),
+                status: OutcomeStatus::NoWidgets,
+                widgets_matched: 0,
+                widgets: Vec::new(),
+                changes: Vec::new(),
+                error: None,
+            },
+        ];
+
+        let html = render_html(
+            "20250120T120000Z-aaaaaaaa",
+            true,
+            Some("eu-central-1"),
+            Some("payments-prod"),
+            &outcomes,
+        );
+        assert!(html.contains("Dry run, 2 dashboard(s) in account payments-prod."));
+        assert!(html.contains(
+            "https://eu-central-1.console.aws.amazon.com/cloudwatch/home?region=eu-central-1#dashboards/dashboard/Service-Dashboard"
+        ));
+        assert!(html.contains("<li>Latency &lt;p99&gt;</li>"));
+        assert!(html.contains("<span class=\"add\">+ version: 1.9.0 at now</span>"));
+        assert!(html.contains("Dashboard has no widgets."));
+    }
+
+    #[test]
+    fn one_link_covers_every_dashboard() {
+        assert_eq!(console_url_of_all("eu-west-1", &[]), None);
+        assert_eq!(
+            console_url_of_all("eu-west-1", &["Orders"]).as_deref(),
+            Some(console_url("eu-west-1", "Orders").as_str())
+        );
+        assert_eq!(
+            console_url_of_all("eu-west-1", &["Orders", "Payments"]).as_deref(),
+            Some("https://eu-west-1.console.aws.amazon.com/cloudwatch/home?region=eu-west-1#dashboards:")
+        );
+    }
+
+    #[test]
+    fn json_document_counts_matched_and_annotated_widgets() {
+        let outcomes = vec![DashboardOutcome {
+            dashboard: "Service-Dashboard".to_string(),
+            status: OutcomeStatus::Annotated,
+            widgets_matched: 3,
+            widgets: vec!["Latency".to_string(), "Errors".to_string()],
+            changes: vec!["+ version: 1.9.0 at now".to_string()],
+            error: None,
+        }];
+
+        let document: Value = serde_json::from_str(&render_json(
+            "20250120T120000Z-aaaaaaaa",
+            false,
+            Some("payments-prod"),
+            &outcomes,
+        ))
+        .unwrap();
+        assert_eq!(document["runId"], "20250120T120000Z-aaaaaaaa");
+        assert_eq!(document["dryRun"], false);
+        assert_eq!(document["accountAlias"], "payments-prod");
+        let dashboard = &document["dashboards"][0];
+        assert_eq!(dashboard["status"], "annotated");
+        assert_eq!(dashboard["widgetsMatched"], 3);
+        assert_eq!(dashboard["widgetsAnnotated"], 2);
+        assert_eq!(dashboard["error"], Value::Null);
+    }
+}
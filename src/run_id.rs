@@ -0,0 +1,34 @@
+use chrono::Utc;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+const RUN_ID_TS_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Generate a unique id for this cwnote invocation, e.g. `20250120T123456Z-1a2b3c4d`.
+///
+/// The timestamp prefix keeps ids sortable; the random suffix keeps two runs
+/// started within the same second apart.
+pub fn generate() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    let suffix = hasher.finish() as u32;
+
+    format!("{}-{:08x}", Utc::now().format(RUN_ID_TS_FORMAT), suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_ids_are_unique_and_well_formed() {
+        let a = generate();
+        let b = generate();
+
+        assert_ne!(a, b);
+        let (ts, suffix) = a.split_once('-').expect("id should contain a dash");
+        assert_eq!(ts.len(), "20250120T123456Z".len());
+        assert_eq!(suffix.len(), 8);
+        assert!(suffix.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+}
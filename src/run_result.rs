@@ -0,0 +1,333 @@
+use std::fmt;
+
+use serde_json::{json, Value};
+
+use crate::annotate::{DashboardOutcome, OutcomeStatus};
+use crate::aws_client;
+use crate::list::OutputFormat;
+
+/// Exit code of a run that failed, with or without `--detailed-exitcode`.
+pub const EXIT_FAILED: u8 = 1;
+/// Exit code of a run that changed something, with `--detailed-exitcode`.
+/// Not 2, which clap exits with on a usage error.
+pub const EXIT_CHANGED: u8 = 6;
+/// Exit code of `--fail-if-no-dashboards` when no dashboard matched.
+pub const EXIT_NO_DASHBOARDS: u8 = 3;
+/// Exit code of `--fail-if-no-match` when no widget matched.
+pub const EXIT_NO_MATCH: u8 = 4;
+/// Exit code of a run stopped by an AWS API error.
+pub const EXIT_AWS_ERROR: u8 = 5;
+
+/// A run that worked but found nothing to do, turned into a failure by
+/// `--fail-if-no-dashboards` or `--fail-if-no-match`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NothingMatched {
+    /// The target matched no dashboards (e.g. a suffix with a typo).
+    Dashboards(String),
+    /// No dashboard had a widget the selector picks.
+    Widgets(String),
+}
+
+impl fmt::Display for NothingMatched {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NothingMatched::Dashboards(target) => write!(f, "no dashboards matched {target}"),
+            NothingMatched::Widgets(target) => write!(f, "no widgets matched on {target}"),
+        }
+    }
+}
+
+impl std::error::Error for NothingMatched {}
+
+/// Some targets of a run failed, reported once the others are done.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetsFailed {
+    pub failed: usize,
+    pub total: usize,
+    pub run_id: String,
+    /// Exit code of the run, see [`RunResult::failure_exit_code`].
+    pub exit_code: u8,
+}
+
+impl fmt::Display for TargetsFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} target(s) failed in run {}",
+            self.failed, self.total, self.run_id
+        )
+    }
+}
+
+impl std::error::Error for TargetsFailed {}
+
+/// Exit code of a run that ended with `err`.
+pub fn exit_code_of(err: &anyhow::Error) -> u8 {
+    if let Some(failed) = err.downcast_ref::<TargetsFailed>() {
+        return failed.exit_code;
+    }
+    match err.downcast_ref::<NothingMatched>() {
+        Some(NothingMatched::Dashboards(_)) => EXIT_NO_DASHBOARDS,
+        Some(NothingMatched::Widgets(_)) => EXIT_NO_MATCH,
+        None if aws_client::is_aws_error(err) => EXIT_AWS_ERROR,
+        None => EXIT_FAILED,
+    }
+}
+
+/// What a run did to one target, the same for every subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ResultKind {
+    /// Nothing to do, or only read.
+    Unchanged,
+    /// Left alone on purpose, e.g. no matching widgets or IaC-managed.
+    Skipped,
+    /// Written (or, in a dry run, would be).
+    Changed,
+    Failed,
+}
+
+impl ResultKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ResultKind::Unchanged => "unchanged",
+            ResultKind::Skipped => "skipped",
+            ResultKind::Changed => "changed",
+            ResultKind::Failed => "failed",
+        }
+    }
+}
+
+impl fmt::Display for ResultKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The result for one target, usually a dashboard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetResult {
+    pub target: String,
+    pub kind: ResultKind,
+    /// e.g. `annotated 3 widget(s)` or `no matching widgets`.
+    pub reason: Option<String>,
+}
+
+impl TargetResult {
+    pub fn new(target: impl Into<String>, kind: ResultKind, reason: impl Into<String>) -> Self {
+        Self {
+            target: target.into(),
+            kind,
+            reason: Some(reason.into()),
+        }
+    }
+}
+
+impl From<&DashboardOutcome> for TargetResult {
+    fn from(outcome: &DashboardOutcome) -> Self {
+        let (kind, reason) = match outcome.status {
+            OutcomeStatus::Annotated => (
+                ResultKind::Changed,
+                format!("annotated {} widget(s)", outcome.widgets.len()),
+            ),
+            OutcomeStatus::NoWidgets => (ResultKind::Skipped, "no widgets".to_string()),
+            OutcomeStatus::NoMatchingWidgets => {
+                (ResultKind::Skipped, "no matching widgets".to_string())
+            }
+            OutcomeStatus::AlreadyAnnotated => {
+                (ResultKind::Unchanged, "already annotated".to_string())
+            }
+            OutcomeStatus::IacManaged => (
+                ResultKind::Skipped,
+                "managed by infrastructure as code".to_string(),
+            ),
+            OutcomeStatus::Failed => (
+                ResultKind::Failed,
+                outcome
+                    .error
+                    .clone()
+                    .unwrap_or_else(|| "unknown error".to_string()),
+            ),
+        };
+        Self::new(outcome.dashboard.as_str(), kind, reason)
+    }
+}
+
+/// Everything a run did, printed with `--result-format` and turned into the
+/// exit code.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunResult {
+    pub targets: Vec<TargetResult>,
+    /// Exit code of the first account or region that failed as a whole
+    /// (e.g. [`EXIT_NO_DASHBOARDS`]), so a fanned-out run exits like a
+    /// single one would.
+    pub failure_exit_code: Option<u8>,
+}
+
+impl RunResult {
+    /// A run with a single result, e.g. for a read-only subcommand.
+    pub fn single(target: impl Into<String>, kind: ResultKind, reason: impl Into<String>) -> Self {
+        Self {
+            targets: vec![TargetResult::new(target, kind, reason)],
+            failure_exit_code: None,
+        }
+    }
+
+    pub fn from_outcomes(outcomes: &[DashboardOutcome]) -> Self {
+        Self {
+            targets: outcomes.iter().map(TargetResult::from).collect(),
+            failure_exit_code: None,
+        }
+    }
+
+    /// The result of the whole run: failed if any target failed, else
+    /// changed if any changed, else skipped if any was skipped.
+    pub fn kind(&self) -> ResultKind {
+        self.targets
+            .iter()
+            .map(|t| t.kind)
+            .max()
+            .unwrap_or(ResultKind::Unchanged)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(ResultKind::Failed)
+    }
+
+    pub fn count(&self, kind: ResultKind) -> usize {
+        self.targets.iter().filter(|t| t.kind == kind).count()
+    }
+
+    /// 0 unless the run failed; with `detailed` (`--detailed-exitcode`),
+    /// changes exit with 2 so a wrapper can tell them from a no-op.
+    pub fn exit_code(&self, detailed: bool) -> u8 {
+        match self.kind() {
+            ResultKind::Failed => EXIT_FAILED,
+            ResultKind::Changed if detailed => EXIT_CHANGED,
+            _ => 0,
+        }
+    }
+
+    pub fn to_json(&self, run_id: &str) -> Value {
+        let targets: Vec<Value> = self
+            .targets
+            .iter()
+            .map(|t| {
+                json!({
+                    "target": t.target,
+                    "result": t.kind.as_str(),
+                    "reason": t.reason,
+                })
+            })
+            .collect();
+        json!({
+            "runId": run_id,
+            "result": self.kind().as_str(),
+            "targets": targets,
+        })
+    }
+
+    /// Render the result for stdout in the given format.
+    pub fn render(&self, format: OutputFormat, run_id: &str) -> String {
+        match format {
+            OutputFormat::Json => {
+                serde_json::to_string_pretty(&self.to_json(run_id)).unwrap_or_default()
+            }
+            OutputFormat::Text => {
+                let mut lines = vec!["RESULT\tTARGET\tREASON".to_string()];
+                lines.extend(self.targets.iter().map(|t| {
+                    format!(
+                        "{}\t{}\t{}",
+                        t.kind,
+                        t.target,
+                        t.reason.as_deref().unwrap_or("")
+                    )
+                }));
+                lines.join("\n")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(dashboard: &str, status: OutcomeStatus) -> DashboardOutcome {
+        DashboardOutcome {
+            dashboard: dashboard.to_string(),
+            status,
+            widgets_matched: 1,
+            widgets: vec!["Latency".to_string()],
+            changes: Vec::new(),
+            error: (status == OutcomeStatus::Failed).then(|| "throttled".to_string()),
+        }
+    }
+
+    #[test]
+    fn run_result_is_the_worst_target_result() {
+        let unchanged = RunResult::single("orders", ResultKind::Unchanged, "read only");
+        assert_eq!(unchanged.kind(), ResultKind::Unchanged);
+        assert_eq!(unchanged.exit_code(true), 0);
+        assert_eq!(RunResult::default().kind(), ResultKind::Unchanged);
+        // Annotations already there: nothing written, so nothing changed.
+        let already =
+            RunResult::from_outcomes(&[outcome("orders", OutcomeStatus::AlreadyAnnotated)]);
+        assert_eq!(already.kind(), ResultKind::Unchanged);
+        assert_eq!(already.exit_code(true), 0);
+
+        let mut result = RunResult::from_outcomes(&[
+            outcome("orders", OutcomeStatus::Annotated),
+            outcome("payments", OutcomeStatus::NoMatchingWidgets),
+        ]);
+        assert_eq!(result.kind(), ResultKind::Changed);
+        assert_eq!(result.exit_code(false), 0);
+        assert_eq!(result.exit_code(true), EXIT_CHANGED);
+
+        result.targets.push(TargetResult::from(&outcome(
+            "shipping",
+            OutcomeStatus::Failed,
+        )));
+        assert_eq!(result.kind(), ResultKind::Failed);
+        assert_eq!(result.failed(), 1);
+        assert_eq!(result.exit_code(false), EXIT_FAILED);
+    }
+
+    #[test]
+    fn exit_codes_tell_nothing_matched_from_aws_errors() {
+        let no_dashboards = anyhow::Error::new(NothingMatched::Dashboards("*-prod".to_string()));
+        assert_eq!(exit_code_of(&no_dashboards), EXIT_NO_DASHBOARDS);
+        let no_widgets = anyhow::Error::new(NothingMatched::Widgets("orders".to_string()));
+        assert_eq!(exit_code_of(&no_widgets), EXIT_NO_MATCH);
+        assert_eq!(exit_code_of(&anyhow::anyhow!("bad input")), EXIT_FAILED);
+        let failed = TargetsFailed {
+            failed: 1,
+            total: 2,
+            run_id: "run".to_string(),
+            exit_code: EXIT_NO_DASHBOARDS,
+        };
+        assert_eq!(
+            exit_code_of(&anyhow::Error::new(failed)),
+            EXIT_NO_DASHBOARDS
+        );
+    }
+
+    #[test]
+    fn renders_results_as_text_and_json() {
+        let result = RunResult::from_outcomes(&[
+            outcome("orders", OutcomeStatus::Annotated),
+            outcome("payments", OutcomeStatus::IacManaged),
+        ]);
+
+        assert_eq!(
+            result.render(OutputFormat::Text, "R"),
+            "RESULT\tTARGET\tREASON\n\
+             changed\torders\tannotated 1 widget(s)\n\
+             skipped\tpayments\tmanaged by infrastructure as code"
+        );
+
+        let json = result.to_json("R");
+        assert_eq!(json["result"], "changed");
+        assert_eq!(json["targets"][1]["result"], "skipped");
+        assert_eq!(json["targets"][1]["target"], "payments");
+    }
+}
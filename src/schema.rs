@@ -0,0 +1,130 @@
+use clap::ValueEnum;
+
+/// JSON documents cwnote reads or writes that automation may want to validate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SchemaKind {
+    /// `annotate --manifest` routes file.
+    Manifest,
+    /// `cwnotePolicy` block in a dashboard body.
+    Policy,
+    /// `list --output json` / `remove --dry-run --output json`.
+    Listing,
+    /// `annotate --output json`.
+    Report,
+    /// `--result-format json`, of any subcommand.
+    Result,
+}
+
+/// JSON Schema (draft 2020-12) of `kind`.
+pub fn schema(kind: SchemaKind) -> &'static str {
+    match kind {
+        SchemaKind::Manifest => include_str!("../schemas/manifest.schema.json"),
+        SchemaKind::Policy => include_str!("../schemas/policy.schema.json"),
+        SchemaKind::Listing => include_str!("../schemas/listing.schema.json"),
+        SchemaKind::Report => include_str!("../schemas/report.schema.json"),
+        SchemaKind::Result => include_str!("../schemas/result.schema.json"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotate::{DashboardOutcome, OutcomeStatus};
+    use crate::list::{ListedAnnotation, OutputFormat};
+    use crate::provenance::Provenance;
+    use crate::remove::Direction;
+    use crate::run_result::{ResultKind, RunResult};
+    use crate::time_display::TimeDisplay;
+    use serde_json::Value;
+
+    fn properties(kind: SchemaKind, pointer: &str) -> Vec<String> {
+        let schema: Value = serde_json::from_str(schema(kind)).unwrap();
+        let mut keys: Vec<String> = schema
+            .pointer(pointer)
+            .and_then(|p| p.as_object())
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    fn keys(object: &Value) -> Vec<String> {
+        let mut keys: Vec<String> = object.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn schemas_cover_the_keys_cwnote_uses() {
+        assert_eq!(
+            properties(SchemaKind::Manifest, "/properties/routes/items/properties"),
+            vec![
+                "color",
+                "fill",
+                "metricName",
+                "metricNamespace",
+                "rowOf",
+                "widgetTitleContains"
+            ]
+        );
+        assert_eq!(
+            properties(SchemaKind::Policy, "/properties"),
+            vec![
+                "allowedLabels",
+                "maxAnnotations",
+                "protectedWidgets",
+                "pruneOldest"
+            ]
+        );
+
+        let listed = ListedAnnotation {
+            dashboard: "D".to_string(),
+            widget: "Latency".to_string(),
+            direction: Direction::Vertical,
+            label: "version: 1.9.0".to_string(),
+            value: "2025-01-20T12:00:00Z".to_string(),
+            end: None,
+            run_id: None,
+            provenance: Provenance::default(),
+        };
+        let output: Value = serde_json::from_str(&crate::list::render(
+            &[listed],
+            OutputFormat::Json,
+            TimeDisplay::Utc,
+        ))
+        .unwrap();
+        assert_eq!(
+            properties(SchemaKind::Listing, "/items/properties"),
+            keys(&output[0])
+        );
+
+        let outcome = DashboardOutcome {
+            dashboard: "D".to_string(),
+            status: OutcomeStatus::Annotated,
+            widgets_matched: 1,
+            widgets: vec!["Latency".to_string()],
+            changes: vec!["+ version: 1.9.0 at now".to_string()],
+            error: None,
+        };
+        let report: Value =
+            serde_json::from_str(&crate::report::render_json("RUN", false, None, &[outcome]))
+                .unwrap();
+        assert_eq!(properties(SchemaKind::Report, "/properties"), keys(&report));
+        assert_eq!(
+            properties(
+                SchemaKind::Report,
+                "/properties/dashboards/items/properties"
+            ),
+            keys(&report["dashboards"][0])
+        );
+
+        let result = RunResult::single("D", ResultKind::Changed, "annotated").to_json("RUN");
+        assert_eq!(properties(SchemaKind::Result, "/properties"), keys(&result));
+        assert_eq!(
+            properties(SchemaKind::Result, "/properties/targets/items/properties"),
+            keys(&result["targets"][0])
+        );
+    }
+}
@@ -0,0 +1,351 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error, Result};
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::annotate::{JSON_KEY_PROPERTIES, JSON_KEY_REGION, JSON_KEY_TITLE};
+use crate::selector;
+use crate::widget_support;
+
+const FIELDS: [&str; 6] = ["title", "type", "region", "namespace", "metric", "account"];
+
+/// Boolean widget selector, e.g.
+/// `(title ~ "Latency" or title ~ "Errors") and not region == "us-west-2"`.
+///
+/// A test is `<field> <op> <value>` with the fields `title`, `type` (widget
+/// type or metric view), `region`, `namespace`, `metric` and `account`, and
+/// the operators `==`, `!=`, `~` (contains) and `=~` (regex). Values are
+/// double-quoted strings or bare words. Tests combine with `not`, `and` and
+/// `or` (binding in that order) and parentheses.
+#[derive(Debug, Clone)]
+pub struct SelectExpr {
+    source: String,
+    root: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Or(Box<Node>, Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Not(Box<Node>),
+    Test { field: String, op: Op },
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Eq(String),
+    Ne(String),
+    Contains(String),
+    Matches(Regex),
+}
+
+impl SelectExpr {
+    /// `true` if `widget_obj` satisfies the expression.
+    pub fn is_match(&self, widget_obj: &Map<String, Value>) -> bool {
+        self.root.eval(widget_obj)
+    }
+}
+
+impl Node {
+    fn eval(&self, widget_obj: &Map<String, Value>) -> bool {
+        match self {
+            Node::Or(a, b) => a.eval(widget_obj) || b.eval(widget_obj),
+            Node::And(a, b) => a.eval(widget_obj) && b.eval(widget_obj),
+            Node::Not(a) => !a.eval(widget_obj),
+            Node::Test { field, op } => {
+                let values = field_values(widget_obj, field);
+                match op {
+                    Op::Eq(v) => values.iter().any(|value| value == v),
+                    Op::Ne(v) => !values.iter().any(|value| value == v),
+                    Op::Contains(v) => values.iter().any(|value| value.contains(v.as_str())),
+                    Op::Matches(re) => values.iter().any(|value| re.is_match(value)),
+                }
+            }
+        }
+    }
+}
+
+/// Every value of `field` on the widget; fields like `metric` can have
+/// several, and a test passes if any of them does.
+fn field_values(widget_obj: &Map<String, Value>, field: &str) -> Vec<String> {
+    let property = |key: &str| {
+        widget_obj
+            .get(JSON_KEY_PROPERTIES)
+            .and_then(|p| p.get(key))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    };
+    match field {
+        "title" => property(JSON_KEY_TITLE).into_iter().collect(),
+        "region" => property(JSON_KEY_REGION).into_iter().collect(),
+        "type" => widget_support::capability(widget_obj)
+            .map(|c| [Some(c.widget_type), c.view])
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(str::to_string)
+            .collect(),
+        "namespace" => selector::metrics(widget_obj)
+            .into_iter()
+            .map(|(ns, _)| ns)
+            .collect(),
+        "metric" => selector::metrics(widget_obj)
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect(),
+        "account" => selector::account_ids(widget_obj)
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Op(&'static str),
+    Word(String),
+    Quoted(String),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => text.push(escaped),
+                            None => return Err(anyhow!("unterminated string")),
+                        },
+                        Some(c) => text.push(c),
+                        None => return Err(anyhow!("unterminated string")),
+                    }
+                }
+                tokens.push(Token::Quoted(text));
+            }
+            '=' | '!' | '~' => {
+                chars.next();
+                let op = match (c, chars.peek()) {
+                    ('=', Some('=')) => "==",
+                    ('=', Some('~')) => "=~",
+                    ('!', Some('=')) => "!=",
+                    ('~', _) => "~",
+                    _ => return Err(anyhow!("unknown operator at '{c}'")),
+                };
+                if op.len() == 2 {
+                    chars.next();
+                }
+                tokens.push(Token::Op(op));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "()\"=!~".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the tokens: or > and > not > test.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn or(&mut self) -> Result<Node> {
+        let mut node = self.and()?;
+        while self.keyword("or") {
+            node = Node::Or(Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Result<Node> {
+        let mut node = self.not()?;
+        while self.keyword("and") {
+            node = Node::And(Box::new(node), Box::new(self.not()?));
+        }
+        Ok(node)
+    }
+
+    fn not(&mut self) -> Result<Node> {
+        if self.keyword("not") {
+            return Ok(Node::Not(Box::new(self.not()?)));
+        }
+        if self.peek() == Some(&Token::Open) {
+            self.pos += 1;
+            let node = self.or()?;
+            return match self.next() {
+                Some(Token::Close) => Ok(node),
+                _ => Err(anyhow!("missing ')'")),
+            };
+        }
+        self.test()
+    }
+
+    fn test(&mut self) -> Result<Node> {
+        let field = match self.next() {
+            Some(Token::Word(w)) if FIELDS.contains(&w.as_str()) => w,
+            Some(Token::Word(w)) | Some(Token::Quoted(w)) => {
+                return Err(anyhow!(
+                    "unknown field '{w}', expected one of: {}",
+                    FIELDS.join(", ")
+                ))
+            }
+            Some(token) => return Err(anyhow!("expected a field, found {token:?}")),
+            None => return Err(anyhow!("expected a field, found the end")),
+        };
+        let Some(Token::Op(op)) = self.next() else {
+            return Err(anyhow!("expected ==, !=, ~ or =~ after '{field}'"));
+        };
+        let value = match self.next() {
+            Some(Token::Word(v)) | Some(Token::Quoted(v)) => v,
+            _ => return Err(anyhow!("expected a value after '{field} {op}'")),
+        };
+        let op = match op {
+            "==" => Op::Eq(value),
+            "!=" => Op::Ne(value),
+            "~" => Op::Contains(value),
+            _ => Op::Matches(
+                Regex::new(&value).map_err(|err| anyhow!("invalid regex '{value}': {err}"))?,
+            ),
+        };
+        Ok(Node::Test { field, op })
+    }
+}
+
+impl FromStr for SelectExpr {
+    type Err = Error;
+
+    fn from_str(source: &str) -> Result<Self> {
+        let invalid = |err: Error| anyhow!("selector expression '{source}' is invalid: {err}");
+        let mut parser = Parser {
+            tokens: tokenize(source).map_err(invalid)?,
+            pos: 0,
+        };
+        let root = parser.or().map_err(invalid)?;
+        if let Some(token) = parser.peek() {
+            return Err(invalid(anyhow!("unexpected {token:?}")));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            root,
+        })
+    }
+}
+
+impl fmt::Display for SelectExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn widget(title: &str, region: &str) -> Map<String, Value> {
+        json!({
+            "type": "metric",
+            "properties": {
+                "title": title,
+                "region": region,
+                "metrics": [["AWS/Lambda", "Errors", "FunctionName", "api"]]
+            }
+        })
+        .as_object()
+        .unwrap()
+        .clone()
+    }
+
+    #[test]
+    fn evaluates_and_or_not_with_precedence() {
+        let expr: SelectExpr =
+            r#"(title ~ "Latency" or title ~ "Errors") and not region == "us-west-2""#
+                .parse()
+                .unwrap();
+        assert!(expr.is_match(&widget("API Latency", "us-east-1")));
+        assert!(expr.is_match(&widget("API Errors", "eu-central-1")));
+        assert!(!expr.is_match(&widget("API Errors", "us-west-2")));
+        assert!(!expr.is_match(&widget("Throughput", "us-east-1")));
+
+        // `and` binds tighter than `or`.
+        let expr: SelectExpr = "title ~ Latency or region == us-west-2 and metric == Throttles"
+            .parse()
+            .unwrap();
+        assert!(expr.is_match(&widget("Latency", "us-west-2")));
+        assert!(!expr.is_match(&widget("Errors", "us-west-2")));
+
+        let expr: SelectExpr = r#"namespace == AWS/Lambda and title =~ "^(p95|p99) ""#
+            .parse()
+            .unwrap();
+        assert!(expr.is_match(&widget("p99 latency", "us-east-1")));
+        assert!(!expr.is_match(&widget("p50 latency", "us-east-1")));
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        for bad in [
+            "",
+            "title",
+            "title ~",
+            "colour == red",
+            "(title ~ a",
+            "title ~ a)",
+            "title ~ a or",
+            "title = a",
+            r#"title ~ "open"#,
+            "title =~ (",
+        ] {
+            assert!(bad.parse::<SelectExpr>().is_err(), "{bad}");
+        }
+        let err = "colour == red".parse::<SelectExpr>().unwrap_err();
+        assert!(err.to_string().contains("unknown field 'colour'"), "{err}");
+    }
+}
@@ -0,0 +1,848 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Error, Result};
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use crate::annotate::{
+    JSON_KEY_MARKDOWN, JSON_KEY_METRICS, JSON_KEY_PROPERTIES, JSON_KEY_REGION, JSON_KEY_TITLE,
+    JSON_KEY_TYPE, WIDGET_TYPE_TEXT,
+};
+use crate::select_expr::SelectExpr;
+use crate::{title_tag, widget_support};
+
+const JSON_KEY_ACCOUNT_ID: &str = "accountId";
+const JSON_KEY_X: &str = "x";
+const JSON_KEY_Y: &str = "y";
+const JSON_KEY_HEIGHT: &str = "height";
+// CloudWatch lays out widgets with a height of 6 grid units unless told otherwise.
+const DEFAULT_WIDGET_HEIGHT: i64 = 6;
+
+/// Controlls which widgets a subcommand touches (annotate, remove, the syncs).
+#[derive(Debug, Clone, Default)]
+pub struct WidgetSelector {
+    pub title_contains: Option<String>,
+    /// Only widgets whose title matches this regex, e.g. `^(p95|p99) latency`.
+    pub title_regex: Option<Regex>,
+    /// Only widgets in the same dashboard row (y range) as the widget with this exact title.
+    pub row_of: Option<String>,
+    /// Only widgets plotting at least one metric from this namespace, e.g. `AWS/DynamoDB`.
+    pub metric_namespace: Option<String>,
+    /// Only widgets plotting a metric of this name, e.g. `Errors`; together
+    /// with `metric_namespace` the same metric must match both.
+    pub metric_name: Option<String>,
+    /// Only widgets whose `properties.region` is this region, e.g. `us-east-1`.
+    pub region: Option<String>,
+    /// Only widgets showing metrics of this AWS account, e.g. `123456789012`,
+    /// on cross-account monitoring dashboards.
+    pub account_id: Option<String>,
+    /// Widgets with one of these exact titles are never selected.
+    pub exclude_titles: Vec<String>,
+    /// Widgets whose title contains one of these substrings are never selected.
+    pub exclude_title_contains: Vec<String>,
+    /// Widgets whose title matches this regex are never selected.
+    pub exclude_title_regex: Option<Regex>,
+    /// Only widgets below the text widget (section header) containing this
+    /// string, up to the next text widget.
+    pub section: Option<String>,
+    /// Only widgets of one of these types or metric views, e.g. `gauge`.
+    pub widget_types: Vec<String>,
+    /// Only the widgets at these positions (0-based) of the dashboard's
+    /// `widgets` list, for dashboards whose widgets have no titles.
+    pub indices: Vec<usize>,
+    /// Only the widgets whose top-left corner is at one of these grid positions.
+    pub positions: Vec<GridPosition>,
+    /// Only widgets satisfying this boolean expression; the other fields
+    /// still apply on top of it.
+    pub expression: Option<SelectExpr>,
+}
+
+/// Top-left corner (`x`, `y`) of a widget on the dashboard grid, written `x,y`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridPosition {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl GridPosition {
+    /// Returns the position of a widget, or `None` if it has no explicit position.
+    pub fn of(widget_obj: &Map<String, Value>) -> Option<Self> {
+        Some(Self {
+            x: widget_obj.get(JSON_KEY_X).and_then(|x| x.as_i64())?,
+            y: widget_obj.get(JSON_KEY_Y).and_then(|y| y.as_i64())?,
+        })
+    }
+}
+
+impl FromStr for GridPosition {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("widget position '{s}' must look like x,y, e.g. 0,6");
+        let (x, y) = s.split_once(',').ok_or_else(invalid)?;
+        Ok(Self {
+            x: x.trim().parse().map_err(|_| invalid())?,
+            y: y.trim().parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl fmt::Display for GridPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
+    }
+}
+
+/// Vertical band `[top, bottom)` a widget occupies on the dashboard grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowBand {
+    pub top: i64,
+    pub bottom: i64,
+}
+
+impl RowBand {
+    /// Returns the band of a widget, or `None` if it has no explicit position.
+    pub fn of(widget_obj: &Map<String, Value>) -> Option<Self> {
+        let top = widget_obj.get(JSON_KEY_Y).and_then(|y| y.as_i64())?;
+        let height = widget_obj
+            .get(JSON_KEY_HEIGHT)
+            .and_then(|h| h.as_i64())
+            .unwrap_or(DEFAULT_WIDGET_HEIGHT);
+        Some(Self {
+            top,
+            bottom: top + height,
+        })
+    }
+}
+
+/// Rows `[top, bottom)` of a dashboard section: below its header text
+/// widget, up to the next one (or the end of the dashboard).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectionBand {
+    pub top: i64,
+    pub bottom: Option<i64>,
+}
+
+impl SectionBand {
+    pub fn contains(&self, widget_obj: &Map<String, Value>) -> bool {
+        RowBand::of(widget_obj).is_some_and(|band| {
+            band.top >= self.top && self.bottom.is_none_or(|bottom| band.top < bottom)
+        })
+    }
+}
+
+/// `(namespace, metric name)` of every metric row of a widget's
+/// `properties.metrics`. A `"."` repeats the value of the row above, as in
+/// the console's shorthand; expression rows are left out.
+pub(crate) fn metrics(widget_obj: &Map<String, Value>) -> Vec<(String, String)> {
+    let rows = widget_obj
+        .get(JSON_KEY_PROPERTIES)
+        .and_then(|p| p.get(JSON_KEY_METRICS))
+        .and_then(|m| m.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|row| row.as_array());
+
+    let mut result: Vec<(String, String)> = Vec::new();
+    for row in rows {
+        let (Some(namespace), Some(name)) = (
+            row.first().and_then(|v| v.as_str()),
+            row.get(1).and_then(|v| v.as_str()),
+        ) else {
+            continue;
+        };
+        let previous = result.last();
+        let inherit = |value: &str, from: Option<&String>| match (value, from) {
+            (".", Some(from)) => from.clone(),
+            _ => value.to_string(),
+        };
+        result.push((
+            inherit(namespace, previous.map(|p| &p.0)),
+            inherit(name, previous.map(|p| &p.1)),
+        ));
+    }
+    result
+}
+
+/// Every `accountId` a widget shows metrics of: from the rendering options
+/// object ending a metric row, or from the widget's properties.
+pub(crate) fn account_ids(widget_obj: &Map<String, Value>) -> Vec<&str> {
+    let Some(props) = widget_obj.get(JSON_KEY_PROPERTIES) else {
+        return Vec::new();
+    };
+    let rows = props
+        .get(JSON_KEY_METRICS)
+        .and_then(|m| m.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|row| row.as_array()?.last()?.get(JSON_KEY_ACCOUNT_ID));
+    props
+        .get(JSON_KEY_ACCOUNT_ID)
+        .into_iter()
+        .chain(rows)
+        .filter_map(|id| id.as_str())
+        .collect()
+}
+
+fn is_text_widget(widget_obj: &Map<String, Value>) -> bool {
+    widget_obj.get(JSON_KEY_TYPE).and_then(|t| t.as_str()) == Some(WIDGET_TYPE_TEXT)
+}
+
+impl WidgetSelector {
+    /// Returns `true` if the given widget matches the selector's criteria.
+    ///
+    /// Currently this selector supports filtering by widget title. If
+    /// `title_contains` is set, the widget's `properties.title` field must
+    /// contain the specified substring. If the widget has no title or the
+    /// substring does not match, the method returns `false`. Likewise a set
+    /// `title_regex` must match somewhere in the title.
+    ///
+    /// Widgets whose title is listed in `exclude_titles`, contains one of
+    /// `exclude_title_contains` or matches `exclude_title_regex` never match.
+    ///
+    /// If `metric_namespace` is set, one of the rows in `properties.metrics`
+    /// must start with that namespace; `metric_name` likewise checks the
+    /// metric name of the rows.
+    ///
+    /// If `region` is set, the widget's `properties.region` must be that
+    /// region; widgets without one don't match.
+    ///
+    /// If `account_id` is set, a metric row's rendering options (or the
+    /// widget's properties) must carry that `accountId`.
+    ///
+    /// If `widget_types` is set, the widget's type or metric view must be one
+    /// of them; if `positions` is set, the widget must sit at one of them.
+    /// `indices` needs the widget's place in the list, so
+    /// [`selected_widgets`] checks it.
+    ///
+    /// If `expression` is set, the widget must satisfy it too.
+    ///
+    /// If no filter is configured, all widgets are considered a match.
+    pub fn matches(&self, widget_obj: &Map<String, Value>) -> bool {
+        self.clauses(widget_obj).iter().all(|c| c.matched)
+    }
+
+    /// Every configured criterion (except the row) and whether the widget
+    /// meets it, in the order `matches` checks them.
+    fn clauses(&self, widget_obj: &Map<String, Value>) -> Vec<Clause> {
+        let title = widget_obj
+            .get(JSON_KEY_PROPERTIES)
+            .and_then(|p| p.get(JSON_KEY_TITLE))
+            .and_then(|t| t.as_str());
+        let mut clauses = Vec::new();
+
+        if !self.widget_types.is_empty() {
+            clauses.push(Clause {
+                description: format!("is one of: {}", self.widget_types.join(", ")),
+                matched: self
+                    .widget_types
+                    .iter()
+                    .any(|kind| widget_support::is_kind(widget_obj, kind)),
+            });
+        }
+        // If we have a title filter, go check it.
+        if let Some(ref title_filter) = self.title_contains {
+            clauses.push(Clause {
+                description: format!("title contains '{title_filter}'"),
+                matched: title.unwrap_or("").contains(title_filter),
+            });
+        }
+        if let Some(ref title_regex) = self.title_regex {
+            clauses.push(Clause {
+                description: format!("title matches /{title_regex}/"),
+                matched: title.is_some_and(|t| title_regex.is_match(t)),
+            });
+        }
+        if !self.exclude_titles.is_empty() {
+            clauses.push(Clause {
+                description: format!("title is not one of: {}", self.exclude_titles.join(", ")),
+                matched: !self
+                    .exclude_titles
+                    .iter()
+                    .any(|e| title_tag::has_title(widget_obj, e)),
+            });
+        }
+        if !self.exclude_title_contains.is_empty() {
+            clauses.push(Clause {
+                description: format!(
+                    "title contains none of: {}",
+                    self.exclude_title_contains.join(", ")
+                ),
+                matched: !title.is_some_and(|t| {
+                    self.exclude_title_contains
+                        .iter()
+                        .any(|e| t.contains(e.as_str()))
+                }),
+            });
+        }
+        if let Some(ref exclude_regex) = self.exclude_title_regex {
+            clauses.push(Clause {
+                description: format!("title does not match /{exclude_regex}/"),
+                matched: !title.is_some_and(|t| exclude_regex.is_match(t)),
+            });
+        }
+        if let Some(ref region) = self.region {
+            clauses.push(Clause {
+                description: format!("is in region '{region}'"),
+                matched: widget_obj
+                    .get(JSON_KEY_PROPERTIES)
+                    .and_then(|p| p.get(JSON_KEY_REGION))
+                    .and_then(|r| r.as_str())
+                    == Some(region.as_str()),
+            });
+        }
+        if let Some(ref account_id) = self.account_id {
+            clauses.push(Clause {
+                description: format!("shows metrics of account {account_id}"),
+                matched: account_ids(widget_obj).contains(&account_id.as_str()),
+            });
+        }
+        if !self.positions.is_empty() {
+            let positions: Vec<String> = self.positions.iter().map(|p| p.to_string()).collect();
+            clauses.push(Clause {
+                description: format!("is at one of: {}", positions.join(" ")),
+                matched: GridPosition::of(widget_obj).is_some_and(|p| self.positions.contains(&p)),
+            });
+        }
+        match (&self.metric_namespace, &self.metric_name) {
+            (Some(namespace), None) => clauses.push(Clause {
+                description: format!("plots a metric from namespace '{namespace}'"),
+                matched: metrics(widget_obj).iter().any(|(ns, _)| ns == namespace),
+            }),
+            (namespace, Some(name)) => clauses.push(Clause {
+                description: match namespace {
+                    Some(namespace) => {
+                        format!("plots metric '{name}' from namespace '{namespace}'")
+                    }
+                    None => format!("plots metric '{name}'"),
+                },
+                matched: metrics(widget_obj).iter().any(|(ns, metric)| {
+                    metric == name && namespace.as_ref().is_none_or(|namespace| ns == namespace)
+                }),
+            }),
+            (None, None) => {}
+        }
+        if let Some(ref expression) = self.expression {
+            clauses.push(Clause {
+                description: format!("satisfies {expression}"),
+                matched: expression.is_match(widget_obj),
+            });
+        }
+        clauses
+    }
+
+    /// `true` if the widget at `index` of the `widgets` list is picked by `indices`.
+    fn selects_index(&self, index: usize) -> bool {
+        self.indices.is_empty() || self.indices.contains(&index)
+    }
+
+    /// Resolves the `row_of` anchor against the dashboard's widgets.
+    ///
+    /// Returns `Ok(None)` when no row filter is configured and an error when
+    /// the anchor widget does not exist (or has no position), in which case
+    /// nothing on the dashboard should match.
+    pub fn row_band(&self, widgets: &[Value]) -> Result<Option<RowBand>> {
+        let Some(ref anchor_title) = self.row_of else {
+            return Ok(None);
+        };
+
+        widgets
+            .iter()
+            .filter_map(|w| w.as_object())
+            .find(|w| title_tag::has_title(w, anchor_title))
+            .and_then(RowBand::of)
+            .map(Some)
+            .with_context(|| {
+                format!("row anchor widget '{anchor_title}' not found or has no position")
+            })
+    }
+
+    /// Resolves the `section` header against the dashboard's widgets.
+    ///
+    /// Like [`WidgetSelector::row_band`], `Ok(None)` means no section filter
+    /// and an error means the header is missing (so nothing should match).
+    pub fn section_band(&self, widgets: &[Value]) -> Result<Option<SectionBand>> {
+        let Some(ref heading) = self.section else {
+            return Ok(None);
+        };
+        let headers: Vec<(&Map<String, Value>, RowBand)> = widgets
+            .iter()
+            .filter_map(|w| w.as_object())
+            .filter(|w| is_text_widget(w))
+            .filter_map(|w| Some((w, RowBand::of(w)?)))
+            .collect();
+
+        let header = headers
+            .iter()
+            .find(|(w, _)| {
+                w.get(JSON_KEY_PROPERTIES)
+                    .and_then(|p| p.get(JSON_KEY_MARKDOWN))
+                    .and_then(|m| m.as_str())
+                    .is_some_and(|m| m.contains(heading.as_str()))
+            })
+            .map(|(_, band)| *band)
+            .with_context(|| format!("section header text widget '{heading}' not found"))?;
+        let bottom = headers
+            .iter()
+            .map(|(_, band)| band.top)
+            .filter(|&top| top >= header.bottom)
+            .min();
+
+        Ok(Some(SectionBand {
+            top: header.bottom,
+            bottom,
+        }))
+    }
+}
+
+/// One criterion of a selector and whether a widget meets it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clause {
+    pub description: String,
+    pub matched: bool,
+}
+
+/// Why a widget is (or isn't) picked by a selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub index: usize,
+    pub title: String,
+    pub clauses: Vec<Clause>,
+}
+
+impl Explanation {
+    pub fn selected(&self) -> bool {
+        self.clauses.iter().all(|c| c.matched)
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.selected() {
+            "selected"
+        } else {
+            "skipped"
+        };
+        write!(f, "#{} '{}': {verdict}", self.index, self.title)?;
+        for clause in &self.clauses {
+            let mark = if clause.matched { "ok  " } else { "FAIL" };
+            write!(f, "\n  {mark} {}", clause.description)?;
+        }
+        Ok(())
+    }
+}
+
+/// Evaluate `selector` clause by clause against every widget, e.g. to learn
+/// the selector flags without touching a dashboard.
+pub fn explain(widgets: &[Value], selector: &WidgetSelector) -> Vec<Explanation> {
+    let row_band = selector.row_band(widgets);
+    let section_band = selector.section_band(widgets);
+
+    widgets
+        .iter()
+        .enumerate()
+        .map(|(index, widget)| {
+            let empty = Map::new();
+            let widget_obj = widget.as_object().unwrap_or(&empty);
+            let mut clauses = vec![Clause {
+                description: format!(
+                    "is a widget that shows annotations ({})",
+                    widget_support::describe(widget_obj)
+                ),
+                matched: widget_support::carries_annotations(widget_obj),
+            }];
+            if !selector.indices.is_empty() {
+                let indices: Vec<String> = selector.indices.iter().map(|i| i.to_string()).collect();
+                clauses.push(Clause {
+                    description: format!("is widget #{}", indices.join(", #")),
+                    matched: selector.selects_index(index),
+                });
+            }
+            clauses.extend(selector.clauses(widget_obj));
+            if let Some(ref anchor) = selector.row_of {
+                clauses.push(match row_band {
+                    Ok(ref band) => Clause {
+                        description: format!("in the row of '{anchor}'"),
+                        matched: RowBand::of(widget_obj) == *band,
+                    },
+                    Err(ref err) => Clause {
+                        description: format!("in the row of '{anchor}' ({err})"),
+                        matched: false,
+                    },
+                });
+            }
+            if let Some(ref heading) = selector.section {
+                clauses.push(match section_band {
+                    Ok(ref band) => Clause {
+                        description: format!("in section '{heading}'"),
+                        matched: band.is_some_and(|b| b.contains(widget_obj)),
+                    },
+                    Err(ref err) => Clause {
+                        description: format!("in section '{heading}' ({err})"),
+                        matched: false,
+                    },
+                });
+            }
+
+            Explanation {
+                index,
+                title: widget_obj
+                    .get(JSON_KEY_PROPERTIES)
+                    .and_then(|p| p.get(JSON_KEY_TITLE))
+                    .and_then(|t| t.as_str())
+                    .unwrap_or("<untitled>")
+                    .to_string(),
+                clauses,
+            }
+        })
+        .collect()
+}
+
+/// Indices of the metric widgets picked by `selector`.
+pub fn selected_widgets(widgets: &[Value], selector: &WidgetSelector) -> Result<Vec<usize>> {
+    let row_band = selector.row_band(widgets)?;
+    let section_band = selector.section_band(widgets)?;
+
+    Ok(widgets
+        .iter()
+        .enumerate()
+        .filter_map(|(i, widget)| widget.as_object().map(|obj| (i, obj)))
+        // Apply index filter (place in the widgets list).
+        .filter(|(i, _)| selector.selects_index(*i))
+        // Only widgets that can show annotations at all.
+        .filter(|(_, widget_obj)| widget_support::carries_annotations(widget_obj))
+        // Apply selector (e.g. title contains substring).
+        .filter(|(_, widget_obj)| selector.matches(widget_obj))
+        // Apply row filter (same y range as the anchor widget).
+        .filter(|(_, widget_obj)| row_band.is_none() || RowBand::of(widget_obj) == row_band)
+        // Apply section filter (between the header and the next text widget).
+        .filter(|(_, widget_obj)| section_band.is_none_or(|band| band.contains(widget_obj)))
+        .map(|(i, _)| i)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn widget_selector_matches_without_filter() {
+        let selector = WidgetSelector {
+            title_contains: None,
+            ..Default::default()
+        };
+
+        // Widget without title, but since no filter, it should match.
+        let widget = json!({
+            "type": "metric",
+            "properties": {
+                "metrics": []
+            }
+        });
+
+        let widget_obj = widget.as_object().unwrap();
+        assert!(selector.matches(widget_obj));
+    }
+
+    #[test]
+    fn widget_selector_matches_when_title_contains_substring() {
+        let selector = WidgetSelector {
+            title_contains: Some("Latency".to_string()),
+            ..Default::default()
+        };
+
+        let widget = json!({
+            "type": "metric",
+            "properties": {
+                "title": "Overall Latency P95",
+                "metrics": []
+            }
+        });
+
+        let widget_obj = widget.as_object().unwrap();
+        assert!(selector.matches(widget_obj));
+    }
+
+    #[test]
+    fn widget_selector_does_not_match_when_title_does_not_contain_substring() {
+        let selector = WidgetSelector {
+            title_contains: Some("Latency".to_string()),
+            ..Default::default()
+        };
+
+        let widget = json!({
+            "type": "metric",
+            "properties": {
+                "title": "Error Rate",
+                "metrics": []
+            }
+        });
+
+        let widget_obj = widget.as_object().unwrap();
+        assert!(!selector.matches(widget_obj));
+    }
+
+    #[test]
+    fn widget_selector_matches_title_regex() {
+        let selector = WidgetSelector {
+            title_regex: Some(Regex::new("^(p95|p99) latency").unwrap()),
+            ..Default::default()
+        };
+        let titled = |title: &str| json!({ "type": "metric", "properties": { "title": title } });
+
+        assert!(selector.matches(titled("p99 latency (ms)").as_object().unwrap()));
+        assert!(!selector.matches(titled("Checkout p99 latency").as_object().unwrap()));
+        assert!(!selector.matches(titled("p50 latency").as_object().unwrap()));
+        assert!(!selector.matches(json!({ "type": "metric" }).as_object().unwrap()));
+    }
+
+    #[test]
+    fn widget_selector_skips_excluded_titles() {
+        let selector = WidgetSelector {
+            exclude_title_contains: vec!["Billing".to_string(), "Quota".to_string()],
+            exclude_title_regex: Some(Regex::new("(?i)^test ").unwrap()),
+            ..Default::default()
+        };
+        let titled = |title: &str| json!({ "type": "metric", "properties": { "title": title } });
+
+        assert!(selector.matches(titled("API Latency").as_object().unwrap()));
+        assert!(!selector.matches(titled("Monthly Billing").as_object().unwrap()));
+        assert!(!selector.matches(titled("Lambda Quota usage").as_object().unwrap()));
+        assert!(!selector.matches(titled("TEST widget").as_object().unwrap()));
+        // Untitled widgets contain nothing to exclude.
+        assert!(selector.matches(json!({ "type": "metric" }).as_object().unwrap()));
+    }
+
+    #[test]
+    fn widget_selector_matches_widget_types() {
+        let selector = WidgetSelector {
+            widget_types: vec!["gauge".to_string(), "bar".to_string()],
+            ..Default::default()
+        };
+        let viewed = |view: &str| json!({ "type": "metric", "properties": { "view": view } });
+
+        assert!(selector.matches(viewed("gauge").as_object().unwrap()));
+        assert!(selector.matches(viewed("bar").as_object().unwrap()));
+        assert!(!selector.matches(viewed("timeSeries").as_object().unwrap()));
+        assert!(!selector.matches(json!({ "type": "metric" }).as_object().unwrap()));
+    }
+
+    #[test]
+    fn selects_untitled_widgets_by_index_or_position() {
+        let widgets = vec![
+            json!({ "type": "metric", "x": 0, "y": 0, "properties": {} }),
+            json!({ "type": "metric", "x": 12, "y": 0, "properties": {} }),
+            json!({ "type": "metric", "x": 0, "y": 6, "properties": {} }),
+        ];
+        let by_index = WidgetSelector {
+            indices: vec![0, 2, 7],
+            ..Default::default()
+        };
+        assert_eq!(selected_widgets(&widgets, &by_index).unwrap(), vec![0, 2]);
+
+        let by_position = WidgetSelector {
+            positions: vec!["12,0".parse().unwrap(), " 0, 6".parse().unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(
+            selected_widgets(&widgets, &by_position).unwrap(),
+            vec![1, 2]
+        );
+        assert!(explain(&widgets, &by_position)[0]
+            .to_string()
+            .contains("FAIL is at one of: 12,0 0,6"));
+
+        assert!("12".parse::<GridPosition>().is_err());
+        assert!("a,b".parse::<GridPosition>().is_err());
+    }
+
+    #[test]
+    fn widget_selector_matches_metric_namespace() {
+        let selector = WidgetSelector {
+            metric_namespace: Some("AWS/DynamoDB".to_string()),
+            ..Default::default()
+        };
+
+        let dynamo = json!({
+            "type": "metric",
+            "properties": {
+                "metrics": [
+                    ["AWS/Lambda", "Errors", "FunctionName", "api"],
+                    ["AWS/DynamoDB", "ThrottledRequests", "TableName", "orders"]
+                ]
+            }
+        });
+        let lambda = json!({
+            "type": "metric",
+            "properties": { "metrics": [["AWS/Lambda", "Errors"]] }
+        });
+
+        assert!(selector.matches(dynamo.as_object().unwrap()));
+        assert!(!selector.matches(lambda.as_object().unwrap()));
+    }
+
+    #[test]
+    fn widget_selector_matches_metric_name_within_namespace() {
+        let lambda_errors = json!({
+            "type": "metric",
+            "properties": {
+                "metrics": [
+                    ["AWS/Lambda", "Duration", "FunctionName", "api"],
+                    [".", "Errors", ".", "."],
+                    [{ "expression": "m1 * 100", "id": "e1" }]
+                ]
+            }
+        });
+        let dynamo_errors = json!({
+            "type": "metric",
+            "properties": { "metrics": [["AWS/DynamoDB", "SystemErrors"], ["Custom", "Errors"]] }
+        });
+        let selector = |namespace: Option<&str>| WidgetSelector {
+            metric_namespace: namespace.map(str::to_string),
+            metric_name: Some("Errors".to_string()),
+            ..Default::default()
+        };
+
+        assert!(selector(Some("AWS/Lambda")).matches(lambda_errors.as_object().unwrap()));
+        assert!(!selector(Some("AWS/Lambda")).matches(dynamo_errors.as_object().unwrap()));
+        // "SystemErrors" is not "Errors", and Custom/Errors is another namespace.
+        assert!(!selector(Some("AWS/DynamoDB")).matches(dynamo_errors.as_object().unwrap()));
+        assert!(selector(None).matches(dynamo_errors.as_object().unwrap()));
+    }
+
+    #[test]
+    fn widget_selector_matches_widget_region() {
+        let selector = WidgetSelector {
+            region: Some("us-east-1".to_string()),
+            ..Default::default()
+        };
+        let in_region =
+            |region: &str| json!({ "type": "metric", "properties": { "region": region } });
+
+        assert!(selector.matches(in_region("us-east-1").as_object().unwrap()));
+        assert!(!selector.matches(in_region("eu-central-1").as_object().unwrap()));
+        assert!(!selector.matches(
+            json!({ "type": "metric", "properties": {} })
+                .as_object()
+                .unwrap()
+        ));
+    }
+
+    #[test]
+    fn widget_selector_matches_account_id() {
+        let selector = WidgetSelector {
+            account_id: Some("123456789012".to_string()),
+            ..Default::default()
+        };
+        let cross_account = json!({
+            "type": "metric",
+            "properties": {
+                "metrics": [
+                    ["AWS/Lambda", "Errors", "FunctionName", "api", { "accountId": "210987654321" }],
+                    [".", ".", ".", ".", { "accountId": "123456789012", "label": "prod" }]
+                ]
+            }
+        });
+        let other_account = json!({
+            "type": "metric",
+            "properties": {
+                "accountId": "210987654321",
+                "metrics": [["AWS/Lambda", "Errors"]]
+            }
+        });
+
+        assert!(selector.matches(cross_account.as_object().unwrap()));
+        assert!(!selector.matches(other_account.as_object().unwrap()));
+        assert!(!selector.matches(
+            json!({ "type": "metric", "properties": { "metrics": [["AWS/Lambda", "Errors"]] } })
+                .as_object()
+                .unwrap()
+        ));
+    }
+
+    #[test]
+    fn explain_reports_each_clause_and_agrees_with_selection() {
+        let widgets = vec![
+            json!({ "type": "metric", "y": 0, "properties": { "title": "API Latency", "metrics": [["AWS/Lambda", "Duration"]] } }),
+            json!({ "type": "metric", "y": 0, "properties": { "title": "API Errors", "metrics": [["AWS/DynamoDB", "ThrottledRequests"]] } }),
+            json!({ "type": "metric", "y": 6, "properties": { "title": "DB Latency", "metrics": [["AWS/Lambda", "Duration"]] } }),
+            json!({ "type": "text", "y": 0, "properties": { "markdown": "# Hi" } }),
+        ];
+        let selector = WidgetSelector {
+            row_of: Some("API Latency".to_string()),
+            metric_namespace: Some("AWS/Lambda".to_string()),
+            ..Default::default()
+        };
+
+        let explained = explain(&widgets, &selector);
+        let picked: Vec<usize> = explained
+            .iter()
+            .filter(|e| e.selected())
+            .map(|e| e.index)
+            .collect();
+        assert_eq!(picked, selected_widgets(&widgets, &selector).unwrap());
+        assert_eq!(picked, vec![0]);
+
+        let failed = |i: usize| -> Vec<&str> {
+            explained[i]
+                .clauses
+                .iter()
+                .filter(|c| !c.matched)
+                .map(|c| c.description.as_str())
+                .collect()
+        };
+        assert_eq!(
+            failed(1),
+            vec!["plots a metric from namespace 'AWS/Lambda'"]
+        );
+        assert_eq!(failed(2), vec!["in the row of 'API Latency'"]);
+        assert_eq!(explained[3].title, "<untitled>");
+        assert!(explained[0]
+            .to_string()
+            .starts_with("#0 'API Latency': selected"));
+
+        let missing_anchor = WidgetSelector {
+            row_of: Some("Nope".to_string()),
+            ..Default::default()
+        };
+        assert!(explain(&widgets, &missing_anchor)
+            .iter()
+            .all(|e| !e.selected()));
+    }
+
+    #[test]
+    fn section_runs_from_its_header_to_the_next_text_widget() {
+        let widgets = vec![
+            json!({ "type": "text", "y": 0, "height": 1, "properties": { "markdown": "# Checkout" } }),
+            json!({ "type": "metric", "y": 1, "properties": { "title": "Checkout Latency" } }),
+            json!({ "type": "metric", "y": 7, "properties": { "title": "Checkout Errors" } }),
+            json!({ "type": "text", "y": 13, "height": 1, "properties": { "markdown": "# Search" } }),
+            json!({ "type": "metric", "y": 14, "properties": { "title": "Search Latency" } }),
+        ];
+        let section = |heading: &str| WidgetSelector {
+            section: Some(heading.to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            selected_widgets(&widgets, &section("Checkout")).unwrap(),
+            vec![1, 2]
+        );
+        // The last section runs to the end of the dashboard.
+        assert_eq!(
+            selected_widgets(&widgets, &section("Search")).unwrap(),
+            vec![4]
+        );
+        assert!(selected_widgets(&widgets, &section("Payments")).is_err());
+
+        let explained = explain(&widgets, &section("Checkout"));
+        assert!(explained[2].selected());
+        assert!(!explained[4].selected());
+        assert_eq!(
+            explained[4].clauses.last().unwrap().description,
+            "in section 'Checkout'"
+        );
+    }
+}
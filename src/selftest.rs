@@ -0,0 +1,227 @@
+use anyhow::{anyhow, Context, Result};
+use aws_sdk_cloudwatch::error::ProvideErrorMetadata;
+use aws_sdk_cloudwatch::Client;
+use log::{error, info};
+use serde_json::{json, Value};
+
+use crate::annotate::{self, AnnotateOptions, AnnotationSpec, OutcomeStatus};
+use crate::list::{self, ListFilter, ListedAnnotation};
+use crate::pacing::Pacer;
+use crate::remove::{self, RemoveFilter};
+use crate::selector::WidgetSelector;
+
+pub const DEFAULT_DASHBOARD: &str = "cwnote-selftest";
+pub const LABEL: &str = "cwnote-selftest";
+
+// Error code of GetDashboard for a dashboard that doesn't exist.
+const NOT_FOUND_CODE: &str = "ResourceNotFound";
+
+/// Body of the sandbox dashboard `--create` puts: one metric widget to
+/// annotate. The metric is CloudWatch's own API usage, present in every
+/// account.
+pub fn sandbox_body(region: &str) -> Value {
+    json!({
+        "widgets": [{
+            "type": "metric",
+            "x": 0,
+            "y": 0,
+            "width": 12,
+            "height": 6,
+            "properties": {
+                "title": "cwnote selftest",
+                "region": region,
+                "view": "timeSeries",
+                "stat": "Sum",
+                "period": 300,
+                "metrics": [["AWS/Usage", "CallCount", "Type", "API", "Resource", "PutDashboard", "Service", "CloudWatch", "Class", "None"]]
+            }
+        }]
+    })
+}
+
+/// Result of one selftest step.
+#[derive(Debug)]
+pub struct Step {
+    pub name: &'static str,
+    pub result: Result<String>,
+}
+
+/// Every step in order; steps after a failure are not run.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub steps: Vec<Step>,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(|step| step.result.is_ok())
+    }
+
+    /// Run `step` and record its result, unless an earlier step failed.
+    async fn run(
+        &mut self,
+        name: &'static str,
+        step: impl std::future::Future<Output = Result<String>>,
+    ) -> bool {
+        if !self.passed() {
+            return false;
+        }
+        let result = step.await;
+        match result {
+            Ok(ref detail) => info!("PASS {name}: {detail}"),
+            Err(ref err) => error!("FAIL {name}: {err:#}"),
+        }
+        self.steps.push(Step { name, result });
+        self.passed()
+    }
+}
+
+/// Annotate the sandbox `dashboard`, read the annotation back, remove it and
+/// check it's gone, as run `run_id`. With `create`, a missing dashboard is
+/// created first (and left in place for the next selftest).
+pub async fn run(client: &Client, dashboard: &str, create: bool, run_id: &str) -> Report {
+    let mut report = Report::default();
+    let selector = WidgetSelector::default();
+
+    report
+        .run("dashboard", ensure_dashboard(client, dashboard, create))
+        .await;
+
+    let spec = AnnotationSpec {
+        label: LABEL.to_string(),
+        value: run_id.to_string(),
+        time: None,
+        end_time: None,
+        run_id: run_id.to_string(),
+    };
+    let options = AnnotateOptions::default();
+    report
+        .run("annotate", async {
+            let outcome = annotate::annotate_single_dashboard(
+                client,
+                &mut Pacer::new(options.retry),
+                dashboard,
+                std::slice::from_ref(&spec),
+                &options,
+                &selector,
+            )
+            .await?;
+            match outcome.status {
+                OutcomeStatus::Annotated => {
+                    Ok(format!("annotated {} widget(s)", outcome.widgets.len()))
+                }
+                _ => Err(anyhow!("nothing was annotated: {}", outcome.summary())),
+            }
+        })
+        .await;
+
+    report
+        .run("verify", async {
+            match own_annotations(client, dashboard, run_id, &selector)
+                .await?
+                .len()
+            {
+                0 => Err(anyhow!("the annotation is not on the dashboard")),
+                n => Ok(format!("found {n} annotation(s) of run {run_id}")),
+            }
+        })
+        .await;
+
+    let filter = RemoveFilter {
+        run_id: Some(run_id.to_string()),
+        ..RemoveFilter::default()
+    };
+    report
+        .run("remove", async {
+            let removed =
+                remove::remove_from_dashboard(client, dashboard, &filter, &selector, false).await?;
+            Ok(format!("removed {} annotation(s)", removed.len()))
+        })
+        .await;
+
+    report
+        .run("verify removal", async {
+            match own_annotations(client, dashboard, run_id, &selector)
+                .await?
+                .len()
+            {
+                0 => Ok("no annotations of this run left".to_string()),
+                n => Err(anyhow!("{n} annotation(s) of run {run_id} are still there")),
+            }
+        })
+        .await;
+
+    report
+}
+
+async fn ensure_dashboard(client: &Client, dashboard: &str, create: bool) -> Result<String> {
+    match client
+        .get_dashboard()
+        .dashboard_name(dashboard)
+        .send()
+        .await
+    {
+        Ok(_) => Ok(format!("'{dashboard}' exists")),
+        Err(err) if err.code() == Some(NOT_FOUND_CODE) => {
+            if !create {
+                return Err(anyhow!(
+                    "dashboard '{dashboard}' doesn't exist (add --create to create it)"
+                ));
+            }
+            let region = client
+                .config()
+                .region()
+                .map(|r| r.to_string())
+                .context("no AWS region configured")?;
+            client
+                .put_dashboard()
+                .dashboard_name(dashboard)
+                .dashboard_body(sandbox_body(&region).to_string())
+                .send()
+                .await
+                .with_context(|| format!("failed to create dashboard {dashboard}"))?;
+            Ok(format!("created '{dashboard}'"))
+        }
+        Err(err) => Err(err).with_context(|| format!("failed to get dashboard {dashboard}")),
+    }
+}
+
+async fn own_annotations(
+    client: &Client,
+    dashboard: &str,
+    run_id: &str,
+    selector: &WidgetSelector,
+) -> Result<Vec<ListedAnnotation>> {
+    let listed = list::list_dashboard(client, dashboard, &ListFilter::default(), selector).await?;
+    Ok(listed
+        .into_iter()
+        .filter(|ann| ann.run_id.as_deref() == Some(run_id))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selector::selected_widgets;
+
+    #[test]
+    fn sandbox_dashboard_has_a_widget_to_annotate() {
+        let body = sandbox_body("eu-central-1");
+        let widgets = body["widgets"].as_array().unwrap();
+        assert_eq!(
+            selected_widgets(widgets, &WidgetSelector::default()).unwrap(),
+            [0]
+        );
+        assert_eq!(widgets[0]["properties"]["region"], "eu-central-1");
+    }
+
+    #[tokio::test]
+    async fn steps_after_a_failure_are_skipped() {
+        let mut report = Report::default();
+        assert!(report.run("first", async { Ok("ok".to_string()) }).await);
+        assert!(!report.run("second", async { Err(anyhow!("denied")) }).await);
+        assert!(!report.run("third", async { Ok("ok".to_string()) }).await);
+        assert_eq!(report.steps.len(), 2);
+        assert!(!report.passed());
+    }
+}
@@ -0,0 +1,207 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{Map, Value};
+
+use crate::selector::WidgetSelector;
+
+const JSON_KEY_SERVICES: &str = "services";
+const JSON_KEY_NAME: &str = "name";
+const JSON_KEY_DASHBOARDS: &str = "dashboards";
+const JSON_KEY_DASHBOARD_SUFFIX: &str = "dashboardSuffix";
+const JSON_KEY_LABEL: &str = "label";
+const JSON_KEY_WIDGET_TITLE_CONTAINS: &str = "widgetTitleContains";
+const JSON_KEY_ROW_OF: &str = "rowOf";
+const JSON_KEY_METRIC_NAMESPACE: &str = "metricNamespace";
+const JSON_KEY_METRIC_NAME: &str = "metricName";
+const JSON_KEY_SECTION: &str = "section";
+
+/// Which dashboards of a service to annotate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceDashboards {
+    Names(Vec<String>),
+    Suffix(String),
+}
+
+/// One service of a monorepo, annotated with its own dashboards, widgets
+/// and label.
+#[derive(Debug, Clone)]
+pub struct Service {
+    pub name: String,
+    pub dashboards: ServiceDashboards,
+    /// Overrides `--label` for this service.
+    pub label: Option<String>,
+    pub selector: WidgetSelector,
+}
+
+/// Read the services of a services file, e.g.
+///
+/// ```json
+/// { "services": [
+///     { "name": "payments", "dashboards": ["payments-prod"], "label": "payments" },
+///     { "name": "checkout", "dashboardSuffix": "checkout-", "widgetTitleContains": "Latency" }
+/// ] }
+/// ```
+pub fn load_services(path: &Path) -> Result<Vec<Service>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read services file {}", path.display()))?;
+    let file: Value = serde_json::from_str(&text)
+        .with_context(|| format!("services file {} is not valid JSON", path.display()))?;
+
+    parse_services(&file).with_context(|| format!("invalid services file {}", path.display()))
+}
+
+/// Services of an already parsed services file, in file order.
+pub fn parse_services(file: &Value) -> Result<Vec<Service>> {
+    let services = file
+        .get(JSON_KEY_SERVICES)
+        .and_then(|s| s.as_array())
+        .with_context(|| format!("'{JSON_KEY_SERVICES}' must be a list"))?;
+    if services.is_empty() {
+        return Err(anyhow!("'{JSON_KEY_SERVICES}' needs at least one service"));
+    }
+
+    let services = services
+        .iter()
+        .enumerate()
+        .map(|(i, service)| {
+            let service = service
+                .as_object()
+                .with_context(|| format!("service {i} must be an object"))?;
+            parse_service(service).with_context(|| format!("service {i}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    for (i, service) in services.iter().enumerate() {
+        if services[..i].iter().any(|s| s.name == service.name) {
+            return Err(anyhow!("service '{}' is listed twice", service.name));
+        }
+    }
+    Ok(services)
+}
+
+fn parse_service(service: &Map<String, Value>) -> Result<Service> {
+    let text = |key: &str| -> Result<Option<String>> {
+        match service.get(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s.clone())),
+            Some(_) => Err(anyhow!("'{key}' must be a string")),
+        }
+    };
+
+    let name = text(JSON_KEY_NAME)?
+        .filter(|n| !n.is_empty())
+        .with_context(|| format!("'{JSON_KEY_NAME}' is required"))?;
+    let dashboards = match (service.get(JSON_KEY_DASHBOARDS), text(JSON_KEY_DASHBOARD_SUFFIX)?) {
+        (Some(list), None) => {
+            let names = list
+                .as_array()
+                .and_then(|l| {
+                    l.iter()
+                        .map(|n| n.as_str().map(str::to_string))
+                        .collect::<Option<Vec<_>>>()
+                })
+                .with_context(|| format!("'{JSON_KEY_DASHBOARDS}' must be a list of names"))?;
+            if names.is_empty() {
+                return Err(anyhow!("'{JSON_KEY_DASHBOARDS}' needs at least one name"));
+            }
+            ServiceDashboards::Names(names)
+        }
+        (None, Some(suffix)) => ServiceDashboards::Suffix(suffix),
+        _ => {
+            return Err(anyhow!(
+                "service '{name}' needs either '{JSON_KEY_DASHBOARDS}' or '{JSON_KEY_DASHBOARD_SUFFIX}'"
+            ))
+        }
+    };
+
+    Ok(Service {
+        name,
+        dashboards,
+        label: text(JSON_KEY_LABEL)?,
+        selector: WidgetSelector {
+            title_contains: text(JSON_KEY_WIDGET_TITLE_CONTAINS)?,
+            row_of: text(JSON_KEY_ROW_OF)?,
+            metric_namespace: text(JSON_KEY_METRIC_NAMESPACE)?,
+            metric_name: text(JSON_KEY_METRIC_NAME)?,
+            section: text(JSON_KEY_SECTION)?,
+            ..Default::default()
+        },
+    })
+}
+
+/// The services named in `only` (all of them if empty), in file order.
+pub fn select(services: Vec<Service>, only: &[String]) -> Result<Vec<Service>> {
+    if let Some(unknown) = only
+        .iter()
+        .find(|name| !services.iter().any(|s| &s.name == *name))
+    {
+        let known: Vec<_> = services.iter().map(|s| s.name.as_str()).collect();
+        return Err(anyhow!(
+            "unknown service '{unknown}' in --only (known: {})",
+            known.join(", ")
+        ));
+    }
+    Ok(services
+        .into_iter()
+        .filter(|s| only.is_empty() || only.contains(&s.name))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_services_and_selects_by_name() {
+        let file = json!({
+            "services": [
+                { "name": "payments", "dashboards": ["payments-prod", "payments-db"], "label": "payments" },
+                { "name": "checkout", "dashboardSuffix": "checkout-", "widgetTitleContains": "Latency" },
+                { "name": "search", "dashboards": ["search"] }
+            ]
+        });
+
+        let services = parse_services(&file).unwrap();
+        assert_eq!(services.len(), 3);
+        assert_eq!(
+            services[0].dashboards,
+            ServiceDashboards::Names(vec!["payments-prod".to_string(), "payments-db".to_string()])
+        );
+        assert_eq!(services[0].label.as_deref(), Some("payments"));
+        assert_eq!(
+            services[1].dashboards,
+            ServiceDashboards::Suffix("checkout-".to_string())
+        );
+        assert_eq!(
+            services[1].selector.title_contains.as_deref(),
+            Some("Latency")
+        );
+
+        let only = vec!["checkout".to_string(), "payments".to_string()];
+        let selected = select(services.clone(), &only).unwrap();
+        let names: Vec<_> = selected.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["payments", "checkout"]);
+        assert_eq!(select(services.clone(), &[]).unwrap().len(), 3);
+        assert!(select(services, &["billing".to_string()]).is_err());
+    }
+
+    #[test]
+    fn rejects_services_without_or_with_both_targets() {
+        for service in [
+            json!({ "name": "payments" }),
+            json!({ "name": "payments", "dashboards": ["a"], "dashboardSuffix": "b" }),
+            json!({ "name": "payments", "dashboards": [] }),
+            json!({ "dashboards": ["a"] }),
+        ] {
+            assert!(parse_services(&json!({ "services": [service] })).is_err());
+        }
+        let twice = json!({ "services": [
+            { "name": "payments", "dashboards": ["a"] },
+            { "name": "payments", "dashboards": ["b"] }
+        ] });
+        assert!(parse_services(&twice).is_err());
+    }
+}
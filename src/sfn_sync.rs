@@ -0,0 +1,130 @@
+use anyhow::{Context, Result};
+use aws_sdk_sfn::Client;
+use chrono::{DateTime, Duration, Utc};
+use log::info;
+
+use crate::annotate::AnnotationSpec;
+use crate::aws_client;
+
+/// One Step Functions execution, reduced to what an annotation needs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Execution {
+    pub name: String,
+    pub status: String,
+    pub started_at: DateTime<Utc>,
+    /// `None` while the execution is still running.
+    pub stopped_at: Option<DateTime<Utc>>,
+}
+
+impl Execution {
+    /// Band annotation covering the execution window, e.g. `batch: nightly-2025-01-20 (SUCCEEDED)`.
+    ///
+    /// Executions that are still running get a single line at their start.
+    pub fn to_spec(&self, label: &str, run_id: &str) -> AnnotationSpec {
+        AnnotationSpec {
+            label: label.to_string(),
+            value: format!("{} ({})", self.name, self.status),
+            time: Some(self.started_at.to_rfc3339()),
+            end_time: self.stopped_at.map(|t| t.to_rfc3339()),
+            run_id: run_id.to_string(),
+        }
+    }
+}
+
+/// List executions of a state machine started within `since`, optionally
+/// restricted to executions whose name contains `name_contains`.
+pub async fn list_executions(
+    client: &Client,
+    state_machine_arn: &str,
+    name_contains: Option<&str>,
+    since: Duration,
+) -> Result<Vec<Execution>> {
+    let cutoff = Utc::now() - since;
+    let mut result = Vec::new();
+    let mut next_token: Option<String> = None;
+
+    'pages: loop {
+        let mut req = client
+            .list_executions()
+            .state_machine_arn(state_machine_arn);
+        if let Some(ref token) = next_token {
+            req = req.next_token(token);
+        }
+
+        let resp = req
+            .send()
+            .await
+            .with_context(|| format!("failed to list executions of {state_machine_arn}"))?;
+
+        // Executions are listed newest first, so stop paging at the cutoff.
+        for item in resp.executions() {
+            let Some(started_at) = aws_client::to_chrono(item.start_date()) else {
+                continue;
+            };
+            if started_at < cutoff {
+                break 'pages;
+            }
+            if let Some(filter) = name_contains {
+                if !item.name().contains(filter) {
+                    continue;
+                }
+            }
+            result.push(Execution {
+                name: item.name().to_string(),
+                status: item.status().as_str().to_string(),
+                started_at,
+                stopped_at: item.stop_date().and_then(aws_client::to_chrono),
+            });
+        }
+
+        match resp.next_token() {
+            Some(t) if !t.is_empty() => {
+                next_token = Some(t.to_string());
+            }
+            _ => break,
+        }
+    }
+
+    info!(
+        "Found {} execution(s) of {}",
+        result.len(),
+        state_machine_arn
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const TEST_RUN_ID: &str = "20250120T120000Z-aaaaaaaa";
+
+    #[test]
+    fn finished_execution_becomes_band() {
+        let execution = Execution {
+            name: "nightly-2025-01-20".to_string(),
+            status: "SUCCEEDED".to_string(),
+            started_at: Utc.with_ymd_and_hms(2025, 1, 20, 1, 0, 0).unwrap(),
+            stopped_at: Some(Utc.with_ymd_and_hms(2025, 1, 20, 2, 30, 0).unwrap()),
+        };
+
+        let spec = execution.to_spec("batch", TEST_RUN_ID);
+        assert_eq!(spec.value, "nightly-2025-01-20 (SUCCEEDED)");
+        assert_eq!(spec.time.as_deref(), Some("2025-01-20T01:00:00+00:00"));
+        assert_eq!(spec.end_time.as_deref(), Some("2025-01-20T02:30:00+00:00"));
+    }
+
+    #[test]
+    fn running_execution_becomes_line() {
+        let execution = Execution {
+            name: "nightly-2025-01-21".to_string(),
+            status: "RUNNING".to_string(),
+            started_at: Utc.with_ymd_and_hms(2025, 1, 21, 1, 0, 0).unwrap(),
+            stopped_at: None,
+        };
+
+        let spec = execution.to_spec("batch", TEST_RUN_ID);
+        assert!(spec.end_time.is_none());
+    }
+}
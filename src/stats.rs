@@ -0,0 +1,112 @@
+use serde_json::{json, Value};
+
+use crate::list::{ListedAnnotation, OutputFormat};
+use crate::remove::Direction;
+
+/// How many vertical annotations one widget carries, against the budget
+/// past which its markers stop being readable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WidgetStats {
+    pub dashboard: String,
+    pub widget: String,
+    pub annotations: usize,
+    /// At or over the annotation budget.
+    pub crowded: bool,
+}
+
+impl WidgetStats {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "dashboard": self.dashboard,
+            "widget": self.widget,
+            "annotations": self.annotations,
+            "crowded": self.crowded,
+        })
+    }
+}
+
+/// Vertical annotations per dashboard and widget in `listed`, in order of
+/// first appearance. A widget with at least `budget` of them is crowded
+/// (never with a budget of 0), as `annotate --annotation-budget` warns.
+pub fn widget_stats(listed: &[ListedAnnotation], budget: usize) -> Vec<WidgetStats> {
+    let mut stats: Vec<WidgetStats> = Vec::new();
+    for ann in listed.iter().filter(|a| a.direction == Direction::Vertical) {
+        match stats
+            .iter_mut()
+            .find(|s| s.dashboard == ann.dashboard && s.widget == ann.widget)
+        {
+            Some(s) => s.annotations += 1,
+            None => stats.push(WidgetStats {
+                dashboard: ann.dashboard.clone(),
+                widget: ann.widget.clone(),
+                annotations: 1,
+                crowded: false,
+            }),
+        }
+    }
+    for s in &mut stats {
+        s.crowded = budget > 0 && s.annotations >= budget;
+    }
+    stats
+}
+
+/// Render `stats` for stdout in the given format.
+pub fn render(stats: &[WidgetStats], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => {
+            let entries: Vec<Value> = stats.iter().map(WidgetStats::to_json).collect();
+            serde_json::to_string_pretty(&entries).unwrap_or_default()
+        }
+        OutputFormat::Text => {
+            let mut lines = vec!["DASHBOARD\tWIDGET\tANNOTATIONS\tBUDGET".to_string()];
+            lines.extend(stats.iter().map(|s| {
+                format!(
+                    "{}\t{}\t{}\t{}",
+                    s.dashboard,
+                    s.widget,
+                    s.annotations,
+                    if s.crowded { "crowded" } else { "ok" }
+                )
+            }));
+            lines.join("\n")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provenance::Provenance;
+
+    fn listed(widget: &str, direction: Direction) -> ListedAnnotation {
+        ListedAnnotation {
+            dashboard: "orders".to_string(),
+            widget: widget.to_string(),
+            direction,
+            label: "version: 1.9.0".to_string(),
+            value: "2025-01-20T12:00:00Z".to_string(),
+            end: None,
+            run_id: None,
+            provenance: Provenance::default(),
+        }
+    }
+
+    #[test]
+    fn widgets_at_the_budget_are_crowded() {
+        let listed = vec![
+            listed("Latency", Direction::Vertical),
+            listed("Errors", Direction::Vertical),
+            listed("Latency", Direction::Vertical),
+            listed("Errors", Direction::Horizontal),
+        ];
+
+        let stats = widget_stats(&listed, 2);
+        assert_eq!(
+            render(&stats, OutputFormat::Text),
+            "DASHBOARD\tWIDGET\tANNOTATIONS\tBUDGET\n\
+             orders\tLatency\t2\tcrowded\n\
+             orders\tErrors\t1\tok"
+        );
+        assert!(widget_stats(&listed, 0).iter().all(|s| !s.crowded));
+    }
+}
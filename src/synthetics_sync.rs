@@ -0,0 +1,133 @@
+use anyhow::{Context, Result};
+use aws_sdk_synthetics::Client;
+use chrono::{DateTime, Duration, Utc};
+use log::info;
+
+use crate::annotate::AnnotationSpec;
+use crate::aws_client;
+
+// `CanaryRunState` of a failed run.
+const STATE_FAILED: &str = "FAILED";
+
+/// One run of a CloudWatch Synthetics canary, reduced to what an annotation needs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanaryRun {
+    pub canary: String,
+    /// `PASSED`, `FAILED` or `RUNNING`.
+    pub state: String,
+    /// Why the run failed, e.g. `Timeout 30000ms exceeded`.
+    pub reason: Option<String>,
+    pub started_at: DateTime<Utc>,
+}
+
+impl CanaryRun {
+    pub fn failed(&self) -> bool {
+        self.state == STATE_FAILED
+    }
+
+    /// Marker at the start of the run, e.g.
+    /// `synthetics: checkout-canary (FAILED: Timeout 30000ms exceeded)`.
+    pub fn to_spec(&self, label: &str, run_id: &str) -> AnnotationSpec {
+        let outcome = match self.reason {
+            Some(ref reason) if self.failed() => format!("{}: {reason}", self.state),
+            _ => self.state.clone(),
+        };
+        AnnotationSpec {
+            label: label.to_string(),
+            value: format!("{} ({outcome})", self.canary),
+            time: Some(self.started_at.to_rfc3339()),
+            end_time: None,
+            run_id: run_id.to_string(),
+        }
+    }
+}
+
+/// List runs of `canary` started within `since`, only failed ones with
+/// `failures_only`.
+pub async fn list_runs(
+    client: &Client,
+    canary: &str,
+    failures_only: bool,
+    since: Duration,
+) -> Result<Vec<CanaryRun>> {
+    let cutoff = Utc::now() - since;
+    let mut result = Vec::new();
+    let mut next_token: Option<String> = None;
+
+    'pages: loop {
+        let mut req = client.get_canary_runs().name(canary);
+        if let Some(ref token) = next_token {
+            req = req.next_token(token);
+        }
+
+        let resp = req
+            .send()
+            .await
+            .with_context(|| format!("failed to get runs of canary {canary}"))?;
+
+        // Runs are listed newest first, so stop paging at the cutoff.
+        for item in resp.canary_runs() {
+            let Some(started_at) = item
+                .timeline()
+                .and_then(|t| t.started())
+                .and_then(aws_client::to_chrono)
+            else {
+                continue;
+            };
+            if started_at < cutoff {
+                break 'pages;
+            }
+            let status = item.status();
+            let run = CanaryRun {
+                canary: item.name().unwrap_or(canary).to_string(),
+                state: status
+                    .and_then(|s| s.state())
+                    .map(|s| s.as_str().to_string())
+                    .unwrap_or_default(),
+                reason: status
+                    .and_then(|s| s.state_reason())
+                    .filter(|r| !r.is_empty())
+                    .map(str::to_string),
+                started_at,
+            };
+            if failures_only && !run.failed() {
+                continue;
+            }
+            result.push(run);
+        }
+
+        match resp.next_token() {
+            Some(t) if !t.is_empty() => {
+                next_token = Some(t.to_string());
+            }
+            _ => break,
+        }
+    }
+
+    info!("Found {} run(s) of canary {}", result.len(), canary);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn failed_run_becomes_marker_with_reason() {
+        let run = CanaryRun {
+            canary: "checkout-canary".to_string(),
+            state: "FAILED".to_string(),
+            reason: Some("Timeout 30000ms exceeded".to_string()),
+            started_at: Utc.with_ymd_and_hms(2025, 1, 20, 12, 5, 0).unwrap(),
+        };
+
+        let spec = run.to_spec("synthetics", "20250120T120000Z-aaaaaaaa");
+        assert_eq!(
+            spec.value,
+            "checkout-canary (FAILED: Timeout 30000ms exceeded)"
+        );
+        assert_eq!(spec.time.as_deref(), Some("2025-01-20T12:05:00+00:00"));
+        assert!(spec.end_time.is_none());
+    }
+}
@@ -0,0 +1,43 @@
+use std::collections::BTreeMap;
+
+/// Template variable: account alias (or account id when the account has no alias).
+pub const VAR_ACCOUNT_ALIAS: &str = "account_alias";
+
+/// Template variable: the `properties.region` of the widget the annotation
+/// lands on (the dashboard's region for widgets without one). Rendered per
+/// widget, in labels only.
+pub const VAR_WIDGET_REGION: &str = "widget_region";
+
+/// Values available to `{name}` placeholders in labels and values.
+pub type TemplateVars = BTreeMap<&'static str, String>;
+
+/// Returns `true` if `template` references the `{name}` placeholder.
+pub fn uses(template: &str, name: &str) -> bool {
+    template.contains(&format!("{{{name}}}"))
+}
+
+/// Replace every known `{name}` placeholder; unknown placeholders are left as-is.
+pub fn render(template: &str, vars: &TemplateVars) -> String {
+    vars.iter()
+        .fold(template.to_string(), |acc, (name, value)| {
+            acc.replace(&format!("{{{name}}}"), value)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_known_placeholders_only() {
+        let mut vars = TemplateVars::new();
+        vars.insert(VAR_ACCOUNT_ALIAS, "payments-prod".to_string());
+
+        assert_eq!(
+            render("deploy ({account_alias}) {unknown}", &vars),
+            "deploy (payments-prod) {unknown}"
+        );
+        assert!(uses("deploy ({account_alias})", VAR_ACCOUNT_ALIAS));
+        assert!(!uses("deploy", VAR_ACCOUNT_ALIAS));
+    }
+}
@@ -0,0 +1,49 @@
+use chrono::{DateTime, Local, Utc};
+use clap::ValueEnum;
+
+/// How times are shown in log and summary output. Annotation JSON always
+/// stays RFC3339 UTC.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TimeDisplay {
+    /// RFC3339 in the local timezone, e.g. `2025-01-20T13:00:00+01:00`.
+    Local,
+    /// RFC3339 in UTC, e.g. `2025-01-20T12:00:00+00:00`.
+    #[default]
+    Utc,
+    /// Seconds since the Unix epoch.
+    Unix,
+}
+
+impl TimeDisplay {
+    pub fn format(self, t: DateTime<Utc>) -> String {
+        match self {
+            TimeDisplay::Local => t.with_timezone(&Local).to_rfc3339(),
+            TimeDisplay::Utc => t.to_rfc3339(),
+            TimeDisplay::Unix => t.timestamp().to_string(),
+        }
+    }
+
+    /// Like [`TimeDisplay::format`] for an RFC3339 string; anything that
+    /// doesn't parse is shown unchanged.
+    pub fn format_rfc3339(self, time: &str) -> String {
+        DateTime::parse_from_rfc3339(time)
+            .map(|t| self.format(t.with_timezone(&Utc)))
+            .unwrap_or_else(|_| time.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_utc_and_unix() {
+        let time = "2025-01-20T13:00:00+01:00";
+        assert_eq!(
+            TimeDisplay::Utc.format_rfc3339(time),
+            "2025-01-20T12:00:00+00:00"
+        );
+        assert_eq!(TimeDisplay::Unix.format_rfc3339(time), "1737374400");
+        assert_eq!(TimeDisplay::Unix.format_rfc3339("soon"), "soon");
+    }
+}
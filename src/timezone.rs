@@ -0,0 +1,114 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error, Result};
+use chrono::{DateTime, Datelike, NaiveDateTime, Timelike, Utc};
+use jiff::civil;
+use jiff::tz::TimeZone;
+
+/// Layouts accepted for timestamps without an offset.
+const NAIVE_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+];
+
+/// Zone that naive timestamps like `2025-03-01 14:00` are read in
+/// (`--timezone`): an IANA name such as `Europe/Berlin`, `UTC` or `local`.
+#[derive(Debug, Clone)]
+pub struct Timezone(TimeZone);
+
+impl Default for Timezone {
+    fn default() -> Self {
+        Self(TimeZone::UTC)
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let zone = if s.eq_ignore_ascii_case("local") {
+            TimeZone::try_system()
+        } else {
+            TimeZone::get(s)
+        };
+        zone.map(Self).map_err(|err| {
+            anyhow!("unknown timezone '{s}' (expected an IANA name like Europe/Berlin, UTC or local): {err}")
+        })
+    }
+}
+
+impl fmt::Display for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0.iana_name().unwrap_or("local"))
+    }
+}
+
+impl Timezone {
+    /// Parse a timestamp without an offset, e.g. `2025-03-01 14:00`, as a
+    /// wall-clock time in this zone. `None` if `input` isn't such a timestamp.
+    ///
+    /// Times skipped by a DST change are moved forward, repeated ones take
+    /// the earlier occurrence.
+    pub fn parse_naive(&self, input: &str) -> Option<Result<DateTime<Utc>>> {
+        let naive = NAIVE_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())?;
+        Some(
+            self.resolve(naive)
+                .map_err(|err| anyhow!("time '{input}' can't be placed in timezone {self}: {err}")),
+        )
+    }
+
+    /// Place a wall-clock time in this zone, as [`Self::parse_naive`] does.
+    pub fn resolve(&self, naive: NaiveDateTime) -> Result<DateTime<Utc>> {
+        let civil = civil::DateTime::new(
+            i16::try_from(naive.year())?,
+            i8::try_from(naive.month())?,
+            i8::try_from(naive.day())?,
+            i8::try_from(naive.hour())?,
+            i8::try_from(naive.minute())?,
+            i8::try_from(naive.second())?,
+            0,
+        )?;
+        let instant = self.0.to_timestamp(civil)?;
+        DateTime::from_timestamp(instant.as_second(), 0).ok_or_else(|| anyhow!("out of range"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn naive_times_are_read_in_the_zone() {
+        let berlin: Timezone = "Europe/Berlin".parse().unwrap();
+        let parsed =
+            |zone: &Timezone, input: &str| zone.parse_naive(input).unwrap().unwrap().to_rfc3339();
+
+        assert_eq!(
+            parsed(&berlin, "2025-03-01 14:00"),
+            "2025-03-01T13:00:00+00:00"
+        );
+        assert_eq!(
+            parsed(&berlin, "2025-07-01T14:00:30"),
+            "2025-07-01T12:00:30+00:00"
+        );
+        // 02:30 doesn't exist on the spring-forward day.
+        assert_eq!(
+            parsed(&berlin, "2025-03-30 02:30"),
+            "2025-03-30T01:30:00+00:00"
+        );
+        assert_eq!(
+            parsed(&Timezone::default(), "2025-03-01 14:00"),
+            "2025-03-01T14:00:00+00:00"
+        );
+
+        assert!(berlin.parse_naive("2025-03-01T14:00:00Z").is_none());
+        assert!(berlin.parse_naive("-15m").is_none());
+        assert!("Mars/Olympus_Mons".parse::<Timezone>().is_err());
+    }
+}
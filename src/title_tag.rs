@@ -0,0 +1,192 @@
+use serde_json::{Map, Value};
+
+use crate::annotate::{JSON_KEY_PROPERTIES, JSON_KEY_TITLE};
+use crate::selector::{selected_widgets, WidgetSelector};
+
+/// Widget property remembering the tag cwnote appended to the title, so the
+/// next tag replaces it and `remove --untag-title` can strip it again.
+pub const JSON_KEY_TITLE_TAG: &str = "cwnoteTitleTag";
+
+/// Title without the tag cwnote added earlier (if any).
+fn base_title(props: &Map<String, Value>) -> String {
+    let title = props
+        .get(JSON_KEY_TITLE)
+        .and_then(|t| t.as_str())
+        .unwrap_or("");
+    let old_tag = props.get(JSON_KEY_TITLE_TAG).and_then(|t| t.as_str());
+
+    old_tag
+        .and_then(|tag| title.strip_suffix(tag))
+        .map(|base| base.trim_end().to_string())
+        .unwrap_or_else(|| title.to_string())
+}
+
+/// `true` if the widget's title is `title`, with or without the tag cwnote
+/// added, so tagging a title never changes which widgets exact-title
+/// criteria (`--row-of`, `protectedWidgets`) pick.
+pub fn has_title(widget_obj: &Map<String, Value>, title: &str) -> bool {
+    let Some(props) = widget_obj
+        .get(JSON_KEY_PROPERTIES)
+        .and_then(|p| p.as_object())
+    else {
+        return false;
+    };
+    props.get(JSON_KEY_TITLE).and_then(|t| t.as_str()) == Some(title)
+        || (props.contains_key(JSON_KEY_TITLE_TAG) && base_title(props) == title)
+}
+
+/// Append `tag` to the widget title, replacing the previous cwnote tag.
+/// Returns `true` if the title changed.
+pub fn tag_title(widget_obj: &mut Map<String, Value>, tag: &str) -> bool {
+    let Some(props) = widget_obj
+        .get_mut(JSON_KEY_PROPERTIES)
+        .and_then(|p| p.as_object_mut())
+    else {
+        return false;
+    };
+
+    let base = base_title(props);
+    let title = if base.is_empty() {
+        tag.to_string()
+    } else {
+        format!("{base} {tag}")
+    };
+    let changed = props.get(JSON_KEY_TITLE).and_then(|t| t.as_str()) != Some(title.as_str());
+
+    props.insert(JSON_KEY_TITLE.to_string(), Value::String(title));
+    props.insert(
+        JSON_KEY_TITLE_TAG.to_string(),
+        Value::String(tag.to_string()),
+    );
+    changed
+}
+
+/// Strip the tag cwnote added to the widget title. Returns `true` if there was one.
+pub fn untag_title(widget_obj: &mut Map<String, Value>) -> bool {
+    let Some(props) = widget_obj
+        .get_mut(JSON_KEY_PROPERTIES)
+        .and_then(|p| p.as_object_mut())
+    else {
+        return false;
+    };
+    if !props.contains_key(JSON_KEY_TITLE_TAG) {
+        return false;
+    }
+
+    let base = base_title(props);
+    props.shift_remove(JSON_KEY_TITLE_TAG);
+    if base.is_empty() {
+        props.shift_remove(JSON_KEY_TITLE);
+    } else {
+        props.insert(JSON_KEY_TITLE.to_string(), Value::String(base));
+    }
+    true
+}
+
+/// Apply `edit` to the widgets at `indices`; returns how many changed.
+fn edit_widgets(
+    body: &mut Value,
+    indices: &[usize],
+    mut edit: impl FnMut(&mut Map<String, Value>) -> bool,
+) -> usize {
+    let Some(widgets) = body.get_mut("widgets").and_then(|w| w.as_array_mut()) else {
+        return 0;
+    };
+
+    let mut changed = 0usize;
+    for i in indices {
+        if let Some(widget_obj) = widgets.get_mut(*i).and_then(|w| w.as_object_mut()) {
+            if edit(widget_obj) {
+                changed += 1;
+            }
+        }
+    }
+    changed
+}
+
+/// Tag the titles of the widgets at `indices`.
+pub fn tag_widgets(body: &mut Value, tag: &str, indices: &[usize]) -> usize {
+    edit_widgets(body, indices, |w| tag_title(w, tag))
+}
+
+/// Untag the titles of all widgets picked by `selector`.
+pub fn untag_selected(body: &mut Value, selector: &WidgetSelector) -> usize {
+    let widgets = body
+        .get("widgets")
+        .and_then(|w| w.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let selected = selected_widgets(widgets, selector).unwrap_or_default();
+
+    edit_widgets(body, &selected, untag_title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tag_replaces_previous_tag_and_untag_restores_title() {
+        let mut widget = json!({
+            "type": "metric",
+            "properties": { "title": "Latency [p99]" }
+        });
+        let widget_obj = widget.as_object_mut().unwrap();
+
+        assert!(tag_title(widget_obj, "[v1.2.3]"));
+        assert_eq!(
+            widget_obj["properties"]["title"],
+            json!("Latency [p99] [v1.2.3]")
+        );
+
+        assert!(tag_title(widget_obj, "[v1.2.4]"));
+        assert_eq!(
+            widget_obj["properties"]["title"],
+            json!("Latency [p99] [v1.2.4]")
+        );
+
+        assert!(untag_title(widget_obj));
+        assert_eq!(widget_obj["properties"]["title"], json!("Latency [p99]"));
+        assert!(widget_obj["properties"].get(JSON_KEY_TITLE_TAG).is_none());
+        assert!(!untag_title(widget_obj));
+    }
+
+    #[test]
+    fn tagged_titles_still_match_their_base_title() {
+        let mut widget = json!({ "type": "metric", "properties": { "title": "Latency" } });
+        let widget_obj = widget.as_object_mut().unwrap();
+        assert!(has_title(widget_obj, "Latency"));
+
+        tag_title(widget_obj, "[v1.2.3]");
+        assert!(has_title(widget_obj, "Latency"));
+        assert!(has_title(widget_obj, "Latency [v1.2.3]"));
+        assert!(!has_title(widget_obj, "Latenc"));
+    }
+
+    #[test]
+    fn untag_selected_only_touches_matching_widgets() {
+        let mut body = json!({
+            "widgets": [
+                { "type": "metric", "properties": { "title": "API Latency" } },
+                { "type": "metric", "properties": { "title": "Errors" } },
+                { "type": "text", "properties": { "markdown": "# Hi" } }
+            ]
+        });
+        assert_eq!(tag_widgets(&mut body, "[v2]", &[0, 1]), 2);
+
+        let selector = WidgetSelector {
+            title_contains: Some("Latency".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(untag_selected(&mut body, &selector), 1);
+        assert_eq!(
+            body["widgets"][0]["properties"]["title"],
+            json!("API Latency")
+        );
+        assert_eq!(
+            body["widgets"][1]["properties"]["title"],
+            json!("Errors [v2]")
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::{anyhow, Context, Result};
+use log::info;
+
+use crate::aws_client::{self, AssumeRole};
+
+const SCHEME_SSM: &str = "ssm:";
+
+/// Where to read the annotation value from at run time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueSource {
+    /// An SSM Parameter Store parameter name, e.g. `/my/service/version`.
+    Ssm(String),
+}
+
+impl ValueSource {
+    /// Parse a `--value-from` argument, e.g. `ssm:/my/service/version`.
+    pub fn parse(input: &str) -> Result<Self> {
+        match input.strip_prefix(SCHEME_SSM) {
+            Some(name) if !name.trim().is_empty() => Ok(Self::Ssm(name.trim().to_string())),
+            Some(_) => Err(anyhow!(
+                "--value-from '{input}' is missing a parameter name"
+            )),
+            None => Err(anyhow!(
+                "--value-from '{input}' has an unsupported source (expected ssm:<parameter-name>)"
+            )),
+        }
+    }
+
+    /// Fetch the current value from the source, as `role` if given.
+    pub async fn resolve(
+        &self,
+        region: Option<&str>,
+        profile: Option<&str>,
+        role: Option<&AssumeRole>,
+    ) -> Result<String> {
+        match self {
+            Self::Ssm(name) => {
+                let client = aws_client::make_ssm_client(region, profile, role).await?;
+                let resp = client
+                    .get_parameter()
+                    .name(name)
+                    .with_decryption(true)
+                    .send()
+                    .await
+                    .with_context(|| format!("failed to read SSM parameter {name}"))?;
+
+                let value = resp
+                    .parameter()
+                    .and_then(|p| p.value())
+                    .with_context(|| format!("SSM parameter {name} has no value"))?;
+
+                info!("Resolved annotation value from SSM parameter {name}");
+                Ok(value.trim().to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ssm_source() {
+        assert_eq!(
+            ValueSource::parse("ssm:/my/service/version").unwrap(),
+            ValueSource::Ssm("/my/service/version".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_or_empty_sources() {
+        assert!(ValueSource::parse("ssm:").is_err());
+        assert!(ValueSource::parse("s3://bucket/key").is_err());
+        assert!(ValueSource::parse("/my/service/version").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::{anyhow, Context, Result};
+
+const TRANSFORM_SEMVER_CORE: &str = "semver-core";
+const TRANSFORM_SHORT_SHA: &str = "short-sha";
+const TRANSFORM_URL_DECODE: &str = "url-decode";
+const TRANSFORM_TRUNCATE: &str = "truncate:";
+// Git's default abbreviation length.
+const SHORT_SHA_LEN: usize = 7;
+
+/// One step of `--value-transform`, applied to the value before templating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueTransform {
+    /// Drop semver build metadata, e.g. `1.9.0+build.42` -> `1.9.0`.
+    SemverCore,
+    /// Shorten full hex commit ids (40 or 64 chars) anywhere in the value.
+    ShortSha,
+    /// Decode `%XX` escapes, e.g. `release%2F1.9` -> `release/1.9`.
+    UrlDecode,
+    /// Keep at most this many characters.
+    Truncate(usize),
+}
+
+impl ValueTransform {
+    /// Parse a single transform, e.g. `semver-core` or `truncate:20`.
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        match input {
+            TRANSFORM_SEMVER_CORE => Ok(Self::SemverCore),
+            TRANSFORM_SHORT_SHA => Ok(Self::ShortSha),
+            TRANSFORM_URL_DECODE => Ok(Self::UrlDecode),
+            _ => match input.strip_prefix(TRANSFORM_TRUNCATE) {
+                Some(len) => len
+                    .parse()
+                    .map(Self::Truncate)
+                    .with_context(|| format!("--value-transform '{input}' needs a length")),
+                None => Err(anyhow!(
+                    "--value-transform '{input}' is unknown (expected {TRANSFORM_SEMVER_CORE}, \
+                     {TRANSFORM_SHORT_SHA}, {TRANSFORM_URL_DECODE} or {TRANSFORM_TRUNCATE}<n>)"
+                )),
+            },
+        }
+    }
+
+    pub fn apply(self, value: &str) -> String {
+        match self {
+            Self::SemverCore => value.split('+').next().unwrap_or_default().to_string(),
+            Self::ShortSha => short_shas(value),
+            Self::UrlDecode => url_decode(value),
+            Self::Truncate(len) => value.chars().take(len).collect(),
+        }
+    }
+}
+
+/// Run `value` through every transform, in order.
+pub fn apply_all(value: &str, transforms: &[ValueTransform]) -> String {
+    transforms
+        .iter()
+        .fold(value.to_string(), |value, t| t.apply(&value))
+}
+
+fn short_shas(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while !rest.is_empty() {
+        let hex_len = rest
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(rest.len());
+        let (word, tail) = if hex_len > 0 {
+            rest.split_at(hex_len)
+        } else {
+            let next = rest.chars().next().map(char::len_utf8).unwrap_or(1);
+            rest.split_at(next)
+        };
+        if hex_len == 40 || hex_len == 64 {
+            out.push_str(&word[..SHORT_SHA_LEN]);
+        } else {
+            out.push_str(word);
+        }
+        rest = tail;
+    }
+    out
+}
+
+fn url_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_known_transforms() {
+        assert_eq!(
+            ValueTransform::parse("semver-core").unwrap(),
+            ValueTransform::SemverCore
+        );
+        assert_eq!(
+            ValueTransform::parse("truncate:20").unwrap(),
+            ValueTransform::Truncate(20)
+        );
+        assert!(ValueTransform::parse("truncate:x").is_err());
+        assert!(ValueTransform::parse("upper").is_err());
+    }
+
+    #[test]
+    fn pipeline_applies_transforms_in_order() {
+        let transforms = [
+            ValueTransform::UrlDecode,
+            ValueTransform::SemverCore,
+            ValueTransform::Truncate(12),
+        ];
+        assert_eq!(
+            apply_all("release%2F1.9.0%2Bbuild.42", &transforms),
+            "release/1.9."
+        );
+    }
+
+    #[test]
+    fn short_sha_only_touches_full_commit_ids() {
+        assert_eq!(
+            ValueTransform::ShortSha.apply("1.9.0-3f786850e387550fdab836ed7e6dc881de23001b (cafe)"),
+            "1.9.0-3f78685 (cafe)"
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+
+use crate::annotate::{JSON_KEY_PROPERTIES, JSON_KEY_TYPE, WIDGET_TYPE_METRIC, WIDGET_TYPE_TEXT};
+use crate::remove::Direction;
+
+const JSON_KEY_VIEW: &str = "view";
+// Metric widgets without a view are line graphs.
+const DEFAULT_METRIC_VIEW: &str = "timeSeries";
+
+/// Which annotations one kind of CloudWatch widget can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capability {
+    pub widget_type: &'static str,
+    /// `view` of a metric widget; `None` for the other types.
+    pub view: Option<&'static str>,
+    pub vertical: bool,
+    pub horizontal: bool,
+}
+
+const fn metric(view: &'static str, vertical: bool, horizontal: bool) -> Capability {
+    Capability {
+        widget_type: WIDGET_TYPE_METRIC,
+        view: Some(view),
+        vertical,
+        horizontal,
+    }
+}
+
+const fn none(widget_type: &'static str) -> Capability {
+    Capability {
+        widget_type,
+        view: None,
+        vertical: false,
+        horizontal: false,
+    }
+}
+
+/// Every widget type of the dashboard body structure. Only metric widgets
+/// have `properties.annotations`: line and stacked-area graphs (`timeSeries`,
+/// including anomaly detection bands) show both kinds, bar charts and gauges
+/// only horizontal ones. Explorer, log, alarm, text and custom widgets show
+/// none.
+pub const CAPABILITIES: [Capability; 11] = [
+    metric(DEFAULT_METRIC_VIEW, true, true),
+    metric("bar", false, true),
+    metric("gauge", false, true),
+    metric("singleValue", false, false),
+    metric("pie", false, false),
+    metric("table", false, false),
+    none("explorer"),
+    none("log"),
+    none("alarm"),
+    none(WIDGET_TYPE_TEXT),
+    none("custom"),
+];
+
+/// The capability of `widget`, `None` for a type or view not in the table.
+pub fn capability(widget: &Map<String, Value>) -> Option<&'static Capability> {
+    let widget_type = widget.get(JSON_KEY_TYPE).and_then(|t| t.as_str())?;
+    let view = (widget_type == WIDGET_TYPE_METRIC).then(|| {
+        widget
+            .get(JSON_KEY_PROPERTIES)
+            .and_then(|p| p.get(JSON_KEY_VIEW))
+            .and_then(|v| v.as_str())
+            .unwrap_or(DEFAULT_METRIC_VIEW)
+    });
+    CAPABILITIES
+        .iter()
+        .find(|c| c.widget_type == widget_type && c.view == view)
+}
+
+/// `true` if `widget` shows annotations of `direction`.
+pub fn supports(widget: &Map<String, Value>, direction: Direction) -> bool {
+    capability(widget).is_some_and(|c| match direction {
+        Direction::Vertical => c.vertical,
+        Direction::Horizontal => c.horizontal,
+    })
+}
+
+/// `true` if `widget` shows any annotations, i.e. is worth selecting.
+pub fn carries_annotations(widget: &Map<String, Value>) -> bool {
+    capability(widget).is_some_and(|c| c.vertical || c.horizontal)
+}
+
+/// `true` if `widget` is of `kind`: a widget type (`metric`) or the view of
+/// a metric widget (`timeSeries`, `bar`, `gauge`).
+pub fn is_kind(widget: &Map<String, Value>, kind: &str) -> bool {
+    capability(widget).is_some_and(|c| c.widget_type == kind || c.view == Some(kind))
+}
+
+/// Checks a `--widget-type` value: a widget type or metric view from the
+/// table that shows annotations.
+pub fn parse_kind(input: &str) -> Result<String> {
+    let annotated = || CAPABILITIES.iter().filter(|c| c.vertical || c.horizontal);
+    if annotated().any(|c| c.widget_type == input || c.view == Some(input)) {
+        return Ok(input.to_string());
+    }
+    let mut kinds: Vec<&str> = Vec::new();
+    for kind in annotated()
+        .flat_map(|c| [Some(c.widget_type), c.view])
+        .flatten()
+    {
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+    Err(anyhow!(
+        "widget type '{input}' does not show annotations; use one of: {}",
+        kinds.join(", ")
+    ))
+}
+
+/// Short description of the widget kind for logs, e.g. `metric (bar)`.
+pub fn describe(widget: &Map<String, Value>) -> String {
+    match capability(widget) {
+        Some(Capability {
+            widget_type,
+            view: Some(view),
+            ..
+        }) => format!("{widget_type} ({view})"),
+        Some(c) => c.widget_type.to_string(),
+        None => "unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn widget(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn table_covers_every_widget_type_and_metric_view() {
+        let line = widget(json!({ "type": "metric", "properties": {} }));
+        assert!(supports(&line, Direction::Vertical));
+        assert!(supports(&line, Direction::Horizontal));
+
+        let anomaly_band = widget(json!({
+            "type": "metric",
+            "properties": {
+                "view": "timeSeries",
+                "metrics": [[{ "expression": "ANOMALY_DETECTION_BAND(m1, 2)", "id": "ad1" }]]
+            }
+        }));
+        assert!(supports(&anomaly_band, Direction::Vertical));
+
+        for view in ["bar", "gauge"] {
+            let w = widget(json!({ "type": "metric", "properties": { "view": view } }));
+            assert!(!supports(&w, Direction::Vertical), "{view}");
+            assert!(supports(&w, Direction::Horizontal), "{view}");
+            assert_eq!(describe(&w), format!("metric ({view})"));
+        }
+        for view in ["singleValue", "pie", "table"] {
+            let w = widget(json!({ "type": "metric", "properties": { "view": view } }));
+            assert!(!carries_annotations(&w), "{view}");
+        }
+        for widget_type in ["explorer", "log", "alarm", "text", "custom"] {
+            let w = widget(json!({ "type": widget_type, "properties": {} }));
+            assert!(capability(&w).is_some(), "{widget_type}");
+            assert!(!carries_annotations(&w), "{widget_type}");
+        }
+
+        let gauge = widget(json!({ "type": "metric", "properties": { "view": "gauge" } }));
+        assert!(is_kind(&gauge, "gauge"));
+        assert!(is_kind(&gauge, "metric"));
+        assert!(!is_kind(&line, "gauge"));
+        assert!(is_kind(&line, "timeSeries"));
+
+        let unknown = widget(json!({ "type": "hologram" }));
+        assert!(capability(&unknown).is_none());
+        assert_eq!(describe(&unknown), "unknown");
+    }
+
+    #[test]
+    fn parse_kind_accepts_only_annotated_kinds() {
+        assert_eq!(parse_kind("gauge").unwrap(), "gauge");
+        assert_eq!(parse_kind("metric").unwrap(), "metric");
+        let err = parse_kind("log").unwrap_err().to_string();
+        assert!(err.contains("metric, timeSeries, bar, gauge"), "{err}");
+    }
+}